@@ -0,0 +1,1295 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::entity::java_class_file::JavaClassFileFormat;
+use crate::entity::constant_pool::ConstantPoolBuilder;
+use crate::entity::attribute_info::*;
+use crate::entity::field_info::FieldInfo;
+use crate::entity::method_info::MethodInfo;
+use crate::entity::access_flags::*;
+use crate::entity::descriptor::{FieldType, MethodDescriptor};
+use crate::entity::class_writer::ClassWriter;
+use crate::entity::class_bytes::ClassBytes;
+use crate::entity::instruction::{encode_instruction, ArrayType, Instruction, WideInstruction};
+
+// A Jasmin-like assembler, so classes can be written by hand where no Java compiler
+// is available. It checks syntax only: a class with a missing Code attribute, an
+// impossible stack or an invalid opcode (via `.bytes`) assembles fine, which is what
+// tests of the format checker and the verifier need.
+//
+//     .version 61 0
+//     .class public super Hello
+//     .super java/lang/Object
+//
+//     .field private static count I = 0
+//
+//     .method public static main([Ljava/lang/String;)V
+//         .limit stack 2
+//         .limit locals 1
+//         getstatic java/lang/System/out Ljava/io/PrintStream;
+//         ldc "Hello"
+//         invokevirtual java/io/PrintStream/println(Ljava/lang/String;)V
+//         return
+//     .end method
+//
+// `.nesthost <class>` and `.nestmembers <class>...` add the class attributes of the
+// same names, and each `.bootstrap <kind> <member>` adds a method handle to the
+// BootstrapMethods attribute, for `ldc dynamic <bootstrap index> <name> <descriptor>`. Comments start with `;` at the beginning of a token. Labels are written `Name:`
+// and may share a line with an instruction. Other directives available inside a
+// method are `.throws`, `.catch <class|all> from L1 to L2 using L3`, `.line`,
+// `.bytes` for raw code bytes, and `.stack` ... `.end stack` blocks whose `locals`
+// and `stack` lines describe a full frame at the next instruction. Switches list
+// their targets on the following lines and end with `default: Label`. Without
+// `.limit`, max_locals covers the arguments and max_stack is 0. Without `.super`,
+// the superclass is Object, except for Object itself. The version defaults to 49.0,
+// the last one that does not need stack map frames.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssemblerError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AssemblerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for AssemblerError {}
+
+/// `churu-vm-x asm <source> [output directory]`: writes `<class name>.class`.
+pub fn run(source_path: &str, output_directory: Option<&str>) -> Result<(), String> {
+    let source = fs::read_to_string(source_path).map_err(|e| format!("{}: {}", source_path, e))?;
+    let class = assemble(&source).map_err(|e| format!("{}:{}", source_path, e))?;
+    write_class_file(&class, output_directory)
+}
+
+/// Writes `class` to `<output directory>/<class name>.class`, creating package directories.
+pub fn write_class_file(class: &JavaClassFileFormat, output_directory: Option<&str>) -> Result<(), String> {
+    let name = class.name().map_err(|e| e.to_string())?;
+
+    let path = Path::new(output_directory.unwrap_or(".")).join(format!("{}.class", name));
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("{}: {}", parent.display(), e))?;
+    }
+    let bytes = class.to_bytes().map_err(|e| format!("{}: {}", name, e))?;
+    fs::write(&path, bytes).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Assembles one class. `to_bytes()` on the result gives the class file; counts and
+/// attribute lengths in the returned structure are left for the writer to fill in.
+pub fn assemble(source: &str) -> Result<JavaClassFileFormat, AssemblerError> {
+    let mut assembler = Assembler::default();
+    let mut line = 0;
+
+    for (number, text) in source.lines().enumerate() {
+        line = number + 1;
+        let tokens = tokenize(text).map_err(|message| AssemblerError { line, message })?;
+        assembler.statement(line, &tokens)?;
+    }
+
+    assembler.finish().map_err(|message| AssemblerError { line, message })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Str(String),
+}
+
+fn tokenize(line: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = line.chars().peekable();
+
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        match chars.peek() {
+            None | Some(';') => break,
+            Some('"') => {
+                chars.next();
+                tokens.push(Token::Str(string_literal(&mut chars)?));
+            }
+            Some(_) => {
+                let mut word = String::new();
+                while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                    word.push(c);
+                }
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Reads up to the closing quote. `\uXXXX` escapes are UTF-16 code units, so
+/// supplementary characters can be written as surrogate pairs.
+fn string_literal(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<String, String> {
+    let mut utf16 = Vec::new();
+
+    loop {
+        let c = chars.next().ok_or("unterminated string")?;
+        let unit = match c {
+            '"' => break,
+            '\\' => match chars.next().ok_or("unterminated string")? {
+                'n' => '\n' as u16,
+                't' => '\t' as u16,
+                'r' => '\r' as u16,
+                'b' => '\u{8}' as u16,
+                'f' => '\u{c}' as u16,
+                '0' => 0,
+                '"' => '"' as u16,
+                '\'' => '\'' as u16,
+                '\\' => '\\' as u16,
+                'u' => {
+                    let hex: String = chars.by_ref().take(4).collect();
+                    u16::from_str_radix(&hex, 16).map_err(|_| format!("invalid escape \\u{}", hex))?
+                }
+                other => return Err(format!("invalid escape \\{}", other)),
+            },
+            c => {
+                let mut buffer = [0; 2];
+                utf16.extend_from_slice(c.encode_utf16(&mut buffer));
+                continue;
+            }
+        };
+        utf16.push(unit);
+    }
+
+    String::from_utf16(&utf16).map_err(|_| "unpaired surrogate in string".to_string())
+}
+
+struct Operands<'t> {
+    tokens: &'t [Token],
+    position: usize,
+}
+
+impl<'t> Operands<'t> {
+    fn next(&mut self) -> Result<&'t Token, String> {
+        let token = self.tokens.get(self.position).ok_or("missing operand")?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn peek_word(&self) -> Option<&'t str> {
+        match self.tokens.get(self.position) {
+            Some(Token::Word(word)) => Some(word),
+            _ => None,
+        }
+    }
+
+    fn word(&mut self) -> Result<&'t str, String> {
+        match self.next()? {
+            Token::Word(word) => Ok(word),
+            Token::Str(text) => Err(format!("unexpected string \"{}\"", text)),
+        }
+    }
+
+    fn keyword(&mut self, keyword: &str) -> Result<(), String> {
+        match self.word()? {
+            word if word == keyword => Ok(()),
+            word => Err(format!("expected `{}` but found `{}`", keyword, word)),
+        }
+    }
+
+    fn int(&mut self, min: i64, max: i64) -> Result<i64, String> {
+        let word = self.word()?;
+        match parse_integer(word) {
+            Some(value) if (min..=max).contains(&value) => Ok(value),
+            Some(_) => Err(format!("{} is out of range {}..={}", word, min, max)),
+            None => Err(format!("expected an integer but found `{}`", word)),
+        }
+    }
+
+    fn label(&mut self) -> Result<String, String> {
+        self.word().map(str::to_string)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.position >= self.tokens.len()
+    }
+
+    fn end(&self) -> Result<(), String> {
+        match self.tokens.get(self.position) {
+            None => Ok(()),
+            Some(token) => Err(format!("unexpected {:?}", token)),
+        }
+    }
+}
+
+fn parse_integer(text: &str) -> Option<i64> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+    let value = match digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+        Some(hex) => i64::from_str_radix(hex, 16).ok()?,
+        None => digits.parse::<i64>().ok()?,
+    };
+    Some(if negative { -value } else { value })
+}
+
+fn parse_floating(text: &str) -> Option<f64> {
+    match text {
+        "NaN" => return Some(f64::NAN),
+        "Infinity" | "+Infinity" => return Some(f64::INFINITY),
+        "-Infinity" => return Some(f64::NEG_INFINITY),
+        _ => {}
+    }
+    if text.starts_with("0x") || text.starts_with("-0x") {
+        return None;
+    }
+    text.trim_end_matches(['f', 'F', 'd', 'D']).parse::<f64>().ok()
+}
+
+fn is_floating(text: &str) -> bool {
+    parse_integer(text).is_none() && parse_floating(text).is_some()
+}
+
+fn access_flags(operands: &mut Operands) -> u16 {
+    let mut flags = 0;
+
+    while let Some(word) = operands.peek_word() {
+        let flag = match word {
+            "public" => 0x0001,
+            "private" => 0x0002,
+            "protected" => 0x0004,
+            "static" => 0x0008,
+            "final" => 0x0010,
+            "super" | "synchronized" => 0x0020,
+            "volatile" | "bridge" => 0x0040,
+            "transient" | "varargs" => 0x0080,
+            "native" => 0x0100,
+            "interface" => 0x0200,
+            "abstract" => 0x0400,
+            "strict" => 0x0800,
+            "synthetic" => 0x1000,
+            "annotation" => 0x2000,
+            "enum" => 0x4000,
+            "module" => 0x8000,
+            _ => break,
+        };
+        flags |= flag;
+        operands.position += 1;
+    }
+
+    flags
+}
+
+/// Splits `java/lang/Object/toString` into the class and the member name.
+fn split_member(reference: &str) -> Result<(&str, &str), String> {
+    reference.rsplit_once('/')
+        .filter(|(class, name)| !class.is_empty() && !name.is_empty())
+        .ok_or_else(|| format!("expected Class/member but found `{}`", reference))
+}
+
+/// `Class/name(descriptor)` into its three parts.
+fn split_method(reference: &str) -> Result<(&str, &str, &str), String> {
+    let split = reference.find('(').ok_or_else(|| format!("expected Class/name(descriptor) but found `{}`", reference))?;
+    let (member, descriptor) = reference.split_at(split);
+    let (class, name) = split_member(member)?;
+    Ok((class, name, descriptor))
+}
+
+fn local(operands: &mut Operands, narrow: fn(u8) -> Instruction, wide: fn(u16) -> WideInstruction) -> Result<Item, String> {
+    let index = operands.int(0, u16::MAX as i64)?;
+    Ok(Item::Instruction(match u8::try_from(index) {
+        Ok(index) => narrow(index),
+        Err(_) => Instruction::Wide(wide(index as u16)),
+    }))
+}
+
+fn iinc(operands: &mut Operands) -> Result<Item, String> {
+    let index = operands.int(0, u16::MAX as i64)?;
+    let constant = operands.int(i16::MIN as i64, i16::MAX as i64)?;
+    Ok(Item::Instruction(match (u8::try_from(index), i8::try_from(constant)) {
+        (Ok(index), Ok(constant)) => Instruction::Iinc { index, constant },
+        _ => Instruction::Wide(WideInstruction::Iinc { index: index as u16, constant: constant as i16 }),
+    }))
+}
+
+fn array_type(operands: &mut Operands) -> Result<ArrayType, String> {
+    let name = operands.word()?;
+    (4..=11).filter_map(ArrayType::from_atype)
+        .find(|array_type| array_type.name() == name)
+        .ok_or_else(|| format!("unknown array type `{}`", name))
+}
+
+fn verification_type_name(name: &str) -> Option<VerificationTypeInfo> {
+    match name {
+        "Top" => Some(VerificationTypeInfo::Top),
+        "Integer" => Some(VerificationTypeInfo::Integer),
+        "Float" => Some(VerificationTypeInfo::Float),
+        "Double" => Some(VerificationTypeInfo::Double),
+        "Long" => Some(VerificationTypeInfo::Long),
+        "Null" => Some(VerificationTypeInfo::Null),
+        "UninitializedThis" => Some(VerificationTypeInfo::UninitializedThis),
+        _ => None,
+    }
+}
+
+/// Code before labels are given addresses.
+enum Item {
+    Instruction(Instruction),
+    Branch(fn(i16) -> Instruction, String),
+    WideBranch(fn(i32) -> Instruction, String),
+    Tableswitch { low: i32, labels: Vec<String>, default: String },
+    Lookupswitch { pairs: Vec<(i32, String)>, default: String },
+    Bytes(Vec<u8>),
+}
+
+enum PendingSwitch {
+    Table { line: usize, low: i32, labels: Vec<String> },
+    Lookup { line: usize, pairs: Vec<(i32, String)> },
+}
+
+enum FrameType {
+    Known(VerificationTypeInfo),
+    Uninitialized(String),
+}
+
+struct Frame {
+    line: usize,
+    item: usize,
+    locals: Vec<FrameType>,
+    stack: Vec<FrameType>,
+}
+
+struct Catch {
+    line: usize,
+    catch_type: u16,
+    from: String,
+    to: String,
+    using: String,
+}
+
+struct MethodBuilder {
+    access_flags: u16,
+    name_index: u16,
+    descriptor_index: u16,
+    argument_slots: u16,
+    max_stack: Option<u16>,
+    max_locals: Option<u16>,
+    items: Vec<(usize, Item)>,
+    labels: HashMap<String, usize>,
+    catches: Vec<Catch>,
+    line_numbers: Vec<(usize, usize, u16)>,  // source line, item, line number
+    frames: Vec<Frame>,
+    exceptions: Vec<u16>,
+    switch: Option<PendingSwitch>,
+    frame: Option<Frame>,
+}
+
+struct Assembler {
+    constant_pool: ConstantPoolBuilder,
+    major_version: u16,
+    minor_version: u16,
+    access_flags: u16,
+    this_class: Option<u16>,
+    super_class: Option<u16>,
+    interfaces: Vec<u16>,
+    fields: Vec<FieldInfo>,
+    methods: Vec<MethodInfo>,
+    attributes: Vec<AttributeKind>,
+    bootstrap_methods: Vec<BootstrapMethod>,
+    method: Option<MethodBuilder>,
+}
+
+impl Default for Assembler {
+    fn default() -> Self {
+        Assembler {
+            constant_pool: ConstantPoolBuilder::new(),
+            major_version: 49,
+            minor_version: 0,
+            access_flags: 0,
+            this_class: None,
+            super_class: None,
+            interfaces: Vec::new(),
+            fields: Vec::new(),
+            methods: Vec::new(),
+            attributes: Vec::new(),
+            bootstrap_methods: Vec::new(),
+            method: None,
+        }
+    }
+}
+
+impl Assembler {
+    fn base(&mut self, name: &str) -> Result<AttributeInfoBase, String> {
+        let attribute_name_index = self.constant_pool.utf8(name).map_err(|e| e.to_string())?;
+        Ok(AttributeInfoBase { attribute_name_index, attribute_length: 0 })
+    }
+
+    fn method(&mut self) -> Result<&mut MethodBuilder, String> {
+        self.method.as_mut().ok_or_else(|| "only allowed inside .method".to_string())
+    }
+
+    fn statement(&mut self, line: usize, tokens: &[Token]) -> Result<(), AssemblerError> {
+        let at_line = |message| AssemblerError { line, message };
+        if tokens.is_empty() {
+            return Ok(());
+        }
+        if let Some(method) = &mut self.method {
+            if method.switch.is_some() {
+                return switch_target(method, tokens).map_err(at_line);
+            }
+            if method.frame.is_some() && tokens[0] != Token::Word(".end".to_string()) {
+                return self.frame_line(tokens).map_err(at_line);
+            }
+        }
+
+        if let Token::Word(first) = &tokens[0] {
+            if let Some(label) = first.strip_suffix(':') {
+                let method = self.method().map_err(at_line)?;
+                let item = method.items.len();
+                if method.labels.insert(label.to_string(), item).is_some() {
+                    return Err(at_line(format!("label {} is defined twice", label)));
+                }
+                return self.statement(line, &tokens[1..]);
+            }
+        }
+        // Errors found while laying out the code point at the line that caused them.
+        if tokens == [Token::Word(".end".to_string()), Token::Word("method".to_string())] {
+            return self.end_method(line);
+        }
+
+        self.parse_line(line, tokens).map_err(at_line)
+    }
+
+    fn parse_line(&mut self, line: usize, tokens: &[Token]) -> Result<(), String> {
+        let mut operands = Operands { tokens, position: 0 };
+        let first = operands.word()?;
+
+        if first.starts_with('.') {
+            self.directive(line, first, &mut operands)?;
+        } else {
+            let item = self.instruction(line, first, &mut operands)?;
+            operands.end()?;
+            if let Some(item) = item {
+                self.method()?.items.push((line, item));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn directive(&mut self, line: usize, directive: &str, operands: &mut Operands) -> Result<(), String> {
+        match directive {
+            ".version" => {
+                self.major_version = operands.int(0, u16::MAX as i64)? as u16;
+                self.minor_version = if operands.is_empty() { 0 } else { operands.int(0, u16::MAX as i64)? as u16 };
+            }
+            ".class" | ".interface" => {
+                self.access_flags = access_flags(operands);
+                if directive == ".interface" {
+                    self.access_flags |= 0x0200 | 0x0400;
+                }
+                let name = operands.word()?;
+                self.this_class = Some(self.constant_pool.class(name).map_err(|e| e.to_string())?);
+            }
+            ".super" => {
+                let name = operands.word()?;
+                self.super_class = Some(self.constant_pool.class(name).map_err(|e| e.to_string())?);
+            }
+            ".implements" => {
+                let name = operands.word()?;
+                let interface = self.constant_pool.class(name).map_err(|e| e.to_string())?;
+                self.interfaces.push(interface);
+            }
+            ".source" => {
+                let source_file = match operands.next()? {
+                    Token::Word(text) | Token::Str(text) => text,
+                };
+                let sourcefile_index = self.constant_pool.utf8(source_file).map_err(|e| e.to_string())?;
+                let base = self.base("SourceFile")?;
+                self.attributes.push(AttributeKind::SourceFile(SourceFileAttribute { base, sourcefile_index }));
+            }
+            ".nesthost" => {
+                let host_class_index = self.constant_pool.class(operands.word()?).map_err(|e| e.to_string())?;
+                let base = self.base("NestHost")?;
+                self.attributes.push(AttributeKind::NestHost(NestHostAttribute { base, host_class_index }));
+            }
+            ".nestmembers" => {
+                let mut classes = Vec::new();
+                while !operands.is_empty() {
+                    classes.push(self.constant_pool.class(operands.word()?).map_err(|e| e.to_string())?);
+                }
+                let base = self.base("NestMembers")?;
+                self.attributes.push(AttributeKind::NestMembers(NestMembersAttribute { base, number_of_classes: classes.len() as u16, classes }));
+            }
+            ".bootstrap" => {
+                let bootstrap_method_ref = self.method_handle(operands)?;
+                self.bootstrap_methods.push(BootstrapMethod { bootstrap_method_ref, num_bootstrap_arguments: 0, bootstrap_arguments: Vec::new() });
+            }
+            ".field" => self.field(operands)?,
+            ".method" => {
+                if self.method.is_some() {
+                    return Err("missing .end method".to_string());
+                }
+                let access_flags = access_flags(operands);
+                let signature = operands.word()?;
+                let split = signature.find('(').ok_or_else(|| format!("expected name(descriptor) but found `{}`", signature))?;
+                let (name, descriptor) = signature.split_at(split);
+                let argument_slots = MethodDescriptor::parse(descriptor).map(|d| d.argument_slots()).unwrap_or(0)
+                    + if access_flags & 0x0008 == 0 { 1 } else { 0 };
+                self.method = Some(MethodBuilder {
+                    access_flags,
+                    name_index: self.constant_pool.utf8(name).map_err(|e| e.to_string())?,
+                    descriptor_index: self.constant_pool.utf8(descriptor).map_err(|e| e.to_string())?,
+                    argument_slots,
+                    max_stack: None,
+                    max_locals: None,
+                    items: Vec::new(),
+                    labels: HashMap::new(),
+                    catches: Vec::new(),
+                    line_numbers: Vec::new(),
+                    frames: Vec::new(),
+                    exceptions: Vec::new(),
+                    switch: None,
+                    frame: None,
+                });
+            }
+            ".limit" => {
+                let kind = operands.word()?;
+                let value = operands.int(0, u16::MAX as i64)? as u16;
+                match kind {
+                    "stack" => self.method()?.max_stack = Some(value),
+                    "locals" => self.method()?.max_locals = Some(value),
+                    _ => return Err(format!("expected `stack` or `locals` but found `{}`", kind)),
+                }
+            }
+            ".throws" => {
+                let name = operands.word()?;
+                let class = self.constant_pool.class(name).map_err(|e| e.to_string())?;
+                self.method()?.exceptions.push(class);
+            }
+            ".catch" => {
+                let name = operands.word()?;
+                let catch_type = if name == "all" { 0 } else { self.constant_pool.class(name).map_err(|e| e.to_string())? };
+                operands.keyword("from")?;
+                let from = operands.label()?;
+                operands.keyword("to")?;
+                let to = operands.label()?;
+                operands.keyword("using")?;
+                let using = operands.label()?;
+                self.method()?.catches.push(Catch { line, catch_type, from, to, using });
+            }
+            ".line" => {
+                let line_number = operands.int(0, u16::MAX as i64)? as u16;
+                let method = self.method()?;
+                let item = method.items.len();
+                method.line_numbers.push((line, item, line_number));
+            }
+            ".bytes" => {
+                let mut bytes = Vec::new();
+                while !operands.is_empty() {
+                    let word = operands.word()?;
+                    let hex = word.strip_prefix("0x").unwrap_or(word);
+                    bytes.push(u8::from_str_radix(hex, 16).map_err(|_| format!("expected a hex byte but found `{}`", word))?);
+                }
+                self.method()?.items.push((line, Item::Bytes(bytes)));
+            }
+            ".stack" => {
+                let method = self.method()?;
+                let item = method.items.len();
+                method.frame = Some(Frame { line, item, locals: Vec::new(), stack: Vec::new() });
+            }
+            ".end" => {
+                match operands.word()? {
+                    "stack" => {
+                        let method = self.method()?;
+                        let frame = method.frame.take().ok_or(".end stack without .stack")?;
+                        method.frames.push(frame);
+                    }
+                    other => return Err(format!("unknown .end {}", other)),
+                }
+            }
+            _ => return Err(format!("unknown directive {}", directive)),
+        }
+
+        operands.end()
+    }
+
+    fn field(&mut self, operands: &mut Operands) -> Result<(), String> {
+        let access_flags = access_flags(operands);
+        let name = operands.word()?;
+        let descriptor = operands.word()?;
+        let mut attributes = Vec::new();
+
+        if !operands.is_empty() {
+            operands.keyword("=")?;
+            let field_type = FieldType::parse(descriptor).map_err(|e| e.to_string())?;
+            let constant_value_index = match (field_type, operands.next()?) {
+                (FieldType::Object(class), Token::Str(text)) if class == "java/lang/String" => self.constant_pool.string(text).map_err(|e| e.to_string())?,
+                (FieldType::Long, Token::Word(word)) => {
+                    let value = parse_integer(word.trim_end_matches(['l', 'L'])).ok_or_else(|| format!("invalid long `{}`", word))?;
+                    self.constant_pool.long(value).map_err(|e| e.to_string())?
+                }
+                (FieldType::Float, Token::Word(word)) => {
+                    self.constant_pool.float(parse_floating(word).ok_or_else(|| format!("invalid float `{}`", word))? as f32).map_err(|e| e.to_string())?
+                }
+                (FieldType::Double, Token::Word(word)) => {
+                    self.constant_pool.double(parse_floating(word).ok_or_else(|| format!("invalid double `{}`", word))?).map_err(|e| e.to_string())?
+                }
+                (FieldType::Int | FieldType::Short | FieldType::Char | FieldType::Byte | FieldType::Boolean, Token::Word(word)) => {
+                    let value = parse_integer(word).ok_or_else(|| format!("invalid integer `{}`", word))?;
+                    self.constant_pool.integer(value as i32).map_err(|e| e.to_string())?
+                }
+                (_, token) => return Err(format!("{:?} is not a constant of type {}", token, descriptor)),
+            };
+            let base = self.base("ConstantValue")?;
+            attributes.push(AttributeKind::ConstantValue(ConstantValueAttribute { base, constant_value_index }));
+        }
+
+        self.fields.push(FieldInfo {
+            access_flags: FieldAccessFlags(access_flags),
+            name_index: self.constant_pool.utf8(name).map_err(|e| e.to_string())?,
+            descriptor_index: self.constant_pool.utf8(descriptor).map_err(|e| e.to_string())?,
+            attributes_count: attributes.len() as u16,
+            attributes,
+        });
+        Ok(())
+    }
+
+    fn frame_line(&mut self, tokens: &[Token]) -> Result<(), String> {
+        let mut operands = Operands { tokens, position: 0 };
+        let kind = operands.word()?;
+        let mut types = Vec::new();
+
+        while !operands.is_empty() {
+            let name = operands.word()?;
+            types.push(match name {
+                "Object" => {
+                    let class = operands.word()?;
+                    FrameType::Known(VerificationTypeInfo::Object { cpool_index: self.constant_pool.class(class).map_err(|e| e.to_string())? })
+                }
+                "Uninitialized" => FrameType::Uninitialized(operands.label()?),
+                _ => FrameType::Known(verification_type_name(name).ok_or_else(|| format!("unknown verification type `{}`", name))?),
+            });
+        }
+
+        let frame = self.method()?.frame.as_mut().expect("inside .stack");
+        match kind {
+            "locals" => frame.locals.extend(types),
+            "stack" => frame.stack.extend(types),
+            _ => return Err(format!("expected `locals` or `stack` but found `{}`", kind)),
+        }
+        Ok(())
+    }
+
+    fn class(&mut self, operands: &mut Operands) -> Result<u16, String> {
+        let name = operands.word()?;
+        self.constant_pool.class(name).map_err(|e| e.to_string())
+    }
+
+    fn field_ref(&mut self, operands: &mut Operands) -> Result<u16, String> {
+        let (class, name) = split_member(operands.word()?)?;
+        let descriptor = operands.word()?;
+        self.constant_pool.field_ref(class, name, descriptor).map_err(|e| e.to_string())
+    }
+
+    /// `Class/name(descriptor)`, preceded by `interface` for an InterfaceMethodref.
+    fn method_ref(&mut self, operands: &mut Operands) -> Result<u16, String> {
+        let is_interface = operands.peek_word() == Some("interface");
+        if is_interface {
+            operands.position += 1;
+        }
+        let (class, name, descriptor) = split_method(operands.word()?)?;
+
+        Ok(if is_interface {
+            self.constant_pool.interface_method_ref(class, name, descriptor).map_err(|e| e.to_string())?
+        } else {
+            self.constant_pool.method_ref(class, name, descriptor).map_err(|e| e.to_string())?
+        })
+    }
+
+    /// `<kind> <member>`, where the kind is named after the instruction the handle behaves
+    /// like (`getfield`, ..., `invokeinterface`, or `newinvokespecial` for a constructor) and
+    /// the member is written as that instruction's operand.
+    fn method_handle(&mut self, operands: &mut Operands) -> Result<u16, String> {
+        let kind = operands.word()?;
+        let (reference_kind, reference_index) = match kind {
+            "getfield" => (1, self.field_ref(operands)?),
+            "getstatic" => (2, self.field_ref(operands)?),
+            "putfield" => (3, self.field_ref(operands)?),
+            "putstatic" => (4, self.field_ref(operands)?),
+            "invokevirtual" => (5, self.method_ref(operands)?),
+            "invokestatic" => (6, self.method_ref(operands)?),
+            "invokespecial" => (7, self.method_ref(operands)?),
+            "newinvokespecial" => (8, self.method_ref(operands)?),
+            "invokeinterface" => {
+                let (class, name, descriptor) = split_method(operands.word()?)?;
+                (9, self.constant_pool.interface_method_ref(class, name, descriptor).map_err(|e| e.to_string())?)
+            }
+            _ => return Err(format!("unknown method handle kind `{}`", kind)),
+        };
+        self.constant_pool.method_handle(reference_kind, reference_index).map_err(|e| e.to_string())
+    }
+
+    fn invokeinterface(&mut self, operands: &mut Operands) -> Result<Item, String> {
+        let (class, name, descriptor) = split_method(operands.word()?)?;
+        let index = self.constant_pool.interface_method_ref(class, name, descriptor).map_err(|e| e.to_string())?;
+
+        let count = if operands.is_empty() {
+            let descriptor = MethodDescriptor::parse(descriptor).map_err(|e| format!("{}; give the count explicitly", e))?;
+            u8::try_from(descriptor.argument_slots() + 1).map_err(|_| "too many arguments".to_string())?
+        } else {
+            operands.int(0, u8::MAX as i64)? as u8
+        };
+        Ok(Item::Instruction(Instruction::Invokeinterface { index, count }))
+    }
+
+    fn multianewarray(&mut self, operands: &mut Operands) -> Result<Item, String> {
+        let index = self.class(operands)?;
+        let dimensions = operands.int(0, u8::MAX as i64)? as u8;
+        Ok(Item::Instruction(Instruction::Multianewarray { index, dimensions }))
+    }
+
+    /// A one-slot loadable constant: a number, a string, `class Name`, `methodtype (...)V`,
+    /// `methodhandle <kind> <member>` or `dynamic <bootstrap index> <name> <descriptor>`.
+    fn ldc(&mut self, operands: &mut Operands, wide: bool) -> Result<Item, String> {
+        let index = match operands.next()? {
+            Token::Str(text) => self.constant_pool.string(text).map_err(|e| e.to_string())?,
+            Token::Word(word) if word == "class" => self.class(operands)?,
+            Token::Word(word) if word == "methodtype" => {
+                let descriptor = operands.word()?;
+                self.constant_pool.method_type(descriptor).map_err(|e| e.to_string())?
+            }
+            Token::Word(word) if word == "methodhandle" => self.method_handle(operands)?,
+            Token::Word(word) if word == "dynamic" => {
+                let bootstrap_method_attr_index = operands.int(0, u16::MAX as i64)? as u16;
+                let name = operands.word()?;
+                let descriptor = operands.word()?;
+                self.constant_pool.dynamic(bootstrap_method_attr_index, name, descriptor).map_err(|e| e.to_string())?
+            }
+            Token::Word(word) if is_floating(word) => self.constant_pool.float(parse_floating(word).unwrap() as f32).map_err(|e| e.to_string())?,
+            Token::Word(word) => match parse_integer(word) {
+                Some(value) if (i32::MIN as i64..=u32::MAX as i64).contains(&value) => self.constant_pool.integer(value as i32).map_err(|e| e.to_string())?,
+                _ => return Err(format!("invalid constant `{}`", word)),
+            },
+        };
+
+        Ok(Item::Instruction(match u8::try_from(index) {
+            Ok(index) if !wide => Instruction::Ldc(index),
+            _ => Instruction::LdcW(index),
+        }))
+    }
+
+    /// A long (optionally suffixed with `L`) or a double.
+    fn ldc2(&mut self, operands: &mut Operands) -> Result<u16, String> {
+        let word = operands.word()?;
+        if let Some(value) = parse_integer(word.trim_end_matches(['l', 'L'])) {
+            return self.constant_pool.long(value).map_err(|e| e.to_string());
+        }
+        match parse_floating(word) {
+            Some(value) => self.constant_pool.double(value).map_err(|e| e.to_string()),
+            None => Err(format!("invalid constant `{}`", word)),
+        }
+    }
+
+    fn instruction(&mut self, line: usize, mnemonic: &str, operands: &mut Operands) -> Result<Option<Item>, String> {
+        self.method()?;
+
+        let item = match mnemonic {
+            "tableswitch" => {
+                let low = operands.int(i32::MIN as i64, i32::MAX as i64)? as i32;
+                self.method()?.switch = Some(PendingSwitch::Table { line, low, labels: Vec::new() });
+                return Ok(None);
+            }
+            "lookupswitch" => {
+                self.method()?.switch = Some(PendingSwitch::Lookup { line, pairs: Vec::new() });
+                return Ok(None);
+            }
+            "wide" => {
+                let mnemonic = operands.word()?;
+                let index = operands.int(0, u16::MAX as i64)? as u16;
+                Item::Instruction(Instruction::Wide(match mnemonic {
+                    "iload" => WideInstruction::Iload(index),
+                    "lload" => WideInstruction::Lload(index),
+                    "fload" => WideInstruction::Fload(index),
+                    "dload" => WideInstruction::Dload(index),
+                    "aload" => WideInstruction::Aload(index),
+                    "istore" => WideInstruction::Istore(index),
+                    "lstore" => WideInstruction::Lstore(index),
+                    "fstore" => WideInstruction::Fstore(index),
+                    "dstore" => WideInstruction::Dstore(index),
+                    "astore" => WideInstruction::Astore(index),
+                    "ret" => WideInstruction::Ret(index),
+                    "iinc" => WideInstruction::Iinc { index, constant: operands.int(i16::MIN as i64, i16::MAX as i64)? as i16 },
+                    _ => return Err(format!("{} cannot be widened", mnemonic)),
+                }))
+            }
+            "nop" => Item::Instruction(Instruction::Nop),
+            "aconst_null" => Item::Instruction(Instruction::AconstNull),
+            "iconst_m1" => Item::Instruction(Instruction::IconstM1),
+            "iconst_0" => Item::Instruction(Instruction::Iconst0),
+            "iconst_1" => Item::Instruction(Instruction::Iconst1),
+            "iconst_2" => Item::Instruction(Instruction::Iconst2),
+            "iconst_3" => Item::Instruction(Instruction::Iconst3),
+            "iconst_4" => Item::Instruction(Instruction::Iconst4),
+            "iconst_5" => Item::Instruction(Instruction::Iconst5),
+            "lconst_0" => Item::Instruction(Instruction::Lconst0),
+            "lconst_1" => Item::Instruction(Instruction::Lconst1),
+            "fconst_0" => Item::Instruction(Instruction::Fconst0),
+            "fconst_1" => Item::Instruction(Instruction::Fconst1),
+            "fconst_2" => Item::Instruction(Instruction::Fconst2),
+            "dconst_0" => Item::Instruction(Instruction::Dconst0),
+            "dconst_1" => Item::Instruction(Instruction::Dconst1),
+            "bipush" => Item::Instruction(Instruction::Bipush(operands.int(i8::MIN as i64, i8::MAX as i64)? as i8)),
+            "sipush" => Item::Instruction(Instruction::Sipush(operands.int(i16::MIN as i64, i16::MAX as i64)? as i16)),
+            "ldc" => self.ldc(operands, false)?,
+            "ldc_w" => self.ldc(operands, true)?,
+            "ldc2_w" => Item::Instruction(Instruction::Ldc2W(self.ldc2(operands)?)),
+            "iload" => local(operands, Instruction::Iload, WideInstruction::Iload)?,
+            "lload" => local(operands, Instruction::Lload, WideInstruction::Lload)?,
+            "fload" => local(operands, Instruction::Fload, WideInstruction::Fload)?,
+            "dload" => local(operands, Instruction::Dload, WideInstruction::Dload)?,
+            "aload" => local(operands, Instruction::Aload, WideInstruction::Aload)?,
+            "iload_0" => Item::Instruction(Instruction::Iload0),
+            "iload_1" => Item::Instruction(Instruction::Iload1),
+            "iload_2" => Item::Instruction(Instruction::Iload2),
+            "iload_3" => Item::Instruction(Instruction::Iload3),
+            "lload_0" => Item::Instruction(Instruction::Lload0),
+            "lload_1" => Item::Instruction(Instruction::Lload1),
+            "lload_2" => Item::Instruction(Instruction::Lload2),
+            "lload_3" => Item::Instruction(Instruction::Lload3),
+            "fload_0" => Item::Instruction(Instruction::Fload0),
+            "fload_1" => Item::Instruction(Instruction::Fload1),
+            "fload_2" => Item::Instruction(Instruction::Fload2),
+            "fload_3" => Item::Instruction(Instruction::Fload3),
+            "dload_0" => Item::Instruction(Instruction::Dload0),
+            "dload_1" => Item::Instruction(Instruction::Dload1),
+            "dload_2" => Item::Instruction(Instruction::Dload2),
+            "dload_3" => Item::Instruction(Instruction::Dload3),
+            "aload_0" => Item::Instruction(Instruction::Aload0),
+            "aload_1" => Item::Instruction(Instruction::Aload1),
+            "aload_2" => Item::Instruction(Instruction::Aload2),
+            "aload_3" => Item::Instruction(Instruction::Aload3),
+            "iaload" => Item::Instruction(Instruction::Iaload),
+            "laload" => Item::Instruction(Instruction::Laload),
+            "faload" => Item::Instruction(Instruction::Faload),
+            "daload" => Item::Instruction(Instruction::Daload),
+            "aaload" => Item::Instruction(Instruction::Aaload),
+            "baload" => Item::Instruction(Instruction::Baload),
+            "caload" => Item::Instruction(Instruction::Caload),
+            "saload" => Item::Instruction(Instruction::Saload),
+            "istore" => local(operands, Instruction::Istore, WideInstruction::Istore)?,
+            "lstore" => local(operands, Instruction::Lstore, WideInstruction::Lstore)?,
+            "fstore" => local(operands, Instruction::Fstore, WideInstruction::Fstore)?,
+            "dstore" => local(operands, Instruction::Dstore, WideInstruction::Dstore)?,
+            "astore" => local(operands, Instruction::Astore, WideInstruction::Astore)?,
+            "istore_0" => Item::Instruction(Instruction::Istore0),
+            "istore_1" => Item::Instruction(Instruction::Istore1),
+            "istore_2" => Item::Instruction(Instruction::Istore2),
+            "istore_3" => Item::Instruction(Instruction::Istore3),
+            "lstore_0" => Item::Instruction(Instruction::Lstore0),
+            "lstore_1" => Item::Instruction(Instruction::Lstore1),
+            "lstore_2" => Item::Instruction(Instruction::Lstore2),
+            "lstore_3" => Item::Instruction(Instruction::Lstore3),
+            "fstore_0" => Item::Instruction(Instruction::Fstore0),
+            "fstore_1" => Item::Instruction(Instruction::Fstore1),
+            "fstore_2" => Item::Instruction(Instruction::Fstore2),
+            "fstore_3" => Item::Instruction(Instruction::Fstore3),
+            "dstore_0" => Item::Instruction(Instruction::Dstore0),
+            "dstore_1" => Item::Instruction(Instruction::Dstore1),
+            "dstore_2" => Item::Instruction(Instruction::Dstore2),
+            "dstore_3" => Item::Instruction(Instruction::Dstore3),
+            "astore_0" => Item::Instruction(Instruction::Astore0),
+            "astore_1" => Item::Instruction(Instruction::Astore1),
+            "astore_2" => Item::Instruction(Instruction::Astore2),
+            "astore_3" => Item::Instruction(Instruction::Astore3),
+            "iastore" => Item::Instruction(Instruction::Iastore),
+            "lastore" => Item::Instruction(Instruction::Lastore),
+            "fastore" => Item::Instruction(Instruction::Fastore),
+            "dastore" => Item::Instruction(Instruction::Dastore),
+            "aastore" => Item::Instruction(Instruction::Aastore),
+            "bastore" => Item::Instruction(Instruction::Bastore),
+            "castore" => Item::Instruction(Instruction::Castore),
+            "sastore" => Item::Instruction(Instruction::Sastore),
+            "pop" => Item::Instruction(Instruction::Pop),
+            "pop2" => Item::Instruction(Instruction::Pop2),
+            "dup" => Item::Instruction(Instruction::Dup),
+            "dup_x1" => Item::Instruction(Instruction::DupX1),
+            "dup_x2" => Item::Instruction(Instruction::DupX2),
+            "dup2" => Item::Instruction(Instruction::Dup2),
+            "dup2_x1" => Item::Instruction(Instruction::Dup2X1),
+            "dup2_x2" => Item::Instruction(Instruction::Dup2X2),
+            "swap" => Item::Instruction(Instruction::Swap),
+            "iadd" => Item::Instruction(Instruction::Iadd),
+            "ladd" => Item::Instruction(Instruction::Ladd),
+            "fadd" => Item::Instruction(Instruction::Fadd),
+            "dadd" => Item::Instruction(Instruction::Dadd),
+            "isub" => Item::Instruction(Instruction::Isub),
+            "lsub" => Item::Instruction(Instruction::Lsub),
+            "fsub" => Item::Instruction(Instruction::Fsub),
+            "dsub" => Item::Instruction(Instruction::Dsub),
+            "imul" => Item::Instruction(Instruction::Imul),
+            "lmul" => Item::Instruction(Instruction::Lmul),
+            "fmul" => Item::Instruction(Instruction::Fmul),
+            "dmul" => Item::Instruction(Instruction::Dmul),
+            "idiv" => Item::Instruction(Instruction::Idiv),
+            "ldiv" => Item::Instruction(Instruction::Ldiv),
+            "fdiv" => Item::Instruction(Instruction::Fdiv),
+            "ddiv" => Item::Instruction(Instruction::Ddiv),
+            "irem" => Item::Instruction(Instruction::Irem),
+            "lrem" => Item::Instruction(Instruction::Lrem),
+            "frem" => Item::Instruction(Instruction::Frem),
+            "drem" => Item::Instruction(Instruction::Drem),
+            "ineg" => Item::Instruction(Instruction::Ineg),
+            "lneg" => Item::Instruction(Instruction::Lneg),
+            "fneg" => Item::Instruction(Instruction::Fneg),
+            "dneg" => Item::Instruction(Instruction::Dneg),
+            "ishl" => Item::Instruction(Instruction::Ishl),
+            "lshl" => Item::Instruction(Instruction::Lshl),
+            "ishr" => Item::Instruction(Instruction::Ishr),
+            "lshr" => Item::Instruction(Instruction::Lshr),
+            "iushr" => Item::Instruction(Instruction::Iushr),
+            "lushr" => Item::Instruction(Instruction::Lushr),
+            "iand" => Item::Instruction(Instruction::Iand),
+            "land" => Item::Instruction(Instruction::Land),
+            "ior" => Item::Instruction(Instruction::Ior),
+            "lor" => Item::Instruction(Instruction::Lor),
+            "ixor" => Item::Instruction(Instruction::Ixor),
+            "lxor" => Item::Instruction(Instruction::Lxor),
+            "iinc" => iinc(operands)?,
+            "i2l" => Item::Instruction(Instruction::I2l),
+            "i2f" => Item::Instruction(Instruction::I2f),
+            "i2d" => Item::Instruction(Instruction::I2d),
+            "l2i" => Item::Instruction(Instruction::L2i),
+            "l2f" => Item::Instruction(Instruction::L2f),
+            "l2d" => Item::Instruction(Instruction::L2d),
+            "f2i" => Item::Instruction(Instruction::F2i),
+            "f2l" => Item::Instruction(Instruction::F2l),
+            "f2d" => Item::Instruction(Instruction::F2d),
+            "d2i" => Item::Instruction(Instruction::D2i),
+            "d2l" => Item::Instruction(Instruction::D2l),
+            "d2f" => Item::Instruction(Instruction::D2f),
+            "i2b" => Item::Instruction(Instruction::I2b),
+            "i2c" => Item::Instruction(Instruction::I2c),
+            "i2s" => Item::Instruction(Instruction::I2s),
+            "lcmp" => Item::Instruction(Instruction::Lcmp),
+            "fcmpl" => Item::Instruction(Instruction::Fcmpl),
+            "fcmpg" => Item::Instruction(Instruction::Fcmpg),
+            "dcmpl" => Item::Instruction(Instruction::Dcmpl),
+            "dcmpg" => Item::Instruction(Instruction::Dcmpg),
+            "ifeq" => Item::Branch(Instruction::Ifeq, operands.label()?),
+            "ifne" => Item::Branch(Instruction::Ifne, operands.label()?),
+            "iflt" => Item::Branch(Instruction::Iflt, operands.label()?),
+            "ifge" => Item::Branch(Instruction::Ifge, operands.label()?),
+            "ifgt" => Item::Branch(Instruction::Ifgt, operands.label()?),
+            "ifle" => Item::Branch(Instruction::Ifle, operands.label()?),
+            "if_icmpeq" => Item::Branch(Instruction::IfIcmpeq, operands.label()?),
+            "if_icmpne" => Item::Branch(Instruction::IfIcmpne, operands.label()?),
+            "if_icmplt" => Item::Branch(Instruction::IfIcmplt, operands.label()?),
+            "if_icmpge" => Item::Branch(Instruction::IfIcmpge, operands.label()?),
+            "if_icmpgt" => Item::Branch(Instruction::IfIcmpgt, operands.label()?),
+            "if_icmple" => Item::Branch(Instruction::IfIcmple, operands.label()?),
+            "if_acmpeq" => Item::Branch(Instruction::IfAcmpeq, operands.label()?),
+            "if_acmpne" => Item::Branch(Instruction::IfAcmpne, operands.label()?),
+            "goto" => Item::Branch(Instruction::Goto, operands.label()?),
+            "jsr" => Item::Branch(Instruction::Jsr, operands.label()?),
+            "ret" => local(operands, Instruction::Ret, WideInstruction::Ret)?,
+            "ireturn" => Item::Instruction(Instruction::Ireturn),
+            "lreturn" => Item::Instruction(Instruction::Lreturn),
+            "freturn" => Item::Instruction(Instruction::Freturn),
+            "dreturn" => Item::Instruction(Instruction::Dreturn),
+            "areturn" => Item::Instruction(Instruction::Areturn),
+            "return" => Item::Instruction(Instruction::Return),
+            "getstatic" => Item::Instruction(Instruction::Getstatic(self.field_ref(operands)?)),
+            "putstatic" => Item::Instruction(Instruction::Putstatic(self.field_ref(operands)?)),
+            "getfield" => Item::Instruction(Instruction::Getfield(self.field_ref(operands)?)),
+            "putfield" => Item::Instruction(Instruction::Putfield(self.field_ref(operands)?)),
+            "invokevirtual" => Item::Instruction(Instruction::Invokevirtual(self.method_ref(operands)?)),
+            "invokespecial" => Item::Instruction(Instruction::Invokespecial(self.method_ref(operands)?)),
+            "invokestatic" => Item::Instruction(Instruction::Invokestatic(self.method_ref(operands)?)),
+            "invokeinterface" => self.invokeinterface(operands)?,
+            "invokedynamic" => return Err("invokedynamic is not supported by the assembler; use .bytes".to_string()),
+            "new" => Item::Instruction(Instruction::New(self.class(operands)?)),
+            "newarray" => Item::Instruction(Instruction::Newarray(array_type(operands)?)),
+            "anewarray" => Item::Instruction(Instruction::Anewarray(self.class(operands)?)),
+            "arraylength" => Item::Instruction(Instruction::Arraylength),
+            "athrow" => Item::Instruction(Instruction::Athrow),
+            "checkcast" => Item::Instruction(Instruction::Checkcast(self.class(operands)?)),
+            "instanceof" => Item::Instruction(Instruction::Instanceof(self.class(operands)?)),
+            "monitorenter" => Item::Instruction(Instruction::Monitorenter),
+            "monitorexit" => Item::Instruction(Instruction::Monitorexit),
+            "multianewarray" => self.multianewarray(operands)?,
+            "ifnull" => Item::Branch(Instruction::Ifnull, operands.label()?),
+            "ifnonnull" => Item::Branch(Instruction::Ifnonnull, operands.label()?),
+            "goto_w" => Item::WideBranch(Instruction::GotoW, operands.label()?),
+            "jsr_w" => Item::WideBranch(Instruction::JsrW, operands.label()?),
+            _ => return Err(format!("unknown instruction {}", mnemonic)),
+        };
+
+        Ok(Some(item))
+    }
+
+    fn end_method(&mut self, line: usize) -> Result<(), AssemblerError> {
+        let at_line = |message: &str| AssemblerError { line, message: message.to_string() };
+        let method = self.method.take().ok_or_else(|| at_line(".end method without .method"))?;
+        if method.frame.is_some() {
+            return Err(at_line("missing .end stack"));
+        }
+
+        let mut attributes = Vec::new();
+        let is_abstract_or_native = method.access_flags & (0x0400 | 0x0100) != 0;
+        if !is_abstract_or_native || !method.items.is_empty() {
+            let code = self.code(line, &method)?;
+            attributes.push(AttributeKind::Code(code));
+        }
+        if !method.exceptions.is_empty() {
+            let base = self.base("Exceptions").map_err(|message| at_line(&message))?;
+            attributes.push(AttributeKind::Exceptions(ExceptionsAttribute {
+                base,
+                number_of_exceptions: method.exceptions.len() as u16,
+                exception_index_table: method.exceptions,
+            }));
+        }
+
+        self.methods.push(MethodInfo {
+            access_flags: MethodAccessFlags(method.access_flags),
+            name_index: method.name_index,
+            descriptor_index: method.descriptor_index,
+            attributes_count: attributes.len() as u16,
+            attributes: attributes.into(),
+        });
+        Ok(())
+    }
+
+    /// Lays out the code of `method`, whose `.end method` is on `end_line`.
+    fn code(&mut self, end_line: usize, method: &MethodBuilder) -> Result<CodeAttribute, AssemblerError> {
+        let at_end = |message| AssemblerError { line: end_line, message };
+        // Instruction sizes do not depend on branch offsets, so a first pass without
+        // labels gives every item its pc.
+        let (_, pcs) = emit(&method.items, None)?;
+        let mut labels = HashMap::new();
+        for (label, &item) in &method.labels {
+            labels.insert(label.as_str(), pcs[item]);
+        }
+        let (code, _) = emit(&method.items, Some(&labels))?;
+
+        // Exception tables, line numbers and stack map frames hold pcs in 16 bits.
+        let narrow = |pc: u32, line: usize| {
+            u16::try_from(pc).map_err(|_| AssemblerError { line, message: format!("pc {} does not fit in 16 bits", pc) })
+        };
+        let label_pc = |label: &str, line: usize| {
+            let pc = labels.get(label).copied().ok_or_else(|| AssemblerError { line, message: format!("undefined label {}", label) })?;
+            narrow(pc, line)
+        };
+        let mut exception_table = Vec::new();
+        for catch in &method.catches {
+            exception_table.push(ExceptionTableEntry {
+                start_pc: label_pc(&catch.from, catch.line)?,
+                end_pc: label_pc(&catch.to, catch.line)?,
+                handler_pc: label_pc(&catch.using, catch.line)?,
+                catch_type: catch.catch_type,
+            });
+        }
+
+        let mut attributes = Vec::new();
+        if !method.line_numbers.is_empty() {
+            let line_number_table = method.line_numbers.iter()
+                .map(|&(line, item, line_number)| Ok(LineNumberTableEntry { start_pc: narrow(pcs[item], line)?, line_number }))
+                .collect::<Result<Vec<_>, AssemblerError>>()?;
+            let base = self.base("LineNumberTable").map_err(at_end)?;
+            attributes.push(AttributeKind::LineNumberTable(LineNumberTableAttribute {
+                base,
+                line_number_table_length: line_number_table.len() as u16,
+                line_number_table,
+            }));
+        }
+        if !method.frames.is_empty() {
+            let mut entries = Vec::new();
+            let mut previous: Option<u32> = None;
+            for frame in &method.frames {
+                let pc = pcs[frame.item];
+                let offset_delta = match previous {
+                    None => pc,
+                    Some(previous) if pc > previous => pc - previous - 1,
+                    Some(_) => return Err(AssemblerError {
+                        line: frame.line,
+                        message: "stack map frames must be at increasing offsets".to_string(),
+                    }),
+                };
+                previous = Some(pc);
+
+                let resolve = |types: &[FrameType]| {
+                    types.iter()
+                        .map(|frame_type| match frame_type {
+                            FrameType::Known(verification_type) => Ok(verification_type.clone()),
+                            FrameType::Uninitialized(label) => {
+                                label_pc(label, frame.line).map(|offset| VerificationTypeInfo::Uninitialized { offset })
+                            }
+                        })
+                        .collect::<Result<Vec<_>, _>>()
+                };
+                let locals = resolve(&frame.locals)?;
+                let stack = resolve(&frame.stack)?;
+                entries.push(StackMapFrame::FullFrame(FullFrame {
+                    frame_type: 255,
+                    offset_delta: narrow(offset_delta, frame.line)?,
+                    number_of_locals: locals.len() as u16,
+                    locals,
+                    number_of_stack_items: stack.len() as u16,
+                    stack,
+                }));
+            }
+            let base = self.base("StackMapTable").map_err(at_end)?;
+            attributes.push(AttributeKind::StackMapTable(StackMapTableAttribute {
+                base,
+                number_of_entries: entries.len() as u16,
+                entries,
+            }));
+        }
+
+        Ok(CodeAttribute {
+            base: self.base("Code").map_err(at_end)?,
+            max_stack: method.max_stack.unwrap_or(0),
+            max_locals: method.max_locals.unwrap_or(method.argument_slots),
+            code_length: code.len() as u32,
+            code: ClassBytes::from(code),
+            exception_table_length: exception_table.len() as u16,
+            exception_table,
+            attributes_count: attributes.len() as u16,
+            attributes,
+        })
+    }
+
+    fn finish(mut self) -> Result<JavaClassFileFormat, String> {
+        if self.method.is_some() {
+            return Err("missing .end method".to_string());
+        }
+        let this_class = self.this_class.ok_or("missing .class")?;
+        let super_class = match self.super_class {
+            Some(super_class) => super_class,
+            // Only Object itself has no superclass.
+            None => match self.constant_pool.class("java/lang/Object").map_err(|e| e.to_string())? {
+                object if object == this_class => 0,
+                object => object,
+            },
+        };
+        if !self.bootstrap_methods.is_empty() {
+            let base = self.base("BootstrapMethods")?;
+            let bootstrap_methods = std::mem::take(&mut self.bootstrap_methods);
+            self.attributes.push(AttributeKind::BootstrapMethods(BootstrapMethodsAttribute {
+                base,
+                num_bootstrap_methods: bootstrap_methods.len() as u16,
+                bootstrap_methods,
+            }));
+        }
+
+        Ok(JavaClassFileFormat {
+            minor_version: self.minor_version,
+            major_version: self.major_version,
+            constant_pool_count: self.constant_pool.len() as u16,
+            constant_pool: self.constant_pool.build(),
+            access_flags: ClassAccessFlags(self.access_flags),
+            this_class,
+            super_class,
+            interfaces_count: self.interfaces.len() as u16,
+            interfaces: self.interfaces,
+            fields_count: self.fields.len() as u16,
+            fields: self.fields,
+            methods_count: self.methods.len() as u16,
+            methods: self.methods,
+            attributes_count: self.attributes.len() as u16,
+            attributes: self.attributes,
+        })
+    }
+}
+
+/// One line of a switch body: `Label` for tableswitch, `key: Label` for lookupswitch,
+/// and `default: Label` to finish either.
+fn switch_target(method: &mut MethodBuilder, tokens: &[Token]) -> Result<(), String> {
+    let mut operands = Operands { tokens, position: 0 };
+    let first = operands.word()?;
+
+    if first.starts_with('.') {
+        return Err("switch is missing its default label".to_string());
+    }
+    if first == "default:" {
+        let default = operands.label()?;
+        operands.end()?;
+        let (line, item) = match method.switch.take().expect("inside a switch") {
+            PendingSwitch::Table { line, low, labels } => {
+                if labels.is_empty() {
+                    return Err("tableswitch needs at least one target".to_string());
+                }
+                (line, Item::Tableswitch { low, labels, default })
+            }
+            PendingSwitch::Lookup { line, mut pairs } => {
+                pairs.sort_by_key(|&(key, _)| key);
+                if pairs.windows(2).any(|pair| pair[0].0 == pair[1].0) {
+                    return Err("lookupswitch has a duplicate key".to_string());
+                }
+                (line, Item::Lookupswitch { pairs, default })
+            }
+        };
+        method.items.push((line, item));
+        return Ok(());
+    }
+
+    match method.switch.as_mut().expect("inside a switch") {
+        PendingSwitch::Table { labels, .. } => labels.push(first.to_string()),
+        PendingSwitch::Lookup { pairs, .. } => {
+            let key = first.strip_suffix(':')
+                .and_then(parse_integer)
+                .filter(|key| (i32::MIN as i64..=i32::MAX as i64).contains(key))
+                .ok_or_else(|| format!("expected `key:` but found `{}`", first))?;
+            pairs.push((key as i32, operands.label()?));
+        }
+    }
+    operands.end()
+}
+
+/// Lays out the code, returning it along with the pc of every item (and of the end).
+/// Without `labels`, branch offsets are written as 0.
+fn emit(items: &[(usize, Item)], labels: Option<&HashMap<&str, u32>>) -> Result<(Vec<u8>, Vec<u32>), AssemblerError> {
+    let mut buffer = ClassWriter::new();
+    let mut pcs = Vec::with_capacity(items.len() + 1);
+
+    for (line, item) in items {
+        let pc = buffer.len() as u32;
+        pcs.push(pc);
+        let line = *line;
+        let offset = |label: &str| -> Result<i64, AssemblerError> {
+            match labels {
+                None => Ok(0),
+                Some(labels) => labels.get(label)
+                    .map(|&target| target as i64 - pc as i64)
+                    .ok_or_else(|| AssemblerError { line, message: format!("undefined label {}", label) }),
+            }
+        };
+
+        let instruction = match item {
+            Item::Bytes(bytes) => {
+                buffer.write_bytes(bytes);
+                continue;
+            }
+            Item::Instruction(instruction) => instruction.clone(),
+            Item::Branch(constructor, label) => {
+                let offset = i16::try_from(offset(label)?)
+                    .map_err(|_| AssemblerError { line, message: format!("{} is too far for a 16-bit branch, use goto_w", label) })?;
+                constructor(offset)
+            }
+            Item::WideBranch(constructor, label) => constructor(offset(label)? as i32),
+            Item::Tableswitch { low, labels, default } => Instruction::Tableswitch {
+                default: offset(default)? as i32,
+                low: *low,
+                high: low + labels.len() as i32 - 1,
+                offsets: labels.iter().map(|label| offset(label).map(|offset| offset as i32)).collect::<Result<_, _>>()?,
+            },
+            Item::Lookupswitch { pairs, default } => Instruction::Lookupswitch {
+                default: offset(default)? as i32,
+                pairs: pairs.iter().map(|(key, label)| offset(label).map(|offset| (*key, offset as i32))).collect::<Result<_, _>>()?,
+            },
+        };
+        encode_instruction(&instruction, &mut buffer);
+    }
+    pcs.push(buffer.len() as u32);
+
+    Ok((buffer.into_bytes(), pcs))
+}
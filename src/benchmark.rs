@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::classloader::bootstrap_class_loader::{check_class_file, define_class};
+use crate::classloader::version_policy::VersionPolicy;
+use crate::entity::class_bytes::ClassBytes;
+use crate::entity::class_reader::ClassReader;
+use crate::entity::java_class_file::JavaClassFileFormat;
+use crate::entity::method_info::MethodParsing;
+
+const ROUNDS: usize = 5;
+
+/// `churu-vm-x bench <class file or directory>...`: defines every class found, once
+/// copying attribute bytes out of the file and cloning classes out of the class map,
+/// once sharing the file buffer and the classes, and once more leaving method attributes
+/// to be decoded on first use. Files are read before timing.
+///
+/// The copying round copies each attribute with one `memcpy`, where class loading used
+/// to copy code a byte at a time, so it understates what sharing saves.
+pub fn run(paths: &[String]) -> Result<(), String> {
+    let mut files = Vec::new();
+    for path in paths {
+        collect_class_files(Path::new(path), &mut files)?;
+    }
+    let mut classes = Vec::new();
+    for file in files {
+        let bytes = fs::read(&file).map_err(|e| format!("{}: {}", file.display(), e))?;
+        classes.push((file.to_string_lossy().into_owned(), bytes));
+    }
+    let versions = VersionPolicy::default();
+    let shared: Vec<_> = classes.iter().map(|(_, bytes)| ClassBytes::from(&bytes[..])).collect();
+
+    let copying = best_of(|| {
+        let mut class_files: HashMap<String, JavaClassFileFormat> = HashMap::new();
+        for (_, bytes) in &classes {
+            if let Ok(class) = check_class_file(&mut ClassReader::new(bytes), MethodParsing::Eager, &versions) {
+                let name = class.name().unwrap().to_string();
+                let _ = class_files.entry(name).or_insert(class).to_owned();
+            }
+        }
+    });
+    let sharing = best_of(|| {
+        let mut class_files: HashMap<String, Arc<JavaClassFileFormat>> = HashMap::new();
+        for bytes in &shared {
+            if let Ok(class) = define_class(None, bytes, MethodParsing::Eager, &versions) {
+                class_files.insert(class.name().unwrap().to_string(), class);
+            }
+        }
+    });
+    let lazy = best_of(|| {
+        let mut class_files: HashMap<String, Arc<JavaClassFileFormat>> = HashMap::new();
+        for bytes in &shared {
+            if let Ok(class) = define_class(None, bytes, MethodParsing::Lazy, &versions) {
+                class_files.insert(class.name().unwrap().to_string(), class);
+            }
+        }
+    });
+
+    println!("{} classes, best of {} rounds", classes.len(), ROUNDS);
+    println!("copying: {:>8.1} ms (memcpy per attribute)", copying.as_secs_f64() * 1000.0);
+    println!("shared:  {:>8.1} ms", sharing.as_secs_f64() * 1000.0);
+    println!("lazy:    {:>8.1} ms", lazy.as_secs_f64() * 1000.0);
+    Ok(())
+}
+
+fn best_of(mut round: impl FnMut()) -> Duration {
+    (0..ROUNDS)
+        .map(|_| {
+            let start = Instant::now();
+            round();
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn collect_class_files(path: &Path, files: &mut Vec<PathBuf>) -> Result<(), String> {
+    if path.is_dir() {
+        let mut entries = fs::read_dir(path)
+            .and_then(|entries| entries.map(|entry| entry.map(|entry| entry.path())).collect::<Result<Vec<_>, _>>())
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        entries.sort();
+        for entry in entries {
+            collect_class_files(&entry, files)?;
+        }
+    } else if path.extension().is_some_and(|extension| extension == "class") {
+        files.push(path.to_path_buf());
+    } else if !path.exists() {
+        return Err(format!("{}: no such file or directory", path.display()));
+    }
+    Ok(())
+}
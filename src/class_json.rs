@@ -0,0 +1,119 @@
+use std::fs;
+
+use serde_json::{Map, Value};
+
+use crate::assembler::write_class_file;
+use crate::entity::java_class_file::JavaClassFileFormat;
+use crate::entity::constant_pool::{ConstantKind, ConstantPool};
+use crate::entity::class_bytes::ClassBytes;
+use crate::entity::access_flags::*;
+use crate::entity::instruction::decode;
+use crate::javap::{constant_text, instruction_text};
+
+// JSON form of a parsed class, for scripts. The structure mirrors `JavaClassFileFormat`
+// field by field; next to every constant pool index `<field>` there is a
+// `<field>_resolved` holding what it refers to, access flags get their names in
+// `<field>_names`, and code its disassembly in `code_resolved`. Those additions are
+// ignored when reading the JSON back, as are counts and lengths, which are taken
+// from the contents when the class file is written. Utf8 constants are rebuilt
+// from `text`, unless unpaired surrogates made it lossy and they carry `utf16`;
+// such a `text` cannot be edited, and one that was is rejected.
+
+/// Fields holding constant pool indices (or lists of them).
+const CONSTANT_POOL_INDICES: &[&str] = &[
+    "attribute_name_index", "bootstrap_arguments", "bootstrap_method_ref", "catch_type", "class_index",
+    "class_info_index", "classes", "const_name_index", "const_value_index", "constant_value_index",
+    "cpool_index", "descriptor_index", "element_name_index", "exception_index_table", "exports_index",
+    "exports_to_index", "host_class_index", "inner_class_info_index", "inner_name_index", "interfaces",
+    "main_class_index", "method_index", "module_name_index", "module_version_index", "name_and_type_index",
+    "name_index", "opens_index", "opens_to_index", "outer_class_info_index", "package_index", "provides_index",
+    "provides_with_index", "reference_index", "requires_index", "requires_version_index", "signature_index",
+    "sourcefile_index", "string_index", "super_class", "this_class", "type_index", "type_name_index", "uses_index",
+];
+
+pub fn to_json(class: &JavaClassFileFormat) -> Result<String, String> {
+    let mut value = serde_json::to_value(class).map_err(|e| e.to_string())?;
+    resolve(&mut value, &class.constant_pool);
+
+    let object = value.as_object_mut().expect("a class serializes to an object");
+    add_flag_names(object, "access_flags", |flags| ClassAccessFlags(flags).names());
+    for field in object["fields"].as_array_mut().into_iter().flatten() {
+        add_flag_names(field.as_object_mut().unwrap(), "access_flags", |flags| FieldAccessFlags(flags).names());
+    }
+    for method in object["methods"].as_array_mut().into_iter().flatten() {
+        add_flag_names(method.as_object_mut().unwrap(), "access_flags", |flags| MethodAccessFlags(flags).names());
+    }
+
+    serde_json::to_string_pretty(&value).map_err(|e| e.to_string())
+}
+
+pub fn from_json(json: &str) -> Result<JavaClassFileFormat, String> {
+    serde_json::from_str(json).map_err(|e| e.to_string())
+}
+
+/// `churu-vm-x import <file.json> [output directory]`: writes `<class name>.class`.
+pub fn run_import(json_path: &str, output_directory: Option<&str>) -> Result<(), String> {
+    let json = fs::read_to_string(json_path).map_err(|e| format!("{}: {}", json_path, e))?;
+    let class = from_json(&json).map_err(|e| format!("{}: {}", json_path, e))?;
+    write_class_file(&class, output_directory)
+}
+
+fn resolve(value: &mut Value, constant_pool: &ConstantPool) {
+    match value {
+        Value::Array(items) => items.iter_mut().for_each(|item| resolve(item, constant_pool)),
+        Value::Object(object) => {
+            let mut additions = Vec::new();
+
+            for (key, field) in object.iter_mut() {
+                let is_index = CONSTANT_POOL_INDICES.contains(&key.as_str());
+                match field {
+                    Value::Number(index) if is_index => {
+                        if let Some(text) = index.as_u64().and_then(|index| symbol(constant_pool, index)) {
+                            additions.push((format!("{}_resolved", key), Value::String(text)));
+                        }
+                    }
+                    Value::Array(items) if is_index && items.iter().all(Value::is_u64) => {
+                        let texts = items.iter()
+                            .map(|index| index.as_u64().and_then(|index| symbol(constant_pool, index)).map_or(Value::Null, Value::String))
+                            .collect();
+                        additions.push((format!("{}_resolved", key), Value::Array(texts)));
+                    }
+                    _ => resolve(field, constant_pool),
+                }
+            }
+
+            if let (Some(code), true) = (object.get("code"), object.contains_key("max_locals")) {
+                if let Some(lines) = disassemble(code, constant_pool) {
+                    additions.push(("code_resolved".to_string(), lines));
+                }
+            }
+            if object.contains_key("inner_class_access_flags") {
+                add_flag_names(object, "inner_class_access_flags", |flags| InnerClassAccessFlags(flags).names());
+            }
+            object.extend(additions);
+        }
+        _ => {}
+    }
+}
+
+/// What a constant pool index refers to, or `None` for 0 and invalid indices.
+fn symbol(constant_pool: &ConstantPool, index: u64) -> Option<String> {
+    let index = u16::try_from(index).ok().filter(|&index| index != 0)?;
+    match constant_pool.get(index).ok()? {
+        ConstantKind::Utf8(utf8_info) => Some(utf8_info.text.clone()),
+        ConstantKind::String(string_info) => constant_pool.utf8(string_info.string_index).ok().map(str::to_string),
+        _ => Some(constant_text(constant_pool, index)),
+    }
+}
+
+fn disassemble(code: &Value, constant_pool: &ConstantPool) -> Option<Value> {
+    let code: ClassBytes = serde_json::from_value(code.clone()).ok()?;
+    let instructions = decode(&code).ok()?;
+    Some(instructions.iter().map(|(pc, instruction)| Value::String(instruction_text(constant_pool, *pc, instruction))).collect())
+}
+
+fn add_flag_names(object: &mut Map<String, Value>, key: &str, names: impl Fn(u16) -> Vec<&'static str>) {
+    if let Some(flags) = object.get(key).and_then(Value::as_u64).and_then(|flags| u16::try_from(flags).ok()) {
+        object.insert(format!("{}_names", key), names(flags).into());
+    }
+}
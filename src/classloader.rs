@@ -1 +1,14 @@
 pub mod bootstrap_class_loader;
+pub mod class_loader;
+pub mod class_loader_natives;
+pub mod class_loading_error;
+pub mod class_path;
+pub mod format_checker;
+pub mod jar_manifest;
+pub mod linker;
+pub mod linking_error;
+pub mod native_error;
+pub mod runtime_class;
+pub mod verifier;
+pub mod version_policy;
+pub mod zip_archive;
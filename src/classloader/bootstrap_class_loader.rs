@@ -1,58 +1,109 @@
-use std::collections::HashMap;
+use std::sync::Arc;
 
 use crate::entity::java_class_file::*;
 use crate::entity::constant_pool::load_constant_pool;
 use crate::entity::field_info::load_fields;
 use crate::entity::attribute_info::load_attributes;
-use crate::entity::method_info::load_methods;
-use byteorder::{BigEndian, ReadBytesExt};
+use crate::entity::method_info::{load_methods, MethodParsing};
+use crate::entity::class_format_error::*;
+use crate::entity::class_reader::ClassReader;
+use crate::entity::class_bytes::ClassBytes;
+use crate::entity::access_flags::ClassAccessFlags;
+use crate::classloader::format_checker::check_format;
+use crate::classloader::version_policy::VersionPolicy;
+use crate::classloader::class_loading_error::ClassLoadingError;
+use crate::classloader::class_path::ClassPath;
 
 // TODO need to rename: remove the prefix `_`
-pub fn _find_class(name: &str, _class_path: &Vec<String>, class_files: &mut HashMap<String, JavaClassFileFormat>) -> JavaClassFileFormat {
-    if let Some(existing_class) = class_files.get(name) {
-        return existing_class.clone();
+/// `name` is a binary name in internal form. The first class path entry holding
+/// `name.class` defines it.
+pub fn _find_class(name: &str, class_path: &ClassPath, parsing: MethodParsing, versions: &VersionPolicy) -> Result<Arc<JavaClassFileFormat>, ClassLoadingError> {
+    let bytes = class_path.find(name)?;
+    define_class(Some(name), &bytes, parsing, versions)
+}
+
+/// `name` is the binary name the class was requested by, if any; a class declaring
+/// another name in `this_class` is rejected. Keeping track of the classes defined, and
+/// refusing to define one twice, is up to the loader (see `ClassLoaders`). With
+/// `MethodParsing::Lazy`, malformed method attributes are reported when they are first
+/// decoded rather than here, and the format checker does not look inside them.
+pub fn define_class(name: Option<&str>, bytes: &ClassBytes, parsing: MethodParsing, versions: &VersionPolicy) -> Result<Arc<JavaClassFileFormat>, ClassLoadingError> {
+    let defined_class = check_class_file(&mut ClassReader::shared(bytes), parsing, versions).map_err(|mut errors| errors.remove(0))?;
+    let class_name = defined_class.name()?;
+    if let Some(requested_name) = name.filter(|requested_name| *requested_name != class_name) {
+        return Err(ClassLoadingError::WrongName { requested: requested_name.to_string(), found: class_name.to_string() });
     }
 
-    // TODO find class from class_path
-    define_class(&(name.to_owned() + ".class"), &[][..], class_files)
+    Ok(Arc::new(defined_class))
 }
 
-pub fn define_class(path: &str, bytes: &[u8], class_files: &mut HashMap<String, JavaClassFileFormat>) -> JavaClassFileFormat {
-    let defined_class = _define_class(bytes);
-    class_files.entry(path.to_string().split("/").last().unwrap().replace(".class", ""))
-        .or_insert(defined_class)
-        .to_owned()
+/// The parsed class shares `bytes` rather than copying code and other raw attributes out of it.
+/// Class file versions are checked against the default `VersionPolicy`.
+pub fn _define_class(bytes: &ClassBytes) -> Result<JavaClassFileFormat, ClassFormatError> {
+    check_class_file(&mut ClassReader::shared(bytes), MethodParsing::Eager, &VersionPolicy::default()).map_err(|mut errors| errors.remove(0))
 }
 
-fn _define_class(bytes: &[u8]) -> JavaClassFileFormat {
-    let mut buffer = &bytes[..];
+/// Parses a class without running the format checker, for tools that show classes
+/// the VM would reject. Bytes after the class are ignored.
+pub fn parse_class_file(bytes: &ClassBytes, versions: &VersionPolicy) -> Result<JavaClassFileFormat, ClassFormatError> {
+    parse_class(&mut ClassReader::shared(bytes), MethodParsing::Eager, versions)
+}
 
-    let magic = buffer.read_u32::<BigEndian>().unwrap();
-    if magic != 0xCAFEBABE {
-        panic!("Invalid binary type.");
+/// Parses the class file behind `buffer` and runs the format checker over the result.
+/// `buffer` is left just past the class, which must be the end of its bytes.
+/// A class that cannot be parsed yields a single error; otherwise every violation
+/// found is returned.
+pub fn check_class_file(buffer: &mut ClassReader, parsing: MethodParsing, versions: &VersionPolicy) -> Result<JavaClassFileFormat, Vec<ClassFormatError>> {
+    let class = parse_class(buffer, parsing, versions).map_err(|e| vec![e])?;
+
+    let mut errors = Vec::new();
+    if !buffer.is_empty() {
+        errors.push(buffer.error(ClassFormatErrorKind::TrailingBytes));
     }
+    errors.extend(check_format(&class));
 
-    let minor_version = buffer.read_u16::<BigEndian>().unwrap();
-    let major_version = buffer.read_u16::<BigEndian>().unwrap();
-    if major_version < 45 || 61 < major_version {
-        panic!("Invalid binary version.");
+    if errors.is_empty() {
+        return Ok(class);
+    }
+    if let Ok(name) = class.name() {
+        errors = errors.into_iter().map(|e| e.with_class_name(name)).collect();
     }
+    Err(errors)
+}
 
-    let constant_pool_count = buffer.read_u16::<BigEndian>().unwrap();
-    let constant_pool = load_constant_pool(constant_pool_count, &mut buffer);
-    let access_flags = buffer.read_u16::<BigEndian>().unwrap();
-    let this_class = buffer.read_u16::<BigEndian>().unwrap();
-    let super_class = buffer.read_u16::<BigEndian>().unwrap();
-    let interfaces_count = buffer.read_u16::<BigEndian>().unwrap();
-    let interfaces = load_interfaces(interfaces_count, &mut buffer);
-    let fields_count = buffer.read_u16::<BigEndian>().unwrap();
-    let fields = load_fields(fields_count, &mut buffer, &constant_pool);
-    let methods_count = buffer.read_u16::<BigEndian>().unwrap();
-    let methods = load_methods(methods_count, &mut buffer, &constant_pool);
-    let attributes_count = buffer.read_u16::<BigEndian>().unwrap();
-    let attributes = load_attributes(attributes_count, &mut buffer, &constant_pool);
-
-    JavaClassFileFormat {
+fn parse_class(buffer: &mut ClassReader, parsing: MethodParsing, versions: &VersionPolicy) -> Result<JavaClassFileFormat, ClassFormatError> {
+    let magic = buffer.read_u32()?;
+    if magic != 0xCAFEBABE {
+        return Err(ClassFormatError::new(ClassFormatErrorKind::InvalidMagic(magic), 0));
+    }
+
+    let minor_version = buffer.read_u16()?;
+    let major_version = buffer.read_u16()?;
+    versions.check(major_version, minor_version).map_err(|kind| ClassFormatError::new(kind, 4))?;
+
+    let constant_pool_count = buffer.read_u16()?;
+    let constant_pool = load_constant_pool(constant_pool_count, major_version, buffer)?;
+    let access_flags = ClassAccessFlags(buffer.read_u16()?);
+    let this_class = buffer.read_u16()?;
+    let class_name = constant_pool.class_name(this_class).ok().map(str::to_string);
+
+    // Errors past this point can be reported against the class being defined.
+    let named = |e: ClassFormatError| match &class_name {
+        Some(name) => e.with_class_name(name),
+        None => e,
+    };
+
+    let super_class = buffer.read_u16().map_err(named)?;
+    let interfaces_count = buffer.read_u16().map_err(named)?;
+    let interfaces = load_interfaces(interfaces_count, buffer).map_err(named)?;
+    let fields_count = buffer.read_u16().map_err(named)?;
+    let fields = load_fields(fields_count, buffer, &constant_pool).map_err(named)?;
+    let methods_count = buffer.read_u16().map_err(named)?;
+    let methods = load_methods(methods_count, buffer, &constant_pool, parsing).map_err(named)?;
+    let attributes_count = buffer.read_u16().map_err(named)?;
+    let attributes = load_attributes(attributes_count, buffer, &constant_pool).map_err(named)?;
+
+    Ok(JavaClassFileFormat {
         minor_version,
         major_version,
         constant_pool_count,
@@ -68,15 +119,15 @@ fn _define_class(bytes: &[u8]) -> JavaClassFileFormat {
         methods,
         attributes_count,
         attributes,
-    }
+    })
 }
 
-fn load_interfaces(count: u16, buffer: &mut &[u8]) -> Vec<u16> {
+fn load_interfaces(count: u16, buffer: &mut ClassReader) -> Result<Vec<u16>, ClassFormatError> {
     let mut interfaces = Vec::new();
 
     for _ in 0..count {
-        interfaces.push(buffer.read_u16::<BigEndian>().unwrap());
+        interfaces.push(buffer.read_u16()?);
     }
 
-    interfaces
+    Ok(interfaces)
 }
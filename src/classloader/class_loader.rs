@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::classloader::bootstrap_class_loader::{_find_class, define_class};
+use crate::classloader::class_loading_error::ClassLoadingError;
+use crate::classloader::class_path::ClassPath;
+use crate::classloader::runtime_class::RuntimeClass;
+use crate::classloader::version_policy::VersionPolicy;
+use crate::entity::class_bytes::ClassBytes;
+use crate::entity::java_class_file::JavaClassFileFormat;
+use crate::entity::method_info::MethodParsing;
+
+// The class loaders of a VM (JVMS 5.3). A runtime class is identified by its binary
+// name together with its defining loader, so two loaders may each define a class of
+// the same name. The bootstrap, platform and application loaders are built in and
+// search their own class path after delegating to their parent; user-defined loaders
+// get classes only through `define_class`, which the natives of
+// `java/lang/ClassLoader` call on their behalf.
+//
+// Every loader records the classes it is an initiating loader of: those it defined
+// and those a parent loaded when asked through it. `load_class` answers from that
+// record first, so a name resolves to the same class each time through one loader.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ClassLoaderId(usize);
+
+impl ClassLoaderId {
+    pub const BOOTSTRAP: ClassLoaderId = ClassLoaderId(0);
+    pub const PLATFORM: ClassLoaderId = ClassLoaderId(1);
+    pub const APPLICATION: ClassLoaderId = ClassLoaderId(2);
+
+    pub fn is_user_defined(self) -> bool {
+        self.0 > ClassLoaderId::APPLICATION.0
+    }
+}
+
+/// Named the way HotSpot names loaders in error messages.
+impl fmt::Display for ClassLoaderId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            ClassLoaderId::BOOTSTRAP => write!(f, "bootstrap"),
+            ClassLoaderId::PLATFORM => write!(f, "platform"),
+            ClassLoaderId::APPLICATION => write!(f, "app"),
+            ClassLoaderId(id) => write!(f, "loader#{}", id),
+        }
+    }
+}
+
+struct ClassLoader {
+    parent: Option<ClassLoaderId>,
+    class_path: ClassPath,
+    /// Classes this loader is the defining loader of.
+    defined: HashMap<String, Arc<RuntimeClass>>,
+    /// The defining loader of every class this loader is an initiating loader of.
+    initiated: HashMap<String, ClassLoaderId>,
+}
+
+impl ClassLoader {
+    fn new(parent: Option<ClassLoaderId>, class_path: ClassPath) -> Self {
+        ClassLoader { parent, class_path, defined: HashMap::new(), initiated: HashMap::new() }
+    }
+}
+
+pub struct ClassLoaders {
+    loaders: Vec<ClassLoader>,
+    versions: VersionPolicy,
+    parsing: MethodParsing,
+}
+
+impl ClassLoaders {
+    /// Every class is parsed with `parsing`; lazily parsed ones can be shared between threads
+    /// before their methods are decoded.
+    pub fn new(boot_class_path: ClassPath, platform_class_path: ClassPath, application_class_path: ClassPath, versions: VersionPolicy, parsing: MethodParsing) -> Self {
+        let loaders = vec![
+            ClassLoader::new(None, boot_class_path),
+            ClassLoader::new(Some(ClassLoaderId::BOOTSTRAP), platform_class_path),
+            ClassLoader::new(Some(ClassLoaderId::PLATFORM), application_class_path),
+        ];
+        ClassLoaders { loaders, versions, parsing }
+    }
+
+    /// Registers a Java class loader object with the VM. `parent` is `None` when the
+    /// Java parent is `null`, which means the bootstrap loader.
+    pub fn create_user_defined(&mut self, parent: Option<ClassLoaderId>) -> ClassLoaderId {
+        self.loaders.push(ClassLoader::new(Some(parent.unwrap_or(ClassLoaderId::BOOTSTRAP)), ClassPath::default()));
+        ClassLoaderId(self.loaders.len() - 1)
+    }
+
+    pub fn parent(&self, loader: ClassLoaderId) -> Option<ClassLoaderId> {
+        self.loaders[loader.0].parent
+    }
+
+    /// Loads `name` (in internal form) with `loader` as the initiating loader: the parent
+    /// is asked first, and only a class it cannot find is looked for by `loader` itself.
+    pub fn load_class(&mut self, loader: ClassLoaderId, name: &str) -> Result<Arc<RuntimeClass>, ClassLoadingError> {
+        if let Some(class) = self.find_loaded_class(loader, name) {
+            return Ok(class);
+        }
+
+        let class = match self.parent(loader) {
+            Some(parent) => match self.load_class(parent, name) {
+                Err(ClassLoadingError::NotFound(_)) => self.find_class(loader, name)?,
+                result => result?,
+            },
+            None => self.find_class(loader, name)?,
+        };
+        self.loaders[loader.0].initiated.insert(name.to_string(), class.defining_loader);
+        Ok(class)
+    }
+
+    /// The class `loader` is recorded as an initiating loader of, if any.
+    pub fn find_loaded_class(&self, loader: ClassLoaderId, name: &str) -> Option<Arc<RuntimeClass>> {
+        let defining_loader = *self.loaders[loader.0].initiated.get(name)?;
+        self.loaders[defining_loader.0].defined.get(name).cloned()
+    }
+
+    pub fn is_initiating_loader(&self, loader: ClassLoaderId, name: &str) -> bool {
+        self.loaders[loader.0].initiated.contains_key(name)
+    }
+
+    /// Defines a class with `loader` as its defining loader (JVMS 5.3.5). `name` is the
+    /// name it was requested by, if any. A loader may not define a name twice, nor one
+    /// it already loaded through its parent.
+    pub fn define_class(&mut self, loader: ClassLoaderId, name: Option<&str>, bytes: &ClassBytes) -> Result<Arc<RuntimeClass>, ClassLoadingError> {
+        let duplicate = |name: &str| ClassLoadingError::DuplicateDefinition { loader: loader.to_string(), name: name.to_string() };
+        if let Some(name) = name.filter(|name| self.is_initiating_loader(loader, name)) {
+            return Err(duplicate(name));
+        }
+
+        let class_file = define_class(name, bytes, self.parsing, &self.versions)?;
+        let class_name = class_file.name()?.to_string();
+        if self.is_initiating_loader(loader, &class_name) {
+            return Err(duplicate(&class_name));
+        }
+
+        Ok(self.record_defined(loader, class_name, class_file))
+    }
+
+    /// Looks for `name` in the class path of a built-in loader and defines it there.
+    /// User-defined loaders find classes in Java code, which hands them to `define_class`.
+    fn find_class(&mut self, loader: ClassLoaderId, name: &str) -> Result<Arc<RuntimeClass>, ClassLoadingError> {
+        if loader.is_user_defined() {
+            return Err(ClassLoadingError::NotFound(name.to_string()));
+        }
+
+        let class_file = _find_class(name, &self.loaders[loader.0].class_path, self.parsing, &self.versions)?;
+        Ok(self.record_defined(loader, name.to_string(), class_file))
+    }
+
+    fn record_defined(&mut self, loader: ClassLoaderId, name: String, class_file: Arc<JavaClassFileFormat>) -> Arc<RuntimeClass> {
+        let class = Arc::new(RuntimeClass::new(loader, class_file));
+        let class_loader = &mut self.loaders[loader.0];
+        class_loader.defined.insert(name.clone(), class.clone());
+        class_loader.initiated.insert(name, loader);
+        class
+    }
+}
+
+impl Default for ClassLoaders {
+    fn default() -> Self {
+        ClassLoaders::new(ClassPath::default(), ClassPath::default(), ClassPath::default(), VersionPolicy::default(), MethodParsing::Eager)
+    }
+}
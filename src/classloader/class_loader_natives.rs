@@ -0,0 +1,131 @@
+use std::sync::Arc;
+
+use crate::classloader::class_loader::{ClassLoaderId, ClassLoaders};
+use crate::classloader::class_loading_error::ClassLoadingError;
+use crate::classloader::native_error::NativeError;
+use crate::classloader::runtime_class::RuntimeClass;
+use crate::entity::class_bytes::ClassBytes;
+
+// The natives of `java/lang/ClassLoader` that back `defineClass`, `findLoadedClass`
+// and the bootstrap lookup of `loadClass`. A `findClass` override in a Java class
+// loader reads the bytes itself and reaches the VM through `defineClass`. Names
+// arrive as Java binary names (`com.acme.Foo`) and `null` as `None`; the receiver or
+// `loader` argument is the `ClassLoaderId` the loader object was registered under.
+//
+// The interpreter finds them with `find_native` when it invokes a native method, and
+// registers every `ClassLoader` object with `ClassLoaders::create_user_defined` as
+// the object is constructed.
+
+pub const CLASS_LOADER: &str = "java/lang/ClassLoader";
+
+/// An argument or result of a native as the interpreter passes it.
+#[derive(Debug, Clone)]
+pub enum NativeValue {
+    Null,
+    Int(i32),
+    String(String),
+    ByteArray(ClassBytes),
+    ClassLoader(ClassLoaderId),
+    Class(Arc<RuntimeClass>),
+    /// An object the natives only pass along, such as a `ProtectionDomain`.
+    Opaque,
+}
+
+/// Takes the arguments in declaration order, preceded by the receiver for instance methods.
+pub type NativeMethod = fn(&mut ClassLoaders, &[NativeValue]) -> Result<NativeValue, NativeError>;
+
+const NATIVES: &[(&str, &str, NativeMethod)] = &[
+    (
+        "defineClass1",
+        "(Ljava/lang/ClassLoader;Ljava/lang/String;[BIILjava/security/ProtectionDomain;Ljava/lang/String;)Ljava/lang/Class;",
+        invoke_define_class1,
+    ),
+    ("findBootstrapClass", "(Ljava/lang/String;)Ljava/lang/Class;", invoke_find_bootstrap_class),
+    ("findLoadedClass0", "(Ljava/lang/String;)Ljava/lang/Class;", invoke_find_loaded_class0),
+];
+
+/// The implementation of native method `class_name.name descriptor`, if it is one of these.
+pub fn find_native(class_name: &str, name: &str, descriptor: &str) -> Option<NativeMethod> {
+    if class_name != CLASS_LOADER {
+        return None;
+    }
+    NATIVES.iter()
+        .find(|(native_name, native_descriptor, _)| *native_name == name && *native_descriptor == descriptor)
+        .map(|&(_, _, native)| native)
+}
+
+/// `static native Class<?> defineClass1(ClassLoader loader, String name, byte[] b,
+/// int off, int len, ProtectionDomain pd, String source)`. `bytes` is `b[off..off + len]`.
+pub fn define_class1(class_loaders: &mut ClassLoaders, loader: ClassLoaderId, name: Option<&str>, bytes: &[u8]) -> Result<Arc<RuntimeClass>, ClassLoadingError> {
+    let name = name.map(internal_name);
+    class_loaders.define_class(loader, name.as_deref(), &ClassBytes::from(bytes))
+}
+
+/// `static native Class<?> findBootstrapClass(String name)`: `None` when the bootstrap
+/// loader cannot find the class.
+pub fn find_bootstrap_class(class_loaders: &mut ClassLoaders, name: &str) -> Result<Option<Arc<RuntimeClass>>, ClassLoadingError> {
+    match class_loaders.load_class(ClassLoaderId::BOOTSTRAP, &internal_name(name)) {
+        Ok(class) => Ok(Some(class)),
+        Err(ClassLoadingError::NotFound(_)) => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+/// `final native Class<?> findLoadedClass0(String name)`.
+pub fn find_loaded_class0(class_loaders: &ClassLoaders, loader: ClassLoaderId, name: &str) -> Option<Arc<RuntimeClass>> {
+    class_loaders.find_loaded_class(loader, &internal_name(name))
+}
+
+fn invoke_define_class1(class_loaders: &mut ClassLoaders, arguments: &[NativeValue]) -> Result<NativeValue, NativeError> {
+    let loader = loader_argument(&arguments[0]);
+    let name = string_argument(&arguments[1]);
+    let b = match &arguments[2] {
+        NativeValue::ByteArray(b) => b,
+        NativeValue::Null => return Err(NativeError::NullPointer("defineClass1: b is null".to_string())),
+        argument => panic!("expected a byte[] but got {:?}", argument),
+    };
+    let (off, len) = (int_argument(&arguments[3]), int_argument(&arguments[4]));
+    let bytes = usize::try_from(off).ok()
+        .zip(usize::try_from(len).ok())
+        .and_then(|(off, len)| b.get(off..off.checked_add(len)?))
+        .ok_or_else(|| NativeError::IndexOutOfBounds(format!("Array region {}..{} out of bounds for length {}", off, i64::from(off) + i64::from(len), b.len())))?;
+    Ok(NativeValue::Class(define_class1(class_loaders, loader, name, bytes)?))
+}
+
+fn invoke_find_bootstrap_class(class_loaders: &mut ClassLoaders, arguments: &[NativeValue]) -> Result<NativeValue, NativeError> {
+    let name = string_argument(&arguments[0]).ok_or_else(|| NativeError::NullPointer("findBootstrapClass: name is null".to_string()))?;
+    Ok(find_bootstrap_class(class_loaders, name)?.map_or(NativeValue::Null, NativeValue::Class))
+}
+
+fn invoke_find_loaded_class0(class_loaders: &mut ClassLoaders, arguments: &[NativeValue]) -> Result<NativeValue, NativeError> {
+    let loader = loader_argument(&arguments[0]);
+    let name = string_argument(&arguments[1]).ok_or_else(|| NativeError::NullPointer("findLoadedClass0: name is null".to_string()))?;
+    Ok(find_loaded_class0(class_loaders, loader, name).map_or(NativeValue::Null, NativeValue::Class))
+}
+
+/// The descriptors above guarantee the kinds of arguments; anything else is a bug in the caller.
+fn loader_argument(argument: &NativeValue) -> ClassLoaderId {
+    match argument {
+        NativeValue::ClassLoader(loader) => *loader,
+        argument => panic!("expected a registered ClassLoader but got {:?}", argument),
+    }
+}
+
+fn int_argument(argument: &NativeValue) -> i32 {
+    match argument {
+        NativeValue::Int(value) => *value,
+        argument => panic!("expected an int but got {:?}", argument),
+    }
+}
+
+fn string_argument(argument: &NativeValue) -> Option<&str> {
+    match argument {
+        NativeValue::String(string) => Some(string),
+        NativeValue::Null => None,
+        argument => panic!("expected a String but got {:?}", argument),
+    }
+}
+
+fn internal_name(binary_name: &str) -> String {
+    binary_name.replace('.', "/")
+}
@@ -0,0 +1,52 @@
+use std::fmt;
+
+use crate::entity::class_format_error::ClassFormatError;
+
+/// Why a class could not be loaded. A class that is not found surfaces as
+/// `NoClassDefFoundError` when the VM resolves a reference to it, and as
+/// `ClassNotFoundException` when it is asked for by name, e.g. by `loadClass`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClassLoadingError {
+    NotFound(String),
+    Unreadable { location: String, message: String },
+    /// The class file found for `requested` declares itself as `found`.
+    WrongName { requested: String, found: String },
+    /// `loader` already is an initiating loader of `name`.
+    DuplicateDefinition { loader: String, name: String },
+    Format(ClassFormatError),
+}
+
+impl ClassLoadingError {
+    /// Binary name of the Java exception the VM should throw when resolving a class fails.
+    pub fn java_exception_name(&self) -> &'static str {
+        match self {
+            ClassLoadingError::NotFound(_)
+            | ClassLoadingError::Unreadable { .. }
+            | ClassLoadingError::WrongName { .. } => "java/lang/NoClassDefFoundError",
+            ClassLoadingError::DuplicateDefinition { .. } => "java/lang/LinkageError",
+            ClassLoadingError::Format(error) => error.java_exception_name(),
+        }
+    }
+}
+
+impl From<ClassFormatError> for ClassLoadingError {
+    fn from(error: ClassFormatError) -> Self {
+        ClassLoadingError::Format(error)
+    }
+}
+
+impl fmt::Display for ClassLoadingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClassLoadingError::NotFound(name) => write!(f, "{}", name),
+            ClassLoadingError::Unreadable { location, message } => write!(f, "{}: {}", location, message),
+            ClassLoadingError::WrongName { requested, found } => write!(f, "{} (wrong name: {})", requested, found),
+            ClassLoadingError::DuplicateDefinition { loader, name } => {
+                write!(f, "loader '{}' attempted duplicate class definition for {}", loader, name.replace('/', "."))
+            }
+            ClassLoadingError::Format(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for ClassLoadingError {}
@@ -0,0 +1,79 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::classloader::class_loading_error::ClassLoadingError;
+use crate::classloader::zip_archive::ZipArchive;
+use crate::entity::class_bytes::ClassBytes;
+
+// The class path: entries are searched in order for `<binary name>.class`, and the
+// first one holding it wins. An entry is either a directory tree of class files or
+// an archive, opened once when the class path is built; entries that do not exist
+// or cannot be opened are skipped, as the JDK does.
+
+#[derive(Clone, Default)]
+pub struct ClassPath {
+    entries: Vec<ClassPathEntry>,
+}
+
+#[derive(Clone)]
+enum ClassPathEntry {
+    Directory(PathBuf),
+    Archive { path: String, archive: Arc<ZipArchive> },
+}
+
+impl ClassPath {
+    pub fn new(entries: &[String]) -> Self {
+        let entries = entries.iter()
+            .filter_map(|entry| {
+                let path = PathBuf::from(entry);
+                if path.is_dir() {
+                    Some(ClassPathEntry::Directory(path))
+                } else if path.is_file() {
+                    let archive = ZipArchive::open(entry).ok()?;
+                    Some(ClassPathEntry::Archive { path: entry.clone(), archive: Arc::new(archive) })
+                } else {
+                    None
+                }
+            })
+            .collect();
+        ClassPath { entries }
+    }
+
+    /// `binary_name` is in internal form, e.g. `com/acme/Foo`.
+    pub fn find(&self, binary_name: &str) -> Result<ClassBytes, ClassLoadingError> {
+        let not_found = || ClassLoadingError::NotFound(binary_name.to_string());
+        if !is_class_file_name(binary_name) {
+            return Err(not_found());
+        }
+        let relative_path = format!("{}.class", binary_name);
+
+        for entry in &self.entries {
+            match entry {
+                ClassPathEntry::Directory(directory) => {
+                    let path = directory.join(&relative_path);
+                    if path.is_file() {
+                        let bytes = fs::read(&path)
+                            .map_err(|e| ClassLoadingError::Unreadable { location: path.display().to_string(), message: e.to_string() })?;
+                        return Ok(ClassBytes::from(bytes));
+                    }
+                }
+                ClassPathEntry::Archive { path, archive } => {
+                    let bytes = archive.read(&relative_path)
+                        .map_err(|message| ClassLoadingError::Unreadable { location: path.clone(), message })?;
+                    if let Some(bytes) = bytes {
+                        return Ok(bytes);
+                    }
+                }
+            }
+        }
+
+        Err(not_found())
+    }
+}
+
+/// Whether `binary_name` can name a class file at all. Array classes are created by
+/// the VM, and `.` or empty segments would let a name escape the class path entry.
+fn is_class_file_name(binary_name: &str) -> bool {
+    binary_name.split('/').all(|segment| !segment.is_empty() && !segment.contains(['.', ';', '[', '\\']))
+}
@@ -0,0 +1,475 @@
+use std::collections::HashSet;
+
+use crate::entity::java_class_file::JavaClassFileFormat;
+use crate::entity::constant_pool::{ConstantKind, ConstantPool};
+use crate::entity::attribute_info::AttributeKind;
+use crate::entity::class_format_error::*;
+use crate::entity::descriptor::*;
+use crate::entity::method_info::MethodInfo;
+
+// Format checking (JVMS 4.8) of a class that has already been parsed. Parsing stops
+// at the first malformed byte, but once the structure is known every remaining
+// violation is collected so they can be reported together.
+
+pub fn check_format(class: &JavaClassFileFormat) -> Vec<ClassFormatError> {
+    let mut checker = FormatChecker {
+        class,
+        constant_pool: &class.constant_pool,
+        errors: Vec::new(),
+    };
+
+    checker.check_constant_pool();
+    checker.check_header();
+    checker.check_fields();
+    checker.check_methods();
+    checker.check_attributes(&class.attributes, Context::Class);
+
+    checker.errors
+}
+
+#[derive(Clone, Copy)]
+enum Context<'a> {
+    Class,
+    Field(Option<&'a FieldType>),
+    Method,
+    Code,
+    RecordComponent,
+}
+
+struct FormatChecker<'a> {
+    class: &'a JavaClassFileFormat,
+    constant_pool: &'a ConstantPool,
+    errors: Vec<ClassFormatError>,
+}
+
+impl<'a> FormatChecker<'a> {
+    fn report<T>(&mut self, result: Result<T, ClassFormatError>) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(e) => {
+                self.errors.push(e);
+                None
+            }
+        }
+    }
+
+    fn error(&mut self, kind: ClassFormatErrorKind, index: u16) {
+        self.errors.push(ClassFormatError::from(kind).with_constant_pool_index(index));
+    }
+
+    fn utf8(&mut self, index: u16) -> Option<&'a str> {
+        let constant_pool = self.constant_pool;
+        self.report(constant_pool.utf8(index))
+    }
+
+    fn class_name(&mut self, index: u16) -> Option<&'a str> {
+        let constant_pool = self.constant_pool;
+        self.report(constant_pool.class_name(index))
+    }
+
+    fn optional_class_name(&mut self, index: u16) {
+        if index != 0 {
+            self.class_name(index);
+        }
+    }
+
+    fn name(&mut self, index: u16, is_valid: fn(&str) -> bool) -> Option<&'a str> {
+        let name = self.utf8(index)?;
+        if !is_valid(name) {
+            self.error(ClassFormatErrorKind::InvalidName(name.to_string()), index);
+            return None;
+        }
+        Some(name)
+    }
+
+    fn field_descriptor(&mut self, index: u16) -> Option<FieldType> {
+        let descriptor = self.utf8(index)?;
+        self.report(FieldType::parse(descriptor).map_err(|e| e.with_constant_pool_index(index)))
+    }
+
+    fn method_descriptor(&mut self, index: u16) -> Option<MethodDescriptor> {
+        let descriptor = self.utf8(index)?;
+        self.report(MethodDescriptor::parse(descriptor).map_err(|e| e.with_constant_pool_index(index)))
+    }
+
+    fn expect(&mut self, matches: bool, expected: &'static str, index: u16) {
+        if !matches {
+            self.error(ClassFormatErrorKind::UnexpectedConstantPoolEntry { expected }, index);
+        }
+    }
+
+    /// JVMS 4.4: every index inside a constant must point at an entry of the right kind.
+    fn check_constant_pool(&mut self) {
+        let constant_pool = self.constant_pool;
+        let num_bootstrap_methods = self.class.attributes.iter()
+            .find_map(|attribute| match attribute {
+                AttributeKind::BootstrapMethods(bootstrap_methods) => Some(bootstrap_methods.bootstrap_methods.len()),
+                _ => None,
+            })
+            .unwrap_or(0);
+
+        for (index, constant) in constant_pool.entries().iter().enumerate() {
+            let index = index as u16;
+            match constant {
+                ConstantKind::Class(class_info) => {
+                    // Array classes are named by their descriptor.
+                    if let Some(name) = self.utf8(class_info.name_index) {
+                        let valid = if name.starts_with('[') {
+                            FieldType::parse(name).is_ok()
+                        } else {
+                            is_valid_binary_name(name)
+                        };
+                        if !valid {
+                            self.error(ClassFormatErrorKind::InvalidName(name.to_string()), class_info.name_index);
+                        }
+                    }
+                }
+                ConstantKind::String(string_info) => {
+                    self.utf8(string_info.string_index);
+                }
+                ConstantKind::Fieldref(fieldref_info) => {
+                    self.class_name(fieldref_info.class_index);
+                    self.check_name_and_type(fieldref_info.name_and_type_index, false, index);
+                }
+                ConstantKind::Methodref(methodref_info) => {
+                    self.class_name(methodref_info.class_index);
+                    self.check_name_and_type(methodref_info.name_and_type_index, true, index);
+                }
+                ConstantKind::InterfaceMethodref(interface_methodref_info) => {
+                    self.class_name(interface_methodref_info.class_index);
+                    if let Some("<init>") = self.check_name_and_type(interface_methodref_info.name_and_type_index, true, index) {
+                        self.error(ClassFormatErrorKind::InvalidName("<init>".to_string()), index);
+                    }
+                }
+                ConstantKind::NameAndType(name_and_type_info) => {
+                    self.utf8(name_and_type_info.name_index);
+                    self.utf8(name_and_type_info.descriptor_index);
+                }
+                ConstantKind::MethodHandle(method_handle_info) => self.check_method_handle(
+                    method_handle_info.reference_kind,
+                    method_handle_info.reference_index,
+                    index,
+                ),
+                ConstantKind::MethodType(method_type_info) => {
+                    self.method_descriptor(method_type_info.descriptor_index);
+                }
+                ConstantKind::Dynamic(dynamic_info) => {
+                    self.check_bootstrap_method_index(dynamic_info.bootstrap_method_attr_index, num_bootstrap_methods, index);
+                    if let Some((_, descriptor)) = self.report(constant_pool.name_and_type(dynamic_info.name_and_type_index)) {
+                        self.report(FieldType::parse(descriptor).map_err(|e| e.with_constant_pool_index(index)));
+                    }
+                }
+                ConstantKind::InvokeDynamic(invoke_dynamic_info) => {
+                    self.check_bootstrap_method_index(invoke_dynamic_info.bootstrap_method_attr_index, num_bootstrap_methods, index);
+                    if let Some((_, descriptor)) = self.report(constant_pool.name_and_type(invoke_dynamic_info.name_and_type_index)) {
+                        self.report(MethodDescriptor::parse(descriptor).map_err(|e| e.with_constant_pool_index(index)));
+                    }
+                }
+                ConstantKind::Module(module_info) => {
+                    self.utf8(module_info.name_index);
+                }
+                ConstantKind::Package(package_info) => {
+                    self.name(package_info.name_index, is_valid_binary_name);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Checks the NameAndType of a field or method reference and returns the referenced name.
+    fn check_name_and_type(&mut self, name_and_type_index: u16, is_method: bool, index: u16) -> Option<&'a str> {
+        let constant_pool = self.constant_pool;
+        let (name, descriptor) = self.report(constant_pool.name_and_type(name_and_type_index))?;
+
+        if is_method {
+            if !is_valid_method_name(name) || name == "<clinit>" {
+                self.error(ClassFormatErrorKind::InvalidName(name.to_string()), index);
+            }
+            let descriptor = self.report(MethodDescriptor::parse(descriptor).map_err(|e| e.with_constant_pool_index(index)))?;
+            if name == "<init>" && descriptor.return_type.is_some() {
+                self.error(ClassFormatErrorKind::InvalidName(name.to_string()), index);
+            }
+        } else {
+            if !is_valid_unqualified_name(name) {
+                self.error(ClassFormatErrorKind::InvalidName(name.to_string()), index);
+            }
+            self.report(FieldType::parse(descriptor).map_err(|e| e.with_constant_pool_index(index)))?;
+        }
+
+        Some(name)
+    }
+
+    /// JVMS 4.4.8
+    fn check_method_handle(&mut self, reference_kind: u8, reference_index: u16, index: u16) {
+        let constant_pool = self.constant_pool;
+        let constant = match self.report(constant_pool.get(reference_index)) {
+            Some(constant) => constant,
+            None => return,
+        };
+
+        let (matches, expected) = match reference_kind {
+            // REF_getField, REF_getStatic, REF_putField, REF_putStatic
+            1..=4 => (matches!(constant, ConstantKind::Fieldref(_)), "Fieldref"),
+            // REF_invokeVirtual, REF_newInvokeSpecial
+            5 | 8 => (matches!(constant, ConstantKind::Methodref(_)), "Methodref"),
+            // REF_invokeStatic, REF_invokeSpecial
+            6 | 7 if self.class.major_version < 52 => (matches!(constant, ConstantKind::Methodref(_)), "Methodref"),
+            6 | 7 => (
+                matches!(constant, ConstantKind::Methodref(_) | ConstantKind::InterfaceMethodref(_)),
+                "Methodref or InterfaceMethodref",
+            ),
+            // REF_invokeInterface
+            9 => (matches!(constant, ConstantKind::InterfaceMethodref(_)), "InterfaceMethodref"),
+            _ => {
+                self.error(ClassFormatErrorKind::InvalidMethodHandleKind(reference_kind), index);
+                return;
+            }
+        };
+        self.expect(matches, expected, reference_index);
+
+        if (5..=9).contains(&reference_kind) {
+            if let Ok((_, name, _)) = constant_pool.member_ref(reference_index) {
+                let is_init = name == "<init>";
+                if (reference_kind == 8) != is_init || name == "<clinit>" {
+                    self.error(ClassFormatErrorKind::InvalidName(name.to_string()), index);
+                }
+            }
+        }
+    }
+
+    fn check_bootstrap_method_index(&mut self, bootstrap_method_attr_index: u16, num_bootstrap_methods: usize, index: u16) {
+        if bootstrap_method_attr_index as usize >= num_bootstrap_methods {
+            self.error(ClassFormatErrorKind::InvalidBootstrapMethodIndex(bootstrap_method_attr_index), index);
+        }
+    }
+
+    /// JVMS 4.1: this_class, super_class and interfaces, and the class's own access flags.
+    fn check_header(&mut self) {
+        let class = self.class;
+        let access_flags = class.access_flags;
+        self.report(access_flags.check(class.major_version));
+
+        let name = self.class_name(class.this_class);
+        if class.super_class == 0 {
+            if name != Some("java/lang/Object") && !access_flags.is_module() {
+                self.error(ClassFormatErrorKind::InvalidSuperclass, 0);
+            }
+        } else if let Some(super_name) = self.class_name(class.super_class) {
+            if access_flags.is_interface() && super_name != "java/lang/Object" {
+                self.error(ClassFormatErrorKind::InvalidSuperclass, class.super_class);
+            }
+        }
+
+        for &interface in &class.interfaces {
+            self.class_name(interface);
+        }
+    }
+
+    /// JVMS 4.5
+    fn check_fields(&mut self) {
+        let class = self.class;
+        let in_interface = class.access_flags.is_interface();
+        let mut seen = HashSet::new();
+
+        for field in &class.fields {
+            self.report(field.access_flags.check(in_interface).map_err(|e| e.with_constant_pool_index(field.name_index)));
+
+            let name = self.name(field.name_index, is_valid_unqualified_name);
+            let field_type = self.field_descriptor(field.descriptor_index);
+            if let (Some(name), Some(descriptor)) = (name, self.constant_pool.utf8(field.descriptor_index).ok()) {
+                if !seen.insert((name, descriptor)) {
+                    let kind = ClassFormatErrorKind::DuplicateField { name: name.to_string(), descriptor: descriptor.to_string() };
+                    self.error(kind, field.name_index);
+                }
+            }
+
+            self.check_attributes(&field.attributes, Context::Field(field_type.as_ref()));
+        }
+    }
+
+    /// JVMS 4.6
+    fn check_methods(&mut self) {
+        let class = self.class;
+        let in_interface = class.access_flags.is_interface();
+        let mut seen = HashSet::new();
+
+        for method in &class.methods {
+            let name = self.name(method.name_index, is_valid_method_name);
+            if let Some(name) = name {
+                self.report(
+                    method.access_flags.check(name, in_interface, class.major_version)
+                        .map_err(|e| e.with_constant_pool_index(method.name_index)),
+                );
+            }
+
+            if let Some(descriptor) = self.method_descriptor(method.descriptor_index) {
+                let takes_arguments = !descriptor.parameters.is_empty() && class.major_version >= 51;
+                let is_initializer = matches!(name, Some("<init>")) || matches!(name, Some("<clinit>"));
+                if is_initializer && descriptor.return_type.is_some() || name == Some("<clinit>") && takes_arguments {
+                    self.error(ClassFormatErrorKind::InvalidName(name.unwrap().to_string()), method.name_index);
+                }
+                if !method.access_flags.is_static() && descriptor.argument_slots() + 1 > 255 {
+                    let kind = ClassFormatErrorKind::InvalidDescriptor(descriptor.to_string());
+                    self.error(kind, method.descriptor_index);
+                }
+            }
+
+            if let (Some(name), Some(descriptor)) = (name, self.constant_pool.utf8(method.descriptor_index).ok()) {
+                if !seen.insert((name, descriptor)) {
+                    let kind = ClassFormatErrorKind::DuplicateMethod { name: name.to_string(), descriptor: descriptor.to_string() };
+                    self.error(kind, method.name_index);
+                }
+            }
+
+            self.check_code_presence(method);
+            if let Some(attributes) = method.attributes.parsed() {
+                self.check_attributes(attributes, Context::Method);
+            }
+        }
+    }
+
+    /// JVMS 4.7.3: exactly one Code attribute unless the method is native or abstract.
+    fn check_code_presence(&mut self, method: &MethodInfo) {
+        let code_count = method.attributes.name_indices().into_iter()
+            .filter(|&name_index| self.constant_pool.utf8(name_index) == Ok("Code"))
+            .count();
+        let needs_code = !method.access_flags.is_abstract() && !method.access_flags.is_native();
+
+        match (needs_code, code_count) {
+            (true, 0) => self.error(ClassFormatErrorKind::MissingCodeAttribute, method.name_index),
+            (true, 1) | (false, 0) => {}
+            _ => self.error(ClassFormatErrorKind::UnexpectedCodeAttribute, method.name_index),
+        }
+    }
+
+    /// Class attributes for features introduced after Java 1.0, which older class
+    /// files must not use (JVMS Table 4.7-B).
+    fn check_attribute_version(&mut self, attribute: &AttributeKind) {
+        let (name, since_major_version) = match attribute {
+            AttributeKind::NestHost(_) => ("NestHost", 55),
+            AttributeKind::NestMembers(_) => ("NestMembers", 55),
+            AttributeKind::Record(_) => ("Record", 60),
+            AttributeKind::PermittedSubclasses(_) => ("PermittedSubclasses", 61),
+            _ => return,
+        };
+
+        let major_version = self.class.major_version;
+        if major_version < since_major_version {
+            self.error(ClassFormatErrorKind::UnsupportedAttribute { name, major_version }, attribute.base().attribute_name_index);
+        }
+    }
+
+    /// Constant pool references held by attributes.
+    fn check_attributes(&mut self, attributes: &'a [AttributeKind], context: Context) {
+        let constant_pool = self.constant_pool;
+
+        for attribute in attributes {
+            if let Context::Class = context {
+                self.check_attribute_version(attribute);
+            }
+            match attribute {
+                AttributeKind::ConstantValue(constant_value) => {
+                    if let Context::Field(field_type) = context {
+                        self.check_constant_value(constant_value.constant_value_index, field_type);
+                    }
+                }
+                AttributeKind::Code(code) => {
+                    for entry in &code.exception_table {
+                        self.optional_class_name(entry.catch_type);
+                    }
+                    self.check_attributes(&code.attributes, Context::Code);
+                }
+                AttributeKind::Exceptions(exceptions) => {
+                    for &exception in &exceptions.exception_index_table {
+                        self.class_name(exception);
+                    }
+                }
+                AttributeKind::InnerClasses(inner_classes) => {
+                    for inner_class in &inner_classes.classes {
+                        self.class_name(inner_class.inner_class_info_index);
+                        self.optional_class_name(inner_class.outer_class_info_index);
+                        if inner_class.inner_name_index != 0 {
+                            self.utf8(inner_class.inner_name_index);
+                        }
+                        self.report(
+                            inner_class.inner_class_access_flags.check()
+                                .map_err(|e| e.with_constant_pool_index(inner_class.inner_class_info_index)),
+                        );
+                    }
+                }
+                AttributeKind::EnclosingMethod(enclosing_method) => {
+                    self.class_name(enclosing_method.class_index);
+                    if enclosing_method.method_index != 0 {
+                        self.report(constant_pool.name_and_type(enclosing_method.method_index));
+                    }
+                }
+                AttributeKind::Signature(signature) => {
+                    let result = match context {
+                        Context::Class => signature.class_signature(constant_pool).map(|_| ()),
+                        Context::Method => signature.method_signature(constant_pool).map(|_| ()),
+                        _ => signature.field_signature(constant_pool).map(|_| ()),
+                    };
+                    self.report(result);
+                }
+                AttributeKind::SourceFile(source_file) => {
+                    self.utf8(source_file.sourcefile_index);
+                }
+                AttributeKind::BootstrapMethods(bootstrap_methods) => {
+                    for bootstrap_method in &bootstrap_methods.bootstrap_methods {
+                        let is_method_handle = matches!(constant_pool.get(bootstrap_method.bootstrap_method_ref), Ok(ConstantKind::MethodHandle(_)));
+                        self.expect(is_method_handle, "MethodHandle", bootstrap_method.bootstrap_method_ref);
+                        for &argument in &bootstrap_method.bootstrap_arguments {
+                            self.report(constant_pool.get(argument));
+                        }
+                    }
+                }
+                AttributeKind::NestHost(nest_host) => {
+                    self.class_name(nest_host.host_class_index);
+                }
+                AttributeKind::NestMembers(nest_members) => {
+                    for &class in &nest_members.classes {
+                        self.class_name(class);
+                    }
+                }
+                AttributeKind::PermittedSubclasses(permitted_subclasses) => {
+                    for &class in &permitted_subclasses.classes {
+                        self.class_name(class);
+                    }
+                }
+                AttributeKind::Record(record) => {
+                    for component in &record.components {
+                        self.name(component.name_index, is_valid_unqualified_name);
+                        self.field_descriptor(component.descriptor_index);
+                        self.check_attributes(&component.attributes, Context::RecordComponent);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// JVMS 4.7.2: the constant must match the field's type.
+    fn check_constant_value(&mut self, index: u16, field_type: Option<&FieldType>) {
+        let constant_pool = self.constant_pool;
+        let constant = match self.report(constant_pool.get(index)) {
+            Some(constant) => constant,
+            None => return,
+        };
+        let field_type = match field_type {
+            Some(field_type) => field_type,
+            None => return,
+        };
+
+        let (matches, expected) = match field_type {
+            FieldType::Long => (matches!(constant, ConstantKind::Long(_)), "Long"),
+            FieldType::Float => (matches!(constant, ConstantKind::Float(_)), "Float"),
+            FieldType::Double => (matches!(constant, ConstantKind::Double(_)), "Double"),
+            FieldType::Object(class_name) if class_name == "java/lang/String" => {
+                (matches!(constant, ConstantKind::String(_)), "String")
+            }
+            FieldType::Object(_) | FieldType::Array { .. } => return,
+            _ => (matches!(constant, ConstantKind::Integer(_)), "Integer"),
+        };
+        self.expect(matches, expected, index);
+    }
+}
@@ -0,0 +1,58 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+// `META-INF/MANIFEST.MF`. Only the main section is kept: attributes are `Name: value`
+// lines, a line starting with a single space continues the previous one, and the
+// first blank line ends the section. Attribute names are case-insensitive.
+
+pub const MANIFEST_PATH: &str = "META-INF/MANIFEST.MF";
+
+#[derive(Debug, Clone, Default)]
+pub struct JarManifest {
+    main_attributes: HashMap<String, String>,
+}
+
+impl JarManifest {
+    pub fn parse(bytes: &[u8]) -> Result<Self, String> {
+        let text = std::str::from_utf8(bytes).map_err(|_| "manifest is not valid UTF-8".to_string())?;
+
+        let mut lines: Vec<String> = Vec::new();
+        for line in text.split('\n').map(|line| line.strip_suffix('\r').unwrap_or(line)) {
+            if line.is_empty() {
+                break;
+            }
+            match (line.strip_prefix(' '), lines.last_mut()) {
+                (Some(continuation), Some(previous)) => previous.push_str(continuation),
+                (Some(_), None) => return Err("manifest starts with a continuation line".to_string()),
+                (None, _) => lines.push(line.to_string()),
+            }
+        }
+
+        let mut main_attributes = HashMap::new();
+        for line in lines {
+            let (name, value) = line.split_once(": ").ok_or_else(|| format!("invalid manifest header: {}", line))?;
+            main_attributes.entry(name.to_ascii_lowercase()).or_insert_with(|| value.to_string());
+        }
+        Ok(JarManifest { main_attributes })
+    }
+
+    pub fn main_attribute(&self, name: &str) -> Option<&str> {
+        self.main_attributes.get(&name.to_ascii_lowercase()).map(String::as_str)
+    }
+
+    /// The `Main-Class` attribute in internal form (`com/acme/Main`).
+    pub fn main_class(&self) -> Option<String> {
+        self.main_attribute("Main-Class").map(|name| name.trim().replace('.', "/"))
+    }
+
+    /// The `Class-Path` attribute: space-separated URLs, relative ones resolved against
+    /// the directory holding the jar. Only local paths are supported.
+    pub fn class_path(&self, jar_path: &str) -> Vec<String> {
+        let base = Path::new(jar_path).parent().unwrap_or(Path::new(""));
+        self.main_attribute("Class-Path").unwrap_or_default()
+            .split_ascii_whitespace()
+            .map(|url| url.strip_prefix("file:").unwrap_or(url))
+            .map(|path| base.join(path).display().to_string())
+            .collect()
+    }
+}
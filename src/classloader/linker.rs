@@ -0,0 +1,516 @@
+use std::sync::Arc;
+
+use crate::classloader::class_loader::ClassLoaders;
+use crate::classloader::linking_error::LinkingError;
+use crate::classloader::runtime_class::{LinkState, RuntimeClass};
+use crate::classloader::verifier::verify;
+use crate::entity::attribute_info::AttributeKind;
+use crate::entity::class_format_error::{ClassFormatError, ClassFormatErrorKind};
+use crate::entity::constant_pool::ConstantKind;
+use crate::entity::descriptor::{FieldType, MethodDescriptor};
+
+// Linking (JVMS 5.4) and resolution of symbolic references (JVMS 5.4.3).
+//
+// `link` takes a class through verification and preparation once its superclass and
+// superinterfaces are linked. Symbolic references are resolved lazily by `resolve`,
+// the first time an instruction uses them; the outcome, error or not, is cached on
+// the constant pool entry, so later uses neither load nor look anything up again.
+// Call sites and dynamically-computed constants resolve to their bootstrap method
+// handle; the static arguments and the call to the bootstrap method are left to the
+// interpreter.
+
+const JAVA_LANG_OBJECT: &str = "java/lang/Object";
+
+/// What a constant pool entry resolved to.
+#[derive(Debug, Clone)]
+pub enum Resolved {
+    Class(Arc<RuntimeClass>),
+    /// `element` is the element class, unless the elements are of a primitive type.
+    ArrayClass { descriptor: String, element: Option<Arc<RuntimeClass>> },
+    /// `index` is into the `fields` of the class declaring the field.
+    Field { class: Arc<RuntimeClass>, index: usize },
+    /// `index` is into the `methods` of the class declaring the method.
+    Method { class: Arc<RuntimeClass>, index: usize },
+    MethodType(String),
+    MethodHandle { reference_kind: u8, member: Box<Resolved> },
+    CallSite { bootstrap_method: Box<Resolved>, bootstrap_arguments: Vec<u16>, name: String, descriptor: String },
+}
+
+pub fn link(class_loaders: &mut ClassLoaders, class: &Arc<RuntimeClass>) -> Result<(), LinkingError> {
+    link_class(class_loaders, class, &mut Vec::new())
+}
+
+/// `in_progress` holds the classes whose supertypes are being linked; meeting one of
+/// them again means the class is its own superclass or superinterface.
+fn link_class(class_loaders: &mut ClassLoaders, class: &Arc<RuntimeClass>, in_progress: &mut Vec<Arc<RuntimeClass>>) -> Result<(), LinkingError> {
+    match class.link_state() {
+        LinkState::Linked => return Ok(()),
+        LinkState::Failed(error) => return Err(error),
+        _ => {}
+    }
+    if in_progress.iter().any(|other| Arc::ptr_eq(other, class)) {
+        return Err(LinkingError::ClassCircularity(class.name().to_string()));
+    }
+
+    in_progress.push(class.clone());
+    let result = link_supertypes(class_loaders, class, in_progress).and_then(|()| {
+        verify(&class.class_file)?;
+        class.set_link_state(LinkState::Verified);
+        class.prepare()?;
+        class.set_link_state(LinkState::Prepared);
+        Ok(())
+    });
+    in_progress.pop();
+
+    match result {
+        Ok(()) => {
+            class.set_link_state(LinkState::Linked);
+            Ok(())
+        }
+        Err(error) => {
+            class.set_link_state(LinkState::Failed(error.clone()));
+            Err(error)
+        }
+    }
+}
+
+/// JVMS 5.3.5 step 3: the superclass must be a class that is not final, and every
+/// superinterface an interface.
+fn link_supertypes(class_loaders: &mut ClassLoaders, class: &Arc<RuntimeClass>, in_progress: &mut Vec<Arc<RuntimeClass>>) -> Result<(), LinkingError> {
+    if let Some(superclass) = superclass(class_loaders, class)? {
+        if superclass.class_file.access_flags.is_interface() {
+            return Err(LinkingError::IncompatibleClassChange(
+                format!("class {} has interface {} as super class", class.name(), superclass.name()),
+            ));
+        }
+        if superclass.class_file.access_flags.is_final() {
+            return Err(LinkingError::Verify(format!("Cannot inherit from final class {}", superclass.name())));
+        }
+        link_class(class_loaders, &superclass, in_progress)?;
+    }
+
+    for interface in superinterfaces(class_loaders, class)? {
+        if !interface.class_file.access_flags.is_interface() {
+            return Err(LinkingError::IncompatibleClassChange(
+                format!("class {} can not implement {}, because it is not an interface", class.name(), interface.name()),
+            ));
+        }
+        link_class(class_loaders, &interface, in_progress)?;
+    }
+    Ok(())
+}
+
+/// Resolves constant pool entry `index` of `class`, which must be a symbolic reference:
+/// a Class, Fieldref, Methodref, InterfaceMethodref, MethodType, MethodHandle,
+/// Dynamic or InvokeDynamic entry.
+pub fn resolve(class_loaders: &mut ClassLoaders, class: &Arc<RuntimeClass>, index: u16) -> Result<Resolved, LinkingError> {
+    if let Some(result) = class.resolved(index) {
+        return result;
+    }
+    let result = resolve_entry(class_loaders, class, index);
+    class.cache_resolution(index, result)
+}
+
+fn resolve_entry(class_loaders: &mut ClassLoaders, class: &Arc<RuntimeClass>, index: u16) -> Result<Resolved, LinkingError> {
+    let constant_pool = &class.class_file.constant_pool;
+    match constant_pool.get(index)? {
+        ConstantKind::Class(_) => resolve_class_name(class_loaders, class, constant_pool.class_name(index)?),
+        ConstantKind::Fieldref(_) => resolve_field(class_loaders, class, index),
+        ConstantKind::Methodref(_) => resolve_method(class_loaders, class, index),
+        ConstantKind::InterfaceMethodref(_) => resolve_interface_method(class_loaders, class, index),
+        ConstantKind::MethodType(method_type_info) => {
+            let descriptor = constant_pool.utf8(method_type_info.descriptor_index)?;
+            resolve_descriptor_classes(class_loaders, class, descriptor)?;
+            Ok(Resolved::MethodType(descriptor.to_string()))
+        }
+        ConstantKind::MethodHandle(method_handle_info) => {
+            resolve_method_handle(class_loaders, class, method_handle_info.reference_kind, method_handle_info.reference_index)
+        }
+        ConstantKind::Dynamic(dynamic_info) => {
+            resolve_call_site(class_loaders, class, dynamic_info.bootstrap_method_attr_index, dynamic_info.name_and_type_index)
+        }
+        ConstantKind::InvokeDynamic(invoke_dynamic_info) => {
+            resolve_call_site(class_loaders, class, invoke_dynamic_info.bootstrap_method_attr_index, invoke_dynamic_info.name_and_type_index)
+        }
+        _ => Err(ClassFormatError::from(ClassFormatErrorKind::UnexpectedConstantPoolEntry { expected: "symbolic reference" })
+            .with_constant_pool_index(index)
+            .into()),
+    }
+}
+
+/// JVMS 5.4.3.1. `name` is a binary name or an array descriptor.
+fn resolve_class_name(class_loaders: &mut ClassLoaders, referrer: &Arc<RuntimeClass>, name: &str) -> Result<Resolved, LinkingError> {
+    if !name.starts_with('[') {
+        let class = class_loaders.load_class(referrer.defining_loader, name)?;
+        check_class_access(referrer, &class)?;
+        return Ok(Resolved::Class(class));
+    }
+
+    let element = match FieldType::parse(name)? {
+        FieldType::Array { component_type, .. } => match *component_type {
+            FieldType::Object(element_name) => {
+                let element = class_loaders.load_class(referrer.defining_loader, &element_name)?;
+                check_class_access(referrer, &element)?;
+                Some(element)
+            }
+            _ => None,
+        },
+        _ => None,
+    };
+    Ok(Resolved::ArrayClass { descriptor: name.to_string(), element })
+}
+
+/// Loads every class named in a field or method descriptor, as resolving a MethodType does.
+fn resolve_descriptor_classes(class_loaders: &mut ClassLoaders, referrer: &Arc<RuntimeClass>, descriptor: &str) -> Result<(), LinkingError> {
+    let field_types = match descriptor.starts_with('(') {
+        true => {
+            let method_descriptor = MethodDescriptor::parse(descriptor)?;
+            method_descriptor.parameters.into_iter().chain(method_descriptor.return_type).collect()
+        }
+        false => vec![FieldType::parse(descriptor)?],
+    };
+
+    for field_type in field_types {
+        let element_type = match field_type {
+            FieldType::Array { component_type, .. } => *component_type,
+            field_type => field_type,
+        };
+        if let FieldType::Object(name) = element_type {
+            resolve_class_name(class_loaders, referrer, &name)?;
+        }
+    }
+    Ok(())
+}
+
+/// The class whose members a Fieldref, Methodref or InterfaceMethodref refers to. Array
+/// classes have the members of `Object`.
+fn resolve_member_owner(class_loaders: &mut ClassLoaders, referrer: &Arc<RuntimeClass>, class_index: u16) -> Result<Arc<RuntimeClass>, LinkingError> {
+    let owner = match resolve(class_loaders, referrer, class_index)? {
+        Resolved::Class(owner) => owner,
+        _ => class_loaders.load_class(referrer.defining_loader, JAVA_LANG_OBJECT)?,
+    };
+    // Member lookup walks the supertypes, which linking has checked for cycles.
+    link(class_loaders, &owner)?;
+    Ok(owner)
+}
+
+fn member_class_index(referrer: &RuntimeClass, index: u16) -> Result<u16, LinkingError> {
+    Ok(match referrer.class_file.constant_pool.get(index)? {
+        ConstantKind::Fieldref(fieldref_info) => fieldref_info.class_index,
+        ConstantKind::Methodref(methodref_info) => methodref_info.class_index,
+        ConstantKind::InterfaceMethodref(interface_methodref_info) => interface_methodref_info.class_index,
+        _ => unreachable!("only called for member references"),
+    })
+}
+
+/// JVMS 5.4.3.2.
+fn resolve_field(class_loaders: &mut ClassLoaders, referrer: &Arc<RuntimeClass>, index: u16) -> Result<Resolved, LinkingError> {
+    let owner = resolve_member_owner(class_loaders, referrer, member_class_index(referrer, index)?)?;
+    let (_, name, descriptor) = referrer.class_file.constant_pool.member_ref(index)?;
+
+    let (class, field_index) = find_field(class_loaders, &owner, name, descriptor)?
+        .ok_or_else(|| LinkingError::NoSuchField(format!("{}.{}", owner.name(), name)))?;
+    let flags = class.class_file.fields[field_index].access_flags;
+    let member = format!("field {}.{}", class.name(), name);
+    check_member_access(class_loaders, referrer, &class, flags.is_public(), flags.is_protected(), flags.is_private(), &member)?;
+    Ok(Resolved::Field { class, index: field_index })
+}
+
+/// Field lookup: the class itself, then its superinterfaces, then its superclass.
+fn find_field(class_loaders: &mut ClassLoaders, class: &Arc<RuntimeClass>, name: &str, descriptor: &str) -> Result<Option<(Arc<RuntimeClass>, usize)>, LinkingError> {
+    let constant_pool = &class.class_file.constant_pool;
+    let declared = class.class_file.fields.iter().position(|field| {
+        constant_pool.utf8(field.name_index) == Ok(name) && constant_pool.utf8(field.descriptor_index) == Ok(descriptor)
+    });
+    if let Some(field_index) = declared {
+        return Ok(Some((class.clone(), field_index)));
+    }
+
+    for interface in superinterfaces(class_loaders, class)? {
+        if let Some(found) = find_field(class_loaders, &interface, name, descriptor)? {
+            return Ok(Some(found));
+        }
+    }
+    match superclass(class_loaders, class)? {
+        Some(superclass) => find_field(class_loaders, &superclass, name, descriptor),
+        None => Ok(None),
+    }
+}
+
+/// JVMS 5.4.3.3.
+fn resolve_method(class_loaders: &mut ClassLoaders, referrer: &Arc<RuntimeClass>, index: u16) -> Result<Resolved, LinkingError> {
+    let owner = resolve_member_owner(class_loaders, referrer, member_class_index(referrer, index)?)?;
+    let (_, name, descriptor) = referrer.class_file.constant_pool.member_ref(index)?;
+    if owner.class_file.access_flags.is_interface() {
+        return Err(LinkingError::IncompatibleClassChange(format!("Found interface {}, but class was expected", owner.name())));
+    }
+
+    let mut found = signature_polymorphic_method(&owner, name).map(|method_index| (owner.clone(), method_index));
+    let mut current = Some(owner.clone());
+    while found.is_none() {
+        let Some(class) = current else { break };
+        found = declared_method(&class, name, descriptor).map(|method_index| (class.clone(), method_index));
+        current = superclass(class_loaders, &class)?;
+    }
+    if found.is_none() {
+        found = maximally_specific_method(class_loaders, &owner, name, descriptor)?;
+    }
+
+    let (class, method_index) = found.ok_or_else(|| LinkingError::NoSuchMethod(format!("{}.{}{}", owner.name(), name, descriptor)))?;
+    check_method_access(class_loaders, referrer, &class, method_index, name)?;
+    Ok(Resolved::Method { class, index: method_index })
+}
+
+/// JVMS 5.4.3.4.
+fn resolve_interface_method(class_loaders: &mut ClassLoaders, referrer: &Arc<RuntimeClass>, index: u16) -> Result<Resolved, LinkingError> {
+    let owner = resolve_member_owner(class_loaders, referrer, member_class_index(referrer, index)?)?;
+    let (_, name, descriptor) = referrer.class_file.constant_pool.member_ref(index)?;
+    if !owner.class_file.access_flags.is_interface() {
+        return Err(LinkingError::IncompatibleClassChange(format!("Found class {}, but interface was expected", owner.name())));
+    }
+
+    let mut found = declared_method(&owner, name, descriptor).map(|method_index| (owner.clone(), method_index));
+    if found.is_none() {
+        // The public instance methods of Object are members of every interface.
+        let object = class_loaders.load_class(owner.defining_loader, JAVA_LANG_OBJECT)?;
+        found = declared_method(&object, name, descriptor)
+            .filter(|&method_index| {
+                let flags = object.class_file.methods[method_index].access_flags;
+                flags.is_public() && !flags.is_static()
+            })
+            .map(|method_index| (object.clone(), method_index));
+    }
+    if found.is_none() {
+        found = maximally_specific_method(class_loaders, &owner, name, descriptor)?;
+    }
+
+    let (class, method_index) = found.ok_or_else(|| LinkingError::NoSuchMethod(format!("{}.{}{}", owner.name(), name, descriptor)))?;
+    check_method_access(class_loaders, referrer, &class, method_index, name)?;
+    Ok(Resolved::Method { class, index: method_index })
+}
+
+fn declared_method(class: &RuntimeClass, name: &str, descriptor: &str) -> Option<usize> {
+    let constant_pool = &class.class_file.constant_pool;
+    class.class_file.methods.iter().position(|method| {
+        constant_pool.utf8(method.name_index) == Ok(name) && constant_pool.utf8(method.descriptor_index) == Ok(descriptor)
+    })
+}
+
+/// JVMS 2.9.3: `MethodHandle` and `VarHandle` declare native varargs methods taking an
+/// `Object[]` that match a reference of any descriptor.
+fn signature_polymorphic_method(class: &RuntimeClass, name: &str) -> Option<usize> {
+    if !matches!(class.name(), "java/lang/invoke/MethodHandle" | "java/lang/invoke/VarHandle") {
+        return None;
+    }
+    let constant_pool = &class.class_file.constant_pool;
+    let mut candidates = class.class_file.methods.iter().enumerate()
+        .filter(|(_, method)| constant_pool.utf8(method.name_index) == Ok(name));
+    let (method_index, method) = candidates.next()?;
+    let is_polymorphic = method.access_flags.is_native()
+        && method.access_flags.is_varargs()
+        && constant_pool.utf8(method.descriptor_index).is_ok_and(|descriptor| descriptor.starts_with("([Ljava/lang/Object;)"));
+    (is_polymorphic && candidates.next().is_none()).then_some(method_index)
+}
+
+/// JVMS 5.4.3.3: the superinterface methods that are neither private nor static, less
+/// those overridden in a subinterface that also declares one. The one that is not
+/// abstract is chosen when it is the only such method among them; otherwise any of
+/// them will do, as resolution only needs a method to check access against.
+fn maximally_specific_method(class_loaders: &mut ClassLoaders, class: &Arc<RuntimeClass>, name: &str, descriptor: &str) -> Result<Option<(Arc<RuntimeClass>, usize)>, LinkingError> {
+    let mut interfaces = all_superinterfaces(class_loaders, class)?;
+    let mut current = superclass(class_loaders, class)?;
+    while let Some(ancestor) = current {
+        for interface in all_superinterfaces(class_loaders, &ancestor)? {
+            if !interfaces.iter().any(|other| Arc::ptr_eq(other, &interface)) {
+                interfaces.push(interface);
+            }
+        }
+        current = superclass(class_loaders, &ancestor)?;
+    }
+
+    let mut candidates = Vec::new();
+    for interface in interfaces {
+        if let Some(method_index) = declared_method(&interface, name, descriptor) {
+            let flags = interface.class_file.methods[method_index].access_flags;
+            if !flags.is_private() && !flags.is_static() {
+                let supertypes = all_superinterfaces(class_loaders, &interface)?;
+                candidates.push((interface, method_index, supertypes));
+            }
+        }
+    }
+
+    let maximally_specific: Vec<_> = candidates.iter()
+        .filter(|(interface, _, _)| {
+            !candidates.iter().any(|(_, _, supertypes)| supertypes.iter().any(|supertype| Arc::ptr_eq(supertype, interface)))
+        })
+        .map(|(interface, method_index, _)| (interface.clone(), *method_index))
+        .collect();
+    let mut concrete = maximally_specific.iter()
+        .filter(|(interface, method_index)| !interface.class_file.methods[*method_index].access_flags.is_abstract());
+    Ok(match (concrete.next(), concrete.next()) {
+        (Some(method), None) => Some(method.clone()),
+        _ => maximally_specific.into_iter().next(),
+    })
+}
+
+/// JVMS 5.4.3.5. The reference kinds are checked by the format checker; what is left
+/// is whether the member found is static when the kind says it should be.
+fn resolve_method_handle(class_loaders: &mut ClassLoaders, referrer: &Arc<RuntimeClass>, reference_kind: u8, reference_index: u16) -> Result<Resolved, LinkingError> {
+    let member = resolve(class_loaders, referrer, reference_index)?;
+    let is_static = match &member {
+        Resolved::Field { class, index } => class.class_file.fields[*index].access_flags.is_static(),
+        Resolved::Method { class, index } => class.class_file.methods[*index].access_flags.is_static(),
+        _ => unreachable!("method handles refer to fields and methods"),
+    };
+    // REF_getStatic, REF_putStatic and REF_invokeStatic
+    let expects_static = matches!(reference_kind, 2 | 4 | 6);
+    if is_static != expects_static {
+        let (_, name, _) = referrer.class_file.constant_pool.member_ref(reference_index)?;
+        let expected = if expects_static { "static" } else { "non-static" };
+        return Err(LinkingError::IncompatibleClassChange(format!("Expected {} member {}", expected, name)));
+    }
+    Ok(Resolved::MethodHandle { reference_kind, member: Box::new(member) })
+}
+
+/// JVMS 5.4.3.6: the bootstrap method handle and the classes in the descriptor. Static
+/// arguments are resolved when the interpreter passes them to the bootstrap method.
+fn resolve_call_site(class_loaders: &mut ClassLoaders, referrer: &Arc<RuntimeClass>, bootstrap_method_attr_index: u16, name_and_type_index: u16) -> Result<Resolved, LinkingError> {
+    let class_file = &referrer.class_file;
+    let bootstrap_method = class_file.attributes.iter()
+        .find_map(|attribute| match attribute {
+            AttributeKind::BootstrapMethods(bootstrap_methods) => {
+                bootstrap_methods.bootstrap_methods.get(usize::from(bootstrap_method_attr_index))
+            }
+            _ => None,
+        })
+        .ok_or_else(|| ClassFormatError::from(ClassFormatErrorKind::InvalidBootstrapMethodIndex(bootstrap_method_attr_index)))?;
+    let (name, descriptor) = class_file.constant_pool.name_and_type(name_and_type_index)?;
+
+    let bootstrap_arguments = bootstrap_method.bootstrap_arguments.clone();
+    let handle = resolve(class_loaders, referrer, bootstrap_method.bootstrap_method_ref)?;
+    resolve_descriptor_classes(class_loaders, referrer, descriptor)?;
+    Ok(Resolved::CallSite {
+        bootstrap_method: Box::new(handle),
+        bootstrap_arguments,
+        name: name.to_string(),
+        descriptor: descriptor.to_string(),
+    })
+}
+
+fn superclass(class_loaders: &mut ClassLoaders, class: &Arc<RuntimeClass>) -> Result<Option<Arc<RuntimeClass>>, LinkingError> {
+    if class.class_file.super_class == 0 {
+        return Ok(None);
+    }
+    match resolve(class_loaders, class, class.class_file.super_class)? {
+        Resolved::Class(superclass) => Ok(Some(superclass)),
+        _ => Err(ClassFormatError::from(ClassFormatErrorKind::InvalidSuperclass).into()),
+    }
+}
+
+fn superinterfaces(class_loaders: &mut ClassLoaders, class: &Arc<RuntimeClass>) -> Result<Vec<Arc<RuntimeClass>>, LinkingError> {
+    let mut interfaces = Vec::new();
+    for &interface_index in &class.class_file.interfaces {
+        match resolve(class_loaders, class, interface_index)? {
+            Resolved::Class(interface) => interfaces.push(interface),
+            _ => return Err(ClassFormatError::from(ClassFormatErrorKind::InvalidSuperclass).into()),
+        }
+    }
+    Ok(interfaces)
+}
+
+/// The superinterfaces of `class` and, transitively, theirs, each once.
+fn all_superinterfaces(class_loaders: &mut ClassLoaders, class: &Arc<RuntimeClass>) -> Result<Vec<Arc<RuntimeClass>>, LinkingError> {
+    let mut interfaces = superinterfaces(class_loaders, class)?;
+    let mut next = 0;
+    while let Some(interface) = interfaces.get(next).cloned() {
+        for superinterface in superinterfaces(class_loaders, &interface)? {
+            if !interfaces.iter().any(|other| Arc::ptr_eq(other, &superinterface)) {
+                interfaces.push(superinterface);
+            }
+        }
+        next += 1;
+    }
+    Ok(interfaces)
+}
+
+/// JVMS 5.4.4: a class is accessible when it is public or in the referrer's run-time package.
+fn check_class_access(referrer: &RuntimeClass, class: &RuntimeClass) -> Result<(), LinkingError> {
+    if class.class_file.access_flags.is_public() || referrer.is_same_runtime_package(class) {
+        return Ok(());
+    }
+    Err(LinkingError::IllegalAccess(format!("failed to access class {} from class {}", class.name(), referrer.name())))
+}
+
+fn check_method_access(class_loaders: &mut ClassLoaders, referrer: &Arc<RuntimeClass>, class: &Arc<RuntimeClass>, method_index: usize, name: &str) -> Result<(), LinkingError> {
+    let flags = class.class_file.methods[method_index].access_flags;
+    let member = format!("method {}.{}", class.name(), name);
+    check_member_access(class_loaders, referrer, class, flags.is_public(), flags.is_protected(), flags.is_private(), &member)
+}
+
+/// JVMS 5.4.4 for a member declared in `class`. Private members are also accessible
+/// to the other members of the class's nest.
+fn check_member_access(class_loaders: &mut ClassLoaders, referrer: &Arc<RuntimeClass>, class: &Arc<RuntimeClass>, is_public: bool, is_protected: bool, is_private: bool, member: &str) -> Result<(), LinkingError> {
+    let accessible = if is_public {
+        true
+    } else if is_private {
+        Arc::ptr_eq(referrer, class) || is_nestmate(class_loaders, referrer, class)
+    } else if referrer.is_same_runtime_package(class) {
+        true
+    } else {
+        is_protected && is_subclass(class_loaders, referrer, class)?
+    };
+
+    if accessible {
+        return Ok(());
+    }
+    Err(LinkingError::IllegalAccess(format!("class {} tried to access {}", referrer.name(), member)))
+}
+
+fn is_subclass(class_loaders: &mut ClassLoaders, class: &Arc<RuntimeClass>, ancestor: &Arc<RuntimeClass>) -> Result<bool, LinkingError> {
+    let mut current = Some(class.clone());
+    while let Some(class) = current {
+        if Arc::ptr_eq(&class, ancestor) {
+            return Ok(true);
+        }
+        current = superclass(class_loaders, &class)?;
+    }
+    Ok(false)
+}
+
+/// JVMS 5.4.4: classes with the same nest host.
+fn is_nestmate(class_loaders: &mut ClassLoaders, class: &Arc<RuntimeClass>, other: &Arc<RuntimeClass>) -> bool {
+    Arc::ptr_eq(&nest_host(class_loaders, class), &nest_host(class_loaders, other))
+}
+
+/// The class named by the NestHost attribute when it resolves to a class of the same
+/// run-time package whose NestMembers attribute names `class` back; otherwise `class`
+/// itself. A failure to resolve the host is not an error, it only leaves `class` alone
+/// in its nest.
+fn nest_host(class_loaders: &mut ClassLoaders, class: &Arc<RuntimeClass>) -> Arc<RuntimeClass> {
+    if let Some(host) = class.nest_host() {
+        return host;
+    }
+    let host = claimed_nest_host(class_loaders, class)
+        .filter(|host| class.is_same_runtime_package(host) && lists_nest_member(host, class.name()))
+        .unwrap_or_else(|| class.clone());
+    class.cache_nest_host(host)
+}
+
+fn claimed_nest_host(class_loaders: &mut ClassLoaders, class: &Arc<RuntimeClass>) -> Option<Arc<RuntimeClass>> {
+    let host_class_index = class.class_file.attributes.iter().find_map(|attribute| match attribute {
+        AttributeKind::NestHost(nest_host) => Some(nest_host.host_class_index),
+        _ => None,
+    })?;
+    match resolve(class_loaders, class, host_class_index) {
+        Ok(Resolved::Class(host)) => Some(host),
+        _ => None,
+    }
+}
+
+fn lists_nest_member(host: &RuntimeClass, name: &str) -> bool {
+    let constant_pool = &host.class_file.constant_pool;
+    host.class_file.attributes.iter().any(|attribute| match attribute {
+        AttributeKind::NestMembers(nest_members) => nest_members.classes.iter().any(|&index| constant_pool.class_name(index) == Ok(name)),
+        _ => false,
+    })
+}
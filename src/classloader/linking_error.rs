@@ -0,0 +1,61 @@
+use std::fmt;
+
+use crate::classloader::class_loading_error::ClassLoadingError;
+use crate::entity::class_format_error::ClassFormatError;
+
+/// Why linking a class or resolving one of its symbolic references failed (JVMS 5.4).
+/// Errors are cached with the constant pool entry or class they came from, so each
+/// later attempt fails with the same error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkingError {
+    Loading(ClassLoadingError),
+    Verify(String),
+    ClassCircularity(String),
+    IncompatibleClassChange(String),
+    IllegalAccess(String),
+    NoSuchField(String),
+    NoSuchMethod(String),
+}
+
+impl LinkingError {
+    /// Binary name of the Java exception the VM should throw.
+    pub fn java_exception_name(&self) -> &'static str {
+        match self {
+            LinkingError::Loading(error) => error.java_exception_name(),
+            LinkingError::Verify(_) => "java/lang/VerifyError",
+            LinkingError::ClassCircularity(_) => "java/lang/ClassCircularityError",
+            LinkingError::IncompatibleClassChange(_) => "java/lang/IncompatibleClassChangeError",
+            LinkingError::IllegalAccess(_) => "java/lang/IllegalAccessError",
+            LinkingError::NoSuchField(_) => "java/lang/NoSuchFieldError",
+            LinkingError::NoSuchMethod(_) => "java/lang/NoSuchMethodError",
+        }
+    }
+}
+
+impl From<ClassLoadingError> for LinkingError {
+    fn from(error: ClassLoadingError) -> Self {
+        LinkingError::Loading(error)
+    }
+}
+
+impl From<ClassFormatError> for LinkingError {
+    fn from(error: ClassFormatError) -> Self {
+        LinkingError::Loading(ClassLoadingError::Format(error))
+    }
+}
+
+impl fmt::Display for LinkingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkingError::Loading(error) => write!(f, "{}", error),
+            LinkingError::Verify(message)
+            | LinkingError::ClassCircularity(message)
+            | LinkingError::IncompatibleClassChange(message)
+            | LinkingError::IllegalAccess(message)
+            | LinkingError::NoSuchField(message)
+            | LinkingError::NoSuchMethod(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for LinkingError {}
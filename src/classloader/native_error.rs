@@ -0,0 +1,39 @@
+use std::fmt;
+
+use crate::classloader::class_loading_error::ClassLoadingError;
+
+/// Why a native method of the class loading machinery threw.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NativeError {
+    Loading(ClassLoadingError),
+    NullPointer(String),
+    IndexOutOfBounds(String),
+}
+
+impl NativeError {
+    /// Binary name of the Java exception the native throws.
+    pub fn java_exception_name(&self) -> &'static str {
+        match self {
+            NativeError::Loading(error) => error.java_exception_name(),
+            NativeError::NullPointer(_) => "java/lang/NullPointerException",
+            NativeError::IndexOutOfBounds(_) => "java/lang/ArrayIndexOutOfBoundsException",
+        }
+    }
+}
+
+impl From<ClassLoadingError> for NativeError {
+    fn from(error: ClassLoadingError) -> Self {
+        NativeError::Loading(error)
+    }
+}
+
+impl fmt::Display for NativeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NativeError::Loading(error) => write!(f, "{}", error),
+            NativeError::NullPointer(message) | NativeError::IndexOutOfBounds(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for NativeError {}
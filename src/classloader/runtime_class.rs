@@ -0,0 +1,233 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, OnceLock, Weak};
+
+use crate::classloader::class_loader::ClassLoaderId;
+use crate::classloader::linker::Resolved;
+use crate::classloader::linking_error::LinkingError;
+use crate::entity::class_format_error::ClassFormatError;
+use crate::entity::descriptor::FieldType;
+use crate::entity::java_class_file::JavaClassFileFormat;
+
+// A class as the VM sees it at run time: the parsed class file and its defining
+// loader, which together identify it, plus what linking (JVMS 5.4) has produced so
+// far. Linking moves it from `Loaded` through `Verified` and `Prepared` to `Linked`;
+// a class that fails stays `Failed` and keeps failing with the same error.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkState {
+    Loaded,
+    Verified,
+    /// Static fields exist and hold their default values.
+    Prepared,
+    Linked,
+    Failed(LinkingError),
+}
+
+/// The value of a static field.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    Null,
+}
+
+impl Value {
+    /// The default value a field of `field_type` holds after preparation (JVMS 2.3, 2.4).
+    pub fn zero(field_type: &FieldType) -> Value {
+        match field_type {
+            FieldType::Long => Value::Long(0),
+            FieldType::Float => Value::Float(0.0),
+            FieldType::Double => Value::Double(0.0),
+            FieldType::Object(_) | FieldType::Array { .. } => Value::Null,
+            _ => Value::Int(0),
+        }
+    }
+}
+
+pub struct RuntimeClass {
+    pub defining_loader: ClassLoaderId,
+    pub class_file: Arc<JavaClassFileFormat>,
+    state: Mutex<LinkState>,
+    /// Keyed by index into `class_file.fields`.
+    static_fields: Mutex<HashMap<usize, Value>>,
+    /// What each constant pool entry resolved to, once it has been.
+    resolved: Vec<OnceLock<Result<Held, LinkingError>>>,
+    /// Determined the first time a private member is accessed from another class.
+    nest_host: OnceLock<ClassLink>,
+}
+
+impl RuntimeClass {
+    /// `class_file` must have passed the format checker.
+    pub fn new(defining_loader: ClassLoaderId, class_file: Arc<JavaClassFileFormat>) -> Self {
+        let resolved = (0..class_file.constant_pool.len()).map(|_| OnceLock::new()).collect();
+        RuntimeClass {
+            defining_loader,
+            class_file,
+            state: Mutex::new(LinkState::Loaded),
+            static_fields: Mutex::new(HashMap::new()),
+            resolved,
+            nest_host: OnceLock::new(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        self.class_file.name().expect("the format checker validates this_class")
+    }
+
+    /// The package part of the name, empty for the unnamed package.
+    pub fn package_name(&self) -> &str {
+        self.name().rsplit_once('/').map_or("", |(package, _)| package)
+    }
+
+    /// JVMS 5.3: a run-time package is a package name together with a defining loader.
+    pub fn is_same_runtime_package(&self, other: &RuntimeClass) -> bool {
+        self.defining_loader == other.defining_loader && self.package_name() == other.package_name()
+    }
+
+    pub fn link_state(&self) -> LinkState {
+        self.state.lock().unwrap().clone()
+    }
+
+    pub fn set_link_state(&self, state: LinkState) {
+        *self.state.lock().unwrap() = state;
+    }
+
+    /// Creates the static fields with their default values. Initializers, including
+    /// `ConstantValue` attributes, only run when the class is initialized.
+    pub fn prepare(&self) -> Result<(), ClassFormatError> {
+        let constant_pool = &self.class_file.constant_pool;
+        let mut static_fields = HashMap::new();
+        for (index, field) in self.class_file.fields.iter().enumerate() {
+            if field.access_flags.is_static() {
+                static_fields.insert(index, Value::zero(&field.descriptor(constant_pool)?));
+            }
+        }
+        *self.static_fields.lock().unwrap() = static_fields;
+        Ok(())
+    }
+
+    /// `None` for instance fields and before preparation.
+    pub fn static_value(&self, field_index: usize) -> Option<Value> {
+        self.static_fields.lock().unwrap().get(&field_index).copied()
+    }
+
+    /// The cached resolution of constant pool entry `index`, if it was resolved before.
+    pub fn resolved(&self, index: u16) -> Option<Result<Resolved, LinkingError>> {
+        let cached = self.resolved.get(usize::from(index))?.get()?;
+        Some(cached.as_ref().map(Held::get).map_err(Clone::clone))
+    }
+
+    /// Caches the outcome of resolving entry `index`. If another thread got there first,
+    /// its outcome is kept and returned, so every use of the entry sees the same one.
+    pub fn cache_resolution(&self, index: u16, result: Result<Resolved, LinkingError>) -> Result<Resolved, LinkingError> {
+        match self.resolved.get(usize::from(index)) {
+            Some(cell) => {
+                let cached = cell.get_or_init(|| result.as_ref().map(|resolved| Held::new(self, resolved)).map_err(Clone::clone));
+                cached.as_ref().map(Held::get).map_err(Clone::clone)
+            }
+            None => result,
+        }
+    }
+
+    pub fn nest_host(&self) -> Option<Arc<RuntimeClass>> {
+        self.nest_host.get().map(ClassLink::get)
+    }
+
+    /// Records the nest host (JVMS 5.4.4), keeping the one recorded first, if any.
+    pub fn cache_nest_host(&self, host: Arc<RuntimeClass>) -> Arc<RuntimeClass> {
+        self.nest_host.get_or_init(|| ClassLink::new(self, &host)).get()
+    }
+}
+
+/// Classes refer to each other through their resolved entries, so only the identity is printed.
+impl fmt::Debug for RuntimeClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (loader {})", self.name(), self.defining_loader)
+    }
+}
+
+/// A class referred to from another class's caches. A class referring to itself, by
+/// its own this_class entry, its own members or as its own nest host, only holds a
+/// weak reference, or it would keep itself alive.
+#[derive(Debug, Clone)]
+enum ClassLink {
+    Other(Arc<RuntimeClass>),
+    Itself(Weak<RuntimeClass>),
+}
+
+impl ClassLink {
+    fn new(owner: &RuntimeClass, class: &Arc<RuntimeClass>) -> ClassLink {
+        if std::ptr::eq(Arc::as_ptr(class), owner) {
+            ClassLink::Itself(Arc::downgrade(class))
+        } else {
+            ClassLink::Other(class.clone())
+        }
+    }
+
+    fn get(&self) -> Arc<RuntimeClass> {
+        match self {
+            ClassLink::Other(class) => class.clone(),
+            ClassLink::Itself(class) => class.upgrade().expect("a class outlives borrows of it"),
+        }
+    }
+}
+
+/// `Resolved` as it is cached, with its classes held through `ClassLink`s.
+#[derive(Debug, Clone)]
+enum Held {
+    Class(ClassLink),
+    ArrayClass { descriptor: String, element: Option<ClassLink> },
+    Field { class: ClassLink, index: usize },
+    Method { class: ClassLink, index: usize },
+    MethodType(String),
+    MethodHandle { reference_kind: u8, member: Box<Held> },
+    CallSite { bootstrap_method: Box<Held>, bootstrap_arguments: Vec<u16>, name: String, descriptor: String },
+}
+
+impl Held {
+    fn new(owner: &RuntimeClass, resolved: &Resolved) -> Held {
+        let link = |class| ClassLink::new(owner, class);
+        match resolved {
+            Resolved::Class(class) => Held::Class(link(class)),
+            Resolved::ArrayClass { descriptor, element } => {
+                Held::ArrayClass { descriptor: descriptor.clone(), element: element.as_ref().map(link) }
+            }
+            Resolved::Field { class, index } => Held::Field { class: link(class), index: *index },
+            Resolved::Method { class, index } => Held::Method { class: link(class), index: *index },
+            Resolved::MethodType(descriptor) => Held::MethodType(descriptor.clone()),
+            Resolved::MethodHandle { reference_kind, member } => {
+                Held::MethodHandle { reference_kind: *reference_kind, member: Box::new(Held::new(owner, member)) }
+            }
+            Resolved::CallSite { bootstrap_method, bootstrap_arguments, name, descriptor } => Held::CallSite {
+                bootstrap_method: Box::new(Held::new(owner, bootstrap_method)),
+                bootstrap_arguments: bootstrap_arguments.clone(),
+                name: name.clone(),
+                descriptor: descriptor.clone(),
+            },
+        }
+    }
+
+    fn get(&self) -> Resolved {
+        match self {
+            Held::Class(class) => Resolved::Class(class.get()),
+            Held::ArrayClass { descriptor, element } => {
+                Resolved::ArrayClass { descriptor: descriptor.clone(), element: element.as_ref().map(ClassLink::get) }
+            }
+            Held::Field { class, index } => Resolved::Field { class: class.get(), index: *index },
+            Held::Method { class, index } => Resolved::Method { class: class.get(), index: *index },
+            Held::MethodType(descriptor) => Resolved::MethodType(descriptor.clone()),
+            Held::MethodHandle { reference_kind, member } => {
+                Resolved::MethodHandle { reference_kind: *reference_kind, member: Box::new(member.get()) }
+            }
+            Held::CallSite { bootstrap_method, bootstrap_arguments, name, descriptor } => Resolved::CallSite {
+                bootstrap_method: Box::new(bootstrap_method.get()),
+                bootstrap_arguments: bootstrap_arguments.clone(),
+                name: name.clone(),
+                descriptor: descriptor.clone(),
+            },
+        }
+    }
+}
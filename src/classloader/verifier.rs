@@ -0,0 +1,225 @@
+use crate::classloader::linking_error::LinkingError;
+use crate::entity::attribute_info::{AttributeKind, CodeAttribute, VerificationTypeInfo};
+use crate::entity::constant_pool::{ConstantKind, ConstantPool};
+use crate::entity::descriptor::{FieldType, MethodDescriptor};
+use crate::entity::instruction::{Instruction, WideInstruction};
+use crate::entity::java_class_file::JavaClassFileFormat;
+use crate::entity::method_info::MethodInfo;
+
+// The verification step of linking (JVMS 4.10, 5.4.1). This checks the static
+// constraints on code (JVMS 4.9.1): instructions decode, branches and exception
+// handlers land on instructions, local variables lie within max_locals, and every
+// constant pool operand is of the kind its instruction needs. StackMapTable frames
+// must sit on instructions and fit into max_locals and max_stack, but type checking
+// against them, the structural constraints of JVMS 4.9.2, is not done.
+
+pub fn verify(class: &JavaClassFileFormat) -> Result<(), LinkingError> {
+    let constant_pool = &class.constant_pool;
+    for method in &class.methods {
+        for attribute in method.attributes.get(constant_pool)? {
+            if let AttributeKind::Code(code) = attribute {
+                verify_code(class, method, code).map_err(|message| {
+                    let name = constant_pool.utf8(method.name_index).unwrap_or("?");
+                    let descriptor = constant_pool.utf8(method.descriptor_index).unwrap_or("?");
+                    LinkingError::Verify(format!("{} in method {}.{}{}", message, class.name().unwrap_or("?"), name, descriptor))
+                })?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn verify_code(class: &JavaClassFileFormat, method: &MethodInfo, code: &CodeAttribute) -> Result<(), String> {
+    let constant_pool = &class.constant_pool;
+    if code.code.is_empty() || code.code.len() > 0xFFFF {
+        return Err("Invalid code length".to_string());
+    }
+
+    let descriptor = MethodDescriptor::parse(constant_pool.utf8(method.descriptor_index).map_err(|e| e.to_string())?)
+        .map_err(|e| e.to_string())?;
+    let this_slot = if method.access_flags.is_static() { 0 } else { 1 };
+    if descriptor.argument_slots() + this_slot > code.max_locals {
+        return Err("Arguments can't fit into locals".to_string());
+    }
+
+    let instructions = code.instructions().map_err(|e| format!("Bad instruction: {}", e))?;
+    let is_instruction = |pc: i64| instructions.binary_search_by_key(&pc, |(start, _)| i64::from(*start)).is_ok();
+
+    for (pc, instruction) in &instructions {
+        let at = |message: &str| format!("{} at pc {}", message, pc);
+
+        if branch_offsets(instruction).into_iter().any(|offset| !is_instruction(i64::from(*pc) + offset)) {
+            return Err(at("Illegal target of jump or branch"));
+        }
+        if let Some((index, slots)) = local_variable(instruction) {
+            if u32::from(index) + u32::from(slots) > u32::from(code.max_locals) {
+                return Err(at("Illegal local variable number"));
+            }
+        }
+        check_constant_operand(constant_pool, class.major_version, instruction).map_err(|message| at(&message))?;
+    }
+
+    let code_length = code.code.len() as i64;
+    for entry in &code.exception_table {
+        let (start, end, handler) = (i64::from(entry.start_pc), i64::from(entry.end_pc), i64::from(entry.handler_pc));
+        if start >= end || !is_instruction(start) || !(end == code_length || is_instruction(end)) {
+            return Err("Illegal exception table range".to_string());
+        }
+        if !is_instruction(handler) {
+            return Err("Illegal exception table handler".to_string());
+        }
+        if entry.catch_type != 0 && constant_pool.class_name(entry.catch_type).is_err() {
+            return Err(format!("Catch type is not a class at constant pool entry {}", entry.catch_type));
+        }
+    }
+
+    for attribute in &code.attributes {
+        if let AttributeKind::StackMapTable(stack_map_table) = attribute {
+            let frames = stack_map_table.expand(&initial_locals(class, method, &descriptor))
+                .map_err(|e| format!("StackMapTable error: {}", e))?;
+            for frame in frames {
+                if !is_instruction(i64::from(frame.offset)) {
+                    return Err(format!("StackMapTable error: bad offset {}", frame.offset));
+                }
+                if frame.locals.len() > usize::from(code.max_locals) {
+                    return Err(format!("StackMapTable error: locals size exceeds max_locals at offset {}", frame.offset));
+                }
+                if frame.stack.len() > usize::from(code.max_stack) {
+                    return Err(format!("StackMapTable error: stack size exceeds max_stack at offset {}", frame.offset));
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The types in the implicit frame a method starts with (JVMS 4.10.1.6), one per
+/// parameter as the StackMapTable lists them. Only the number of slots they take is
+/// checked, so reference parameters are `Object` without a constant of their own.
+fn initial_locals(class: &JavaClassFileFormat, method: &MethodInfo, descriptor: &MethodDescriptor) -> Vec<VerificationTypeInfo> {
+    let mut locals = Vec::new();
+    if !method.access_flags.is_static() {
+        let is_constructor = class.constant_pool.utf8(method.name_index) == Ok("<init>");
+        locals.push(if is_constructor && class.name() != Ok("java/lang/Object") {
+            VerificationTypeInfo::UninitializedThis
+        } else {
+            VerificationTypeInfo::Object { cpool_index: class.this_class }
+        });
+    }
+    locals.extend(descriptor.parameters.iter().map(|parameter| match parameter {
+        FieldType::Long => VerificationTypeInfo::Long,
+        FieldType::Float => VerificationTypeInfo::Float,
+        FieldType::Double => VerificationTypeInfo::Double,
+        FieldType::Object(_) | FieldType::Array { .. } => VerificationTypeInfo::Object { cpool_index: 0 },
+        _ => VerificationTypeInfo::Integer,
+    }));
+    locals
+}
+
+/// Offsets relative to the instruction's own pc.
+fn branch_offsets(instruction: &Instruction) -> Vec<i64> {
+    use Instruction::*;
+    match instruction {
+        Ifeq(offset) | Ifne(offset) | Iflt(offset) | Ifge(offset) | Ifgt(offset) | Ifle(offset)
+        | IfIcmpeq(offset) | IfIcmpne(offset) | IfIcmplt(offset) | IfIcmpge(offset) | IfIcmpgt(offset) | IfIcmple(offset)
+        | IfAcmpeq(offset) | IfAcmpne(offset) | Goto(offset) | Jsr(offset) | Ifnull(offset) | Ifnonnull(offset) => {
+            vec![i64::from(*offset)]
+        }
+        GotoW(offset) | JsrW(offset) => vec![i64::from(*offset)],
+        Tableswitch { default, offsets, .. } => std::iter::once(default).chain(offsets).map(|&offset| i64::from(offset)).collect(),
+        Lookupswitch { default, pairs } => {
+            std::iter::once(*default).chain(pairs.iter().map(|&(_, offset)| offset)).map(i64::from).collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// The local variable an instruction reads or writes and how many slots it takes.
+fn local_variable(instruction: &Instruction) -> Option<(u16, u16)> {
+    use Instruction::*;
+    let variable = match instruction {
+        Iload(index) | Fload(index) | Aload(index) | Istore(index) | Fstore(index) | Astore(index) | Ret(index) => (*index, 1),
+        Iinc { index, .. } => (*index, 1),
+        Lload(index) | Dload(index) | Lstore(index) | Dstore(index) => (*index, 2),
+        Iload0 | Fload0 | Aload0 | Istore0 | Fstore0 | Astore0 => (0, 1),
+        Iload1 | Fload1 | Aload1 | Istore1 | Fstore1 | Astore1 => (1, 1),
+        Iload2 | Fload2 | Aload2 | Istore2 | Fstore2 | Astore2 => (2, 1),
+        Iload3 | Fload3 | Aload3 | Istore3 | Fstore3 | Astore3 => (3, 1),
+        Lload0 | Dload0 | Lstore0 | Dstore0 => (0, 2),
+        Lload1 | Dload1 | Lstore1 | Dstore1 => (1, 2),
+        Lload2 | Dload2 | Lstore2 | Dstore2 => (2, 2),
+        Lload3 | Dload3 | Lstore3 | Dstore3 => (3, 2),
+        Wide(wide) => {
+            return Some(match wide {
+                WideInstruction::Iload(index) | WideInstruction::Fload(index) | WideInstruction::Aload(index)
+                | WideInstruction::Istore(index) | WideInstruction::Fstore(index) | WideInstruction::Astore(index)
+                | WideInstruction::Ret(index) => (*index, 1),
+                WideInstruction::Iinc { index, .. } => (*index, 1),
+                WideInstruction::Lload(index) | WideInstruction::Dload(index)
+                | WideInstruction::Lstore(index) | WideInstruction::Dstore(index) => (*index, 2),
+            });
+        }
+        _ => return None,
+    };
+    Some((u16::from(variable.0), variable.1))
+}
+
+fn check_constant_operand(constant_pool: &ConstantPool, major_version: u16, instruction: &Instruction) -> Result<(), String> {
+    use Instruction::*;
+    let (index, valid) = match instruction {
+        Ldc(index) => (u16::from(*index), loadable_slots(constant_pool, u16::from(*index)) == Some(1)),
+        LdcW(index) => (*index, loadable_slots(constant_pool, *index) == Some(1)),
+        Ldc2W(index) => (*index, loadable_slots(constant_pool, *index) == Some(2)),
+        Getstatic(index) | Putstatic(index) | Getfield(index) | Putfield(index) => {
+            (*index, matches!(constant_pool.get(*index), Ok(ConstantKind::Fieldref(_))))
+        }
+        Invokevirtual(index) => (*index, is_method_ref(constant_pool, *index, false)),
+        Invokespecial(index) | Invokestatic(index) => (*index, is_method_ref(constant_pool, *index, major_version >= 52)),
+        Invokeinterface { index, count } => {
+            (*index, *count != 0 && matches!(constant_pool.get(*index), Ok(ConstantKind::InterfaceMethodref(_))))
+        }
+        Invokedynamic(index) => (*index, matches!(constant_pool.get(*index), Ok(ConstantKind::InvokeDynamic(_)))),
+        New(index) => (*index, constant_pool.class_name(*index).is_ok_and(|name| !name.starts_with('['))),
+        Anewarray(index) | Checkcast(index) | Instanceof(index) => (*index, constant_pool.class_name(*index).is_ok()),
+        Multianewarray { index, dimensions } => {
+            let array_dimensions = constant_pool.class_name(*index).map_or(0, |name| name.chars().take_while(|&c| c == '[').count());
+            (*index, *dimensions >= 1 && usize::from(*dimensions) <= array_dimensions)
+        }
+        _ => return Ok(()),
+    };
+
+    match instruction {
+        Invokevirtual(_) | Invokespecial(_) | Invokestatic(_) | Invokeinterface { .. } if valid => {
+            let (_, name, _) = constant_pool.member_ref(index).map_err(|e| e.to_string())?;
+            if name == "<clinit>" || (name == "<init>" && !matches!(instruction, Invokespecial(_))) {
+                return Err(format!("Illegal call to {}", name));
+            }
+            Ok(())
+        }
+        _ if valid => Ok(()),
+        _ => Err(format!("Illegal type at constant pool entry {}", index)),
+    }
+}
+
+/// Slots the value of a loadable constant takes on the stack, `None` if it is not loadable.
+fn loadable_slots(constant_pool: &ConstantPool, index: u16) -> Option<u16> {
+    match constant_pool.get(index).ok()? {
+        ConstantKind::Integer(_) | ConstantKind::Float(_) | ConstantKind::String(_) | ConstantKind::Class(_)
+        | ConstantKind::MethodType(_) | ConstantKind::MethodHandle(_) => Some(1),
+        ConstantKind::Long(_) | ConstantKind::Double(_) => Some(2),
+        ConstantKind::Dynamic(dynamic_info) => {
+            let (_, descriptor) = constant_pool.name_and_type(dynamic_info.name_and_type_index).ok()?;
+            Some(if matches!(descriptor, "J" | "D") { 2 } else { 1 })
+        }
+        _ => None,
+    }
+}
+
+fn is_method_ref(constant_pool: &ConstantPool, index: u16, allow_interface: bool) -> bool {
+    match constant_pool.get(index) {
+        Ok(ConstantKind::Methodref(_)) => true,
+        Ok(ConstantKind::InterfaceMethodref(_)) => allow_interface,
+        _ => false,
+    }
+}
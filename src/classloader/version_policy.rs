@@ -0,0 +1,96 @@
+use crate::entity::class_format_error::ClassFormatErrorKind;
+
+// Which class file versions the loader accepts (JVMS 4.1). A preview class file,
+// minor_version 0xFFFF, may use preview features of exactly one release, so it is
+// only accepted when preview features are enabled and its major version is the
+// newest one supported.
+
+const PREVIEW_MINOR_VERSION: u16 = 0xFFFF;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VersionPolicy {
+    pub min_major_version: u16,
+    pub max_major_version: u16,
+    pub enable_preview: bool,
+}
+
+impl Default for VersionPolicy {
+    fn default() -> Self {
+        VersionPolicy {
+            min_major_version: 45,
+            max_major_version: 61,
+            enable_preview: false,
+        }
+    }
+}
+
+impl VersionPolicy {
+    pub fn check(&self, major_version: u16, minor_version: u16) -> Result<(), ClassFormatErrorKind> {
+        let unsupported = ClassFormatErrorKind::UnsupportedClassVersion { major_version, minor_version };
+        if !(self.min_major_version..=self.max_major_version).contains(&major_version) {
+            return Err(unsupported);
+        }
+
+        // Before Java 12 (major version 56) every minor version was valid.
+        match minor_version {
+            _ if major_version < 56 => Ok(()),
+            0 => Ok(()),
+            PREVIEW_MINOR_VERSION if major_version != self.max_major_version => Err(unsupported),
+            PREVIEW_MINOR_VERSION if !self.enable_preview => Err(ClassFormatErrorKind::PreviewNotEnabled { major_version }),
+            PREVIEW_MINOR_VERSION => Ok(()),
+            _ => Err(unsupported),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unsupported(major_version: u16, minor_version: u16) -> Result<(), ClassFormatErrorKind> {
+        Err(ClassFormatErrorKind::UnsupportedClassVersion { major_version, minor_version })
+    }
+
+    #[test]
+    fn major_versions_outside_the_range_are_rejected() {
+        let versions = VersionPolicy::default();
+        assert_eq!(versions.check(44, 0), unsupported(44, 0));
+        assert_eq!(versions.check(45, 0), Ok(()));
+        assert_eq!(versions.check(61, 0), Ok(()));
+        assert_eq!(versions.check(62, 0), unsupported(62, 0));
+    }
+
+    #[test]
+    fn preview_minor_versions_need_enable_preview_and_the_newest_major_version() {
+        let versions = VersionPolicy::default();
+        assert_eq!(versions.check(61, 0xFFFF), Err(ClassFormatErrorKind::PreviewNotEnabled { major_version: 61 }));
+
+        let versions = VersionPolicy { enable_preview: true, ..VersionPolicy::default() };
+        assert_eq!(versions.check(61, 0xFFFF), Ok(()));
+        assert_eq!(versions.check(60, 0xFFFF), unsupported(60, 0xFFFF));
+        assert_eq!(versions.check(56, 0xFFFF), unsupported(56, 0xFFFF));
+
+        let versions = VersionPolicy { enable_preview: true, max_major_version: 65, ..VersionPolicy::default() };
+        assert_eq!(versions.check(65, 0xFFFF), Ok(()));
+        assert_eq!(versions.check(61, 0xFFFF), unsupported(61, 0xFFFF));
+    }
+
+    #[test]
+    fn other_minor_versions_are_rejected_from_major_version_56() {
+        let versions = VersionPolicy { enable_preview: true, ..VersionPolicy::default() };
+        for major_version in [56, 60, 61] {
+            assert_eq!(versions.check(major_version, 1), unsupported(major_version, 1));
+            assert_eq!(versions.check(major_version, 0xFFFE), unsupported(major_version, 0xFFFE));
+        }
+    }
+
+    #[test]
+    fn any_minor_version_is_accepted_below_major_version_56() {
+        let versions = VersionPolicy::default();
+        for major_version in [45, 49, 52, 55] {
+            for minor_version in [0, 3, 0xFFFE, 0xFFFF] {
+                assert_eq!(versions.check(major_version, minor_version), Ok(()), "{}.{}", major_version, minor_version);
+            }
+        }
+    }
+}
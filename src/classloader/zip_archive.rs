@@ -0,0 +1,259 @@
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Range;
+
+use crate::entity::class_bytes::ClassBytes;
+
+// A read-only ZIP archive (and so a JAR), held in memory. Entries are located through
+// the central directory, which also supplies the sizes and CRC of entries written
+// with a trailing data descriptor. ZIP64 extensions are understood; compression
+// methods other than stored and deflated, encryption and multi-disk archives are not.
+
+const LOCAL_FILE_HEADER_SIGNATURE: u32 = 0x04034B50;
+const CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x02014B50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x06054B50;
+const ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x06064B50;
+const ZIP64_LOCATOR_SIGNATURE: u32 = 0x07064B50;
+const ZIP64_EXTRA_FIELD_ID: u16 = 0x0001;
+
+const END_OF_CENTRAL_DIRECTORY_SIZE: usize = 22;
+const ZIP64_LOCATOR_SIZE: usize = 20;
+const MAX_COMMENT_LENGTH: usize = 0xFFFF;
+
+const METHOD_STORED: u16 = 0;
+const METHOD_DEFLATED: u16 = 8;
+const FLAG_ENCRYPTED: u16 = 0x0001;
+
+pub struct ZipArchive {
+    data: ClassBytes,
+    entries: HashMap<String, ZipEntry>,
+}
+
+#[derive(Debug, Clone)]
+struct ZipEntry {
+    flags: u16,
+    method: u16,
+    crc32: u32,
+    compressed_size: u64,
+    uncompressed_size: u64,
+    local_header_offset: u64,
+}
+
+impl ZipArchive {
+    pub fn open(path: &str) -> Result<Self, String> {
+        let data = fs::read(path).map_err(|e| e.to_string())?;
+        ZipArchive::new(ClassBytes::from(data))
+    }
+
+    pub fn new(data: ClassBytes) -> Result<Self, String> {
+        let end = find_end_of_central_directory(&data)?;
+        let mut directory = Cursor::new(&data, end + 4);
+        let disk = directory.u16()?;
+        let directory_disk = directory.u16()?;
+        directory.u16()?; // entries on this disk
+        let mut entry_count = u64::from(directory.u16()?);
+        let mut directory_size = u64::from(directory.u32()?);
+        let mut directory_offset = u64::from(directory.u32()?);
+
+        if disk != 0 || directory_disk != 0 {
+            return Err("multi-disk archives are not supported".to_string());
+        }
+        if entry_count == 0xFFFF || directory_size == 0xFFFF_FFFF || directory_offset == 0xFFFF_FFFF {
+            (entry_count, directory_size, directory_offset) = read_zip64_end_of_central_directory(&data, end)?;
+        }
+
+        let directory_start = to_usize(directory_offset)?;
+        let directory_end = directory_start.checked_add(to_usize(directory_size)?).filter(|&end| end <= data.len())
+            .ok_or("central directory lies outside the archive")?;
+
+        let mut entries = HashMap::new();
+        let mut cursor = Cursor::new(&data, directory_start);
+        for _ in 0..entry_count {
+            let (name, entry) = read_central_directory_header(&mut cursor)?;
+            if cursor.position > directory_end {
+                return Err("central directory is truncated".to_string());
+            }
+            entries.entry(name).or_insert(entry);
+        }
+
+        Ok(ZipArchive { data, entries })
+    }
+
+    /// The uncompressed contents of entry `name`, or `None` if there is no such entry.
+    /// Stored entries share the archive's buffer.
+    pub fn read(&self, name: &str) -> Result<Option<ClassBytes>, String> {
+        let Some(entry) = self.entries.get(name) else {
+            return Ok(None);
+        };
+        let error = |message: &str| format!("{}: {}", name, message);
+
+        if entry.flags & FLAG_ENCRYPTED != 0 {
+            return Err(error("encrypted entries are not supported"));
+        }
+        let range = self.entry_data(entry).map_err(|message| error(&message))?;
+        let uncompressed_size = to_usize(entry.uncompressed_size).map_err(|message| error(&message))?;
+
+        let contents = match entry.method {
+            METHOD_STORED => self.data.slice(range),
+            METHOD_DEFLATED => {
+                miniz_oxide::inflate::decompress_to_vec_with_limit(&self.data[range], uncompressed_size)
+                    .map(ClassBytes::from)
+                    .map_err(|e| error(&format!("invalid deflate data ({:?})", e.status)))?
+            }
+            method => return Err(error(&format!("unsupported compression method {}", method))),
+        };
+
+        if contents.len() != uncompressed_size {
+            return Err(error("size does not match the central directory"));
+        }
+        if crc32fast::hash(&contents) != entry.crc32 {
+            return Err(error("CRC mismatch"));
+        }
+        Ok(Some(contents))
+    }
+
+    /// Where the compressed data of `entry` lies. The local header repeats the name and
+    /// may carry a different extra field, so only its lengths are taken from it.
+    fn entry_data(&self, entry: &ZipEntry) -> Result<Range<usize>, String> {
+        let mut header = Cursor::new(&self.data, to_usize(entry.local_header_offset)?);
+        if header.u32()? != LOCAL_FILE_HEADER_SIGNATURE {
+            return Err("missing local file header".to_string());
+        }
+        header.skip(22)?;
+        let name_length = usize::from(header.u16()?);
+        let extra_length = usize::from(header.u16()?);
+
+        let start = header.position + name_length + extra_length;
+        let end = start.checked_add(to_usize(entry.compressed_size)?).filter(|&end| end <= self.data.len())
+            .ok_or("entry data lies outside the archive")?;
+        Ok(start..end)
+    }
+}
+
+/// The end of central directory record is the last thing in the archive, followed only
+/// by a comment of up to 64 KiB, so it is searched for backwards from the end.
+fn find_end_of_central_directory(data: &[u8]) -> Result<usize, String> {
+    if data.len() < END_OF_CENTRAL_DIRECTORY_SIZE {
+        return Err("not a zip archive".to_string());
+    }
+    let last = data.len() - END_OF_CENTRAL_DIRECTORY_SIZE;
+    let first = last.saturating_sub(MAX_COMMENT_LENGTH);
+    (first..=last).rev()
+        .find(|&position| {
+            let comment_length = usize::from(u16::from_le_bytes([data[position + 20], data[position + 21]]));
+            u32::from_le_bytes(data[position..position + 4].try_into().unwrap()) == END_OF_CENTRAL_DIRECTORY_SIGNATURE
+                && position + END_OF_CENTRAL_DIRECTORY_SIZE + comment_length == data.len()
+        })
+        .ok_or_else(|| "end of central directory not found".to_string())
+}
+
+/// Returns the entry count, size and offset of the central directory.
+fn read_zip64_end_of_central_directory(data: &[u8], end: usize) -> Result<(u64, u64, u64), String> {
+    let locator = end.checked_sub(ZIP64_LOCATOR_SIZE).ok_or("missing zip64 end of central directory locator")?;
+    let mut cursor = Cursor::new(data, locator);
+    if cursor.u32()? != ZIP64_LOCATOR_SIGNATURE {
+        return Err("missing zip64 end of central directory locator".to_string());
+    }
+    cursor.u32()?; // disk holding the record
+    let record = to_usize(cursor.u64()?)?;
+
+    let mut cursor = Cursor::new(data, record);
+    if cursor.u32()? != ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE {
+        return Err("missing zip64 end of central directory record".to_string());
+    }
+    // Record size, versions made by and needed, and disk numbers.
+    cursor.skip(8 + 2 + 2 + 4 + 4)?;
+    cursor.u64()?; // entries on this disk
+    let entry_count = cursor.u64()?;
+    let directory_size = cursor.u64()?;
+    let directory_offset = cursor.u64()?;
+    Ok((entry_count, directory_size, directory_offset))
+}
+
+fn read_central_directory_header(cursor: &mut Cursor) -> Result<(String, ZipEntry), String> {
+    if cursor.u32()? != CENTRAL_DIRECTORY_SIGNATURE {
+        return Err("invalid central directory header".to_string());
+    }
+    cursor.skip(4)?; // versions made by and needed
+    let flags = cursor.u16()?;
+    let method = cursor.u16()?;
+    cursor.skip(4)?; // modification time and date
+    let crc32 = cursor.u32()?;
+    let mut compressed_size = u64::from(cursor.u32()?);
+    let mut uncompressed_size = u64::from(cursor.u32()?);
+    let name_length = usize::from(cursor.u16()?);
+    let extra_length = usize::from(cursor.u16()?);
+    let comment_length = usize::from(cursor.u16()?);
+    cursor.skip(2 + 2 + 4)?; // disk number, internal and external attributes
+    let mut local_header_offset = u64::from(cursor.u32()?);
+
+    let name = String::from_utf8_lossy(cursor.bytes(name_length)?).into_owned();
+    let extra = cursor.bytes(extra_length)?;
+    cursor.skip(comment_length)?;
+
+    // The ZIP64 extra field holds, in this order, only the values that overflowed.
+    let mut fields = Cursor::new(extra, 0);
+    while fields.remaining() >= 4 {
+        let id = fields.u16()?;
+        let size = usize::from(fields.u16()?);
+        let mut field = Cursor::new(fields.bytes(size)?, 0);
+        if id != ZIP64_EXTRA_FIELD_ID {
+            continue;
+        }
+        if uncompressed_size == 0xFFFF_FFFF {
+            uncompressed_size = field.u64()?;
+        }
+        if compressed_size == 0xFFFF_FFFF {
+            compressed_size = field.u64()?;
+        }
+        if local_header_offset == 0xFFFF_FFFF {
+            local_header_offset = field.u64()?;
+        }
+    }
+
+    Ok((name, ZipEntry { flags, method, crc32, compressed_size, uncompressed_size, local_header_offset }))
+}
+
+fn to_usize(value: u64) -> Result<usize, String> {
+    usize::try_from(value).map_err(|_| "archive is too large".to_string())
+}
+
+/// Little-endian reads over a byte slice, failing rather than panicking at its end.
+struct Cursor<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(data: &'a [u8], position: usize) -> Self {
+        Cursor { data, position }
+    }
+
+    fn remaining(&self) -> usize {
+        self.data.len().saturating_sub(self.position)
+    }
+
+    fn bytes(&mut self, length: usize) -> Result<&'a [u8], String> {
+        let bytes = self.position.checked_add(length)
+            .and_then(|end| self.data.get(self.position..end))
+            .ok_or("unexpected end of archive")?;
+        self.position += length;
+        Ok(bytes)
+    }
+
+    fn skip(&mut self, length: usize) -> Result<(), String> {
+        self.bytes(length).map(|_| ())
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        Ok(u16::from_le_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_le_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+}
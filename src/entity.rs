@@ -2,4 +2,12 @@ pub mod java_class_file;
 pub mod constant_pool;
 pub mod field_info;
 pub mod attribute_info;
-pub mod method_info;
\ No newline at end of file
+pub mod method_info;
+pub mod class_format_error;
+pub mod class_reader;
+pub mod class_writer;
+pub mod class_bytes;
+pub mod mutf8;
+pub mod descriptor;
+pub mod signature;
+pub mod access_flags;pub mod instruction;
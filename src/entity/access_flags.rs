@@ -0,0 +1,330 @@
+use crate::entity::class_format_error::*;
+use serde::{Deserialize, Serialize};
+
+// Access flags are interpreted per context: 0x0020 is ACC_SUPER on a class but
+// ACC_SYNCHRONIZED on a method, 0x0040 is ACC_VOLATILE on a field but ACC_BRIDGE
+// on a method, and so on.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ClassAccessFlags(pub u16);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct FieldAccessFlags(pub u16);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct MethodAccessFlags(pub u16);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct InnerClassAccessFlags(pub u16);
+
+const ACC_PUBLIC: u16 = 0x0001;
+const ACC_PRIVATE: u16 = 0x0002;
+const ACC_PROTECTED: u16 = 0x0004;
+const ACC_STATIC: u16 = 0x0008;
+const ACC_FINAL: u16 = 0x0010;
+const ACC_SUPER: u16 = 0x0020;
+const ACC_SYNCHRONIZED: u16 = 0x0020;
+const ACC_VOLATILE: u16 = 0x0040;
+const ACC_BRIDGE: u16 = 0x0040;
+const ACC_TRANSIENT: u16 = 0x0080;
+const ACC_VARARGS: u16 = 0x0080;
+const ACC_NATIVE: u16 = 0x0100;
+const ACC_INTERFACE: u16 = 0x0200;
+const ACC_ABSTRACT: u16 = 0x0400;
+const ACC_STRICT: u16 = 0x0800;
+const ACC_SYNTHETIC: u16 = 0x1000;
+const ACC_ANNOTATION: u16 = 0x2000;
+const ACC_ENUM: u16 = 0x4000;
+const ACC_MODULE: u16 = 0x8000;
+
+fn illegal(context: &'static str, flags: u16) -> ClassFormatError {
+    ClassFormatError::from(ClassFormatErrorKind::IllegalModifiers { context, flags })
+}
+
+fn has_more_than_one(flags: u16, mask: u16) -> bool {
+    (flags & mask).count_ones() > 1
+}
+
+fn names(flags: u16, table: &[(u16, &'static str)]) -> Vec<&'static str> {
+    table.iter()
+        .filter(|(flag, _)| flags & flag != 0)
+        .map(|&(_, name)| name)
+        .collect()
+}
+
+impl ClassAccessFlags {
+    pub fn bits(&self) -> u16 { self.0 }
+
+    /// Names of the flags that are set, in the order `javap` lists them.
+    pub fn names(&self) -> Vec<&'static str> {
+        names(self.0, &[
+            (ACC_PUBLIC, "ACC_PUBLIC"), (ACC_FINAL, "ACC_FINAL"), (ACC_SUPER, "ACC_SUPER"),
+            (ACC_INTERFACE, "ACC_INTERFACE"), (ACC_ABSTRACT, "ACC_ABSTRACT"),
+            (ACC_SYNTHETIC, "ACC_SYNTHETIC"), (ACC_ANNOTATION, "ACC_ANNOTATION"), (ACC_ENUM, "ACC_ENUM"),
+            (ACC_MODULE, "ACC_MODULE"),
+        ])
+    }
+
+    pub fn is_public(&self) -> bool { self.0 & ACC_PUBLIC != 0 }
+    pub fn is_final(&self) -> bool { self.0 & ACC_FINAL != 0 }
+    pub fn is_super(&self) -> bool { self.0 & ACC_SUPER != 0 }
+    pub fn is_interface(&self) -> bool { self.0 & ACC_INTERFACE != 0 }
+    pub fn is_abstract(&self) -> bool { self.0 & ACC_ABSTRACT != 0 }
+    pub fn is_annotation(&self) -> bool { self.0 & ACC_ANNOTATION != 0 }
+    pub fn is_enum(&self) -> bool { self.0 & ACC_ENUM != 0 }
+    pub fn is_module(&self) -> bool { self.0 & ACC_MODULE != 0 }
+
+    /// JVMS 4.1
+    pub fn check(&self, major_version: u16) -> Result<(), ClassFormatError> {
+        if self.is_module() {
+            if major_version < 53 || self.0 != ACC_MODULE {
+                return Err(illegal("class", self.0));
+            }
+            return Ok(());
+        }
+
+        let legal = if self.is_interface() {
+            self.is_abstract() && !self.is_final() && !self.is_super() && !self.is_enum()
+        } else {
+            !self.is_annotation() && (!self.is_final() || !self.is_abstract())
+        };
+        if !legal {
+            return Err(illegal("class", self.0));
+        }
+
+        Ok(())
+    }
+}
+
+impl FieldAccessFlags {
+    pub fn bits(&self) -> u16 { self.0 }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        names(self.0, &[
+            (ACC_PUBLIC, "ACC_PUBLIC"), (ACC_PRIVATE, "ACC_PRIVATE"), (ACC_PROTECTED, "ACC_PROTECTED"),
+            (ACC_STATIC, "ACC_STATIC"), (ACC_FINAL, "ACC_FINAL"), (ACC_VOLATILE, "ACC_VOLATILE"),
+            (ACC_TRANSIENT, "ACC_TRANSIENT"), (ACC_SYNTHETIC, "ACC_SYNTHETIC"), (ACC_ENUM, "ACC_ENUM"),
+        ])
+    }
+
+    pub fn is_public(&self) -> bool { self.0 & ACC_PUBLIC != 0 }
+    pub fn is_private(&self) -> bool { self.0 & ACC_PRIVATE != 0 }
+    pub fn is_protected(&self) -> bool { self.0 & ACC_PROTECTED != 0 }
+    pub fn is_static(&self) -> bool { self.0 & ACC_STATIC != 0 }
+    pub fn is_final(&self) -> bool { self.0 & ACC_FINAL != 0 }
+    pub fn is_volatile(&self) -> bool { self.0 & ACC_VOLATILE != 0 }
+
+    /// JVMS 4.5
+    pub fn check(&self, in_interface: bool) -> Result<(), ClassFormatError> {
+        let legal = if in_interface {
+            self.is_public() && self.is_static() && self.is_final()
+                && self.0 & !(ACC_PUBLIC | ACC_STATIC | ACC_FINAL | ACC_SYNTHETIC) == 0
+        } else {
+            !has_more_than_one(self.0, ACC_PUBLIC | ACC_PRIVATE | ACC_PROTECTED)
+                && (!self.is_final() || !self.is_volatile())
+        };
+        if !legal {
+            return Err(illegal("field", self.0));
+        }
+
+        Ok(())
+    }
+}
+
+impl MethodAccessFlags {
+    pub fn bits(&self) -> u16 { self.0 }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        names(self.0, &[
+            (ACC_PUBLIC, "ACC_PUBLIC"), (ACC_PRIVATE, "ACC_PRIVATE"), (ACC_PROTECTED, "ACC_PROTECTED"),
+            (ACC_STATIC, "ACC_STATIC"), (ACC_FINAL, "ACC_FINAL"), (ACC_SYNCHRONIZED, "ACC_SYNCHRONIZED"),
+            (ACC_BRIDGE, "ACC_BRIDGE"), (ACC_VARARGS, "ACC_VARARGS"), (ACC_NATIVE, "ACC_NATIVE"),
+            (ACC_ABSTRACT, "ACC_ABSTRACT"), (ACC_STRICT, "ACC_STRICT"), (ACC_SYNTHETIC, "ACC_SYNTHETIC"),
+        ])
+    }
+
+    pub fn is_public(&self) -> bool { self.0 & ACC_PUBLIC != 0 }
+    pub fn is_private(&self) -> bool { self.0 & ACC_PRIVATE != 0 }
+    pub fn is_protected(&self) -> bool { self.0 & ACC_PROTECTED != 0 }
+    pub fn is_static(&self) -> bool { self.0 & ACC_STATIC != 0 }
+    pub fn is_final(&self) -> bool { self.0 & ACC_FINAL != 0 }
+    pub fn is_synchronized(&self) -> bool { self.0 & ACC_SYNCHRONIZED != 0 }
+    pub fn is_varargs(&self) -> bool { self.0 & ACC_VARARGS != 0 }
+    pub fn is_native(&self) -> bool { self.0 & ACC_NATIVE != 0 }
+    pub fn is_abstract(&self) -> bool { self.0 & ACC_ABSTRACT != 0 }
+    pub fn is_strict(&self) -> bool { self.0 & ACC_STRICT != 0 }
+
+    /// JVMS 4.6. Flags of `<clinit>` are ignored apart from ACC_STATIC.
+    pub fn check(&self, name: &str, in_interface: bool, major_version: u16) -> Result<(), ClassFormatError> {
+        if name == "<clinit>" {
+            if major_version >= 51 && !self.is_static() {
+                return Err(illegal("method", self.0));
+            }
+            return Ok(());
+        }
+
+        let mut legal = !has_more_than_one(self.0, ACC_PUBLIC | ACC_PRIVATE | ACC_PROTECTED);
+
+        if in_interface {
+            legal &= if major_version < 52 {
+                self.is_public() && self.is_abstract()
+            } else {
+                !self.is_protected() && !self.is_final() && !self.is_synchronized() && !self.is_native()
+                    && self.is_public() != self.is_private()
+            };
+        }
+
+        if self.is_abstract() {
+            legal &= !self.is_private() && !self.is_static() && !self.is_final() && !self.is_synchronized() && !self.is_native();
+            if (46..=60).contains(&major_version) {
+                legal &= !self.is_strict();
+            }
+        }
+
+        if name == "<init>" {
+            legal &= !in_interface
+                && self.0 & !(ACC_PUBLIC | ACC_PRIVATE | ACC_PROTECTED | ACC_VARARGS | ACC_STRICT | ACC_SYNTHETIC) == 0;
+        }
+
+        if !legal {
+            return Err(illegal("method", self.0));
+        }
+
+        Ok(())
+    }
+}
+
+impl InnerClassAccessFlags {
+    pub fn bits(&self) -> u16 { self.0 }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        names(self.0, &[
+            (ACC_PUBLIC, "ACC_PUBLIC"), (ACC_PRIVATE, "ACC_PRIVATE"), (ACC_PROTECTED, "ACC_PROTECTED"),
+            (ACC_STATIC, "ACC_STATIC"), (ACC_FINAL, "ACC_FINAL"), (ACC_INTERFACE, "ACC_INTERFACE"),
+            (ACC_ABSTRACT, "ACC_ABSTRACT"), (ACC_SYNTHETIC, "ACC_SYNTHETIC"),
+            (ACC_ANNOTATION, "ACC_ANNOTATION"), (ACC_ENUM, "ACC_ENUM"),
+        ])
+    }
+
+    pub fn is_final(&self) -> bool { self.0 & ACC_FINAL != 0 }
+    pub fn is_interface(&self) -> bool { self.0 & ACC_INTERFACE != 0 }
+    pub fn is_abstract(&self) -> bool { self.0 & ACC_ABSTRACT != 0 }
+    pub fn is_annotation(&self) -> bool { self.0 & ACC_ANNOTATION != 0 }
+    pub fn is_enum(&self) -> bool { self.0 & ACC_ENUM != 0 }
+
+    /// JVMS 4.7.6 refers back to the class rules of 4.1.
+    pub fn check(&self) -> Result<(), ClassFormatError> {
+        let legal = if self.is_interface() {
+            self.is_abstract() && !self.is_final() && !self.is_enum()
+        } else {
+            !self.is_annotation() && (!self.is_final() || !self.is_abstract())
+        };
+        if !legal {
+            return Err(illegal("inner class", self.0));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn class_flags() {
+        for (flags, major_version, legal) in [
+            (ACC_PUBLIC | ACC_SUPER, 52, true),
+            (ACC_PUBLIC | ACC_FINAL | ACC_SUPER, 52, true),
+            (ACC_FINAL | ACC_ABSTRACT, 52, false),
+            (ACC_INTERFACE | ACC_ABSTRACT, 52, true),
+            (ACC_INTERFACE | ACC_ABSTRACT | ACC_ANNOTATION, 52, true),
+            (ACC_INTERFACE, 52, false),
+            (ACC_INTERFACE | ACC_ABSTRACT | ACC_FINAL, 52, false),
+            (ACC_INTERFACE | ACC_ABSTRACT | ACC_ENUM, 52, false),
+            (ACC_ANNOTATION, 52, false),
+            (ACC_MODULE, 53, true),
+            (ACC_MODULE, 52, false),
+            (ACC_MODULE | ACC_PUBLIC, 53, false),
+        ] {
+            assert_eq!(ClassAccessFlags(flags).check(major_version).is_ok(), legal, "{:#06X} at {}", flags, major_version);
+        }
+    }
+
+    #[test]
+    fn field_flags() {
+        for (flags, in_interface, legal) in [
+            (ACC_PRIVATE | ACC_STATIC | ACC_FINAL, false, true),
+            (ACC_PUBLIC | ACC_PRIVATE, false, false),
+            (ACC_PROTECTED | ACC_PRIVATE, false, false),
+            (ACC_FINAL | ACC_VOLATILE, false, false),
+            (ACC_VOLATILE | ACC_TRANSIENT, false, true),
+            (ACC_PUBLIC | ACC_STATIC | ACC_FINAL, true, true),
+            (ACC_PUBLIC | ACC_STATIC | ACC_FINAL | ACC_SYNTHETIC, true, true),
+            (ACC_PUBLIC | ACC_STATIC, true, false),
+            (ACC_PUBLIC | ACC_STATIC | ACC_FINAL | ACC_TRANSIENT, true, false),
+        ] {
+            assert_eq!(FieldAccessFlags(flags).check(in_interface).is_ok(), legal, "{:#06X} in interface: {}", flags, in_interface);
+        }
+    }
+
+    #[test]
+    fn method_flags() {
+        for (name, flags, in_interface, major_version, legal) in [
+            ("m", ACC_PUBLIC | ACC_ABSTRACT, false, 52, true),
+            ("m", ACC_PUBLIC | ACC_PRIVATE, false, 52, false),
+            ("m", ACC_PRIVATE | ACC_ABSTRACT, false, 52, false),
+            ("m", ACC_STATIC | ACC_ABSTRACT, false, 52, false),
+            ("m", ACC_SYNCHRONIZED | ACC_ABSTRACT, false, 52, false),
+            ("m", ACC_FINAL | ACC_ABSTRACT, false, 52, false),
+            ("m", ACC_NATIVE | ACC_ABSTRACT, false, 52, false),
+            ("m", ACC_STRICT | ACC_ABSTRACT, false, 52, false),
+            ("m", ACC_STRICT | ACC_ABSTRACT, false, 61, true),
+            ("<clinit>", 0, false, 50, true),
+            ("<clinit>", 0, false, 51, false),
+            ("<clinit>", ACC_STATIC | ACC_PRIVATE | ACC_PUBLIC, false, 51, true),
+            ("<init>", ACC_PUBLIC | ACC_VARARGS, false, 52, true),
+            ("<init>", ACC_STATIC, false, 52, false),
+            ("<init>", ACC_PUBLIC, true, 52, false),
+            // Before Java 8, interface methods are public and abstract.
+            ("m", ACC_PUBLIC | ACC_ABSTRACT, true, 51, true),
+            ("m", ACC_PUBLIC, true, 51, false),
+            ("m", ACC_PUBLIC | ACC_STATIC, true, 51, false),
+            ("m", ACC_PRIVATE, true, 51, false),
+            // Since then they may have bodies, be static, or be private.
+            ("m", ACC_PUBLIC, true, 52, true),
+            ("m", ACC_PUBLIC | ACC_STATIC, true, 52, true),
+            ("m", ACC_PRIVATE, true, 52, true),
+            ("m", 0, true, 52, false),
+            ("m", ACC_PUBLIC | ACC_PRIVATE, true, 52, false),
+            ("m", ACC_PROTECTED, true, 52, false),
+            ("m", ACC_PUBLIC | ACC_FINAL, true, 52, false),
+            ("m", ACC_PUBLIC | ACC_SYNCHRONIZED, true, 52, false),
+            ("m", ACC_PUBLIC | ACC_NATIVE, true, 52, false),
+        ] {
+            let result = MethodAccessFlags(flags).check(name, in_interface, major_version);
+            assert_eq!(result.is_ok(), legal, "{} {:#06X} in interface: {} at {}", name, flags, in_interface, major_version);
+        }
+    }
+
+    #[test]
+    fn bit_0x0020_is_acc_super_on_classes_and_acc_synchronized_on_methods() {
+        assert_eq!(ClassAccessFlags(0x0020).names(), ["ACC_SUPER"]);
+        assert_eq!(MethodAccessFlags(0x0020).names(), ["ACC_SYNCHRONIZED"]);
+        assert!(ClassAccessFlags(0x0020).is_super());
+        assert!(MethodAccessFlags(0x0020).is_synchronized());
+
+        // Legal on a class and on a concrete method, but not on an interface or an abstract method.
+        assert!(ClassAccessFlags(ACC_PUBLIC | 0x0020).check(52).is_ok());
+        assert!(ClassAccessFlags(ACC_INTERFACE | ACC_ABSTRACT | 0x0020).check(52).is_err());
+        assert!(MethodAccessFlags(ACC_PUBLIC | 0x0020).check("m", false, 52).is_ok());
+        assert!(MethodAccessFlags(ACC_ABSTRACT | 0x0020).check("m", false, 52).is_err());
+    }
+
+    #[test]
+    fn illegal_flags_are_reported_with_their_context() {
+        let error = FieldAccessFlags(ACC_PUBLIC | ACC_PRIVATE).check(false).unwrap_err();
+        assert_eq!(error.kind, ClassFormatErrorKind::IllegalModifiers { context: "field", flags: 0x0003 });
+        assert_eq!(error.to_string(), "Illegal field modifiers 0x0003");
+    }
+}
@@ -1,32 +1,45 @@
 use crate::entity::constant_pool::*;
-use byteorder::{BigEndian, ReadBytesExt};
+use crate::entity::class_format_error::*;
+use crate::entity::class_reader::ClassReader;
+use crate::entity::class_writer::ClassWriter;
+use crate::entity::class_bytes::ClassBytes;
+use crate::entity::signature::*;
+use crate::entity::access_flags::InnerClassAccessFlags;
+use crate::entity::instruction::{decode, Instruction};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AttributeInfoBase {
     pub attribute_name_index: u16,
     pub attribute_length: u32,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConstantValueAttribute {
     pub base: AttributeInfoBase,
     pub constant_value_index: u16,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CodeAttribute {
     pub base: AttributeInfoBase,
     pub max_stack: u16,
     pub max_locals: u16,
     pub code_length: u32,
-    pub code: Vec<u8>,
+    pub code: ClassBytes,
     pub exception_table_length: u16,
     pub exception_table: Vec<ExceptionTableEntry>,
     pub attributes_count: u16,
     pub attributes: Vec<AttributeKind>,
 }
 
-#[derive(Debug, Clone)]
+impl CodeAttribute {
+    pub fn instructions(&self) -> Result<Vec<(u32, Instruction)>, ClassFormatError> {
+        decode(&self.code)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExceptionTableEntry {
     pub start_pc: u16,
     pub end_pc: u16,
@@ -34,69 +47,75 @@ pub struct ExceptionTableEntry {
     pub catch_type: u16,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StackMapTableAttribute {
     pub base: AttributeInfoBase,
     pub number_of_entries: u16,
     pub entries: Vec<StackMapFrame>,
 }
 
-#[derive(Debug, Clone)]
-pub struct StackMapFrame {
-    pub frame_type: u8,
-    pub same_frame: SameFrame,
-    pub same_locals_1_stack_item_frame: SameLocals1StackItemFrame,
-    pub same_locals_1_stack_item_frame_extended: SameLocals1StackItemFrameExtended,
-    pub chop_frame: ChopFrame,
-    pub same_frame_extended: SameFrameExtended,
-    pub append_frame: AppendFrame,
-    pub full_frame: FullFrame,
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum StackMapFrame {
+    SameFrame(SameFrame),  // 0-63
+    SameLocals1StackItemFrame(SameLocals1StackItemFrame),  // 64-127
+    SameLocals1StackItemFrameExtended(SameLocals1StackItemFrameExtended),  // 247
+    ChopFrame(ChopFrame),  // 248-250
+    SameFrameExtended(SameFrameExtended),  // 251
+    AppendFrame(AppendFrame),  // 252-254
+    FullFrame(FullFrame),  // 255
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SameFrame {
     pub frame_type: u8,
 }
 
-#[derive(Debug, Clone)]
-pub struct VerificationTypeInfo {
-    pub tag: u8,
-    pub value: u16,
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VerificationTypeInfo {
+    Top,  // 0
+    Integer,  // 1
+    Float,  // 2
+    Double,  // 3
+    Long,  // 4
+    Null,  // 5
+    UninitializedThis,  // 6
+    Object { cpool_index: u16 },  // 7
+    Uninitialized { offset: u16 },  // 8
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SameLocals1StackItemFrame {
     pub frame_type: u8,
     pub stack: VerificationTypeInfo,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SameLocals1StackItemFrameExtended {
     pub frame_type: u8,
     pub offset_delta: u16,
     pub stack: VerificationTypeInfo,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChopFrame {
     pub frame_type: u8,
     pub offset_delta: u16,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SameFrameExtended {
     pub frame_type: u8,
     pub offset_delta: u16,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppendFrame {
     pub frame_type: u8,
     pub offset_delta: u16,
     pub locals: Vec<VerificationTypeInfo>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FullFrame {
     pub frame_type: u8,
     pub offset_delta: u16,
@@ -106,119 +125,1488 @@ pub struct FullFrame {
     pub stack: Vec<VerificationTypeInfo>,
 }
 
-#[derive(Debug, Clone)]
+/// A frame of the StackMapTable with its absolute bytecode offset and the full
+/// contents of the locals and the operand stack. Long and Double take two slots
+/// here, the second one being Top, as in JVMS 4.10.1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpandedFrame {
+    pub offset: u16,
+    pub locals: Vec<VerificationTypeInfo>,
+    pub stack: Vec<VerificationTypeInfo>,
+}
+
+impl StackMapFrame {
+    pub fn offset_delta(&self) -> u16 {
+        match self {
+            StackMapFrame::SameFrame(frame) => frame.frame_type as u16,
+            StackMapFrame::SameLocals1StackItemFrame(frame) => frame.frame_type as u16 - 64,
+            StackMapFrame::SameLocals1StackItemFrameExtended(frame) => frame.offset_delta,
+            StackMapFrame::ChopFrame(frame) => frame.offset_delta,
+            StackMapFrame::SameFrameExtended(frame) => frame.offset_delta,
+            StackMapFrame::AppendFrame(frame) => frame.offset_delta,
+            StackMapFrame::FullFrame(frame) => frame.offset_delta,
+        }
+    }
+}
+
+impl VerificationTypeInfo {
+    pub fn is_category2(&self) -> bool {
+        matches!(self, VerificationTypeInfo::Long | VerificationTypeInfo::Double)
+    }
+}
+
+impl StackMapTableAttribute {
+    /// Applies the delta-encoded frames in order, starting from the method's implicit
+    /// initial frame. `initial_locals` lists the types of `this` (if any) and the
+    /// parameters, one entry per parameter as in the StackMapTable itself.
+    pub fn expand(&self, initial_locals: &[VerificationTypeInfo]) -> Result<Vec<ExpandedFrame>, ClassFormatError> {
+        let mut frames = Vec::with_capacity(self.entries.len());
+        let mut locals = initial_locals.to_vec();
+        let mut offset: Option<u16> = None;
+
+        for frame in &self.entries {
+            let delta = frame.offset_delta();
+            let frame_offset = match offset {
+                None => Some(delta),
+                Some(previous) => previous.checked_add(delta).and_then(|o| o.checked_add(1)),
+            }
+            .ok_or_else(|| ClassFormatError::from(ClassFormatErrorKind::InvalidStackMapFrameOffset))?;
+            offset = Some(frame_offset);
+
+            let stack = match frame {
+                StackMapFrame::SameFrame(_) | StackMapFrame::SameFrameExtended(_) => Vec::new(),
+                StackMapFrame::SameLocals1StackItemFrame(frame) => vec![frame.stack.clone()],
+                StackMapFrame::SameLocals1StackItemFrameExtended(frame) => vec![frame.stack.clone()],
+                StackMapFrame::ChopFrame(frame) => {
+                    let k = (251 - frame.frame_type) as usize;
+                    if k > locals.len() {
+                        return Err(ClassFormatError::from(ClassFormatErrorKind::InvalidStackMapFrameType(frame.frame_type)));
+                    }
+                    locals.truncate(locals.len() - k);
+                    Vec::new()
+                }
+                StackMapFrame::AppendFrame(frame) => {
+                    locals.extend(frame.locals.iter().cloned());
+                    Vec::new()
+                }
+                StackMapFrame::FullFrame(frame) => {
+                    locals = frame.locals.clone();
+                    frame.stack.clone()
+                }
+            };
+
+            frames.push(ExpandedFrame {
+                offset: frame_offset,
+                locals: to_slots(&locals),
+                stack: to_slots(&stack),
+            });
+        }
+
+        Ok(frames)
+    }
+}
+
+fn to_slots(types: &[VerificationTypeInfo]) -> Vec<VerificationTypeInfo> {
+    let mut slots = Vec::with_capacity(types.len());
+
+    for verification_type in types {
+        slots.push(verification_type.clone());
+        if verification_type.is_category2() {
+            slots.push(VerificationTypeInfo::Top);
+        }
+    }
+
+    slots
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BootstrapMethodsAttribute {
     pub base: AttributeInfoBase,
     pub num_bootstrap_methods: u16,
     pub bootstrap_methods: Vec<BootstrapMethod>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BootstrapMethod {
     pub bootstrap_method_ref: u16,
     pub num_bootstrap_arguments: u16,
     pub bootstrap_arguments: Vec<u16>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NestHostAttribute {
     pub base: AttributeInfoBase,
     pub host_class_index: u16,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NestMembersAttribute {
     pub base: AttributeInfoBase,
     pub number_of_classes: u16,
     pub classes: Vec<u16>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PermittedSubclassesAttribute {
     pub base: AttributeInfoBase,
     pub number_of_classes: u16,
     pub classes: Vec<u16>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExceptionsAttribute {
+    pub base: AttributeInfoBase,
+    pub number_of_exceptions: u16,
+    pub exception_index_table: Vec<u16>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InnerClassesAttribute {
+    pub base: AttributeInfoBase,
+    pub number_of_classes: u16,
+    pub classes: Vec<InnerClass>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InnerClass {
+    pub inner_class_info_index: u16,
+    pub outer_class_info_index: u16,
+    pub inner_name_index: u16,
+    pub inner_class_access_flags: InnerClassAccessFlags,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnclosingMethodAttribute {
+    pub base: AttributeInfoBase,
+    pub class_index: u16,
+    pub method_index: u16,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyntheticAttribute {
+    pub base: AttributeInfoBase,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignatureAttribute {
+    pub base: AttributeInfoBase,
+    pub signature_index: u16,
+}
+
+impl SignatureAttribute {
+    pub fn class_signature(&self, constant_pool: &ConstantPool) -> Result<ClassSignature, ClassFormatError> {
+        ClassSignature::parse(constant_pool.utf8(self.signature_index)?)
+            .map_err(|e| e.with_constant_pool_index(self.signature_index))
+    }
+
+    pub fn method_signature(&self, constant_pool: &ConstantPool) -> Result<MethodSignature, ClassFormatError> {
+        MethodSignature::parse(constant_pool.utf8(self.signature_index)?)
+            .map_err(|e| e.with_constant_pool_index(self.signature_index))
+    }
+
+    /// Signature of a field or record component.
+    pub fn field_signature(&self, constant_pool: &ConstantPool) -> Result<FieldSignature, ClassFormatError> {
+        ReferenceTypeSignature::parse(constant_pool.utf8(self.signature_index)?)
+            .map_err(|e| e.with_constant_pool_index(self.signature_index))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceFileAttribute {
+    pub base: AttributeInfoBase,
+    pub sourcefile_index: u16,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceDebugExtensionAttribute {
+    pub base: AttributeInfoBase,
+    pub debug_extension: ClassBytes,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LineNumberTableAttribute {
+    pub base: AttributeInfoBase,
+    pub line_number_table_length: u16,
+    pub line_number_table: Vec<LineNumberTableEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LineNumberTableEntry {
+    pub start_pc: u16,
+    pub line_number: u16,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalVariableTableAttribute {
+    pub base: AttributeInfoBase,
+    pub local_variable_table_length: u16,
+    pub local_variable_table: Vec<LocalVariableTableEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalVariableTableEntry {
+    pub start_pc: u16,
+    pub length: u16,
+    pub name_index: u16,
+    pub descriptor_index: u16,
+    pub index: u16,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalVariableTypeTableAttribute {
+    pub base: AttributeInfoBase,
+    pub local_variable_type_table_length: u16,
+    pub local_variable_type_table: Vec<LocalVariableTypeTableEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalVariableTypeTableEntry {
+    pub start_pc: u16,
+    pub length: u16,
+    pub name_index: u16,
+    pub signature_index: u16,
+    pub index: u16,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeprecatedAttribute {
+    pub base: AttributeInfoBase,
+}
+
+// Shared by RuntimeVisibleAnnotations and RuntimeInvisibleAnnotations.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuntimeAnnotationsAttribute {
+    pub base: AttributeInfoBase,
+    pub num_annotations: u16,
+    pub annotations: Vec<Annotation>,
+}
+
+// Shared by RuntimeVisibleParameterAnnotations and RuntimeInvisibleParameterAnnotations.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuntimeParameterAnnotationsAttribute {
+    pub base: AttributeInfoBase,
+    pub num_parameters: u8,
+    pub parameter_annotations: Vec<ParameterAnnotations>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParameterAnnotations {
+    pub num_annotations: u16,
+    pub annotations: Vec<Annotation>,
+}
+
+// Shared by RuntimeVisibleTypeAnnotations and RuntimeInvisibleTypeAnnotations.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuntimeTypeAnnotationsAttribute {
+    pub base: AttributeInfoBase,
+    pub num_annotations: u16,
+    pub annotations: Vec<TypeAnnotation>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Annotation {
+    pub type_index: u16,
+    pub num_element_value_pairs: u16,
+    pub element_value_pairs: Vec<ElementValuePair>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ElementValuePair {
+    pub element_name_index: u16,
+    pub value: ElementValue,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ElementValue {
+    // B, C, D, F, I, J, S, Z and s
+    Const { tag: u8, const_value_index: u16 },
+    EnumConst { type_name_index: u16, const_name_index: u16 },
+    Class { class_info_index: u16 },
+    Annotation(Annotation),
+    Array { num_values: u16, values: Vec<ElementValue> },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TypeAnnotation {
+    pub target_type: u8,
+    pub target_info: TargetInfo,
+    pub target_path: TypePath,
+    pub type_index: u16,
+    pub num_element_value_pairs: u16,
+    pub element_value_pairs: Vec<ElementValuePair>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum TargetInfo {
+    TypeParameter { type_parameter_index: u8 },
+    Supertype { supertype_index: u16 },
+    TypeParameterBound { type_parameter_index: u8, bound_index: u8 },
+    Empty,
+    FormalParameter { formal_parameter_index: u8 },
+    Throws { throws_type_index: u16 },
+    Localvar { table_length: u16, table: Vec<LocalvarTarget> },
+    Catch { exception_table_index: u16 },
+    Offset { offset: u16 },
+    TypeArgument { offset: u16, type_argument_index: u8 },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalvarTarget {
+    pub start_pc: u16,
+    pub length: u16,
+    pub index: u16,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TypePath {
+    pub path_length: u8,
+    pub path: Vec<TypePathEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TypePathEntry {
+    pub type_path_kind: u8,
+    pub type_argument_index: u8,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnnotationDefaultAttribute {
+    pub base: AttributeInfoBase,
+    pub default_value: ElementValue,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MethodParametersAttribute {
+    pub base: AttributeInfoBase,
+    pub parameters_count: u8,
+    pub parameters: Vec<MethodParameter>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MethodParameter {
+    pub name_index: u16,
+    pub access_flags: u16,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModuleAttribute {
+    pub base: AttributeInfoBase,
+    pub module_name_index: u16,
+    pub module_flags: u16,
+    pub module_version_index: u16,
+    pub requires_count: u16,
+    pub requires: Vec<ModuleRequires>,
+    pub exports_count: u16,
+    pub exports: Vec<ModuleExports>,
+    pub opens_count: u16,
+    pub opens: Vec<ModuleOpens>,
+    pub uses_count: u16,
+    pub uses_index: Vec<u16>,
+    pub provides_count: u16,
+    pub provides: Vec<ModuleProvides>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModuleRequires {
+    pub requires_index: u16,
+    pub requires_flags: u16,
+    pub requires_version_index: u16,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModuleExports {
+    pub exports_index: u16,
+    pub exports_flags: u16,
+    pub exports_to_count: u16,
+    pub exports_to_index: Vec<u16>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModuleOpens {
+    pub opens_index: u16,
+    pub opens_flags: u16,
+    pub opens_to_count: u16,
+    pub opens_to_index: Vec<u16>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModuleProvides {
+    pub provides_index: u16,
+    pub provides_with_count: u16,
+    pub provides_with_index: Vec<u16>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModulePackagesAttribute {
+    pub base: AttributeInfoBase,
+    pub package_count: u16,
+    pub package_index: Vec<u16>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModuleMainClassAttribute {
+    pub base: AttributeInfoBase,
+    pub main_class_index: u16,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordAttribute {
+    pub base: AttributeInfoBase,
+    pub components_count: u16,
+    pub components: Vec<RecordComponentInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordComponentInfo {
+    pub name_index: u16,
+    pub descriptor_index: u16,
+    pub attributes_count: u16,
+    pub attributes: Vec<AttributeKind>,
+}
+
+// Attributes this VM does not know, kept as-is so they can be written back out.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnknownAttribute {
+    pub base: AttributeInfoBase,
+    pub name: String,
+    pub bytes: ClassBytes,
+}
+
 // TODO: use unused attributes.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[allow(dead_code)]  // TODO remove
 pub enum AttributeKind {
     ConstantValue(ConstantValueAttribute),
     Code(CodeAttribute),
     StackMapTable(StackMapTableAttribute),
+    Exceptions(ExceptionsAttribute),
+    InnerClasses(InnerClassesAttribute),
+    EnclosingMethod(EnclosingMethodAttribute),
+    Synthetic(SyntheticAttribute),
+    Signature(SignatureAttribute),
+    SourceFile(SourceFileAttribute),
+    SourceDebugExtension(SourceDebugExtensionAttribute),
+    LineNumberTable(LineNumberTableAttribute),
+    LocalVariableTable(LocalVariableTableAttribute),
+    LocalVariableTypeTable(LocalVariableTypeTableAttribute),
+    Deprecated(DeprecatedAttribute),
+    RuntimeVisibleAnnotations(RuntimeAnnotationsAttribute),
+    RuntimeInvisibleAnnotations(RuntimeAnnotationsAttribute),
+    RuntimeVisibleParameterAnnotations(RuntimeParameterAnnotationsAttribute),
+    RuntimeInvisibleParameterAnnotations(RuntimeParameterAnnotationsAttribute),
+    RuntimeVisibleTypeAnnotations(RuntimeTypeAnnotationsAttribute),
+    RuntimeInvisibleTypeAnnotations(RuntimeTypeAnnotationsAttribute),
+    AnnotationDefault(AnnotationDefaultAttribute),
     BootstrapMethods(BootstrapMethodsAttribute),
+    MethodParameters(MethodParametersAttribute),
+    Module(ModuleAttribute),
+    ModulePackages(ModulePackagesAttribute),
+    ModuleMainClass(ModuleMainClassAttribute),
     NestHost(NestHostAttribute),
     NestMembers(NestMembersAttribute),
+    Record(RecordAttribute),
     PermittedSubclasses(PermittedSubclassesAttribute),
+    Unknown(UnknownAttribute),
 }
 
-pub fn load_attributes(count: u16, buffer: &mut &[u8], constant_pool: &Vec<ConstantKind>) -> Vec<AttributeKind> {
+pub fn load_attributes(count: u16, buffer: &mut ClassReader, constant_pool: &ConstantPool) -> Result<Vec<AttributeKind>, ClassFormatError> {
     let mut attributes: Vec<AttributeKind> = Vec::new();
 
     for _ in 0..count {
-        let attribute_name_index = buffer.read_u16::<BigEndian>().unwrap();
-        let attribute_length = buffer.read_u32::<BigEndian>().unwrap();
-        let name = match &constant_pool[attribute_name_index as usize] {
-            ConstantKind::Utf8(utf8_info) => utf8_info.text.clone(),
-            _ => panic!("Invalid constant pool entry: {:?}", attribute_name_index),
+        let name_offset = buffer.position();
+        let attribute_name_index = buffer.read_u16()?;
+        let attribute_length = buffer.read_u32()?;
+        let name = constant_pool.utf8(attribute_name_index)
+            .map_err(|e| e.with_offset(name_offset))?
+            .to_string();
+
+        // The body is parsed on its own so it can neither overrun nor fall short of `attribute_length`.
+        let mut body = buffer.sub_reader(attribute_length as usize)?;
+        let base = AttributeInfoBase {
+            attribute_name_index, attribute_length,
         };
+        attributes.push(load_attribute(&name, base, &mut body, constant_pool)?);
+        if !body.is_empty() {
+            return Err(body.error(ClassFormatErrorKind::AttributeLengthMismatch(name)));
+        }
+    }
+
+    Ok(attributes)
+}
+
+fn load_attribute(name: &str, base: AttributeInfoBase, buffer: &mut ClassReader, constant_pool: &ConstantPool) -> Result<AttributeKind, ClassFormatError> {
+    let attribute = match name {
+        "ConstantValue" => {
+            AttributeKind::ConstantValue(ConstantValueAttribute {
+                base,
+                constant_value_index: buffer.read_u16()?,
+            })
+        }
+
+        "Code" => {
+            let code_length;
+            let exception_table_length;
+            let attributes_count;
+
+            AttributeKind::Code(CodeAttribute {
+                base,
+                max_stack: buffer.read_u16()?,
+                max_locals: buffer.read_u16()?,
+                code_length: {
+                    code_length = buffer.read_u32()?;
+                    code_length
+                },
+                code: buffer.read_shared(code_length as usize)?,
+                exception_table_length: {
+                    exception_table_length = buffer.read_u16()?;
+                    exception_table_length
+                },
+                exception_table: {
+                    let mut exception_table = Vec::new();
+                    for _ in 0..exception_table_length {
+                        exception_table.push(ExceptionTableEntry {
+                            start_pc: buffer.read_u16()?,
+                            end_pc: buffer.read_u16()?,
+                            handler_pc: buffer.read_u16()?,
+                            catch_type: buffer.read_u16()?,
+                        });
+                    }
+                    exception_table
+                },
+                attributes_count: {
+                    attributes_count = buffer.read_u16()?;
+                    attributes_count
+                },
+                attributes: load_attributes(attributes_count, buffer, constant_pool)?,
+            })
+        }
+
+        "BootstrapMethods" => {
+            let num_bootstrap_methods;
+
+            AttributeKind::BootstrapMethods(BootstrapMethodsAttribute {
+                base,
+                num_bootstrap_methods: {
+                    num_bootstrap_methods = buffer.read_u16()?;
+                    num_bootstrap_methods
+                },
+                bootstrap_methods: {
+                    let mut bootstrap_methods = Vec::new();
+                    for _ in 0..num_bootstrap_methods {
+                        let num_bootstrap_arguments;
+                        bootstrap_methods.push(BootstrapMethod {
+                            bootstrap_method_ref: buffer.read_u16()?,
+                            num_bootstrap_arguments: {
+                                num_bootstrap_arguments = buffer.read_u16()?;
+                                num_bootstrap_arguments
+                            },
+                            bootstrap_arguments: {
+                                let mut bootstrap_arguments = Vec::new();
+                                for _ in 0..num_bootstrap_arguments {
+                                    bootstrap_arguments.push(buffer.read_u16()?);
+                                }
+                                bootstrap_arguments
+                            },
+                        });
+                    }
+                    bootstrap_methods
+                },
+            })
+        }
+
+        "StackMapTable" => {
+            let number_of_entries = buffer.read_u16()?;
+            AttributeKind::StackMapTable(StackMapTableAttribute {
+                base,
+                number_of_entries,
+                entries: load_table(number_of_entries, buffer, load_stack_map_frame)?,
+            })
+        }
+
+        "Exceptions" => {
+            let number_of_exceptions = buffer.read_u16()?;
+            AttributeKind::Exceptions(ExceptionsAttribute {
+                base,
+                number_of_exceptions,
+                exception_index_table: load_u16s(number_of_exceptions, buffer)?,
+            })
+        }
+
+        "InnerClasses" => {
+            let number_of_classes = buffer.read_u16()?;
+            AttributeKind::InnerClasses(InnerClassesAttribute {
+                base,
+                number_of_classes,
+                classes: load_table(number_of_classes, buffer, |buffer| {
+                    Ok(InnerClass {
+                        inner_class_info_index: buffer.read_u16()?,
+                        outer_class_info_index: buffer.read_u16()?,
+                        inner_name_index: buffer.read_u16()?,
+                        inner_class_access_flags: InnerClassAccessFlags(buffer.read_u16()?),
+                    })
+                })?,
+            })
+        }
+
+        "EnclosingMethod" => {
+            AttributeKind::EnclosingMethod(EnclosingMethodAttribute {
+                base,
+                class_index: buffer.read_u16()?,
+                method_index: buffer.read_u16()?,
+            })
+        }
+
+        "Synthetic" => AttributeKind::Synthetic(SyntheticAttribute { base }),
+
+        "Signature" => {
+            AttributeKind::Signature(SignatureAttribute {
+                base,
+                signature_index: buffer.read_u16()?,
+            })
+        }
+
+        "SourceFile" => {
+            AttributeKind::SourceFile(SourceFileAttribute {
+                base,
+                sourcefile_index: buffer.read_u16()?,
+            })
+        }
 
-        match &name[..] {
-            "Code" => {
-                let code_length;
-                let exception_table_length;
-                let attributes_count;
-
-                attributes.push(AttributeKind::Code(CodeAttribute {
-                    base: AttributeInfoBase{
-                        attribute_name_index, attribute_length,
-                    },
-                    max_stack: buffer.read_u16::<BigEndian>().unwrap(),
-                    max_locals: buffer.read_u16::<BigEndian>().unwrap(),
-                    code_length: {
-                        code_length = buffer.read_u32::<BigEndian>().unwrap();
-                        code_length
-                    },
-                    code: {
-                        let mut code = Vec::new();
-                        for _ in 0..code_length {
-                            code.push(buffer.read_u8().unwrap());
-                        }
-                        code
-                    },
-                    exception_table_length: {
-                        exception_table_length = buffer.read_u16::<BigEndian>().unwrap();
-                        exception_table_length
-                    },
-                    exception_table: {
-                        let mut exception_table = Vec::new();
-                        for _ in 0..exception_table_length {
-                            exception_table.push(ExceptionTableEntry {
-                                start_pc: buffer.read_u16::<BigEndian>().unwrap(),
-                                end_pc: buffer.read_u16::<BigEndian>().unwrap(),
-                                handler_pc: buffer.read_u16::<BigEndian>().unwrap(),
-                                catch_type: buffer.read_u16::<BigEndian>().unwrap(),
-                            });
-                        }
-                        exception_table
-                    },
-                    attributes_count: {
-                        attributes_count = buffer.read_u16::<BigEndian>().unwrap();
-                        attributes_count
-                    },
-                    attributes: load_attributes(attributes_count, buffer, constant_pool),
-                }));
+        "SourceDebugExtension" => {
+            let attribute_length = base.attribute_length;
+            AttributeKind::SourceDebugExtension(SourceDebugExtensionAttribute {
+                base,
+                debug_extension: buffer.read_shared(attribute_length as usize)?,
+            })
+        }
+
+        "LineNumberTable" => {
+            let line_number_table_length = buffer.read_u16()?;
+            AttributeKind::LineNumberTable(LineNumberTableAttribute {
+                base,
+                line_number_table_length,
+                line_number_table: load_table(line_number_table_length, buffer, |buffer| {
+                    Ok(LineNumberTableEntry {
+                        start_pc: buffer.read_u16()?,
+                        line_number: buffer.read_u16()?,
+                    })
+                })?,
+            })
+        }
+
+        "LocalVariableTable" => {
+            let local_variable_table_length = buffer.read_u16()?;
+            AttributeKind::LocalVariableTable(LocalVariableTableAttribute {
+                base,
+                local_variable_table_length,
+                local_variable_table: load_table(local_variable_table_length, buffer, |buffer| {
+                    Ok(LocalVariableTableEntry {
+                        start_pc: buffer.read_u16()?,
+                        length: buffer.read_u16()?,
+                        name_index: buffer.read_u16()?,
+                        descriptor_index: buffer.read_u16()?,
+                        index: buffer.read_u16()?,
+                    })
+                })?,
+            })
+        }
+
+        "LocalVariableTypeTable" => {
+            let local_variable_type_table_length = buffer.read_u16()?;
+            AttributeKind::LocalVariableTypeTable(LocalVariableTypeTableAttribute {
+                base,
+                local_variable_type_table_length,
+                local_variable_type_table: load_table(local_variable_type_table_length, buffer, |buffer| {
+                    Ok(LocalVariableTypeTableEntry {
+                        start_pc: buffer.read_u16()?,
+                        length: buffer.read_u16()?,
+                        name_index: buffer.read_u16()?,
+                        signature_index: buffer.read_u16()?,
+                        index: buffer.read_u16()?,
+                    })
+                })?,
+            })
+        }
+
+        "Deprecated" => AttributeKind::Deprecated(DeprecatedAttribute { base }),
+
+        "RuntimeVisibleAnnotations" => AttributeKind::RuntimeVisibleAnnotations(load_runtime_annotations(base, buffer)?),
+        "RuntimeInvisibleAnnotations" => AttributeKind::RuntimeInvisibleAnnotations(load_runtime_annotations(base, buffer)?),
+        "RuntimeVisibleParameterAnnotations" => {
+            AttributeKind::RuntimeVisibleParameterAnnotations(load_runtime_parameter_annotations(base, buffer)?)
+        }
+        "RuntimeInvisibleParameterAnnotations" => {
+            AttributeKind::RuntimeInvisibleParameterAnnotations(load_runtime_parameter_annotations(base, buffer)?)
+        }
+        "RuntimeVisibleTypeAnnotations" => AttributeKind::RuntimeVisibleTypeAnnotations(load_runtime_type_annotations(base, buffer)?),
+        "RuntimeInvisibleTypeAnnotations" => AttributeKind::RuntimeInvisibleTypeAnnotations(load_runtime_type_annotations(base, buffer)?),
+
+        "AnnotationDefault" => {
+            AttributeKind::AnnotationDefault(AnnotationDefaultAttribute {
+                base,
+                default_value: load_element_value(buffer)?,
+            })
+        }
+
+        "MethodParameters" => {
+            let parameters_count = buffer.read_u8()?;
+            AttributeKind::MethodParameters(MethodParametersAttribute {
+                base,
+                parameters_count,
+                parameters: load_table(parameters_count as u16, buffer, |buffer| {
+                    Ok(MethodParameter {
+                        name_index: buffer.read_u16()?,
+                        access_flags: buffer.read_u16()?,
+                    })
+                })?,
+            })
+        }
+
+        "Module" => {
+            let module_name_index = buffer.read_u16()?;
+            let module_flags = buffer.read_u16()?;
+            let module_version_index = buffer.read_u16()?;
+            let requires_count = buffer.read_u16()?;
+            let requires = load_table(requires_count, buffer, |buffer| {
+                Ok(ModuleRequires {
+                    requires_index: buffer.read_u16()?,
+                    requires_flags: buffer.read_u16()?,
+                    requires_version_index: buffer.read_u16()?,
+                })
+            })?;
+            let exports_count = buffer.read_u16()?;
+            let exports = load_table(exports_count, buffer, |buffer| {
+                let exports_index = buffer.read_u16()?;
+                let exports_flags = buffer.read_u16()?;
+                let exports_to_count = buffer.read_u16()?;
+                Ok(ModuleExports {
+                    exports_index,
+                    exports_flags,
+                    exports_to_count,
+                    exports_to_index: load_u16s(exports_to_count, buffer)?,
+                })
+            })?;
+            let opens_count = buffer.read_u16()?;
+            let opens = load_table(opens_count, buffer, |buffer| {
+                let opens_index = buffer.read_u16()?;
+                let opens_flags = buffer.read_u16()?;
+                let opens_to_count = buffer.read_u16()?;
+                Ok(ModuleOpens {
+                    opens_index,
+                    opens_flags,
+                    opens_to_count,
+                    opens_to_index: load_u16s(opens_to_count, buffer)?,
+                })
+            })?;
+            let uses_count = buffer.read_u16()?;
+            let uses_index = load_u16s(uses_count, buffer)?;
+            let provides_count = buffer.read_u16()?;
+            let provides = load_table(provides_count, buffer, |buffer| {
+                let provides_index = buffer.read_u16()?;
+                let provides_with_count = buffer.read_u16()?;
+                Ok(ModuleProvides {
+                    provides_index,
+                    provides_with_count,
+                    provides_with_index: load_u16s(provides_with_count, buffer)?,
+                })
+            })?;
+
+            AttributeKind::Module(ModuleAttribute {
+                base,
+                module_name_index,
+                module_flags,
+                module_version_index,
+                requires_count,
+                requires,
+                exports_count,
+                exports,
+                opens_count,
+                opens,
+                uses_count,
+                uses_index,
+                provides_count,
+                provides,
+            })
+        }
+
+        "ModulePackages" => {
+            let package_count = buffer.read_u16()?;
+            AttributeKind::ModulePackages(ModulePackagesAttribute {
+                base,
+                package_count,
+                package_index: load_u16s(package_count, buffer)?,
+            })
+        }
+
+        "ModuleMainClass" => {
+            AttributeKind::ModuleMainClass(ModuleMainClassAttribute {
+                base,
+                main_class_index: buffer.read_u16()?,
+            })
+        }
+
+        "NestHost" => {
+            AttributeKind::NestHost(NestHostAttribute {
+                base,
+                host_class_index: buffer.read_u16()?,
+            })
+        }
+
+        "NestMembers" => {
+            let number_of_classes = buffer.read_u16()?;
+            AttributeKind::NestMembers(NestMembersAttribute {
+                base,
+                number_of_classes,
+                classes: load_u16s(number_of_classes, buffer)?,
+            })
+        }
+
+        "Record" => {
+            let components_count = buffer.read_u16()?;
+            AttributeKind::Record(RecordAttribute {
+                base,
+                components_count,
+                components: load_table(components_count, buffer, |buffer| {
+                    let name_index = buffer.read_u16()?;
+                    let descriptor_index = buffer.read_u16()?;
+                    let attributes_count = buffer.read_u16()?;
+                    Ok(RecordComponentInfo {
+                        name_index,
+                        descriptor_index,
+                        attributes_count,
+                        attributes: load_attributes(attributes_count, buffer, constant_pool)?,
+                    })
+                })?,
+            })
+        }
+
+        "PermittedSubclasses" => {
+            let number_of_classes = buffer.read_u16()?;
+            AttributeKind::PermittedSubclasses(PermittedSubclassesAttribute {
+                base,
+                number_of_classes,
+                classes: load_u16s(number_of_classes, buffer)?,
+            })
+        }
+
+        _ => {
+            let attribute_length = base.attribute_length;
+            AttributeKind::Unknown(UnknownAttribute {
+                base,
+                name: name.to_string(),
+                bytes: buffer.read_shared(attribute_length as usize)?,
+            })
+        }
+    };
+
+    Ok(attribute)
+}
+
+fn load_table<'a, T>(
+    count: u16,
+    buffer: &mut ClassReader<'a>,
+    mut load_entry: impl FnMut(&mut ClassReader<'a>) -> Result<T, ClassFormatError>,
+) -> Result<Vec<T>, ClassFormatError> {
+    let mut table = Vec::with_capacity(count as usize);
+
+    for _ in 0..count {
+        table.push(load_entry(buffer)?);
+    }
+
+    Ok(table)
+}
+
+fn load_u16s(count: u16, buffer: &mut ClassReader) -> Result<Vec<u16>, ClassFormatError> {
+    load_table(count, buffer, |buffer| buffer.read_u16())
+}
+
+fn load_stack_map_frame(buffer: &mut ClassReader) -> Result<StackMapFrame, ClassFormatError> {
+    let frame_type_offset = buffer.position();
+    let frame_type = buffer.read_u8()?;
+
+    let frame = match frame_type {
+        0..=63 => StackMapFrame::SameFrame(SameFrame { frame_type }),
+        64..=127 => {
+            StackMapFrame::SameLocals1StackItemFrame(SameLocals1StackItemFrame {
+                frame_type,
+                stack: load_verification_type_info(buffer)?,
+            })
+        }
+        247 => {
+            StackMapFrame::SameLocals1StackItemFrameExtended(SameLocals1StackItemFrameExtended {
+                frame_type,
+                offset_delta: buffer.read_u16()?,
+                stack: load_verification_type_info(buffer)?,
+            })
+        }
+        248..=250 => {
+            StackMapFrame::ChopFrame(ChopFrame {
+                frame_type,
+                offset_delta: buffer.read_u16()?,
+            })
+        }
+        251 => {
+            StackMapFrame::SameFrameExtended(SameFrameExtended {
+                frame_type,
+                offset_delta: buffer.read_u16()?,
+            })
+        }
+        252..=254 => {
+            let offset_delta = buffer.read_u16()?;
+            StackMapFrame::AppendFrame(AppendFrame {
+                frame_type,
+                offset_delta,
+                locals: load_table(frame_type as u16 - 251, buffer, load_verification_type_info)?,
+            })
+        }
+        255 => {
+            let offset_delta = buffer.read_u16()?;
+            let number_of_locals = buffer.read_u16()?;
+            let locals = load_table(number_of_locals, buffer, load_verification_type_info)?;
+            let number_of_stack_items = buffer.read_u16()?;
+            StackMapFrame::FullFrame(FullFrame {
+                frame_type,
+                offset_delta,
+                number_of_locals,
+                locals,
+                number_of_stack_items,
+                stack: load_table(number_of_stack_items, buffer, load_verification_type_info)?,
+            })
+        }
+        // 128-246 are reserved for future use.
+        _ => return Err(ClassFormatError::new(ClassFormatErrorKind::InvalidStackMapFrameType(frame_type), frame_type_offset)),
+    };
+
+    Ok(frame)
+}
+
+fn load_verification_type_info(buffer: &mut ClassReader) -> Result<VerificationTypeInfo, ClassFormatError> {
+    let tag_offset = buffer.position();
+    let tag = buffer.read_u8()?;
+
+    match tag {
+        0 => Ok(VerificationTypeInfo::Top),
+        1 => Ok(VerificationTypeInfo::Integer),
+        2 => Ok(VerificationTypeInfo::Float),
+        3 => Ok(VerificationTypeInfo::Double),
+        4 => Ok(VerificationTypeInfo::Long),
+        5 => Ok(VerificationTypeInfo::Null),
+        6 => Ok(VerificationTypeInfo::UninitializedThis),
+        7 => Ok(VerificationTypeInfo::Object { cpool_index: buffer.read_u16()? }),
+        8 => Ok(VerificationTypeInfo::Uninitialized { offset: buffer.read_u16()? }),
+        _ => Err(ClassFormatError::new(ClassFormatErrorKind::InvalidVerificationTypeTag(tag), tag_offset)),
+    }
+}
+
+fn load_runtime_annotations(base: AttributeInfoBase, buffer: &mut ClassReader) -> Result<RuntimeAnnotationsAttribute, ClassFormatError> {
+    let num_annotations = buffer.read_u16()?;
+    Ok(RuntimeAnnotationsAttribute {
+        base,
+        num_annotations,
+        annotations: load_table(num_annotations, buffer, load_annotation)?,
+    })
+}
+
+fn load_runtime_parameter_annotations(base: AttributeInfoBase, buffer: &mut ClassReader) -> Result<RuntimeParameterAnnotationsAttribute, ClassFormatError> {
+    let num_parameters = buffer.read_u8()?;
+    Ok(RuntimeParameterAnnotationsAttribute {
+        base,
+        num_parameters,
+        parameter_annotations: load_table(num_parameters as u16, buffer, |buffer| {
+            let num_annotations = buffer.read_u16()?;
+            Ok(ParameterAnnotations {
+                num_annotations,
+                annotations: load_table(num_annotations, buffer, load_annotation)?,
+            })
+        })?,
+    })
+}
+
+fn load_runtime_type_annotations(base: AttributeInfoBase, buffer: &mut ClassReader) -> Result<RuntimeTypeAnnotationsAttribute, ClassFormatError> {
+    let num_annotations = buffer.read_u16()?;
+    Ok(RuntimeTypeAnnotationsAttribute {
+        base,
+        num_annotations,
+        annotations: load_table(num_annotations, buffer, load_type_annotation)?,
+    })
+}
+
+fn load_annotation(buffer: &mut ClassReader) -> Result<Annotation, ClassFormatError> {
+    let type_index = buffer.read_u16()?;
+    let num_element_value_pairs = buffer.read_u16()?;
+    Ok(Annotation {
+        type_index,
+        num_element_value_pairs,
+        element_value_pairs: load_table(num_element_value_pairs, buffer, load_element_value_pair)?,
+    })
+}
+
+fn load_element_value_pair(buffer: &mut ClassReader) -> Result<ElementValuePair, ClassFormatError> {
+    Ok(ElementValuePair {
+        element_name_index: buffer.read_u16()?,
+        value: load_element_value(buffer)?,
+    })
+}
+
+fn load_element_value(buffer: &mut ClassReader) -> Result<ElementValue, ClassFormatError> {
+    let tag_offset = buffer.position();
+    let tag = buffer.read_u8()?;
+
+    match tag {
+        b'B' | b'C' | b'D' | b'F' | b'I' | b'J' | b'S' | b'Z' | b's' => {
+            Ok(ElementValue::Const {
+                tag,
+                const_value_index: buffer.read_u16()?,
+            })
+        }
+        b'e' => {
+            Ok(ElementValue::EnumConst {
+                type_name_index: buffer.read_u16()?,
+                const_name_index: buffer.read_u16()?,
+            })
+        }
+        b'c' => {
+            Ok(ElementValue::Class {
+                class_info_index: buffer.read_u16()?,
+            })
+        }
+        b'@' => Ok(ElementValue::Annotation(load_annotation(buffer)?)),
+        b'[' => {
+            let num_values = buffer.read_u16()?;
+            Ok(ElementValue::Array {
+                num_values,
+                values: load_table(num_values, buffer, load_element_value)?,
+            })
+        }
+        _ => Err(ClassFormatError::new(ClassFormatErrorKind::InvalidElementValueTag(tag), tag_offset)),
+    }
+}
+
+fn load_type_annotation(buffer: &mut ClassReader) -> Result<TypeAnnotation, ClassFormatError> {
+    let target_type_offset = buffer.position();
+    let target_type = buffer.read_u8()?;
+
+    // JVMS Table 4.7.20-A/B
+    let target_info = match target_type {
+        0x00 | 0x01 => TargetInfo::TypeParameter { type_parameter_index: buffer.read_u8()? },
+        0x10 => TargetInfo::Supertype { supertype_index: buffer.read_u16()? },
+        0x11 | 0x12 => {
+            TargetInfo::TypeParameterBound {
+                type_parameter_index: buffer.read_u8()?,
+                bound_index: buffer.read_u8()?,
             }
+        }
+        0x13..=0x15 => TargetInfo::Empty,
+        0x16 => TargetInfo::FormalParameter { formal_parameter_index: buffer.read_u8()? },
+        0x17 => TargetInfo::Throws { throws_type_index: buffer.read_u16()? },
+        0x40 | 0x41 => {
+            let table_length = buffer.read_u16()?;
+            TargetInfo::Localvar {
+                table_length,
+                table: load_table(table_length, buffer, |buffer| {
+                    Ok(LocalvarTarget {
+                        start_pc: buffer.read_u16()?,
+                        length: buffer.read_u16()?,
+                        index: buffer.read_u16()?,
+                    })
+                })?,
+            }
+        }
+        0x42 => TargetInfo::Catch { exception_table_index: buffer.read_u16()? },
+        0x43..=0x46 => TargetInfo::Offset { offset: buffer.read_u16()? },
+        0x47..=0x4B => {
+            TargetInfo::TypeArgument {
+                offset: buffer.read_u16()?,
+                type_argument_index: buffer.read_u8()?,
+            }
+        }
+        _ => return Err(ClassFormatError::new(ClassFormatErrorKind::InvalidTypeAnnotationTarget(target_type), target_type_offset)),
+    };
 
-            // Unimplemented attributes
-            _ => {
-                for _ in 0..attribute_length {
-                    let _ = buffer.read_u8();
-                }
+    let path_length = buffer.read_u8()?;
+    let target_path = TypePath {
+        path_length,
+        path: load_table(path_length as u16, buffer, |buffer| {
+            Ok(TypePathEntry {
+                type_path_kind: buffer.read_u8()?,
+                type_argument_index: buffer.read_u8()?,
+            })
+        })?,
+    };
+    let type_index = buffer.read_u16()?;
+    let num_element_value_pairs = buffer.read_u16()?;
+
+    Ok(TypeAnnotation {
+        target_type,
+        target_info,
+        target_path,
+        type_index,
+        num_element_value_pairs,
+        element_value_pairs: load_table(num_element_value_pairs, buffer, load_element_value_pair)?,
+    })
+}
+
+impl AttributeKind {
+    pub fn base(&self) -> &AttributeInfoBase {
+        match self {
+            AttributeKind::ConstantValue(attribute) => &attribute.base,
+            AttributeKind::Code(attribute) => &attribute.base,
+            AttributeKind::StackMapTable(attribute) => &attribute.base,
+            AttributeKind::Exceptions(attribute) => &attribute.base,
+            AttributeKind::InnerClasses(attribute) => &attribute.base,
+            AttributeKind::EnclosingMethod(attribute) => &attribute.base,
+            AttributeKind::Synthetic(attribute) => &attribute.base,
+            AttributeKind::Signature(attribute) => &attribute.base,
+            AttributeKind::SourceFile(attribute) => &attribute.base,
+            AttributeKind::SourceDebugExtension(attribute) => &attribute.base,
+            AttributeKind::LineNumberTable(attribute) => &attribute.base,
+            AttributeKind::LocalVariableTable(attribute) => &attribute.base,
+            AttributeKind::LocalVariableTypeTable(attribute) => &attribute.base,
+            AttributeKind::Deprecated(attribute) => &attribute.base,
+            AttributeKind::RuntimeVisibleAnnotations(attribute) => &attribute.base,
+            AttributeKind::RuntimeInvisibleAnnotations(attribute) => &attribute.base,
+            AttributeKind::RuntimeVisibleParameterAnnotations(attribute) => &attribute.base,
+            AttributeKind::RuntimeInvisibleParameterAnnotations(attribute) => &attribute.base,
+            AttributeKind::RuntimeVisibleTypeAnnotations(attribute) => &attribute.base,
+            AttributeKind::RuntimeInvisibleTypeAnnotations(attribute) => &attribute.base,
+            AttributeKind::AnnotationDefault(attribute) => &attribute.base,
+            AttributeKind::BootstrapMethods(attribute) => &attribute.base,
+            AttributeKind::MethodParameters(attribute) => &attribute.base,
+            AttributeKind::Module(attribute) => &attribute.base,
+            AttributeKind::ModulePackages(attribute) => &attribute.base,
+            AttributeKind::ModuleMainClass(attribute) => &attribute.base,
+            AttributeKind::NestHost(attribute) => &attribute.base,
+            AttributeKind::NestMembers(attribute) => &attribute.base,
+            AttributeKind::Record(attribute) => &attribute.base,
+            AttributeKind::PermittedSubclasses(attribute) => &attribute.base,
+            AttributeKind::Unknown(attribute) => &attribute.base,
+        }
+    }
+}
+
+/// Writes `attributes_count` followed by the attributes. Counts and `attribute_length`
+/// are recomputed from the contents, so edited attributes are written consistently.
+pub fn write_attributes(attributes: &[AttributeKind], buffer: &mut ClassWriter) -> Result<(), ClassFormatError> {
+    buffer.write_length_u16(attributes.len(), "attributes_count")?;
+
+    for attribute in attributes {
+        let mut body = ClassWriter::new();
+        write_attribute(attribute, &mut body)?;
+
+        buffer.write_u16(attribute.base().attribute_name_index);
+        buffer.write_length_u32(body.len(), "attribute_length")?;
+        buffer.write_bytes(&body.into_bytes());
+    }
+    Ok(())
+}
+
+fn write_attribute(attribute: &AttributeKind, buffer: &mut ClassWriter) -> Result<(), ClassFormatError> {
+    match attribute {
+        AttributeKind::ConstantValue(attribute) => buffer.write_u16(attribute.constant_value_index),
+
+        AttributeKind::Code(attribute) => {
+            buffer.write_u16(attribute.max_stack);
+            buffer.write_u16(attribute.max_locals);
+            buffer.write_length_u32(attribute.code.len(), "code_length")?;
+            buffer.write_bytes(&attribute.code);
+            write_table(&attribute.exception_table, buffer, |entry, buffer| {
+                buffer.write_u16(entry.start_pc);
+                buffer.write_u16(entry.end_pc);
+                buffer.write_u16(entry.handler_pc);
+                buffer.write_u16(entry.catch_type);
+                Ok(())
+            })?;
+            write_attributes(&attribute.attributes, buffer)?;
+        }
+
+        AttributeKind::StackMapTable(attribute) => write_table(&attribute.entries, buffer, write_stack_map_frame)?,
+
+        AttributeKind::Exceptions(attribute) => write_u16s(&attribute.exception_index_table, buffer)?,
+
+        AttributeKind::InnerClasses(attribute) => {
+            write_table(&attribute.classes, buffer, |class, buffer| {
+                buffer.write_u16(class.inner_class_info_index);
+                buffer.write_u16(class.outer_class_info_index);
+                buffer.write_u16(class.inner_name_index);
+                buffer.write_u16(class.inner_class_access_flags.bits());
+                Ok(())
+            })?;
+        }
+
+        AttributeKind::EnclosingMethod(attribute) => {
+            buffer.write_u16(attribute.class_index);
+            buffer.write_u16(attribute.method_index);
+        }
+
+        AttributeKind::Synthetic(_) | AttributeKind::Deprecated(_) => {}
+
+        AttributeKind::Signature(attribute) => buffer.write_u16(attribute.signature_index),
+
+        AttributeKind::SourceFile(attribute) => buffer.write_u16(attribute.sourcefile_index),
+
+        AttributeKind::SourceDebugExtension(attribute) => buffer.write_bytes(&attribute.debug_extension),
+
+        AttributeKind::LineNumberTable(attribute) => {
+            write_table(&attribute.line_number_table, buffer, |entry, buffer| {
+                buffer.write_u16(entry.start_pc);
+                buffer.write_u16(entry.line_number);
+                Ok(())
+            })?;
+        }
+
+        AttributeKind::LocalVariableTable(attribute) => {
+            write_table(&attribute.local_variable_table, buffer, |entry, buffer| {
+                buffer.write_u16(entry.start_pc);
+                buffer.write_u16(entry.length);
+                buffer.write_u16(entry.name_index);
+                buffer.write_u16(entry.descriptor_index);
+                buffer.write_u16(entry.index);
+                Ok(())
+            })?;
+        }
+
+        AttributeKind::LocalVariableTypeTable(attribute) => {
+            write_table(&attribute.local_variable_type_table, buffer, |entry, buffer| {
+                buffer.write_u16(entry.start_pc);
+                buffer.write_u16(entry.length);
+                buffer.write_u16(entry.name_index);
+                buffer.write_u16(entry.signature_index);
+                buffer.write_u16(entry.index);
+                Ok(())
+            })?;
+        }
+
+        AttributeKind::RuntimeVisibleAnnotations(attribute) | AttributeKind::RuntimeInvisibleAnnotations(attribute) => {
+            write_table(&attribute.annotations, buffer, write_annotation)?;
+        }
+
+        AttributeKind::RuntimeVisibleParameterAnnotations(attribute) | AttributeKind::RuntimeInvisibleParameterAnnotations(attribute) => {
+            buffer.write_length_u8(attribute.parameter_annotations.len(), "num_parameters")?;
+            for parameter_annotations in &attribute.parameter_annotations {
+                write_table(&parameter_annotations.annotations, buffer, write_annotation)?;
+            }
+        }
+
+        AttributeKind::RuntimeVisibleTypeAnnotations(attribute) | AttributeKind::RuntimeInvisibleTypeAnnotations(attribute) => {
+            write_table(&attribute.annotations, buffer, write_type_annotation)?;
+        }
+
+        AttributeKind::AnnotationDefault(attribute) => write_element_value(&attribute.default_value, buffer)?,
+
+        AttributeKind::BootstrapMethods(attribute) => {
+            write_table(&attribute.bootstrap_methods, buffer, |bootstrap_method, buffer| {
+                buffer.write_u16(bootstrap_method.bootstrap_method_ref);
+                write_u16s(&bootstrap_method.bootstrap_arguments, buffer)
+            })?;
+        }
+
+        AttributeKind::MethodParameters(attribute) => {
+            buffer.write_length_u8(attribute.parameters.len(), "parameters_count")?;
+            for parameter in &attribute.parameters {
+                buffer.write_u16(parameter.name_index);
+                buffer.write_u16(parameter.access_flags);
+            }
+        }
+
+        AttributeKind::Module(attribute) => {
+            buffer.write_u16(attribute.module_name_index);
+            buffer.write_u16(attribute.module_flags);
+            buffer.write_u16(attribute.module_version_index);
+            write_table(&attribute.requires, buffer, |requires, buffer| {
+                buffer.write_u16(requires.requires_index);
+                buffer.write_u16(requires.requires_flags);
+                buffer.write_u16(requires.requires_version_index);
+                Ok(())
+            })?;
+            write_table(&attribute.exports, buffer, |exports, buffer| {
+                buffer.write_u16(exports.exports_index);
+                buffer.write_u16(exports.exports_flags);
+                write_u16s(&exports.exports_to_index, buffer)
+            })?;
+            write_table(&attribute.opens, buffer, |opens, buffer| {
+                buffer.write_u16(opens.opens_index);
+                buffer.write_u16(opens.opens_flags);
+                write_u16s(&opens.opens_to_index, buffer)
+            })?;
+            write_u16s(&attribute.uses_index, buffer)?;
+            write_table(&attribute.provides, buffer, |provides, buffer| {
+                buffer.write_u16(provides.provides_index);
+                write_u16s(&provides.provides_with_index, buffer)
+            })?;
+        }
+
+        AttributeKind::ModulePackages(attribute) => write_u16s(&attribute.package_index, buffer)?,
+
+        AttributeKind::ModuleMainClass(attribute) => buffer.write_u16(attribute.main_class_index),
+
+        AttributeKind::NestHost(attribute) => buffer.write_u16(attribute.host_class_index),
+
+        AttributeKind::NestMembers(attribute) => write_u16s(&attribute.classes, buffer)?,
+
+        AttributeKind::Record(attribute) => {
+            write_table(&attribute.components, buffer, |component, buffer| {
+                buffer.write_u16(component.name_index);
+                buffer.write_u16(component.descriptor_index);
+                write_attributes(&component.attributes, buffer)
+            })?;
+        }
+
+        AttributeKind::PermittedSubclasses(attribute) => write_u16s(&attribute.classes, buffer)?,
+
+        AttributeKind::Unknown(attribute) => buffer.write_bytes(&attribute.bytes),
+    }
+    Ok(())
+}
+
+/// Writes a u2 entry count followed by the entries.
+fn write_table<T>(
+    table: &[T],
+    buffer: &mut ClassWriter,
+    mut write_entry: impl FnMut(&T, &mut ClassWriter) -> Result<(), ClassFormatError>,
+) -> Result<(), ClassFormatError> {
+    buffer.write_length_u16(table.len(), "table length")?;
+
+    for entry in table {
+        write_entry(entry, buffer)?;
+    }
+    Ok(())
+}
+
+fn write_u16s(values: &[u16], buffer: &mut ClassWriter) -> Result<(), ClassFormatError> {
+    write_table(values, buffer, |value, buffer| {
+        buffer.write_u16(*value);
+        Ok(())
+    })
+}
+
+fn write_stack_map_frame(frame: &StackMapFrame, buffer: &mut ClassWriter) -> Result<(), ClassFormatError> {
+    match frame {
+        StackMapFrame::SameFrame(frame) => buffer.write_u8(frame.frame_type),
+        StackMapFrame::SameLocals1StackItemFrame(frame) => {
+            buffer.write_u8(frame.frame_type);
+            write_verification_type_info(&frame.stack, buffer)?;
+        }
+        StackMapFrame::SameLocals1StackItemFrameExtended(frame) => {
+            buffer.write_u8(frame.frame_type);
+            buffer.write_u16(frame.offset_delta);
+            write_verification_type_info(&frame.stack, buffer)?;
+        }
+        StackMapFrame::ChopFrame(frame) => {
+            buffer.write_u8(frame.frame_type);
+            buffer.write_u16(frame.offset_delta);
+        }
+        StackMapFrame::SameFrameExtended(frame) => {
+            buffer.write_u8(frame.frame_type);
+            buffer.write_u16(frame.offset_delta);
+        }
+        StackMapFrame::AppendFrame(frame) => {
+            buffer.write_u8(frame.frame_type);
+            buffer.write_u16(frame.offset_delta);
+            for local in &frame.locals {
+                write_verification_type_info(local, buffer)?;
             }
         }
+        StackMapFrame::FullFrame(frame) => {
+            buffer.write_u8(frame.frame_type);
+            buffer.write_u16(frame.offset_delta);
+            write_table(&frame.locals, buffer, write_verification_type_info)?;
+            write_table(&frame.stack, buffer, write_verification_type_info)?;
+        }
     }
+    Ok(())
+}
 
-    attributes
+fn write_verification_type_info(verification_type: &VerificationTypeInfo, buffer: &mut ClassWriter) -> Result<(), ClassFormatError> {
+    match verification_type {
+        VerificationTypeInfo::Top => buffer.write_u8(0),
+        VerificationTypeInfo::Integer => buffer.write_u8(1),
+        VerificationTypeInfo::Float => buffer.write_u8(2),
+        VerificationTypeInfo::Double => buffer.write_u8(3),
+        VerificationTypeInfo::Long => buffer.write_u8(4),
+        VerificationTypeInfo::Null => buffer.write_u8(5),
+        VerificationTypeInfo::UninitializedThis => buffer.write_u8(6),
+        VerificationTypeInfo::Object { cpool_index } => {
+            buffer.write_u8(7);
+            buffer.write_u16(*cpool_index);
+        }
+        VerificationTypeInfo::Uninitialized { offset } => {
+            buffer.write_u8(8);
+            buffer.write_u16(*offset);
+        }
+    }
+    Ok(())
+}
+
+fn write_annotation(annotation: &Annotation, buffer: &mut ClassWriter) -> Result<(), ClassFormatError> {
+    buffer.write_u16(annotation.type_index);
+    write_table(&annotation.element_value_pairs, buffer, write_element_value_pair)
+}
+
+fn write_element_value_pair(pair: &ElementValuePair, buffer: &mut ClassWriter) -> Result<(), ClassFormatError> {
+    buffer.write_u16(pair.element_name_index);
+    write_element_value(&pair.value, buffer)
+}
+
+fn write_element_value(value: &ElementValue, buffer: &mut ClassWriter) -> Result<(), ClassFormatError> {
+    match value {
+        ElementValue::Const { tag, const_value_index } => {
+            buffer.write_u8(*tag);
+            buffer.write_u16(*const_value_index);
+        }
+        ElementValue::EnumConst { type_name_index, const_name_index } => {
+            buffer.write_u8(b'e');
+            buffer.write_u16(*type_name_index);
+            buffer.write_u16(*const_name_index);
+        }
+        ElementValue::Class { class_info_index } => {
+            buffer.write_u8(b'c');
+            buffer.write_u16(*class_info_index);
+        }
+        ElementValue::Annotation(annotation) => {
+            buffer.write_u8(b'@');
+            write_annotation(annotation, buffer)?;
+        }
+        ElementValue::Array { values, .. } => {
+            buffer.write_u8(b'[');
+            write_table(values, buffer, write_element_value)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_type_annotation(annotation: &TypeAnnotation, buffer: &mut ClassWriter) -> Result<(), ClassFormatError> {
+    buffer.write_u8(annotation.target_type);
+    match &annotation.target_info {
+        TargetInfo::TypeParameter { type_parameter_index } => buffer.write_u8(*type_parameter_index),
+        TargetInfo::Supertype { supertype_index } => buffer.write_u16(*supertype_index),
+        TargetInfo::TypeParameterBound { type_parameter_index, bound_index } => {
+            buffer.write_u8(*type_parameter_index);
+            buffer.write_u8(*bound_index);
+        }
+        TargetInfo::Empty => {}
+        TargetInfo::FormalParameter { formal_parameter_index } => buffer.write_u8(*formal_parameter_index),
+        TargetInfo::Throws { throws_type_index } => buffer.write_u16(*throws_type_index),
+        TargetInfo::Localvar { table, .. } => {
+            write_table(table, buffer, |target, buffer| {
+                buffer.write_u16(target.start_pc);
+                buffer.write_u16(target.length);
+                buffer.write_u16(target.index);
+                Ok(())
+            })?;
+        }
+        TargetInfo::Catch { exception_table_index } => buffer.write_u16(*exception_table_index),
+        TargetInfo::Offset { offset } => buffer.write_u16(*offset),
+        TargetInfo::TypeArgument { offset, type_argument_index } => {
+            buffer.write_u16(*offset);
+            buffer.write_u8(*type_argument_index);
+        }
+    }
+
+    buffer.write_length_u8(annotation.target_path.path.len(), "path_length")?;
+    for entry in &annotation.target_path.path {
+        buffer.write_u8(entry.type_path_kind);
+        buffer.write_u8(entry.type_argument_index);
+    }
+    buffer.write_u16(annotation.type_index);
+    write_table(&annotation.element_value_pairs, buffer, write_element_value_pair)
 }
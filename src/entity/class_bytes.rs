@@ -0,0 +1,91 @@
+use std::fmt;
+use std::ops::{Deref, Range};
+use std::sync::Arc;
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/// A range of a class file's bytes, sharing one reference-counted buffer with every
+/// other piece parsed from the same file. Cloning never copies the bytes.
+#[derive(Clone)]
+pub struct ClassBytes {
+    buffer: Arc<[u8]>,
+    range: Range<usize>,
+}
+
+impl ClassBytes {
+    pub fn new(buffer: Arc<[u8]>) -> Self {
+        let range = 0..buffer.len();
+        ClassBytes { buffer, range }
+    }
+
+    /// `range` is relative to this slice.
+    pub fn slice(&self, range: Range<usize>) -> ClassBytes {
+        assert!(range.start <= range.end && range.end <= self.range.len(), "slice out of range");
+        ClassBytes {
+            buffer: self.buffer.clone(),
+            range: self.range.start + range.start..self.range.start + range.end,
+        }
+    }
+}
+
+impl Deref for ClassBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.buffer[self.range.clone()]
+    }
+}
+
+impl Default for ClassBytes {
+    fn default() -> Self {
+        ClassBytes::new(Arc::from([]))
+    }
+}
+
+impl From<Vec<u8>> for ClassBytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        ClassBytes::new(Arc::from(bytes))
+    }
+}
+
+impl From<&[u8]> for ClassBytes {
+    fn from(bytes: &[u8]) -> Self {
+        ClassBytes::new(Arc::from(bytes))
+    }
+}
+
+impl PartialEq for ClassBytes {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl Eq for ClassBytes {}
+
+impl fmt::Debug for ClassBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+/// Serialized as a string of hex digits, two per byte.
+impl Serialize for ClassBytes {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let hex: String = self.iter().map(|byte| format!("{:02x}", byte)).collect();
+        serializer.serialize_str(&hex)
+    }
+}
+
+impl<'de> Deserialize<'de> for ClassBytes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        if hex.len() % 2 != 0 || !hex.is_ascii() {
+            return Err(de::Error::custom("expected an even number of hex digits"));
+        }
+        (0..hex.len()).step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .map(ClassBytes::from)
+            .map_err(de::Error::custom)
+    }
+}
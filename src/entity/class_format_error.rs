@@ -0,0 +1,177 @@
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClassFormatErrorKind {
+    TruncatedClassFile,
+    InvalidMagic(u32),
+    UnsupportedClassVersion { major_version: u16, minor_version: u16 },
+    PreviewNotEnabled { major_version: u16 },
+    InvalidConstantPoolTag(u8),
+    UnsupportedConstantPoolTag { tag: u8, major_version: u16 },
+    UnsupportedAttribute { name: &'static str, major_version: u16 },
+    InvalidConstantPoolIndex,
+    UnexpectedConstantPoolEntry { expected: &'static str },
+    InvalidUtf8,
+    InvalidBootstrapMethodIndex(u16),
+    AttributeLengthMismatch(String),
+    InvalidElementValueTag(u8),
+    InvalidTypeAnnotationTarget(u8),
+    InvalidStackMapFrameType(u8),
+    InvalidStackMapFrameOffset,
+    InvalidVerificationTypeTag(u8),
+    TrailingBytes,
+    InvalidDescriptor(String),
+    InvalidSignature(String),
+    IllegalModifiers { context: &'static str, flags: u16 },
+    InvalidName(String),
+    InvalidSuperclass,
+    InvalidMethodHandleKind(u8),
+    DuplicateField { name: String, descriptor: String },
+    DuplicateMethod { name: String, descriptor: String },
+    MissingCodeAttribute,
+    UnexpectedCodeAttribute,
+    InvalidOpcode { opcode: u8, pc: u32 },
+    InvalidOperand { opcode: u8, pc: u32 },
+    TruncatedInstruction { pc: u32 },
+    /// A count or length too large for the field it is written to.
+    LengthOverflow { what: &'static str, length: usize, max: u32 },
+}
+
+/// Structured form of `java.lang.ClassFormatError` (and `UnsupportedClassVersionError`).
+/// `offset` is the byte offset in the class file where the malformed item starts,
+/// or `None` for errors found after parsing, by cross-checking the class.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClassFormatError {
+    pub kind: ClassFormatErrorKind,
+    pub offset: Option<usize>,
+    pub constant_pool_index: Option<u16>,
+    pub class_name: Option<String>,
+}
+
+impl ClassFormatError {
+    pub fn new(kind: ClassFormatErrorKind, offset: usize) -> Self {
+        ClassFormatError {
+            offset: Some(offset),
+            ..ClassFormatError::from(kind)
+        }
+    }
+
+    pub fn with_offset(mut self, offset: usize) -> Self {
+        self.offset.get_or_insert(offset);
+        self
+    }
+
+    pub fn with_constant_pool_index(mut self, index: u16) -> Self {
+        self.constant_pool_index.get_or_insert(index);
+        self
+    }
+
+    pub fn with_class_name(mut self, name: &str) -> Self {
+        self.class_name.get_or_insert_with(|| name.to_string());
+        self
+    }
+
+    /// Binary name of the Java exception the VM should throw for this error.
+    pub fn java_exception_name(&self) -> &'static str {
+        match self.kind {
+            ClassFormatErrorKind::UnsupportedClassVersion { .. } | ClassFormatErrorKind::PreviewNotEnabled { .. } => {
+                "java/lang/UnsupportedClassVersionError"
+            }
+            _ => "java/lang/ClassFormatError",
+        }
+    }
+}
+
+impl From<ClassFormatErrorKind> for ClassFormatError {
+    fn from(kind: ClassFormatErrorKind) -> Self {
+        ClassFormatError {
+            kind,
+            offset: None,
+            constant_pool_index: None,
+            class_name: None,
+        }
+    }
+}
+
+impl fmt::Display for ClassFormatErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClassFormatErrorKind::TruncatedClassFile => write!(f, "Truncated class file"),
+            ClassFormatErrorKind::InvalidMagic(magic) => write!(f, "Incompatible magic value {:#010X}", magic),
+            ClassFormatErrorKind::UnsupportedClassVersion { major_version, minor_version } => {
+                write!(f, "Unsupported class file version {}.{}", major_version, minor_version)
+            }
+            ClassFormatErrorKind::PreviewNotEnabled { major_version } => write!(
+                f,
+                "Preview features are not enabled for class file version {}.65535. Try running with '--enable-preview'",
+                major_version
+            ),
+            ClassFormatErrorKind::InvalidConstantPoolTag(tag) => write!(f, "Invalid constant pool tag {}", tag),
+            ClassFormatErrorKind::UnsupportedConstantPoolTag { tag, major_version } => {
+                write!(f, "Class file version {} does not support constant tag {}", major_version, tag)
+            }
+            ClassFormatErrorKind::UnsupportedAttribute { name, major_version } => {
+                write!(f, "Class file version {} does not support the {} attribute", major_version, name)
+            }
+            ClassFormatErrorKind::InvalidConstantPoolIndex => write!(f, "Invalid constant pool index"),
+            ClassFormatErrorKind::UnexpectedConstantPoolEntry { expected } => {
+                write!(f, "Constant pool entry is not a {}", expected)
+            }
+            ClassFormatErrorKind::InvalidUtf8 => write!(f, "Illegal UTF8 string in constant pool"),
+            ClassFormatErrorKind::InvalidBootstrapMethodIndex(index) => {
+                write!(f, "Bootstrap method index {} is out of range of BootstrapMethods", index)
+            }
+            ClassFormatErrorKind::AttributeLengthMismatch(name) => write!(f, "Wrong attribute_length of {} attribute", name),
+            ClassFormatErrorKind::InvalidElementValueTag(tag) => write!(f, "Invalid element_value tag {}", tag),
+            ClassFormatErrorKind::InvalidTypeAnnotationTarget(target_type) => {
+                write!(f, "Invalid type annotation target_type {:#04X}", target_type)
+            }
+            ClassFormatErrorKind::InvalidStackMapFrameType(frame_type) => write!(f, "Invalid StackMapTable frame_type {}", frame_type),
+            ClassFormatErrorKind::InvalidStackMapFrameOffset => write!(f, "StackMapTable frame offset exceeds the code length"),
+            ClassFormatErrorKind::InvalidVerificationTypeTag(tag) => write!(f, "Invalid verification_type_info tag {}", tag),
+            ClassFormatErrorKind::TrailingBytes => write!(f, "Extra bytes at the end of class file"),
+            ClassFormatErrorKind::InvalidDescriptor(descriptor) => write!(f, "Invalid descriptor \"{}\"", descriptor),
+            ClassFormatErrorKind::InvalidSignature(signature) => write!(f, "Invalid signature \"{}\"", signature),
+            ClassFormatErrorKind::IllegalModifiers { context, flags } => write!(f, "Illegal {} modifiers {:#06X}", context, flags),
+            ClassFormatErrorKind::InvalidName(name) => write!(f, "Illegal name \"{}\"", name),
+            ClassFormatErrorKind::InvalidSuperclass => write!(f, "Invalid superclass"),
+            ClassFormatErrorKind::InvalidMethodHandleKind(reference_kind) => {
+                write!(f, "Invalid method handle reference_kind {}", reference_kind)
+            }
+            ClassFormatErrorKind::DuplicateField { name, descriptor } => {
+                write!(f, "Duplicate field name \"{}\" with signature \"{}\"", name, descriptor)
+            }
+            ClassFormatErrorKind::DuplicateMethod { name, descriptor } => {
+                write!(f, "Duplicate method name \"{}\" with signature \"{}\"", name, descriptor)
+            }
+            ClassFormatErrorKind::MissingCodeAttribute => write!(f, "Absent Code attribute in method that is not native or abstract"),
+            ClassFormatErrorKind::UnexpectedCodeAttribute => {
+                write!(f, "Code attribute in native or abstract method, or more than one Code attribute")
+            }
+            ClassFormatErrorKind::InvalidOpcode { opcode, pc } => write!(f, "Invalid opcode {} at pc {}", opcode, pc),
+            ClassFormatErrorKind::InvalidOperand { opcode, pc } => write!(f, "Invalid operand of opcode {} at pc {}", opcode, pc),
+            ClassFormatErrorKind::TruncatedInstruction { pc } => write!(f, "Truncated instruction at pc {}", pc),
+            ClassFormatErrorKind::LengthOverflow { what, length, max } => {
+                write!(f, "{} of {} exceeds the class file format limit of {}", what, length, max)
+            }
+        }
+    }
+}
+
+impl fmt::Display for ClassFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+        if let Some(offset) = self.offset {
+            write!(f, " at offset {}", offset)?;
+        }
+        if let Some(index) = self.constant_pool_index {
+            write!(f, " (constant pool #{})", index)?;
+        }
+        if let Some(class_name) = &self.class_name {
+            write!(f, " in class file {}", class_name)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ClassFormatError {}
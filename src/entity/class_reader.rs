@@ -0,0 +1,83 @@
+use crate::entity::class_format_error::*;
+use crate::entity::class_bytes::ClassBytes;
+use byteorder::{BigEndian, ReadBytesExt};
+
+/// Big-endian reader over a class file that keeps track of its byte offset,
+/// so every parse failure can be reported with the position it happened at.
+#[derive(Debug, Clone)]
+pub struct ClassReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    shared: Option<&'a ClassBytes>,
+}
+
+impl<'a> ClassReader<'a> {
+    /// A reader whose `read_shared` copies what it returns.
+    pub fn new(bytes: &'a [u8]) -> Self {
+        ClassReader { bytes, position: 0, shared: None }
+    }
+
+    /// A reader whose `read_shared` hands out slices of `bytes` without copying.
+    pub fn shared(bytes: &'a ClassBytes) -> Self {
+        ClassReader { bytes, position: 0, shared: Some(bytes) }
+    }
+
+    pub fn shared_bytes(&self) -> Option<&'a ClassBytes> {
+        self.shared
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.position == self.bytes.len()
+    }
+
+    pub fn error(&self, kind: ClassFormatErrorKind) -> ClassFormatError {
+        ClassFormatError::new(kind, self.position)
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, ClassFormatError> {
+        let mut bytes = self.read_bytes(1)?;
+        Ok(bytes.read_u8().unwrap())
+    }
+
+    pub fn read_u16(&mut self) -> Result<u16, ClassFormatError> {
+        let mut bytes = self.read_bytes(2)?;
+        Ok(bytes.read_u16::<BigEndian>().unwrap())
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32, ClassFormatError> {
+        let mut bytes = self.read_bytes(4)?;
+        Ok(bytes.read_u32::<BigEndian>().unwrap())
+    }
+
+    pub fn read_bytes(&mut self, length: usize) -> Result<&'a [u8], ClassFormatError> {
+        if self.bytes.len() - self.position < length {
+            return Err(self.error(ClassFormatErrorKind::TruncatedClassFile));
+        }
+
+        let bytes = &self.bytes[self.position..self.position + length];
+        self.position += length;
+        Ok(bytes)
+    }
+
+    /// Like `read_bytes`, for bytes the parsed class keeps, such as code.
+    pub fn read_shared(&mut self, length: usize) -> Result<ClassBytes, ClassFormatError> {
+        let start = self.position;
+        let bytes = self.read_bytes(length)?;
+        Ok(match self.shared {
+            Some(shared) => shared.slice(start..start + length),
+            None => ClassBytes::from(bytes),
+        })
+    }
+
+    /// Splits off the next `length` bytes as a reader of their own.
+    /// Offsets reported by the new reader are still relative to the whole class file.
+    pub fn sub_reader(&mut self, length: usize) -> Result<ClassReader<'a>, ClassFormatError> {
+        let start = self.position;
+        self.read_bytes(length)?;
+        Ok(ClassReader { bytes: &self.bytes[..start + length], position: start, shared: self.shared })
+    }
+}
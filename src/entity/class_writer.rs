@@ -0,0 +1,64 @@
+use crate::entity::class_format_error::{ClassFormatError, ClassFormatErrorKind};
+
+/// Big-endian counterpart of `ClassReader`, collecting a class file in memory.
+#[derive(Debug, Default)]
+pub struct ClassWriter {
+    bytes: Vec<u8>,
+}
+
+impl ClassWriter {
+    pub fn new() -> Self {
+        ClassWriter::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    pub fn write_u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    pub fn write_u16(&mut self, value: u16) {
+        self.bytes.extend_from_slice(&value.to_be_bytes());
+    }
+
+    pub fn write_u32(&mut self, value: u32) {
+        self.bytes.extend_from_slice(&value.to_be_bytes());
+    }
+
+    /// Writes a u1 count or length, failing instead of truncating one that does not fit.
+    pub fn write_length_u8(&mut self, length: usize, what: &'static str) -> Result<(), ClassFormatError> {
+        self.write_u8(checked_length(length, what, u8::MAX.into())? as u8);
+        Ok(())
+    }
+
+    pub fn write_length_u16(&mut self, length: usize, what: &'static str) -> Result<(), ClassFormatError> {
+        self.write_u16(checked_length(length, what, u16::MAX.into())? as u16);
+        Ok(())
+    }
+
+    pub fn write_length_u32(&mut self, length: usize, what: &'static str) -> Result<(), ClassFormatError> {
+        self.write_u32(checked_length(length, what, u32::MAX)?);
+        Ok(())
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+fn checked_length(length: usize, what: &'static str, max: u32) -> Result<u32, ClassFormatError> {
+    match u32::try_from(length) {
+        Ok(checked) if checked <= max => Ok(checked),
+        _ => Err(ClassFormatError::from(ClassFormatErrorKind::LengthOverflow { what, length, max })),
+    }
+}
@@ -1,119 +1,185 @@
-use byteorder::{BigEndian, ReadBytesExt};
+use crate::entity::class_format_error::*;
+use crate::entity::class_reader::ClassReader;
+use crate::entity::class_writer::ClassWriter;
+use crate::entity::class_bytes::ClassBytes;
+use crate::entity::mutf8;
+use std::collections::HashMap;
 use std::string::String;
+use serde::{ser, Deserialize, Serialize, Serializer};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConstantInfoBase {
     pub tag: u8,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "Utf8Json")]
 pub struct ConstantUtf8Info {
     pub base: ConstantInfoBase,  // 1
     pub length: u16,
-    pub text: String,
+    pub text: String,  // lossy when `bytes` holds unpaired surrogates; `utf16()` is exact
+    pub bytes: ClassBytes,  // Modified UTF-8, as in the class file
 }
 
-#[derive(Debug, Clone)]
+impl ConstantUtf8Info {
+    /// The exact UTF-16 code units of the string, unpaired surrogates included, as a
+    /// `java.lang.String` made from this constant holds them. Constants loaded from a
+    /// class file always decode; `bytes` set by hand may not be Modified UTF-8.
+    pub fn utf16(&self) -> Result<Vec<u16>, ClassFormatError> {
+        if self.bytes.iter().all(|&byte| (0x01..0x80).contains(&byte)) {
+            return Ok(self.bytes.iter().map(|&byte| u16::from(byte)).collect());
+        }
+        mutf8::decode(&self.bytes).map_err(|_| ClassFormatError::from(ClassFormatErrorKind::InvalidUtf8))
+    }
+}
+
+/// Serialized form of a Utf8 constant. `utf16` is only present when `text` is lossy;
+/// otherwise the bytes are rebuilt from `text`, so editing it takes effect. A `text`
+/// that no longer matches `utf16` is rejected rather than silently ignored.
+#[derive(Serialize, Deserialize)]
+struct Utf8Json {
+    base: ConstantInfoBase,
+    length: u16,
+    text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    utf16: Option<Vec<u16>>,
+}
+
+/// Serialized as `Utf8Json`, which `bytes` that are not Modified UTF-8 cannot be.
+impl Serialize for ConstantUtf8Info {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let utf16 = self.utf16().map_err(ser::Error::custom)?;
+        let utf16 = Some(utf16).filter(|utf16| String::from_utf16(utf16).is_err());
+        Utf8Json { base: self.base.clone(), length: self.length, text: self.text.clone(), utf16 }.serialize(serializer)
+    }
+}
+
+impl TryFrom<Utf8Json> for ConstantUtf8Info {
+    type Error = String;
+
+    fn try_from(json: Utf8Json) -> Result<Self, String> {
+        let bytes = match &json.utf16 {
+            Some(utf16) if String::from_utf16_lossy(utf16) != json.text => {
+                return Err(format!("Utf8 constant \"{}\" does not match its utf16 code units", json.text));
+            }
+            Some(utf16) => mutf8::encode(utf16),
+            None => mutf8::encode_str(&json.text),
+        };
+        let length = u16::try_from(bytes.len())
+            .map_err(|_| format!("Utf8 constant of {} bytes exceeds the class file format limit of 65535", bytes.len()))?;
+        Ok(ConstantUtf8Info { base: json.base, length, text: json.text, bytes: ClassBytes::from(bytes) })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConstantIntegerInfo {
     pub base: ConstantInfoBase,  // 3
     pub bytes: u32,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConstantFloatInfo {
     pub base: ConstantInfoBase,  // 4
     pub bytes: u32,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConstantLongInfo {
     pub base: ConstantInfoBase,  // 5
     pub high_bytes: u32,
     pub low_bytes: u32,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConstantDoubleInfo {
     pub base: ConstantInfoBase,  // 6
     pub high_bytes: u32,
     pub low_bytes: u32,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConstantClassInfo {
     pub base: ConstantInfoBase,  // 7
     pub name_index: u16,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConstantStringInfo {
     pub base: ConstantInfoBase,  // 8
     pub string_index: u16,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConstantFieldrefInfo {
     pub base: ConstantInfoBase,  // 9
     pub class_index: u16,
     pub name_and_type_index: u16,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConstantMethodrefInfo {
     pub base: ConstantInfoBase,  // 10
     pub class_index: u16,
     pub name_and_type_index: u16,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConstantInterfaceMethodrefInfo {
     pub base: ConstantInfoBase,  // 11
     pub class_index: u16,
     pub name_and_type_index: u16,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConstantNameAndTypeInfo {
     pub base: ConstantInfoBase,  // 12
     pub name_index: u16,
     pub descriptor_index: u16,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConstantMethodHandleInfo {
     pub base: ConstantInfoBase,  // 15
     pub reference_kind: u8,
     pub reference_index: u16,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConstantMethodTypeInfo {
     pub base: ConstantInfoBase,  // 16
     pub descriptor_index: u16,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConstantDynamicInfo {
+    pub base: ConstantInfoBase,  // 17
+    pub bootstrap_method_attr_index: u16,
+    pub name_and_type_index: u16,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConstantInvokeDynamicInfo {
     pub base: ConstantInfoBase,  // 18
     pub bootstrap_method_attr_index: u16,
     pub name_and_type_index: u16,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConstantModuleInfo {
     pub base: ConstantInfoBase,  // 19
     pub name_index: u16,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConstantPackageInfo {
     pub base: ConstantInfoBase,  // 20
     pub name_index: u16,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ConstantKind {
     Empty(ConstantInfoBase),
+    Unusable(ConstantInfoBase),  // second slot of Long and Double
     Utf8(ConstantUtf8Info),
     Integer(ConstantIntegerInfo),
     Float(ConstantFloatInfo),
@@ -127,12 +193,345 @@ pub enum ConstantKind {
     NameAndType(ConstantNameAndTypeInfo),
     MethodHandle(ConstantMethodHandleInfo),
     MethodType(ConstantMethodTypeInfo),
+    Dynamic(ConstantDynamicInfo),
     InvokeDynamic(ConstantInvokeDynamicInfo),
     Module(ConstantModuleInfo),
     Package(ConstantPackageInfo),
 }
 
-pub fn load_constant_pool(count: u16, buffer: &mut &[u8]) -> Vec<ConstantKind> {
+/// The constant pool of a class, indexed from 1 as in the class file. Index 0 and
+/// the slot after each Long and Double hold placeholders that are never returned.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(from = "Vec<ConstantKind>", into = "Vec<ConstantKind>")]
+pub struct ConstantPool {
+    constants: Vec<ConstantKind>,
+}
+
+impl From<Vec<ConstantKind>> for ConstantPool {
+    fn from(constants: Vec<ConstantKind>) -> Self {
+        ConstantPool::new(constants)
+    }
+}
+
+impl From<ConstantPool> for Vec<ConstantKind> {
+    fn from(constant_pool: ConstantPool) -> Self {
+        constant_pool.constants
+    }
+}
+
+impl ConstantPool {
+    /// `constants` must start with the `Empty` entry for index 0.
+    pub fn new(constants: Vec<ConstantKind>) -> Self {
+        ConstantPool { constants }
+    }
+
+    /// The `constant_pool_count` of the class file.
+    pub fn len(&self) -> usize {
+        self.constants.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.constants.len() <= 1
+    }
+
+    /// All entries including the placeholders, so positions match constant pool indices.
+    pub fn entries(&self) -> &[ConstantKind] {
+        &self.constants
+    }
+
+    pub fn get(&self, index: u16) -> Result<&ConstantKind, ClassFormatError> {
+        match self.constants.get(index as usize) {
+            Some(ConstantKind::Empty(_)) | Some(ConstantKind::Unusable(_)) | None => {
+                Err(ClassFormatError::from(ClassFormatErrorKind::InvalidConstantPoolIndex).with_constant_pool_index(index))
+            }
+            Some(constant) => Ok(constant),
+        }
+    }
+
+    pub fn utf8(&self, index: u16) -> Result<&str, ClassFormatError> {
+        match self.get(index)? {
+            ConstantKind::Utf8(utf8_info) => Ok(&utf8_info.text),
+            _ => Err(unexpected(index, "Utf8")),
+        }
+    }
+
+    pub fn class_name(&self, index: u16) -> Result<&str, ClassFormatError> {
+        match self.get(index)? {
+            ConstantKind::Class(class_info) => self.utf8(class_info.name_index),
+            _ => Err(unexpected(index, "Class")),
+        }
+    }
+
+    /// Returns `(name, descriptor)`.
+    pub fn name_and_type(&self, index: u16) -> Result<(&str, &str), ClassFormatError> {
+        match self.get(index)? {
+            ConstantKind::NameAndType(name_and_type_info) => {
+                Ok((self.utf8(name_and_type_info.name_index)?, self.utf8(name_and_type_info.descriptor_index)?))
+            }
+            _ => Err(unexpected(index, "NameAndType")),
+        }
+    }
+
+    /// Resolves a Fieldref, Methodref or InterfaceMethodref to `(class, name, descriptor)`.
+    pub fn member_ref(&self, index: u16) -> Result<(&str, &str, &str), ClassFormatError> {
+        let (class_index, name_and_type_index) = match self.get(index)? {
+            ConstantKind::Fieldref(fieldref_info) => (fieldref_info.class_index, fieldref_info.name_and_type_index),
+            ConstantKind::Methodref(methodref_info) => (methodref_info.class_index, methodref_info.name_and_type_index),
+            ConstantKind::InterfaceMethodref(interface_methodref_info) => {
+                (interface_methodref_info.class_index, interface_methodref_info.name_and_type_index)
+            }
+            _ => return Err(unexpected(index, "Fieldref, Methodref or InterfaceMethodref")),
+        };
+        let (name, descriptor) = self.name_and_type(name_and_type_index)?;
+        Ok((self.class_name(class_index)?, name, descriptor))
+    }
+
+    /// Contents of a CONSTANT_String.
+    pub fn string(&self, index: u16) -> Result<&str, ClassFormatError> {
+        match self.get(index)? {
+            ConstantKind::String(string_info) => self.utf8(string_info.string_index),
+            _ => Err(unexpected(index, "String")),
+        }
+    }
+
+    pub fn integer(&self, index: u16) -> Result<i32, ClassFormatError> {
+        match self.get(index)? {
+            ConstantKind::Integer(integer_info) => Ok(integer_info.bytes as i32),
+            _ => Err(unexpected(index, "Integer")),
+        }
+    }
+
+    pub fn float(&self, index: u16) -> Result<f32, ClassFormatError> {
+        match self.get(index)? {
+            ConstantKind::Float(float_info) => Ok(f32::from_bits(float_info.bytes)),
+            _ => Err(unexpected(index, "Float")),
+        }
+    }
+
+    pub fn long(&self, index: u16) -> Result<i64, ClassFormatError> {
+        match self.get(index)? {
+            ConstantKind::Long(long_info) => Ok(((long_info.high_bytes as u64) << 32 | long_info.low_bytes as u64) as i64),
+            _ => Err(unexpected(index, "Long")),
+        }
+    }
+
+    pub fn double(&self, index: u16) -> Result<f64, ClassFormatError> {
+        match self.get(index)? {
+            ConstantKind::Double(double_info) => {
+                Ok(f64::from_bits((double_info.high_bytes as u64) << 32 | double_info.low_bytes as u64))
+            }
+            _ => Err(unexpected(index, "Double")),
+        }
+    }
+}
+
+/// Builds a constant pool one constant at a time, handing back the index of an
+/// equal constant added earlier instead of a duplicate.
+#[derive(Debug)]
+pub struct ConstantPoolBuilder {
+    constants: Vec<ConstantKind>,
+    indices: HashMap<ConstantKey, u16>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum ConstantKey {
+    Utf8(String),
+    Integer(u32),
+    Float(u32),
+    Long(u64),
+    Double(u64),
+    Class(u16),
+    String(u16),
+    Fieldref(u16, u16),
+    Methodref(u16, u16),
+    InterfaceMethodref(u16, u16),
+    NameAndType(u16, u16),
+    MethodHandle(u8, u16),
+    MethodType(u16),
+    Dynamic(u16, u16),
+}
+
+impl Default for ConstantPoolBuilder {
+    fn default() -> Self {
+        ConstantPoolBuilder {
+            constants: vec![ConstantKind::Empty(ConstantInfoBase { tag: 0 })],
+            indices: HashMap::new(),
+        }
+    }
+}
+
+impl ConstantPoolBuilder {
+    pub fn new() -> Self {
+        ConstantPoolBuilder::default()
+    }
+
+    /// The `constant_pool_count` so far; adding a constant that would take it past
+    /// `u16::MAX` is an error.
+    pub fn len(&self) -> usize {
+        self.constants.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.constants.len() <= 1
+    }
+
+    pub fn build(self) -> ConstantPool {
+        ConstantPool::new(self.constants)
+    }
+
+    fn add(&mut self, key: ConstantKey, constant: ConstantKind) -> Result<u16, ClassFormatError> {
+        if let Some(&index) = self.indices.get(&key) {
+            return Ok(index);
+        }
+
+        let is_category2 = matches!(constant, ConstantKind::Long(_) | ConstantKind::Double(_));
+        let count = self.constants.len() + if is_category2 { 2 } else { 1 };
+        if count > usize::from(u16::MAX) {
+            return Err(ClassFormatError::from(ClassFormatErrorKind::LengthOverflow {
+                what: "constant_pool_count",
+                length: count,
+                max: u32::from(u16::MAX),
+            }));
+        }
+
+        let index = self.constants.len() as u16;
+        self.constants.push(constant);
+        if is_category2 {
+            self.constants.push(ConstantKind::Unusable(ConstantInfoBase { tag: 0 }));
+        }
+        self.indices.insert(key, index);
+        Ok(index)
+    }
+
+    pub fn utf8(&mut self, text: &str) -> Result<u16, ClassFormatError> {
+        let bytes = mutf8::encode_str(text);
+        let constant = ConstantKind::Utf8(ConstantUtf8Info {
+            base: ConstantInfoBase { tag: 1 },
+            length: bytes.len() as u16,
+            text: text.to_string(),
+            bytes: ClassBytes::from(bytes),
+        });
+        self.add(ConstantKey::Utf8(text.to_string()), constant)
+    }
+
+    pub fn integer(&mut self, value: i32) -> Result<u16, ClassFormatError> {
+        let bytes = value as u32;
+        self.add(ConstantKey::Integer(bytes), ConstantKind::Integer(ConstantIntegerInfo { base: ConstantInfoBase { tag: 3 }, bytes }))
+    }
+
+    pub fn float(&mut self, value: f32) -> Result<u16, ClassFormatError> {
+        let bytes = value.to_bits();
+        self.add(ConstantKey::Float(bytes), ConstantKind::Float(ConstantFloatInfo { base: ConstantInfoBase { tag: 4 }, bytes }))
+    }
+
+    pub fn long(&mut self, value: i64) -> Result<u16, ClassFormatError> {
+        let bits = value as u64;
+        let constant = ConstantKind::Long(ConstantLongInfo {
+            base: ConstantInfoBase { tag: 5 },
+            high_bytes: (bits >> 32) as u32,
+            low_bytes: bits as u32,
+        });
+        self.add(ConstantKey::Long(bits), constant)
+    }
+
+    pub fn double(&mut self, value: f64) -> Result<u16, ClassFormatError> {
+        let bits = value.to_bits();
+        let constant = ConstantKind::Double(ConstantDoubleInfo {
+            base: ConstantInfoBase { tag: 6 },
+            high_bytes: (bits >> 32) as u32,
+            low_bytes: bits as u32,
+        });
+        self.add(ConstantKey::Double(bits), constant)
+    }
+
+    pub fn class(&mut self, name: &str) -> Result<u16, ClassFormatError> {
+        let name_index = self.utf8(name)?;
+        self.add(ConstantKey::Class(name_index), ConstantKind::Class(ConstantClassInfo { base: ConstantInfoBase { tag: 7 }, name_index }))
+    }
+
+    pub fn string(&mut self, text: &str) -> Result<u16, ClassFormatError> {
+        let string_index = self.utf8(text)?;
+        let constant = ConstantKind::String(ConstantStringInfo { base: ConstantInfoBase { tag: 8 }, string_index });
+        self.add(ConstantKey::String(string_index), constant)
+    }
+
+    pub fn name_and_type(&mut self, name: &str, descriptor: &str) -> Result<u16, ClassFormatError> {
+        let name_index = self.utf8(name)?;
+        let descriptor_index = self.utf8(descriptor)?;
+        let constant = ConstantKind::NameAndType(ConstantNameAndTypeInfo {
+            base: ConstantInfoBase { tag: 12 },
+            name_index,
+            descriptor_index,
+        });
+        self.add(ConstantKey::NameAndType(name_index, descriptor_index), constant)
+    }
+
+    pub fn field_ref(&mut self, class: &str, name: &str, descriptor: &str) -> Result<u16, ClassFormatError> {
+        let class_index = self.class(class)?;
+        let name_and_type_index = self.name_and_type(name, descriptor)?;
+        let constant = ConstantKind::Fieldref(ConstantFieldrefInfo {
+            base: ConstantInfoBase { tag: 9 },
+            class_index,
+            name_and_type_index,
+        });
+        self.add(ConstantKey::Fieldref(class_index, name_and_type_index), constant)
+    }
+
+    pub fn method_ref(&mut self, class: &str, name: &str, descriptor: &str) -> Result<u16, ClassFormatError> {
+        let class_index = self.class(class)?;
+        let name_and_type_index = self.name_and_type(name, descriptor)?;
+        let constant = ConstantKind::Methodref(ConstantMethodrefInfo {
+            base: ConstantInfoBase { tag: 10 },
+            class_index,
+            name_and_type_index,
+        });
+        self.add(ConstantKey::Methodref(class_index, name_and_type_index), constant)
+    }
+
+    pub fn interface_method_ref(&mut self, class: &str, name: &str, descriptor: &str) -> Result<u16, ClassFormatError> {
+        let class_index = self.class(class)?;
+        let name_and_type_index = self.name_and_type(name, descriptor)?;
+        let constant = ConstantKind::InterfaceMethodref(ConstantInterfaceMethodrefInfo {
+            base: ConstantInfoBase { tag: 11 },
+            class_index,
+            name_and_type_index,
+        });
+        self.add(ConstantKey::InterfaceMethodref(class_index, name_and_type_index), constant)
+    }
+
+    pub fn method_handle(&mut self, reference_kind: u8, reference_index: u16) -> Result<u16, ClassFormatError> {
+        let constant = ConstantKind::MethodHandle(ConstantMethodHandleInfo {
+            base: ConstantInfoBase { tag: 15 },
+            reference_kind,
+            reference_index,
+        });
+        self.add(ConstantKey::MethodHandle(reference_kind, reference_index), constant)
+    }
+
+    pub fn method_type(&mut self, descriptor: &str) -> Result<u16, ClassFormatError> {
+        let descriptor_index = self.utf8(descriptor)?;
+        let constant = ConstantKind::MethodType(ConstantMethodTypeInfo { base: ConstantInfoBase { tag: 16 }, descriptor_index });
+        self.add(ConstantKey::MethodType(descriptor_index), constant)
+    }
+
+    /// A dynamically-computed constant; `bootstrap_method_attr_index` indexes the
+    /// class's BootstrapMethods attribute, which is up to the caller.
+    pub fn dynamic(&mut self, bootstrap_method_attr_index: u16, name: &str, descriptor: &str) -> Result<u16, ClassFormatError> {
+        let name_and_type_index = self.name_and_type(name, descriptor)?;
+        let constant = ConstantKind::Dynamic(ConstantDynamicInfo {
+            base: ConstantInfoBase { tag: 17 },
+            bootstrap_method_attr_index,
+            name_and_type_index,
+        });
+        self.add(ConstantKey::Dynamic(bootstrap_method_attr_index, name_and_type_index), constant)
+    }
+}
+
+fn unexpected(index: u16, expected: &'static str) -> ClassFormatError {
+    ClassFormatError::from(ClassFormatErrorKind::UnexpectedConstantPoolEntry { expected }).with_constant_pool_index(index)
+}
+
+pub fn load_constant_pool(count: u16, major_version: u16, buffer: &mut ClassReader) -> Result<ConstantPool, ClassFormatError> {
     let mut constant_pool = Vec::new();
 
     // This constant is for accessing constant pool by index.
@@ -140,144 +539,412 @@ pub fn load_constant_pool(count: u16, buffer: &mut &[u8]) -> Vec<ConstantKind> {
          tag: 0,
     }));
 
-    for _ in 0..count - 1 {
-        let tag = buffer.read_u8().unwrap();
-
-        match tag {
-            1 => {
-                let mut bytes: Vec<u8> = Vec::new();
-                let length = buffer.read_u16::<BigEndian>().unwrap();
-                for _ in 0..length {
-                    bytes.push(buffer.read_u8().unwrap());
-                }
-
-                constant_pool.push(ConstantKind::Utf8(ConstantUtf8Info {
-                    base: ConstantInfoBase { tag },
-                    length,
-                    text: String::from_utf8(bytes).unwrap(),
-                }));
+    let mut index = 1;
+    while index < count {
+        let offset = buffer.position();
+        let constant = load_constant(major_version, buffer).map_err(|e| e.with_constant_pool_index(index))?;
+        let takes_two_slots = matches!(constant, ConstantKind::Long(_) | ConstantKind::Double(_));
+        constant_pool.push(constant);
+
+        // Long and Double take up two indices (JVMS 4.4.5); the second one must not be used.
+        if takes_two_slots {
+            if index + 1 >= count {
+                return Err(ClassFormatError::new(ClassFormatErrorKind::InvalidConstantPoolIndex, offset)
+                    .with_constant_pool_index(index + 1));
             }
+            constant_pool.push(ConstantKind::Unusable(ConstantInfoBase {
+                tag: 0,
+            }));
+            index += 1;
+        }
+        index += 1;
+    }
 
-            3 => {
-                constant_pool.push(ConstantKind::Integer(ConstantIntegerInfo {
-                    base: ConstantInfoBase { tag },
-                    bytes: buffer.read_u32::<BigEndian>().unwrap(),
-                }));
-            }
+    Ok(ConstantPool::new(constant_pool))
+}
 
-            4 => {
-                constant_pool.push(ConstantKind::Float(ConstantFloatInfo {
-                    base: ConstantInfoBase { tag },
-                    bytes: buffer.read_u32::<BigEndian>().unwrap(),
-                }));
-            }
+fn load_constant(major_version: u16, buffer: &mut ClassReader) -> Result<ConstantKind, ClassFormatError> {
+    let tag_offset = buffer.position();
+    let tag = buffer.read_u8()?;
+
+    // Constant kinds introduced after Java 1.0 (JVMS Table 4.4-B).
+    let since_major_version = match tag {
+        15 | 16 | 18 => 51,
+        19 | 20 => 53,
+        17 => 55,
+        _ => 45,
+    };
+    if major_version < since_major_version {
+        return Err(ClassFormatError::new(ClassFormatErrorKind::UnsupportedConstantPoolTag { tag, major_version }, tag_offset));
+    }
 
+    match tag {
+        1 => {
+            let length = buffer.read_u16()?;
+            let bytes_offset = buffer.position();
+            let bytes = buffer.read_shared(length as usize)?;
+
+            // Most names and descriptors are plain ASCII, which reads the same in both encodings.
+            let text = if bytes.iter().all(|&byte| (0x01..0x80).contains(&byte)) {
+                String::from_utf8(bytes.to_vec()).unwrap()
+            } else {
+                let utf16 = mutf8::decode(&bytes)
+                    .map_err(|i| ClassFormatError::new(ClassFormatErrorKind::InvalidUtf8, bytes_offset + i))?;
+                String::from_utf16_lossy(&utf16)
+            };
+
+            Ok(ConstantKind::Utf8(ConstantUtf8Info {
+                base: ConstantInfoBase { tag },
+                length,
+                text,
+                bytes,
+            }))
+        }
 
-            5 => {
-                constant_pool.push(ConstantKind::Long(ConstantLongInfo {
-                    base: ConstantInfoBase { tag },
-                    high_bytes: buffer.read_u32::<BigEndian>().unwrap(),
-                    low_bytes: buffer.read_u32::<BigEndian>().unwrap(),
-                }));
-            }
+        3 => {
+            Ok(ConstantKind::Integer(ConstantIntegerInfo {
+                base: ConstantInfoBase { tag },
+                bytes: buffer.read_u32()?,
+            }))
+        }
 
-            6 => {
-                constant_pool.push(ConstantKind::Double(ConstantDoubleInfo {
-                    base: ConstantInfoBase { tag },
-                    high_bytes: buffer.read_u32::<BigEndian>().unwrap(),
-                    low_bytes: buffer.read_u32::<BigEndian>().unwrap(),
-                }));
-            }
+        4 => {
+            Ok(ConstantKind::Float(ConstantFloatInfo {
+                base: ConstantInfoBase { tag },
+                bytes: buffer.read_u32()?,
+            }))
+        }
 
-            7 => {
-                constant_pool.push(ConstantKind::Class(ConstantClassInfo {
-                    base: ConstantInfoBase { tag },
-                    name_index: buffer.read_u16::<BigEndian>().unwrap(),
-                }));
-            }
 
-            8 => {
-                constant_pool.push(ConstantKind::String(ConstantStringInfo {
-                    base: ConstantInfoBase { tag },
-                    string_index: buffer.read_u16::<BigEndian>().unwrap(),
-                }));
-            }
+        5 => {
+            Ok(ConstantKind::Long(ConstantLongInfo {
+                base: ConstantInfoBase { tag },
+                high_bytes: buffer.read_u32()?,
+                low_bytes: buffer.read_u32()?,
+            }))
+        }
 
-            9 => {
-                constant_pool.push(ConstantKind::Fieldref(ConstantFieldrefInfo {
-                    base: ConstantInfoBase { tag },
-                    class_index: buffer.read_u16::<BigEndian>().unwrap(),
-                    name_and_type_index: buffer.read_u16::<BigEndian>().unwrap(),
-                }));
-            }
+        6 => {
+            Ok(ConstantKind::Double(ConstantDoubleInfo {
+                base: ConstantInfoBase { tag },
+                high_bytes: buffer.read_u32()?,
+                low_bytes: buffer.read_u32()?,
+            }))
+        }
 
-            10 => {
-                constant_pool.push(ConstantKind::Methodref(ConstantMethodrefInfo {
-                    base: ConstantInfoBase { tag },
-                    class_index: buffer.read_u16::<BigEndian>().unwrap(),
-                    name_and_type_index: buffer.read_u16::<BigEndian>().unwrap(),
-                }));
-            }
+        7 => {
+            Ok(ConstantKind::Class(ConstantClassInfo {
+                base: ConstantInfoBase { tag },
+                name_index: buffer.read_u16()?,
+            }))
+        }
 
-            11 => {
-                constant_pool.push(ConstantKind::InterfaceMethodref(ConstantInterfaceMethodrefInfo {
-                    base: ConstantInfoBase { tag },
-                    class_index: buffer.read_u16::<BigEndian>().unwrap(),
-                    name_and_type_index: buffer.read_u16::<BigEndian>().unwrap(),
-                }));
-            }
+        8 => {
+            Ok(ConstantKind::String(ConstantStringInfo {
+                base: ConstantInfoBase { tag },
+                string_index: buffer.read_u16()?,
+            }))
+        }
 
-            12 => {
-                constant_pool.push(ConstantKind::NameAndType(ConstantNameAndTypeInfo {
-                    base: ConstantInfoBase { tag },
-                    name_index: buffer.read_u16::<BigEndian>().unwrap(),
-                    descriptor_index: buffer.read_u16::<BigEndian>().unwrap(),
-                }));
-            }
+        9 => {
+            Ok(ConstantKind::Fieldref(ConstantFieldrefInfo {
+                base: ConstantInfoBase { tag },
+                class_index: buffer.read_u16()?,
+                name_and_type_index: buffer.read_u16()?,
+            }))
+        }
 
-            15 => {
-                constant_pool.push(ConstantKind::MethodHandle(ConstantMethodHandleInfo {
-                    base: ConstantInfoBase { tag },
-                    reference_kind: buffer.read_u8().unwrap(),
-                    reference_index: buffer.read_u16::<BigEndian>().unwrap(),
-                }));
-            }
+        10 => {
+            Ok(ConstantKind::Methodref(ConstantMethodrefInfo {
+                base: ConstantInfoBase { tag },
+                class_index: buffer.read_u16()?,
+                name_and_type_index: buffer.read_u16()?,
+            }))
+        }
 
-            16 => {
-                constant_pool.push(ConstantKind::MethodType(ConstantMethodTypeInfo {
-                    base: ConstantInfoBase { tag },
-                    descriptor_index: buffer.read_u16::<BigEndian>().unwrap(),
-                }));
-            }
+        11 => {
+            Ok(ConstantKind::InterfaceMethodref(ConstantInterfaceMethodrefInfo {
+                base: ConstantInfoBase { tag },
+                class_index: buffer.read_u16()?,
+                name_and_type_index: buffer.read_u16()?,
+            }))
+        }
 
-            18 => {
-                constant_pool.push(ConstantKind::InvokeDynamic(ConstantInvokeDynamicInfo {
-                    base: ConstantInfoBase { tag },
-                    bootstrap_method_attr_index: buffer.read_u16::<BigEndian>().unwrap(),
-                    name_and_type_index: buffer.read_u16::<BigEndian>().unwrap(),
-                }));
-            }
+        12 => {
+            Ok(ConstantKind::NameAndType(ConstantNameAndTypeInfo {
+                base: ConstantInfoBase { tag },
+                name_index: buffer.read_u16()?,
+                descriptor_index: buffer.read_u16()?,
+            }))
+        }
 
-            19 => {
-                constant_pool.push(ConstantKind::Module(ConstantModuleInfo {
-                    base: ConstantInfoBase { tag },
-                    name_index: buffer.read_u16::<BigEndian>().unwrap(),
-                }));
-            }
+        15 => {
+            Ok(ConstantKind::MethodHandle(ConstantMethodHandleInfo {
+                base: ConstantInfoBase { tag },
+                reference_kind: buffer.read_u8()?,
+                reference_index: buffer.read_u16()?,
+            }))
+        }
 
-            20 => {
-                constant_pool.push(ConstantKind::Package(ConstantPackageInfo {
-                    base: ConstantInfoBase { tag },
-                    name_index: buffer.read_u16::<BigEndian>().unwrap(),
-                }));
-            }
+        16 => {
+            Ok(ConstantKind::MethodType(ConstantMethodTypeInfo {
+                base: ConstantInfoBase { tag },
+                descriptor_index: buffer.read_u16()?,
+            }))
+        }
 
-            _ => {
-                panic!("Invalid constant pool tag.");
-            }
+        17 => {
+            Ok(ConstantKind::Dynamic(ConstantDynamicInfo {
+                base: ConstantInfoBase { tag },
+                bootstrap_method_attr_index: buffer.read_u16()?,
+                name_and_type_index: buffer.read_u16()?,
+            }))
+        }
+
+        18 => {
+            Ok(ConstantKind::InvokeDynamic(ConstantInvokeDynamicInfo {
+                base: ConstantInfoBase { tag },
+                bootstrap_method_attr_index: buffer.read_u16()?,
+                name_and_type_index: buffer.read_u16()?,
+            }))
+        }
+
+        19 => {
+            Ok(ConstantKind::Module(ConstantModuleInfo {
+                base: ConstantInfoBase { tag },
+                name_index: buffer.read_u16()?,
+            }))
+        }
+
+        20 => {
+            Ok(ConstantKind::Package(ConstantPackageInfo {
+                base: ConstantInfoBase { tag },
+                name_index: buffer.read_u16()?,
+            }))
+        }
+
+        // 0, 2, 13, 14 and 21 onwards are reserved.
+        _ => {
+            Err(ClassFormatError::new(ClassFormatErrorKind::InvalidConstantPoolTag(tag), tag_offset))
+        }
+    }
+}
+
+pub fn write_constant_pool(constant_pool: &ConstantPool, buffer: &mut ClassWriter) -> Result<(), ClassFormatError> {
+    buffer.write_length_u16(constant_pool.len(), "constant_pool_count")?;
+
+    for constant in constant_pool.entries() {
+        write_constant(constant, buffer)?;
+    }
+    Ok(())
+}
+
+fn write_constant(constant: &ConstantKind, buffer: &mut ClassWriter) -> Result<(), ClassFormatError> {
+    match constant {
+        // Index 0 and the slot after Long and Double have no representation in the file.
+        ConstantKind::Empty(_) | ConstantKind::Unusable(_) => {}
+
+        ConstantKind::Utf8(utf8_info) => {
+            buffer.write_u8(1);
+            buffer.write_length_u16(utf8_info.bytes.len(), "Utf8 constant length")?;
+            buffer.write_bytes(&utf8_info.bytes);
+        }
+
+        ConstantKind::Integer(integer_info) => {
+            buffer.write_u8(3);
+            buffer.write_u32(integer_info.bytes);
+        }
+
+        ConstantKind::Float(float_info) => {
+            buffer.write_u8(4);
+            buffer.write_u32(float_info.bytes);
+        }
+
+        ConstantKind::Long(long_info) => {
+            buffer.write_u8(5);
+            buffer.write_u32(long_info.high_bytes);
+            buffer.write_u32(long_info.low_bytes);
+        }
+
+        ConstantKind::Double(double_info) => {
+            buffer.write_u8(6);
+            buffer.write_u32(double_info.high_bytes);
+            buffer.write_u32(double_info.low_bytes);
+        }
+
+        ConstantKind::Class(class_info) => {
+            buffer.write_u8(7);
+            buffer.write_u16(class_info.name_index);
+        }
+
+        ConstantKind::String(string_info) => {
+            buffer.write_u8(8);
+            buffer.write_u16(string_info.string_index);
+        }
+
+        ConstantKind::Fieldref(fieldref_info) => {
+            buffer.write_u8(9);
+            buffer.write_u16(fieldref_info.class_index);
+            buffer.write_u16(fieldref_info.name_and_type_index);
+        }
+
+        ConstantKind::Methodref(methodref_info) => {
+            buffer.write_u8(10);
+            buffer.write_u16(methodref_info.class_index);
+            buffer.write_u16(methodref_info.name_and_type_index);
+        }
+
+        ConstantKind::InterfaceMethodref(interface_methodref_info) => {
+            buffer.write_u8(11);
+            buffer.write_u16(interface_methodref_info.class_index);
+            buffer.write_u16(interface_methodref_info.name_and_type_index);
+        }
+
+        ConstantKind::NameAndType(name_and_type_info) => {
+            buffer.write_u8(12);
+            buffer.write_u16(name_and_type_info.name_index);
+            buffer.write_u16(name_and_type_info.descriptor_index);
+        }
+
+        ConstantKind::MethodHandle(method_handle_info) => {
+            buffer.write_u8(15);
+            buffer.write_u8(method_handle_info.reference_kind);
+            buffer.write_u16(method_handle_info.reference_index);
+        }
+
+        ConstantKind::MethodType(method_type_info) => {
+            buffer.write_u8(16);
+            buffer.write_u16(method_type_info.descriptor_index);
+        }
+
+        ConstantKind::Dynamic(dynamic_info) => {
+            buffer.write_u8(17);
+            buffer.write_u16(dynamic_info.bootstrap_method_attr_index);
+            buffer.write_u16(dynamic_info.name_and_type_index);
+        }
+
+        ConstantKind::InvokeDynamic(invoke_dynamic_info) => {
+            buffer.write_u8(18);
+            buffer.write_u16(invoke_dynamic_info.bootstrap_method_attr_index);
+            buffer.write_u16(invoke_dynamic_info.name_and_type_index);
+        }
+
+        ConstantKind::Module(module_info) => {
+            buffer.write_u8(19);
+            buffer.write_u16(module_info.name_index);
+        }
+
+        ConstantKind::Package(package_info) => {
+            buffer.write_u8(20);
+            buffer.write_u16(package_info.name_index);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A pool holding `wide` (a Long or Double, tag 5 or 6) at index 1 and a Methodref
+    /// `A.m:()V` after it, laid out as in a class file.
+    fn pool_after_wide_constant(wide_tag: u8) -> Result<ConstantPool, ClassFormatError> {
+        let mut bytes = vec![wide_tag, 0x12, 0x34, 0x56, 0x78, 0x9A, 0xBC, 0xDE, 0xF0];
+        bytes.extend([1, 0, 1, b'A']);  // #3
+        bytes.extend([7, 0, 3]);  // #4
+        bytes.extend([1, 0, 1, b'm']);  // #5
+        bytes.extend([1, 0, 3, b'(', b')', b'V']);  // #6
+        bytes.extend([12, 0, 5, 0, 6]);  // #7
+        bytes.extend([10, 0, 4, 0, 7]);  // #8
+        load_constant_pool(9, 52, &mut ClassReader::new(&bytes))
+    }
+
+    #[test]
+    fn methodref_after_long_is_found_at_the_shifted_index() {
+        let constant_pool = pool_after_wide_constant(5).unwrap();
+        assert_eq!(constant_pool.len(), 9);
+        assert_eq!(constant_pool.long(1).unwrap(), 0x123456789ABCDEF0);
+        assert_eq!(constant_pool.member_ref(8).unwrap(), ("A", "m", "()V"));
+    }
+
+    #[test]
+    fn methodref_after_double_is_found_at_the_shifted_index() {
+        let constant_pool = pool_after_wide_constant(6).unwrap();
+        assert_eq!(constant_pool.double(1).unwrap(), f64::from_bits(0x123456789ABCDEF0));
+        assert_eq!(constant_pool.member_ref(8).unwrap(), ("A", "m", "()V"));
+    }
+
+    #[test]
+    fn unusable_slot_is_an_invalid_index() {
+        let constant_pool = pool_after_wide_constant(5).unwrap();
+        assert!(matches!(constant_pool.entries()[2], ConstantKind::Unusable(_)));
+
+        let error = constant_pool.get(2).unwrap_err();
+        assert_eq!(error.kind, ClassFormatErrorKind::InvalidConstantPoolIndex);
+        assert_eq!(error.constant_pool_index, Some(2));
+        assert!(constant_pool.long(2).is_err());
+        assert!(constant_pool.member_ref(2).is_err());
+        assert!(constant_pool.get(0).is_err());
+        assert!(constant_pool.get(9).is_err());
+    }
+
+    #[test]
+    fn utf16_keeps_unpaired_surrogates() {
+        // "a", a lone high surrogate, NUL and U+1F600 as a surrogate pair, in Modified UTF-8.
+        let bytes = [1, 0, 13, b'a', 0xED, 0xA0, 0xBD, 0xC0, 0x80, 0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80, b'z'];
+        let constant_pool = load_constant_pool(2, 52, &mut ClassReader::new(&bytes)).unwrap();
+        let ConstantKind::Utf8(utf8_info) = constant_pool.get(1).unwrap() else { panic!("not a Utf8 constant") };
+        assert_eq!(utf8_info.utf16().unwrap(), [0x61, 0xD83D, 0x0000, 0xD83D, 0xDE00, 0x7A]);
+        assert_eq!(utf8_info.text, "a\u{FFFD}\0\u{1F600}z");
+    }
+
+    #[test]
+    fn utf16_of_bytes_that_are_not_modified_utf8_is_an_error() {
+        for bytes in [&[b'a', 0x00][..], &[0xF0, 0x9F, 0x98, 0x80], &[0xE2, 0x82]] {
+            let utf8_info = ConstantUtf8Info {
+                base: ConstantInfoBase { tag: 1 },
+                length: bytes.len() as u16,
+                text: String::new(),
+                bytes: ClassBytes::from(bytes),
+            };
+            assert_eq!(utf8_info.utf16().unwrap_err().kind, ClassFormatErrorKind::InvalidUtf8, "{:02X?}", bytes);
+            assert!(serde_json::to_string(&utf8_info).is_err());
         }
+    }
+
+    #[test]
+    fn utf8_constants_too_long_for_their_length_are_not_written() {
+        let mut builder = ConstantPoolBuilder::new();
+        builder.utf8(&"x".repeat(usize::from(u16::MAX))).unwrap();
+        assert!(write_constant_pool(&builder.build(), &mut ClassWriter::new()).is_ok());
 
+        let mut builder = ConstantPoolBuilder::new();
+        builder.utf8(&"x".repeat(usize::from(u16::MAX) + 1)).unwrap();
+        let error = write_constant_pool(&builder.build(), &mut ClassWriter::new()).unwrap_err();
+        assert_eq!(error.kind, ClassFormatErrorKind::LengthOverflow { what: "Utf8 constant length", length: 65536, max: 65535 });
     }
 
-    constant_pool
-}
\ No newline at end of file
+    #[test]
+    fn builder_stops_at_the_last_constant_pool_index() {
+        let mut builder = ConstantPoolBuilder::new();
+        for value in 1..u16::MAX - 1 {
+            builder.integer(i32::from(value)).unwrap();
+        }
+        // A Long needs two slots but only one is left.
+        let error = builder.long(0).unwrap_err();
+        assert_eq!(error.kind, ClassFormatErrorKind::LengthOverflow { what: "constant_pool_count", length: 65536, max: 65535 });
+
+        assert_eq!(builder.integer(0), Ok(65534));
+        assert_eq!(builder.integer(1), Ok(1));
+        assert!(builder.integer(-1).is_err());
+        assert_eq!(builder.len(), usize::from(u16::MAX));
+        assert!(write_constant_pool(&builder.build(), &mut ClassWriter::new()).is_ok());
+    }
+
+    #[test]
+    fn long_in_the_last_slot_is_rejected() {
+        let bytes = [5, 0, 0, 0, 0, 0, 0, 0, 1];
+        let error = load_constant_pool(2, 52, &mut ClassReader::new(&bytes)).unwrap_err();
+        assert_eq!(error.kind, ClassFormatErrorKind::InvalidConstantPoolIndex);
+    }
+}
@@ -0,0 +1,282 @@
+use std::fmt;
+
+use crate::entity::class_format_error::*;
+
+// Field and method descriptors (JVMS 4.3).
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FieldType {
+    Byte,  // B
+    Char,  // C
+    Double,  // D
+    Float,  // F
+    Int,  // I
+    Long,  // J
+    Short,  // S
+    Boolean,  // Z
+    Object(String),  // L<binary name>;
+    Array { dimensions: u8, component_type: Box<FieldType> },  // [, component_type is never an array
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MethodDescriptor {
+    pub parameters: Vec<FieldType>,
+    pub return_type: Option<FieldType>,  // None for void
+}
+
+impl FieldType {
+    pub fn parse(descriptor: &str) -> Result<FieldType, ClassFormatError> {
+        let mut parser = DescriptorParser::new(descriptor);
+        let field_type = parser.field_type()?;
+        parser.expect_end()?;
+        Ok(field_type)
+    }
+
+    /// Number of local variable / operand stack slots a value of this type takes.
+    pub fn slots(&self) -> u16 {
+        match self {
+            FieldType::Long | FieldType::Double => 2,
+            _ => 1,
+        }
+    }
+
+    pub fn is_reference(&self) -> bool {
+        matches!(self, FieldType::Object(_) | FieldType::Array { .. })
+    }
+}
+
+impl MethodDescriptor {
+    pub fn parse(descriptor: &str) -> Result<MethodDescriptor, ClassFormatError> {
+        let mut parser = DescriptorParser::new(descriptor);
+        parser.expect('(')?;
+        let mut parameters = Vec::new();
+        while !parser.eat(')') {
+            parameters.push(parser.field_type()?);
+        }
+        let return_type = if parser.eat('V') {
+            None
+        } else {
+            Some(parser.field_type()?)
+        };
+        parser.expect_end()?;
+
+        let descriptor = MethodDescriptor { parameters, return_type };
+        // JVMS 4.3.3: at most 255 parameter slots. Instance methods also pass `this`, which
+        // the format checker counts once it knows the method's access flags.
+        if descriptor.argument_slots() > 255 {
+            return Err(parser.error());
+        }
+        Ok(descriptor)
+    }
+
+    /// Slots taken by the arguments, not counting `this`.
+    pub fn argument_slots(&self) -> u16 {
+        self.parameters.iter().map(FieldType::slots).sum()
+    }
+
+    pub fn return_slots(&self) -> u16 {
+        self.return_type.as_ref().map_or(0, FieldType::slots)
+    }
+}
+
+impl fmt::Display for FieldType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldType::Byte => write!(f, "B"),
+            FieldType::Char => write!(f, "C"),
+            FieldType::Double => write!(f, "D"),
+            FieldType::Float => write!(f, "F"),
+            FieldType::Int => write!(f, "I"),
+            FieldType::Long => write!(f, "J"),
+            FieldType::Short => write!(f, "S"),
+            FieldType::Boolean => write!(f, "Z"),
+            FieldType::Object(class_name) => write!(f, "L{};", class_name),
+            FieldType::Array { dimensions, component_type } => {
+                write!(f, "{}{}", "[".repeat(*dimensions as usize), component_type)
+            }
+        }
+    }
+}
+
+impl fmt::Display for MethodDescriptor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "(")?;
+        for parameter in &self.parameters {
+            write!(f, "{}", parameter)?;
+        }
+        write!(f, ")")?;
+        match &self.return_type {
+            Some(return_type) => write!(f, "{}", return_type),
+            None => write!(f, "V"),
+        }
+    }
+}
+
+struct DescriptorParser<'a> {
+    descriptor: &'a str,
+    rest: &'a str,
+}
+
+impl<'a> DescriptorParser<'a> {
+    fn new(descriptor: &'a str) -> Self {
+        DescriptorParser { descriptor, rest: descriptor }
+    }
+
+    fn error(&self) -> ClassFormatError {
+        ClassFormatError::from(ClassFormatErrorKind::InvalidDescriptor(self.descriptor.to_string()))
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        match self.rest.strip_prefix(c) {
+            Some(rest) => {
+                self.rest = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), ClassFormatError> {
+        if self.eat(c) { Ok(()) } else { Err(self.error()) }
+    }
+
+    fn expect_end(&self) -> Result<(), ClassFormatError> {
+        if self.rest.is_empty() { Ok(()) } else { Err(self.error()) }
+    }
+
+    fn field_type(&mut self) -> Result<FieldType, ClassFormatError> {
+        let mut dimensions: u8 = 0;
+        while self.eat('[') {
+            // JVMS 4.4.1: an array type has at most 255 dimensions.
+            dimensions = dimensions.checked_add(1).ok_or_else(|| self.error())?;
+        }
+
+        let mut chars = self.rest.chars();
+        let component_type = match chars.next() {
+            Some('B') => FieldType::Byte,
+            Some('C') => FieldType::Char,
+            Some('D') => FieldType::Double,
+            Some('F') => FieldType::Float,
+            Some('I') => FieldType::Int,
+            Some('J') => FieldType::Long,
+            Some('S') => FieldType::Short,
+            Some('Z') => FieldType::Boolean,
+            Some('L') => {
+                let end = self.rest.find(';').ok_or_else(|| self.error())?;
+                let class_name = &self.rest[1..end];
+                if !is_valid_binary_name(class_name) {
+                    return Err(self.error());
+                }
+                self.rest = &self.rest[end + 1..];
+                FieldType::Object(class_name.to_string())
+            }
+            _ => return Err(self.error()),
+        };
+        if !matches!(component_type, FieldType::Object(_)) {
+            self.rest = chars.as_str();
+        }
+
+        if dimensions == 0 {
+            Ok(component_type)
+        } else {
+            Ok(FieldType::Array { dimensions, component_type: Box::new(component_type) })
+        }
+    }
+}
+
+/// Binary class or interface name in internal form (JVMS 4.2.1), e.g. `java/lang/Object`.
+pub fn is_valid_binary_name(name: &str) -> bool {
+    !name.is_empty() && name.split('/').all(is_valid_unqualified_name)
+}
+
+/// Unqualified name of a field, local variable or formal parameter (JVMS 4.2.2).
+pub fn is_valid_unqualified_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains(['.', ';', '[', '/'])
+}
+
+/// Method name (JVMS 4.2.2): an unqualified name without `<` or `>`, or one of the
+/// special names `<init>` and `<clinit>`.
+pub fn is_valid_method_name(name: &str) -> bool {
+    name == "<init>" || name == "<clinit>" || (is_valid_unqualified_name(name) && !name.contains(['<', '>']))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn object(name: &str) -> FieldType {
+        FieldType::Object(name.to_string())
+    }
+
+    fn array(dimensions: u8, component_type: FieldType) -> FieldType {
+        FieldType::Array { dimensions, component_type: Box::new(component_type) }
+    }
+
+    #[test]
+    fn field_types_are_parsed() {
+        assert_eq!(FieldType::parse("I"), Ok(FieldType::Int));
+        assert_eq!(FieldType::parse("Ljava/lang/String;"), Ok(object("java/lang/String")));
+        assert_eq!(FieldType::parse("[[J"), Ok(array(2, FieldType::Long)));
+        assert_eq!(FieldType::parse("[Ljava/util/List;"), Ok(array(1, object("java/util/List"))));
+    }
+
+    #[test]
+    fn method_descriptors_are_parsed() {
+        let descriptor = MethodDescriptor::parse("(IDLjava/lang/Thread;[Z)Ljava/lang/Object;").unwrap();
+        assert_eq!(descriptor.parameters, [FieldType::Int, FieldType::Double, object("java/lang/Thread"), array(1, FieldType::Boolean)]);
+        assert_eq!(descriptor.return_type, Some(object("java/lang/Object")));
+
+        let descriptor = MethodDescriptor::parse("()V").unwrap();
+        assert!(descriptor.parameters.is_empty());
+        assert_eq!(descriptor.return_type, None);
+    }
+
+    #[test]
+    fn descriptors_display_as_they_were_written() {
+        for descriptor in ["B", "C", "D", "F", "I", "J", "S", "Z", "Ljava/lang/Object;", "[[[Ljava/lang/Object;", "[I"] {
+            assert_eq!(FieldType::parse(descriptor).unwrap().to_string(), descriptor);
+        }
+        for descriptor in ["()V", "(IJ)D", "([Ljava/lang/String;)V", "(Ljava/util/Map;[[BZ)Ljava/lang/String;"] {
+            assert_eq!(MethodDescriptor::parse(descriptor).unwrap().to_string(), descriptor);
+        }
+    }
+
+    #[test]
+    fn longs_and_doubles_take_two_slots() {
+        assert_eq!(FieldType::Long.slots(), 2);
+        assert_eq!(FieldType::Double.slots(), 2);
+        assert_eq!(FieldType::Int.slots(), 1);
+        assert_eq!(array(1, FieldType::Long).slots(), 1);
+
+        let descriptor = MethodDescriptor::parse("(JIDLjava/lang/Object;)J").unwrap();
+        assert_eq!(descriptor.argument_slots(), 6);
+        assert_eq!(descriptor.return_slots(), 2);
+        assert_eq!(MethodDescriptor::parse("()V").unwrap().return_slots(), 0);
+    }
+
+    #[test]
+    fn methods_take_at_most_255_argument_slots() {
+        assert!(MethodDescriptor::parse(&format!("({})V", "I".repeat(255))).is_ok());
+        assert!(MethodDescriptor::parse(&format!("({})V", "I".repeat(256))).is_err());
+        assert!(MethodDescriptor::parse(&format!("({}I)V", "J".repeat(127))).is_ok());
+        assert!(MethodDescriptor::parse(&format!("({})V", "J".repeat(128))).is_err());
+    }
+
+    #[test]
+    fn malformed_descriptors_are_rejected() {
+        let invalid = |descriptor: &str| ClassFormatError::from(ClassFormatErrorKind::InvalidDescriptor(descriptor.to_string()));
+        for descriptor in ["", "L;", "Ljava/lang/Object", "La//b;", "[", "[[", "V", "X", "II", "Ljava/lang/Object;I"] {
+            assert_eq!(FieldType::parse(descriptor).unwrap_err(), invalid(descriptor), "{:?}", descriptor);
+        }
+        for descriptor in ["", "V", "()", "(V)V", "()VV", "(I)I;", "(L;)V", "([)V", "(I", "I)V", "()[V"] {
+            assert_eq!(MethodDescriptor::parse(descriptor).unwrap_err(), invalid(descriptor), "{:?}", descriptor);
+        }
+    }
+
+    #[test]
+    fn arrays_have_at_most_255_dimensions() {
+        let descriptor = format!("{}I", "[".repeat(255));
+        assert_eq!(FieldType::parse(&descriptor), Ok(array(255, FieldType::Int)));
+        assert!(FieldType::parse(&format!("[{}", descriptor)).is_err());
+    }
+}
@@ -1,30 +1,58 @@
 use crate::entity::attribute_info::AttributeKind;
 use crate::entity::constant_pool::*;
 use crate::entity::attribute_info::*;
-use byteorder::{BigEndian, ReadBytesExt};
+use crate::entity::class_format_error::ClassFormatError;
+use crate::entity::class_reader::ClassReader;
+use crate::entity::class_writer::ClassWriter;
+use crate::entity::descriptor::FieldType;
+use crate::entity::access_flags::FieldAccessFlags;
+use serde::{Deserialize, Serialize};
 
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FieldInfo {
-    pub access_flags: u16,
+    pub access_flags: FieldAccessFlags,
     pub name_index: u16,
     pub descriptor_index: u16,
     pub attributes_count: u16,
     pub attributes: Vec<AttributeKind>,
 }
 
-pub fn load_fields(count: u16, buffer: &mut &[u8], constant_pool: &Vec<ConstantKind>) -> Vec<FieldInfo> {
+impl FieldInfo {
+    pub fn descriptor(&self, constant_pool: &ConstantPool) -> Result<FieldType, ClassFormatError> {
+        FieldType::parse(constant_pool.utf8(self.descriptor_index)?)
+            .map_err(|e| e.with_constant_pool_index(self.descriptor_index))
+    }
+}
+
+pub fn load_fields(count: u16, buffer: &mut ClassReader, constant_pool: &ConstantPool) -> Result<Vec<FieldInfo>, ClassFormatError> {
     let mut fields = Vec::new();
 
     for _ in 0..count {
+        let attributes_count;
         fields.push(FieldInfo {
-            access_flags: buffer.read_u16::<BigEndian>().unwrap(),
-            name_index: buffer.read_u16::<BigEndian>().unwrap(),
-            descriptor_index: buffer.read_u16::<BigEndian>().unwrap(),
-            attributes_count: buffer.read_u16::<BigEndian>().unwrap(),
-            attributes: load_attributes(count, buffer, constant_pool),
+            access_flags: FieldAccessFlags(buffer.read_u16()?),
+            name_index: buffer.read_u16()?,
+            descriptor_index: buffer.read_u16()?,
+            attributes_count: {
+                attributes_count = buffer.read_u16()?;
+                attributes_count
+            },
+            attributes: load_attributes(attributes_count, buffer, constant_pool)?,
         });
     }
 
-    fields
+    Ok(fields)
+}
+
+pub fn write_fields(fields: &[FieldInfo], buffer: &mut ClassWriter) -> Result<(), ClassFormatError> {
+    buffer.write_length_u16(fields.len(), "fields_count")?;
+
+    for field in fields {
+        buffer.write_u16(field.access_flags.bits());
+        buffer.write_u16(field.name_index);
+        buffer.write_u16(field.descriptor_index);
+        write_attributes(&field.attributes, buffer)?;
+    }
+    Ok(())
 }
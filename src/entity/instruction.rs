@@ -0,0 +1,1182 @@
+use crate::entity::class_format_error::*;
+use crate::entity::class_reader::ClassReader;
+use crate::entity::class_writer::ClassWriter;
+
+// JVM instructions (JVMS 6.5). Branch offsets are kept as written, relative to the
+// pc of the instruction they belong to, so decoding and encoding are lossless.
+// Opcodes 202 (breakpoint), 254 and 255 (impdep1/2) are reserved and never valid
+// in a class file.
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Instruction {
+    Nop,
+    AconstNull,
+    IconstM1,
+    Iconst0,
+    Iconst1,
+    Iconst2,
+    Iconst3,
+    Iconst4,
+    Iconst5,
+    Lconst0,
+    Lconst1,
+    Fconst0,
+    Fconst1,
+    Fconst2,
+    Dconst0,
+    Dconst1,
+    Bipush(i8),
+    Sipush(i16),
+    Ldc(u8),
+    LdcW(u16),
+    Ldc2W(u16),
+    Iload(u8),
+    Lload(u8),
+    Fload(u8),
+    Dload(u8),
+    Aload(u8),
+    Iload0,
+    Iload1,
+    Iload2,
+    Iload3,
+    Lload0,
+    Lload1,
+    Lload2,
+    Lload3,
+    Fload0,
+    Fload1,
+    Fload2,
+    Fload3,
+    Dload0,
+    Dload1,
+    Dload2,
+    Dload3,
+    Aload0,
+    Aload1,
+    Aload2,
+    Aload3,
+    Iaload,
+    Laload,
+    Faload,
+    Daload,
+    Aaload,
+    Baload,
+    Caload,
+    Saload,
+    Istore(u8),
+    Lstore(u8),
+    Fstore(u8),
+    Dstore(u8),
+    Astore(u8),
+    Istore0,
+    Istore1,
+    Istore2,
+    Istore3,
+    Lstore0,
+    Lstore1,
+    Lstore2,
+    Lstore3,
+    Fstore0,
+    Fstore1,
+    Fstore2,
+    Fstore3,
+    Dstore0,
+    Dstore1,
+    Dstore2,
+    Dstore3,
+    Astore0,
+    Astore1,
+    Astore2,
+    Astore3,
+    Iastore,
+    Lastore,
+    Fastore,
+    Dastore,
+    Aastore,
+    Bastore,
+    Castore,
+    Sastore,
+    Pop,
+    Pop2,
+    Dup,
+    DupX1,
+    DupX2,
+    Dup2,
+    Dup2X1,
+    Dup2X2,
+    Swap,
+    Iadd,
+    Ladd,
+    Fadd,
+    Dadd,
+    Isub,
+    Lsub,
+    Fsub,
+    Dsub,
+    Imul,
+    Lmul,
+    Fmul,
+    Dmul,
+    Idiv,
+    Ldiv,
+    Fdiv,
+    Ddiv,
+    Irem,
+    Lrem,
+    Frem,
+    Drem,
+    Ineg,
+    Lneg,
+    Fneg,
+    Dneg,
+    Ishl,
+    Lshl,
+    Ishr,
+    Lshr,
+    Iushr,
+    Lushr,
+    Iand,
+    Land,
+    Ior,
+    Lor,
+    Ixor,
+    Lxor,
+    Iinc { index: u8, constant: i8 },
+    I2l,
+    I2f,
+    I2d,
+    L2i,
+    L2f,
+    L2d,
+    F2i,
+    F2l,
+    F2d,
+    D2i,
+    D2l,
+    D2f,
+    I2b,
+    I2c,
+    I2s,
+    Lcmp,
+    Fcmpl,
+    Fcmpg,
+    Dcmpl,
+    Dcmpg,
+    Ifeq(i16),
+    Ifne(i16),
+    Iflt(i16),
+    Ifge(i16),
+    Ifgt(i16),
+    Ifle(i16),
+    IfIcmpeq(i16),
+    IfIcmpne(i16),
+    IfIcmplt(i16),
+    IfIcmpge(i16),
+    IfIcmpgt(i16),
+    IfIcmple(i16),
+    IfAcmpeq(i16),
+    IfAcmpne(i16),
+    Goto(i16),
+    Jsr(i16),
+    Ret(u8),
+    Tableswitch { default: i32, low: i32, high: i32, offsets: Vec<i32> },
+    Lookupswitch { default: i32, pairs: Vec<(i32, i32)> },  // sorted by match
+    Ireturn,
+    Lreturn,
+    Freturn,
+    Dreturn,
+    Areturn,
+    Return,
+    Getstatic(u16),
+    Putstatic(u16),
+    Getfield(u16),
+    Putfield(u16),
+    Invokevirtual(u16),
+    Invokespecial(u16),
+    Invokestatic(u16),
+    Invokeinterface { index: u16, count: u8 },
+    Invokedynamic(u16),
+    New(u16),
+    Newarray(ArrayType),
+    Anewarray(u16),
+    Arraylength,
+    Athrow,
+    Checkcast(u16),
+    Instanceof(u16),
+    Monitorenter,
+    Monitorexit,
+    Wide(WideInstruction),
+    Multianewarray { index: u16, dimensions: u8 },
+    Ifnull(i16),
+    Ifnonnull(i16),
+    GotoW(i32),
+    JsrW(i32),
+}
+
+/// Instructions that follow a `wide` prefix, with 16-bit local variable indices.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum WideInstruction {
+    Iload(u16),
+    Lload(u16),
+    Fload(u16),
+    Dload(u16),
+    Aload(u16),
+    Istore(u16),
+    Lstore(u16),
+    Fstore(u16),
+    Dstore(u16),
+    Astore(u16),
+    Ret(u16),
+    Iinc { index: u16, constant: i16 },
+}
+
+/// `atype` operand of `newarray`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ArrayType {
+    Boolean = 4,
+    Char = 5,
+    Float = 6,
+    Double = 7,
+    Byte = 8,
+    Short = 9,
+    Int = 10,
+    Long = 11,
+}
+
+impl ArrayType {
+    pub fn from_atype(atype: u8) -> Option<ArrayType> {
+        match atype {
+            4 => Some(ArrayType::Boolean),
+            5 => Some(ArrayType::Char),
+            6 => Some(ArrayType::Float),
+            7 => Some(ArrayType::Double),
+            8 => Some(ArrayType::Byte),
+            9 => Some(ArrayType::Short),
+            10 => Some(ArrayType::Int),
+            11 => Some(ArrayType::Long),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ArrayType::Boolean => "boolean",
+            ArrayType::Char => "char",
+            ArrayType::Float => "float",
+            ArrayType::Double => "double",
+            ArrayType::Byte => "byte",
+            ArrayType::Short => "short",
+            ArrayType::Int => "int",
+            ArrayType::Long => "long",
+        }
+    }
+}
+
+impl WideInstruction {
+    pub fn opcode(&self) -> u8 {
+        match self {
+            WideInstruction::Iload(_) => 21,
+            WideInstruction::Lload(_) => 22,
+            WideInstruction::Fload(_) => 23,
+            WideInstruction::Dload(_) => 24,
+            WideInstruction::Aload(_) => 25,
+            WideInstruction::Istore(_) => 54,
+            WideInstruction::Lstore(_) => 55,
+            WideInstruction::Fstore(_) => 56,
+            WideInstruction::Dstore(_) => 57,
+            WideInstruction::Astore(_) => 58,
+            WideInstruction::Ret(_) => 169,
+            WideInstruction::Iinc { .. } => 132,
+        }
+    }
+
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            WideInstruction::Iload(_) => "iload",
+            WideInstruction::Lload(_) => "lload",
+            WideInstruction::Fload(_) => "fload",
+            WideInstruction::Dload(_) => "dload",
+            WideInstruction::Aload(_) => "aload",
+            WideInstruction::Istore(_) => "istore",
+            WideInstruction::Lstore(_) => "lstore",
+            WideInstruction::Fstore(_) => "fstore",
+            WideInstruction::Dstore(_) => "dstore",
+            WideInstruction::Astore(_) => "astore",
+            WideInstruction::Ret(_) => "ret",
+            WideInstruction::Iinc { .. } => "iinc",
+        }
+    }
+}
+
+impl Instruction {
+    pub fn opcode(&self) -> u8 {
+        match self {
+            Instruction::Nop => 0,
+            Instruction::AconstNull => 1,
+            Instruction::IconstM1 => 2,
+            Instruction::Iconst0 => 3,
+            Instruction::Iconst1 => 4,
+            Instruction::Iconst2 => 5,
+            Instruction::Iconst3 => 6,
+            Instruction::Iconst4 => 7,
+            Instruction::Iconst5 => 8,
+            Instruction::Lconst0 => 9,
+            Instruction::Lconst1 => 10,
+            Instruction::Fconst0 => 11,
+            Instruction::Fconst1 => 12,
+            Instruction::Fconst2 => 13,
+            Instruction::Dconst0 => 14,
+            Instruction::Dconst1 => 15,
+            Instruction::Bipush(_) => 16,
+            Instruction::Sipush(_) => 17,
+            Instruction::Ldc(_) => 18,
+            Instruction::LdcW(_) => 19,
+            Instruction::Ldc2W(_) => 20,
+            Instruction::Iload(_) => 21,
+            Instruction::Lload(_) => 22,
+            Instruction::Fload(_) => 23,
+            Instruction::Dload(_) => 24,
+            Instruction::Aload(_) => 25,
+            Instruction::Iload0 => 26,
+            Instruction::Iload1 => 27,
+            Instruction::Iload2 => 28,
+            Instruction::Iload3 => 29,
+            Instruction::Lload0 => 30,
+            Instruction::Lload1 => 31,
+            Instruction::Lload2 => 32,
+            Instruction::Lload3 => 33,
+            Instruction::Fload0 => 34,
+            Instruction::Fload1 => 35,
+            Instruction::Fload2 => 36,
+            Instruction::Fload3 => 37,
+            Instruction::Dload0 => 38,
+            Instruction::Dload1 => 39,
+            Instruction::Dload2 => 40,
+            Instruction::Dload3 => 41,
+            Instruction::Aload0 => 42,
+            Instruction::Aload1 => 43,
+            Instruction::Aload2 => 44,
+            Instruction::Aload3 => 45,
+            Instruction::Iaload => 46,
+            Instruction::Laload => 47,
+            Instruction::Faload => 48,
+            Instruction::Daload => 49,
+            Instruction::Aaload => 50,
+            Instruction::Baload => 51,
+            Instruction::Caload => 52,
+            Instruction::Saload => 53,
+            Instruction::Istore(_) => 54,
+            Instruction::Lstore(_) => 55,
+            Instruction::Fstore(_) => 56,
+            Instruction::Dstore(_) => 57,
+            Instruction::Astore(_) => 58,
+            Instruction::Istore0 => 59,
+            Instruction::Istore1 => 60,
+            Instruction::Istore2 => 61,
+            Instruction::Istore3 => 62,
+            Instruction::Lstore0 => 63,
+            Instruction::Lstore1 => 64,
+            Instruction::Lstore2 => 65,
+            Instruction::Lstore3 => 66,
+            Instruction::Fstore0 => 67,
+            Instruction::Fstore1 => 68,
+            Instruction::Fstore2 => 69,
+            Instruction::Fstore3 => 70,
+            Instruction::Dstore0 => 71,
+            Instruction::Dstore1 => 72,
+            Instruction::Dstore2 => 73,
+            Instruction::Dstore3 => 74,
+            Instruction::Astore0 => 75,
+            Instruction::Astore1 => 76,
+            Instruction::Astore2 => 77,
+            Instruction::Astore3 => 78,
+            Instruction::Iastore => 79,
+            Instruction::Lastore => 80,
+            Instruction::Fastore => 81,
+            Instruction::Dastore => 82,
+            Instruction::Aastore => 83,
+            Instruction::Bastore => 84,
+            Instruction::Castore => 85,
+            Instruction::Sastore => 86,
+            Instruction::Pop => 87,
+            Instruction::Pop2 => 88,
+            Instruction::Dup => 89,
+            Instruction::DupX1 => 90,
+            Instruction::DupX2 => 91,
+            Instruction::Dup2 => 92,
+            Instruction::Dup2X1 => 93,
+            Instruction::Dup2X2 => 94,
+            Instruction::Swap => 95,
+            Instruction::Iadd => 96,
+            Instruction::Ladd => 97,
+            Instruction::Fadd => 98,
+            Instruction::Dadd => 99,
+            Instruction::Isub => 100,
+            Instruction::Lsub => 101,
+            Instruction::Fsub => 102,
+            Instruction::Dsub => 103,
+            Instruction::Imul => 104,
+            Instruction::Lmul => 105,
+            Instruction::Fmul => 106,
+            Instruction::Dmul => 107,
+            Instruction::Idiv => 108,
+            Instruction::Ldiv => 109,
+            Instruction::Fdiv => 110,
+            Instruction::Ddiv => 111,
+            Instruction::Irem => 112,
+            Instruction::Lrem => 113,
+            Instruction::Frem => 114,
+            Instruction::Drem => 115,
+            Instruction::Ineg => 116,
+            Instruction::Lneg => 117,
+            Instruction::Fneg => 118,
+            Instruction::Dneg => 119,
+            Instruction::Ishl => 120,
+            Instruction::Lshl => 121,
+            Instruction::Ishr => 122,
+            Instruction::Lshr => 123,
+            Instruction::Iushr => 124,
+            Instruction::Lushr => 125,
+            Instruction::Iand => 126,
+            Instruction::Land => 127,
+            Instruction::Ior => 128,
+            Instruction::Lor => 129,
+            Instruction::Ixor => 130,
+            Instruction::Lxor => 131,
+            Instruction::Iinc { .. } => 132,
+            Instruction::I2l => 133,
+            Instruction::I2f => 134,
+            Instruction::I2d => 135,
+            Instruction::L2i => 136,
+            Instruction::L2f => 137,
+            Instruction::L2d => 138,
+            Instruction::F2i => 139,
+            Instruction::F2l => 140,
+            Instruction::F2d => 141,
+            Instruction::D2i => 142,
+            Instruction::D2l => 143,
+            Instruction::D2f => 144,
+            Instruction::I2b => 145,
+            Instruction::I2c => 146,
+            Instruction::I2s => 147,
+            Instruction::Lcmp => 148,
+            Instruction::Fcmpl => 149,
+            Instruction::Fcmpg => 150,
+            Instruction::Dcmpl => 151,
+            Instruction::Dcmpg => 152,
+            Instruction::Ifeq(_) => 153,
+            Instruction::Ifne(_) => 154,
+            Instruction::Iflt(_) => 155,
+            Instruction::Ifge(_) => 156,
+            Instruction::Ifgt(_) => 157,
+            Instruction::Ifle(_) => 158,
+            Instruction::IfIcmpeq(_) => 159,
+            Instruction::IfIcmpne(_) => 160,
+            Instruction::IfIcmplt(_) => 161,
+            Instruction::IfIcmpge(_) => 162,
+            Instruction::IfIcmpgt(_) => 163,
+            Instruction::IfIcmple(_) => 164,
+            Instruction::IfAcmpeq(_) => 165,
+            Instruction::IfAcmpne(_) => 166,
+            Instruction::Goto(_) => 167,
+            Instruction::Jsr(_) => 168,
+            Instruction::Ret(_) => 169,
+            Instruction::Tableswitch { .. } => 170,
+            Instruction::Lookupswitch { .. } => 171,
+            Instruction::Ireturn => 172,
+            Instruction::Lreturn => 173,
+            Instruction::Freturn => 174,
+            Instruction::Dreturn => 175,
+            Instruction::Areturn => 176,
+            Instruction::Return => 177,
+            Instruction::Getstatic(_) => 178,
+            Instruction::Putstatic(_) => 179,
+            Instruction::Getfield(_) => 180,
+            Instruction::Putfield(_) => 181,
+            Instruction::Invokevirtual(_) => 182,
+            Instruction::Invokespecial(_) => 183,
+            Instruction::Invokestatic(_) => 184,
+            Instruction::Invokeinterface { .. } => 185,
+            Instruction::Invokedynamic(_) => 186,
+            Instruction::New(_) => 187,
+            Instruction::Newarray(_) => 188,
+            Instruction::Anewarray(_) => 189,
+            Instruction::Arraylength => 190,
+            Instruction::Athrow => 191,
+            Instruction::Checkcast(_) => 192,
+            Instruction::Instanceof(_) => 193,
+            Instruction::Monitorenter => 194,
+            Instruction::Monitorexit => 195,
+            Instruction::Wide(_) => 196,
+            Instruction::Multianewarray { .. } => 197,
+            Instruction::Ifnull(_) => 198,
+            Instruction::Ifnonnull(_) => 199,
+            Instruction::GotoW(_) => 200,
+            Instruction::JsrW(_) => 201,
+        }
+    }
+
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Instruction::Nop => "nop",
+            Instruction::AconstNull => "aconst_null",
+            Instruction::IconstM1 => "iconst_m1",
+            Instruction::Iconst0 => "iconst_0",
+            Instruction::Iconst1 => "iconst_1",
+            Instruction::Iconst2 => "iconst_2",
+            Instruction::Iconst3 => "iconst_3",
+            Instruction::Iconst4 => "iconst_4",
+            Instruction::Iconst5 => "iconst_5",
+            Instruction::Lconst0 => "lconst_0",
+            Instruction::Lconst1 => "lconst_1",
+            Instruction::Fconst0 => "fconst_0",
+            Instruction::Fconst1 => "fconst_1",
+            Instruction::Fconst2 => "fconst_2",
+            Instruction::Dconst0 => "dconst_0",
+            Instruction::Dconst1 => "dconst_1",
+            Instruction::Bipush(_) => "bipush",
+            Instruction::Sipush(_) => "sipush",
+            Instruction::Ldc(_) => "ldc",
+            Instruction::LdcW(_) => "ldc_w",
+            Instruction::Ldc2W(_) => "ldc2_w",
+            Instruction::Iload(_) => "iload",
+            Instruction::Lload(_) => "lload",
+            Instruction::Fload(_) => "fload",
+            Instruction::Dload(_) => "dload",
+            Instruction::Aload(_) => "aload",
+            Instruction::Iload0 => "iload_0",
+            Instruction::Iload1 => "iload_1",
+            Instruction::Iload2 => "iload_2",
+            Instruction::Iload3 => "iload_3",
+            Instruction::Lload0 => "lload_0",
+            Instruction::Lload1 => "lload_1",
+            Instruction::Lload2 => "lload_2",
+            Instruction::Lload3 => "lload_3",
+            Instruction::Fload0 => "fload_0",
+            Instruction::Fload1 => "fload_1",
+            Instruction::Fload2 => "fload_2",
+            Instruction::Fload3 => "fload_3",
+            Instruction::Dload0 => "dload_0",
+            Instruction::Dload1 => "dload_1",
+            Instruction::Dload2 => "dload_2",
+            Instruction::Dload3 => "dload_3",
+            Instruction::Aload0 => "aload_0",
+            Instruction::Aload1 => "aload_1",
+            Instruction::Aload2 => "aload_2",
+            Instruction::Aload3 => "aload_3",
+            Instruction::Iaload => "iaload",
+            Instruction::Laload => "laload",
+            Instruction::Faload => "faload",
+            Instruction::Daload => "daload",
+            Instruction::Aaload => "aaload",
+            Instruction::Baload => "baload",
+            Instruction::Caload => "caload",
+            Instruction::Saload => "saload",
+            Instruction::Istore(_) => "istore",
+            Instruction::Lstore(_) => "lstore",
+            Instruction::Fstore(_) => "fstore",
+            Instruction::Dstore(_) => "dstore",
+            Instruction::Astore(_) => "astore",
+            Instruction::Istore0 => "istore_0",
+            Instruction::Istore1 => "istore_1",
+            Instruction::Istore2 => "istore_2",
+            Instruction::Istore3 => "istore_3",
+            Instruction::Lstore0 => "lstore_0",
+            Instruction::Lstore1 => "lstore_1",
+            Instruction::Lstore2 => "lstore_2",
+            Instruction::Lstore3 => "lstore_3",
+            Instruction::Fstore0 => "fstore_0",
+            Instruction::Fstore1 => "fstore_1",
+            Instruction::Fstore2 => "fstore_2",
+            Instruction::Fstore3 => "fstore_3",
+            Instruction::Dstore0 => "dstore_0",
+            Instruction::Dstore1 => "dstore_1",
+            Instruction::Dstore2 => "dstore_2",
+            Instruction::Dstore3 => "dstore_3",
+            Instruction::Astore0 => "astore_0",
+            Instruction::Astore1 => "astore_1",
+            Instruction::Astore2 => "astore_2",
+            Instruction::Astore3 => "astore_3",
+            Instruction::Iastore => "iastore",
+            Instruction::Lastore => "lastore",
+            Instruction::Fastore => "fastore",
+            Instruction::Dastore => "dastore",
+            Instruction::Aastore => "aastore",
+            Instruction::Bastore => "bastore",
+            Instruction::Castore => "castore",
+            Instruction::Sastore => "sastore",
+            Instruction::Pop => "pop",
+            Instruction::Pop2 => "pop2",
+            Instruction::Dup => "dup",
+            Instruction::DupX1 => "dup_x1",
+            Instruction::DupX2 => "dup_x2",
+            Instruction::Dup2 => "dup2",
+            Instruction::Dup2X1 => "dup2_x1",
+            Instruction::Dup2X2 => "dup2_x2",
+            Instruction::Swap => "swap",
+            Instruction::Iadd => "iadd",
+            Instruction::Ladd => "ladd",
+            Instruction::Fadd => "fadd",
+            Instruction::Dadd => "dadd",
+            Instruction::Isub => "isub",
+            Instruction::Lsub => "lsub",
+            Instruction::Fsub => "fsub",
+            Instruction::Dsub => "dsub",
+            Instruction::Imul => "imul",
+            Instruction::Lmul => "lmul",
+            Instruction::Fmul => "fmul",
+            Instruction::Dmul => "dmul",
+            Instruction::Idiv => "idiv",
+            Instruction::Ldiv => "ldiv",
+            Instruction::Fdiv => "fdiv",
+            Instruction::Ddiv => "ddiv",
+            Instruction::Irem => "irem",
+            Instruction::Lrem => "lrem",
+            Instruction::Frem => "frem",
+            Instruction::Drem => "drem",
+            Instruction::Ineg => "ineg",
+            Instruction::Lneg => "lneg",
+            Instruction::Fneg => "fneg",
+            Instruction::Dneg => "dneg",
+            Instruction::Ishl => "ishl",
+            Instruction::Lshl => "lshl",
+            Instruction::Ishr => "ishr",
+            Instruction::Lshr => "lshr",
+            Instruction::Iushr => "iushr",
+            Instruction::Lushr => "lushr",
+            Instruction::Iand => "iand",
+            Instruction::Land => "land",
+            Instruction::Ior => "ior",
+            Instruction::Lor => "lor",
+            Instruction::Ixor => "ixor",
+            Instruction::Lxor => "lxor",
+            Instruction::Iinc { .. } => "iinc",
+            Instruction::I2l => "i2l",
+            Instruction::I2f => "i2f",
+            Instruction::I2d => "i2d",
+            Instruction::L2i => "l2i",
+            Instruction::L2f => "l2f",
+            Instruction::L2d => "l2d",
+            Instruction::F2i => "f2i",
+            Instruction::F2l => "f2l",
+            Instruction::F2d => "f2d",
+            Instruction::D2i => "d2i",
+            Instruction::D2l => "d2l",
+            Instruction::D2f => "d2f",
+            Instruction::I2b => "i2b",
+            Instruction::I2c => "i2c",
+            Instruction::I2s => "i2s",
+            Instruction::Lcmp => "lcmp",
+            Instruction::Fcmpl => "fcmpl",
+            Instruction::Fcmpg => "fcmpg",
+            Instruction::Dcmpl => "dcmpl",
+            Instruction::Dcmpg => "dcmpg",
+            Instruction::Ifeq(_) => "ifeq",
+            Instruction::Ifne(_) => "ifne",
+            Instruction::Iflt(_) => "iflt",
+            Instruction::Ifge(_) => "ifge",
+            Instruction::Ifgt(_) => "ifgt",
+            Instruction::Ifle(_) => "ifle",
+            Instruction::IfIcmpeq(_) => "if_icmpeq",
+            Instruction::IfIcmpne(_) => "if_icmpne",
+            Instruction::IfIcmplt(_) => "if_icmplt",
+            Instruction::IfIcmpge(_) => "if_icmpge",
+            Instruction::IfIcmpgt(_) => "if_icmpgt",
+            Instruction::IfIcmple(_) => "if_icmple",
+            Instruction::IfAcmpeq(_) => "if_acmpeq",
+            Instruction::IfAcmpne(_) => "if_acmpne",
+            Instruction::Goto(_) => "goto",
+            Instruction::Jsr(_) => "jsr",
+            Instruction::Ret(_) => "ret",
+            Instruction::Tableswitch { .. } => "tableswitch",
+            Instruction::Lookupswitch { .. } => "lookupswitch",
+            Instruction::Ireturn => "ireturn",
+            Instruction::Lreturn => "lreturn",
+            Instruction::Freturn => "freturn",
+            Instruction::Dreturn => "dreturn",
+            Instruction::Areturn => "areturn",
+            Instruction::Return => "return",
+            Instruction::Getstatic(_) => "getstatic",
+            Instruction::Putstatic(_) => "putstatic",
+            Instruction::Getfield(_) => "getfield",
+            Instruction::Putfield(_) => "putfield",
+            Instruction::Invokevirtual(_) => "invokevirtual",
+            Instruction::Invokespecial(_) => "invokespecial",
+            Instruction::Invokestatic(_) => "invokestatic",
+            Instruction::Invokeinterface { .. } => "invokeinterface",
+            Instruction::Invokedynamic(_) => "invokedynamic",
+            Instruction::New(_) => "new",
+            Instruction::Newarray(_) => "newarray",
+            Instruction::Anewarray(_) => "anewarray",
+            Instruction::Arraylength => "arraylength",
+            Instruction::Athrow => "athrow",
+            Instruction::Checkcast(_) => "checkcast",
+            Instruction::Instanceof(_) => "instanceof",
+            Instruction::Monitorenter => "monitorenter",
+            Instruction::Monitorexit => "monitorexit",
+            Instruction::Wide(_) => "wide",
+            Instruction::Multianewarray { .. } => "multianewarray",
+            Instruction::Ifnull(_) => "ifnull",
+            Instruction::Ifnonnull(_) => "ifnonnull",
+            Instruction::GotoW(_) => "goto_w",
+            Instruction::JsrW(_) => "jsr_w",
+        }
+    }
+}
+
+/// Decodes a method's code into instructions paired with their pc.
+pub fn decode(code: &[u8]) -> Result<Vec<(u32, Instruction)>, ClassFormatError> {
+    let mut reader = ClassReader::new(code);
+    let mut instructions = Vec::new();
+
+    while !reader.is_empty() {
+        let pc = reader.position() as u32;
+        let instruction = decode_instruction(&mut reader, pc).map_err(|e| match e.kind {
+            ClassFormatErrorKind::TruncatedClassFile => ClassFormatError::from(ClassFormatErrorKind::TruncatedInstruction { pc }),
+            _ => e,
+        })?;
+        instructions.push((pc, instruction));
+    }
+
+    Ok(instructions)
+}
+
+fn invalid_operand(opcode: u8, pc: u32) -> ClassFormatError {
+    ClassFormatError::from(ClassFormatErrorKind::InvalidOperand { opcode, pc })
+}
+
+/// Skips the 0-3 bytes that align switch operands to a multiple of four from the start of the code.
+fn skip_padding(reader: &mut ClassReader) -> Result<(), ClassFormatError> {
+    let padding = (4 - reader.position() % 4) % 4;
+    reader.read_bytes(padding)?;
+    Ok(())
+}
+
+fn decode_instruction(reader: &mut ClassReader, pc: u32) -> Result<Instruction, ClassFormatError> {
+    let opcode = reader.read_u8()?;
+
+    let instruction = match opcode {
+        0 => Instruction::Nop,
+        1 => Instruction::AconstNull,
+        2 => Instruction::IconstM1,
+        3 => Instruction::Iconst0,
+        4 => Instruction::Iconst1,
+        5 => Instruction::Iconst2,
+        6 => Instruction::Iconst3,
+        7 => Instruction::Iconst4,
+        8 => Instruction::Iconst5,
+        9 => Instruction::Lconst0,
+        10 => Instruction::Lconst1,
+        11 => Instruction::Fconst0,
+        12 => Instruction::Fconst1,
+        13 => Instruction::Fconst2,
+        14 => Instruction::Dconst0,
+        15 => Instruction::Dconst1,
+        16 => Instruction::Bipush(reader.read_u8()? as i8),
+        17 => Instruction::Sipush(reader.read_u16()? as i16),
+        18 => Instruction::Ldc(reader.read_u8()?),
+        19 => Instruction::LdcW(reader.read_u16()?),
+        20 => Instruction::Ldc2W(reader.read_u16()?),
+        21 => Instruction::Iload(reader.read_u8()?),
+        22 => Instruction::Lload(reader.read_u8()?),
+        23 => Instruction::Fload(reader.read_u8()?),
+        24 => Instruction::Dload(reader.read_u8()?),
+        25 => Instruction::Aload(reader.read_u8()?),
+        26 => Instruction::Iload0,
+        27 => Instruction::Iload1,
+        28 => Instruction::Iload2,
+        29 => Instruction::Iload3,
+        30 => Instruction::Lload0,
+        31 => Instruction::Lload1,
+        32 => Instruction::Lload2,
+        33 => Instruction::Lload3,
+        34 => Instruction::Fload0,
+        35 => Instruction::Fload1,
+        36 => Instruction::Fload2,
+        37 => Instruction::Fload3,
+        38 => Instruction::Dload0,
+        39 => Instruction::Dload1,
+        40 => Instruction::Dload2,
+        41 => Instruction::Dload3,
+        42 => Instruction::Aload0,
+        43 => Instruction::Aload1,
+        44 => Instruction::Aload2,
+        45 => Instruction::Aload3,
+        46 => Instruction::Iaload,
+        47 => Instruction::Laload,
+        48 => Instruction::Faload,
+        49 => Instruction::Daload,
+        50 => Instruction::Aaload,
+        51 => Instruction::Baload,
+        52 => Instruction::Caload,
+        53 => Instruction::Saload,
+        54 => Instruction::Istore(reader.read_u8()?),
+        55 => Instruction::Lstore(reader.read_u8()?),
+        56 => Instruction::Fstore(reader.read_u8()?),
+        57 => Instruction::Dstore(reader.read_u8()?),
+        58 => Instruction::Astore(reader.read_u8()?),
+        59 => Instruction::Istore0,
+        60 => Instruction::Istore1,
+        61 => Instruction::Istore2,
+        62 => Instruction::Istore3,
+        63 => Instruction::Lstore0,
+        64 => Instruction::Lstore1,
+        65 => Instruction::Lstore2,
+        66 => Instruction::Lstore3,
+        67 => Instruction::Fstore0,
+        68 => Instruction::Fstore1,
+        69 => Instruction::Fstore2,
+        70 => Instruction::Fstore3,
+        71 => Instruction::Dstore0,
+        72 => Instruction::Dstore1,
+        73 => Instruction::Dstore2,
+        74 => Instruction::Dstore3,
+        75 => Instruction::Astore0,
+        76 => Instruction::Astore1,
+        77 => Instruction::Astore2,
+        78 => Instruction::Astore3,
+        79 => Instruction::Iastore,
+        80 => Instruction::Lastore,
+        81 => Instruction::Fastore,
+        82 => Instruction::Dastore,
+        83 => Instruction::Aastore,
+        84 => Instruction::Bastore,
+        85 => Instruction::Castore,
+        86 => Instruction::Sastore,
+        87 => Instruction::Pop,
+        88 => Instruction::Pop2,
+        89 => Instruction::Dup,
+        90 => Instruction::DupX1,
+        91 => Instruction::DupX2,
+        92 => Instruction::Dup2,
+        93 => Instruction::Dup2X1,
+        94 => Instruction::Dup2X2,
+        95 => Instruction::Swap,
+        96 => Instruction::Iadd,
+        97 => Instruction::Ladd,
+        98 => Instruction::Fadd,
+        99 => Instruction::Dadd,
+        100 => Instruction::Isub,
+        101 => Instruction::Lsub,
+        102 => Instruction::Fsub,
+        103 => Instruction::Dsub,
+        104 => Instruction::Imul,
+        105 => Instruction::Lmul,
+        106 => Instruction::Fmul,
+        107 => Instruction::Dmul,
+        108 => Instruction::Idiv,
+        109 => Instruction::Ldiv,
+        110 => Instruction::Fdiv,
+        111 => Instruction::Ddiv,
+        112 => Instruction::Irem,
+        113 => Instruction::Lrem,
+        114 => Instruction::Frem,
+        115 => Instruction::Drem,
+        116 => Instruction::Ineg,
+        117 => Instruction::Lneg,
+        118 => Instruction::Fneg,
+        119 => Instruction::Dneg,
+        120 => Instruction::Ishl,
+        121 => Instruction::Lshl,
+        122 => Instruction::Ishr,
+        123 => Instruction::Lshr,
+        124 => Instruction::Iushr,
+        125 => Instruction::Lushr,
+        126 => Instruction::Iand,
+        127 => Instruction::Land,
+        128 => Instruction::Ior,
+        129 => Instruction::Lor,
+        130 => Instruction::Ixor,
+        131 => Instruction::Lxor,
+        132 => Instruction::Iinc { index: reader.read_u8()?, constant: reader.read_u8()? as i8 },
+        133 => Instruction::I2l,
+        134 => Instruction::I2f,
+        135 => Instruction::I2d,
+        136 => Instruction::L2i,
+        137 => Instruction::L2f,
+        138 => Instruction::L2d,
+        139 => Instruction::F2i,
+        140 => Instruction::F2l,
+        141 => Instruction::F2d,
+        142 => Instruction::D2i,
+        143 => Instruction::D2l,
+        144 => Instruction::D2f,
+        145 => Instruction::I2b,
+        146 => Instruction::I2c,
+        147 => Instruction::I2s,
+        148 => Instruction::Lcmp,
+        149 => Instruction::Fcmpl,
+        150 => Instruction::Fcmpg,
+        151 => Instruction::Dcmpl,
+        152 => Instruction::Dcmpg,
+        153 => Instruction::Ifeq(reader.read_u16()? as i16),
+        154 => Instruction::Ifne(reader.read_u16()? as i16),
+        155 => Instruction::Iflt(reader.read_u16()? as i16),
+        156 => Instruction::Ifge(reader.read_u16()? as i16),
+        157 => Instruction::Ifgt(reader.read_u16()? as i16),
+        158 => Instruction::Ifle(reader.read_u16()? as i16),
+        159 => Instruction::IfIcmpeq(reader.read_u16()? as i16),
+        160 => Instruction::IfIcmpne(reader.read_u16()? as i16),
+        161 => Instruction::IfIcmplt(reader.read_u16()? as i16),
+        162 => Instruction::IfIcmpge(reader.read_u16()? as i16),
+        163 => Instruction::IfIcmpgt(reader.read_u16()? as i16),
+        164 => Instruction::IfIcmple(reader.read_u16()? as i16),
+        165 => Instruction::IfAcmpeq(reader.read_u16()? as i16),
+        166 => Instruction::IfAcmpne(reader.read_u16()? as i16),
+        167 => Instruction::Goto(reader.read_u16()? as i16),
+        168 => Instruction::Jsr(reader.read_u16()? as i16),
+        169 => Instruction::Ret(reader.read_u8()?),
+        170 => {
+            skip_padding(reader)?;
+            let default = reader.read_u32()? as i32;
+            let low = reader.read_u32()? as i32;
+            let high = reader.read_u32()? as i32;
+            if low > high {
+                return Err(invalid_operand(opcode, pc));
+            }
+            let count = (high as i64 - low as i64 + 1) as usize;
+            let offsets = reader.read_bytes(count * 4)?
+                .chunks(4)
+                .map(|offset| i32::from_be_bytes([offset[0], offset[1], offset[2], offset[3]]))
+                .collect();
+            Instruction::Tableswitch { default, low, high, offsets }
+        }
+        171 => {
+            skip_padding(reader)?;
+            let default = reader.read_u32()? as i32;
+            let npairs = reader.read_u32()? as i32;
+            if npairs < 0 {
+                return Err(invalid_operand(opcode, pc));
+            }
+            let pairs: Vec<(i32, i32)> = reader.read_bytes(npairs as usize * 8)?
+                .chunks(8)
+                .map(|pair| {
+                    (i32::from_be_bytes([pair[0], pair[1], pair[2], pair[3]]), i32::from_be_bytes([pair[4], pair[5], pair[6], pair[7]]))
+                })
+                .collect();
+            if pairs.windows(2).any(|window| window[0].0 >= window[1].0) {
+                return Err(invalid_operand(opcode, pc));
+            }
+            Instruction::Lookupswitch { default, pairs }
+        }
+        172 => Instruction::Ireturn,
+        173 => Instruction::Lreturn,
+        174 => Instruction::Freturn,
+        175 => Instruction::Dreturn,
+        176 => Instruction::Areturn,
+        177 => Instruction::Return,
+        178 => Instruction::Getstatic(reader.read_u16()?),
+        179 => Instruction::Putstatic(reader.read_u16()?),
+        180 => Instruction::Getfield(reader.read_u16()?),
+        181 => Instruction::Putfield(reader.read_u16()?),
+        182 => Instruction::Invokevirtual(reader.read_u16()?),
+        183 => Instruction::Invokespecial(reader.read_u16()?),
+        184 => Instruction::Invokestatic(reader.read_u16()?),
+        185 => {
+            let index = reader.read_u16()?;
+            let count = reader.read_u8()?;
+            if count == 0 || reader.read_u8()? != 0 {
+                return Err(invalid_operand(opcode, pc));
+            }
+            Instruction::Invokeinterface { index, count }
+        }
+        186 => {
+            let index = reader.read_u16()?;
+            if reader.read_u16()? != 0 {
+                return Err(invalid_operand(opcode, pc));
+            }
+            Instruction::Invokedynamic(index)
+        }
+        187 => Instruction::New(reader.read_u16()?),
+        188 => {
+            let atype = reader.read_u8()?;
+            Instruction::Newarray(ArrayType::from_atype(atype).ok_or_else(|| invalid_operand(opcode, pc))?)
+        }
+        189 => Instruction::Anewarray(reader.read_u16()?),
+        190 => Instruction::Arraylength,
+        191 => Instruction::Athrow,
+        192 => Instruction::Checkcast(reader.read_u16()?),
+        193 => Instruction::Instanceof(reader.read_u16()?),
+        194 => Instruction::Monitorenter,
+        195 => Instruction::Monitorexit,
+        196 => {
+            let modified_opcode = reader.read_u8()?;
+            Instruction::Wide(match modified_opcode {
+                21 => WideInstruction::Iload(reader.read_u16()?),
+                22 => WideInstruction::Lload(reader.read_u16()?),
+                23 => WideInstruction::Fload(reader.read_u16()?),
+                24 => WideInstruction::Dload(reader.read_u16()?),
+                25 => WideInstruction::Aload(reader.read_u16()?),
+                54 => WideInstruction::Istore(reader.read_u16()?),
+                55 => WideInstruction::Lstore(reader.read_u16()?),
+                56 => WideInstruction::Fstore(reader.read_u16()?),
+                57 => WideInstruction::Dstore(reader.read_u16()?),
+                58 => WideInstruction::Astore(reader.read_u16()?),
+                169 => WideInstruction::Ret(reader.read_u16()?),
+                132 => WideInstruction::Iinc { index: reader.read_u16()?, constant: reader.read_u16()? as i16 },
+                _ => return Err(invalid_operand(opcode, pc)),
+            })
+        }
+        197 => {
+            let index = reader.read_u16()?;
+            let dimensions = reader.read_u8()?;
+            if dimensions == 0 {
+                return Err(invalid_operand(opcode, pc));
+            }
+            Instruction::Multianewarray { index, dimensions }
+        }
+        198 => Instruction::Ifnull(reader.read_u16()? as i16),
+        199 => Instruction::Ifnonnull(reader.read_u16()? as i16),
+        200 => Instruction::GotoW(reader.read_u32()? as i32),
+        201 => Instruction::JsrW(reader.read_u32()? as i32),
+        _ => return Err(ClassFormatError::from(ClassFormatErrorKind::InvalidOpcode { opcode, pc })),
+    };
+
+    Ok(instruction)
+}
+
+/// Encodes instructions laid out from pc 0, the inverse of `decode`.
+pub fn encode(instructions: &[Instruction]) -> Vec<u8> {
+    let mut buffer = ClassWriter::new();
+
+    for instruction in instructions {
+        encode_instruction(instruction, &mut buffer);
+    }
+
+    buffer.into_bytes()
+}
+
+fn write_padding(buffer: &mut ClassWriter) {
+    while !buffer.len().is_multiple_of(4) {
+        buffer.write_u8(0);
+    }
+}
+
+/// Appends one instruction. Switch padding is computed from `buffer.len()`, so the
+/// buffer must hold the method's code from pc 0.
+pub fn encode_instruction(instruction: &Instruction, buffer: &mut ClassWriter) {
+    buffer.write_u8(instruction.opcode());
+
+    match instruction {
+        Instruction::Bipush(value) => buffer.write_u8(*value as u8),
+        Instruction::Sipush(value) | Instruction::Ifeq(value) | Instruction::Ifne(value)
+        | Instruction::Iflt(value) | Instruction::Ifge(value) | Instruction::Ifgt(value)
+        | Instruction::Ifle(value) | Instruction::IfIcmpeq(value) | Instruction::IfIcmpne(value)
+        | Instruction::IfIcmplt(value) | Instruction::IfIcmpge(value) | Instruction::IfIcmpgt(value)
+        | Instruction::IfIcmple(value) | Instruction::IfAcmpeq(value) | Instruction::IfAcmpne(value)
+        | Instruction::Goto(value) | Instruction::Jsr(value) | Instruction::Ifnull(value)
+        | Instruction::Ifnonnull(value) => buffer.write_u16(*value as u16),
+        Instruction::Ldc(value) | Instruction::Iload(value) | Instruction::Lload(value)
+        | Instruction::Fload(value) | Instruction::Dload(value) | Instruction::Aload(value)
+        | Instruction::Istore(value) | Instruction::Lstore(value) | Instruction::Fstore(value)
+        | Instruction::Dstore(value) | Instruction::Astore(value) | Instruction::Ret(value) => buffer.write_u8(*value),
+        Instruction::LdcW(value) | Instruction::Ldc2W(value) | Instruction::Getstatic(value)
+        | Instruction::Putstatic(value) | Instruction::Getfield(value) | Instruction::Putfield(value)
+        | Instruction::Invokevirtual(value) | Instruction::Invokespecial(value)
+        | Instruction::Invokestatic(value) | Instruction::New(value) | Instruction::Anewarray(value)
+        | Instruction::Checkcast(value) | Instruction::Instanceof(value) => buffer.write_u16(*value),
+        Instruction::GotoW(value) | Instruction::JsrW(value) => buffer.write_u32(*value as u32),
+        Instruction::Iinc { index, constant } => {
+            buffer.write_u8(*index);
+            buffer.write_u8(*constant as u8);
+        }
+        Instruction::Tableswitch { default, low, high, offsets } => {
+            write_padding(buffer);
+            buffer.write_u32(*default as u32);
+            buffer.write_u32(*low as u32);
+            buffer.write_u32(*high as u32);
+            for offset in offsets {
+                buffer.write_u32(*offset as u32);
+            }
+        }
+        Instruction::Lookupswitch { default, pairs } => {
+            write_padding(buffer);
+            buffer.write_u32(*default as u32);
+            buffer.write_u32(pairs.len() as u32);
+            for (key, offset) in pairs {
+                buffer.write_u32(*key as u32);
+                buffer.write_u32(*offset as u32);
+            }
+        }
+        Instruction::Invokeinterface { index, count } => {
+            buffer.write_u16(*index);
+            buffer.write_u8(*count);
+            buffer.write_u8(0);
+        }
+        Instruction::Invokedynamic(index) => {
+            buffer.write_u16(*index);
+            buffer.write_u16(0);
+        }
+        Instruction::Newarray(array_type) => buffer.write_u8(*array_type as u8),
+        Instruction::Wide(wide_instruction) => {
+            buffer.write_u8(wide_instruction.opcode());
+            match wide_instruction {
+                WideInstruction::Iinc { index, constant } => {
+                    buffer.write_u16(*index);
+                    buffer.write_u16(*constant as u16);
+                }
+                WideInstruction::Iload(index) | WideInstruction::Lload(index) | WideInstruction::Fload(index)
+                | WideInstruction::Dload(index) | WideInstruction::Aload(index) | WideInstruction::Istore(index)
+                | WideInstruction::Lstore(index) | WideInstruction::Fstore(index) | WideInstruction::Dstore(index)
+                | WideInstruction::Astore(index) | WideInstruction::Ret(index) => buffer.write_u16(*index),
+            }
+        }
+        Instruction::Multianewarray { index, dimensions } => {
+            buffer.write_u16(*index);
+            buffer.write_u8(*dimensions);
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Places each switch after 0 to 3 `nop`s, so every padding length is covered.
+    fn round_trips_after_each_padding(switch: Instruction, encoded_length: usize) {
+        for nops in 0..4 {
+            let mut instructions = vec![Instruction::Nop; nops];
+            instructions.push(switch.clone());
+            instructions.push(Instruction::Return);
+
+            let code = encode(&instructions);
+            let padding = (4 - (nops + 1) % 4) % 4;
+            assert_eq!(code.len(), nops + 1 + padding + encoded_length + 1);
+            assert!(code[nops + 1..nops + 1 + padding].iter().all(|&byte| byte == 0));
+
+            let decoded = decode(&code).unwrap();
+            assert_eq!(decoded.iter().map(|(_, instruction)| instruction.clone()).collect::<Vec<_>>(), instructions);
+            assert_eq!(decoded.last().unwrap().0 as usize, code.len() - 1);
+        }
+    }
+
+    #[test]
+    fn tableswitch_round_trips() {
+        let switch = Instruction::Tableswitch { default: 40, low: -1, high: 1, offsets: vec![20, 24, 28] };
+        round_trips_after_each_padding(switch, 12 + 3 * 4);
+    }
+
+    #[test]
+    fn lookupswitch_round_trips() {
+        let switch = Instruction::Lookupswitch { default: 40, pairs: vec![(-5, 20), (0, 24), (1 << 20, 28)] };
+        round_trips_after_each_padding(switch, 8 + 3 * 8);
+    }
+
+    #[test]
+    fn wide_and_multi_byte_operands_round_trip() {
+        let instructions = vec![
+            Instruction::Wide(WideInstruction::Iinc { index: 300, constant: -2 }),
+            Instruction::Wide(WideInstruction::Lload(0x1234)),
+            Instruction::Invokeinterface { index: 7, count: 2 },
+            Instruction::Invokedynamic(9),
+            Instruction::Multianewarray { index: 3, dimensions: 2 },
+            Instruction::Sipush(-300),
+            Instruction::GotoW(-16),
+            Instruction::Iinc { index: 1, constant: -1 },
+        ];
+        let code = encode(&instructions);
+        let decoded: Vec<_> = decode(&code).unwrap().into_iter().map(|(_, instruction)| instruction).collect();
+        assert_eq!(decoded, instructions);
+    }
+}
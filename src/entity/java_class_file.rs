@@ -1,14 +1,18 @@
-use crate::entity::constant_pool::ConstantKind;
+use crate::entity::constant_pool::{write_constant_pool, ConstantPool};
+use crate::entity::class_format_error::ClassFormatError;
+use crate::entity::class_writer::ClassWriter;
+use crate::entity::access_flags::ClassAccessFlags;
 
-use super::{field_info::FieldInfo, method_info::MethodInfo, attribute_info::AttributeKind};
+use super::{field_info::*, method_info::*, attribute_info::*};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JavaClassFileFormat {
     pub minor_version: u16,
     pub major_version: u16,
     pub constant_pool_count: u16,
-    pub constant_pool: Vec<ConstantKind>,
-    pub access_flags: u16,
+    pub constant_pool: ConstantPool,
+    pub access_flags: ClassAccessFlags,
     pub this_class: u16,
     pub super_class: u16,
     pub interfaces_count: u16,
@@ -21,6 +25,52 @@ pub struct JavaClassFileFormat {
     pub attributes: Vec<AttributeKind>,
 }
 
+impl JavaClassFileFormat {
+    /// Binary name of this class, e.g. `java/lang/String`.
+    pub fn name(&self) -> Result<&str, ClassFormatError> {
+        self.constant_pool.class_name(self.this_class)
+    }
+
+    /// `None` for `java/lang/Object` and `module-info`, which have no superclass.
+    pub fn super_name(&self) -> Result<Option<&str>, ClassFormatError> {
+        if self.super_class == 0 {
+            return Ok(None);
+        }
+        self.constant_pool.class_name(self.super_class).map(Some)
+    }
+
+    pub fn interface_names(&self) -> Result<Vec<&str>, ClassFormatError> {
+        self.interfaces.iter()
+            .map(|&interface| self.constant_pool.class_name(interface))
+            .collect()
+    }
+
+    /// Serializes the class back to the class file format. All counts and lengths
+    /// are taken from the contents, so a class parsed by `define_class` and written
+    /// without changes comes out byte for byte identical. A count or length that does
+    /// not fit its field is an error rather than being truncated.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, ClassFormatError> {
+        let mut buffer = ClassWriter::new();
+
+        buffer.write_u32(0xCAFEBABE);
+        buffer.write_u16(self.minor_version);
+        buffer.write_u16(self.major_version);
+        write_constant_pool(&self.constant_pool, &mut buffer)?;
+        buffer.write_u16(self.access_flags.bits());
+        buffer.write_u16(self.this_class);
+        buffer.write_u16(self.super_class);
+        buffer.write_length_u16(self.interfaces.len(), "interfaces_count")?;
+        for interface in &self.interfaces {
+            buffer.write_u16(*interface);
+        }
+        write_fields(&self.fields, &mut buffer)?;
+        write_methods(&self.methods, &mut buffer)?;
+        write_attributes(&self.attributes, &mut buffer)?;
+
+        Ok(buffer.into_bytes())
+    }
+}
+
 pub trait JavaClassFile {
     
 }
@@ -1,33 +1,178 @@
+use std::ops::Range;
+use std::sync::OnceLock;
+
 use crate::entity::attribute_info::AttributeKind;
 use crate::entity::constant_pool::*;
 use crate::entity::attribute_info::*;
-use byteorder::{BigEndian, ReadBytesExt};
+use crate::entity::class_format_error::ClassFormatError;
+use crate::entity::class_reader::ClassReader;
+use crate::entity::class_bytes::ClassBytes;
+use crate::entity::class_writer::ClassWriter;
+use crate::entity::descriptor::MethodDescriptor;
+use crate::entity::access_flags::MethodAccessFlags;
+use serde::{ser, Deserialize, Deserializer, Serialize, Serializer};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MethodInfo {
-    pub access_flags: u16,
+    pub access_flags: MethodAccessFlags,
     pub name_index: u16,
     pub descriptor_index: u16,
     pub attributes_count: u16,
-    pub attributes: Vec<AttributeKind>,
+    pub attributes: MethodAttributes,
+}
+
+/// Whether `load_methods` decodes method attributes up front or on first access.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MethodParsing {
+    Eager,
+    Lazy,
+}
+
+/// A method's attributes. Lazily loaded ones are only located in the class file;
+/// `Code` and everything nested in it is decoded the first time `get` is called,
+/// once, even when several threads ask at the same time.
+#[derive(Debug, Clone)]
+pub struct MethodAttributes {
+    unparsed: Option<UnparsedAttributes>,
+    parsed: OnceLock<Result<Vec<AttributeKind>, ClassFormatError>>,
+}
+
+#[derive(Debug, Clone)]
+struct UnparsedAttributes {
+    count: u16,
+    class_file: ClassBytes,
+    range: Range<usize>,
+    name_indices: Vec<u16>,
+}
+
+impl MethodAttributes {
+    /// `constant_pool` must be the one of the class the method was loaded from.
+    pub fn get(&self, constant_pool: &ConstantPool) -> Result<&[AttributeKind], ClassFormatError> {
+        let parsed = self.parsed.get_or_init(|| {
+            let unparsed = self.unparsed.as_ref().expect("eagerly loaded attributes are always parsed");
+            // Read from the start of the file so offsets in errors stay absolute.
+            let mut reader = ClassReader::shared(&unparsed.class_file);
+            reader.read_bytes(unparsed.range.start)?;
+            let mut table = reader.sub_reader(unparsed.range.len())?;
+            load_attributes(unparsed.count, &mut table, constant_pool)
+        });
+        parsed.as_deref().map_err(Clone::clone)
+    }
+
+    /// The attributes if they have been decoded already.
+    pub fn parsed(&self) -> Option<&[AttributeKind]> {
+        match self.parsed.get() {
+            Some(Ok(attributes)) => Some(attributes),
+            _ => None,
+        }
+    }
+
+    /// Known without decoding anything.
+    pub fn name_indices(&self) -> Vec<u16> {
+        match (&self.unparsed, self.parsed()) {
+            (Some(unparsed), _) => unparsed.name_indices.clone(),
+            (None, Some(attributes)) => attributes.iter().map(|attribute| attribute.base().attribute_name_index).collect(),
+            (None, None) => unreachable!("eagerly loaded attributes are always parsed"),
+        }
+    }
+}
+
+/// Serialized as the decoded attributes, so lazily loaded ones must have been decoded.
+impl Serialize for MethodAttributes {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.parsed() {
+            Some(attributes) => attributes.serialize(serializer),
+            None => Err(ser::Error::custom("method attributes have not been decoded")),
+        }
+    }
 }
 
-pub fn load_methods(count: u16, buffer: &mut &[u8], constant_pool: &Vec<ConstantKind>) -> Vec<MethodInfo> {
+impl<'de> Deserialize<'de> for MethodAttributes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::<AttributeKind>::deserialize(deserializer).map(MethodAttributes::from)
+    }
+}
+
+impl From<Vec<AttributeKind>> for MethodAttributes {
+    fn from(attributes: Vec<AttributeKind>) -> Self {
+        MethodAttributes { unparsed: None, parsed: OnceLock::from(Ok(attributes)) }
+    }
+}
+
+impl MethodInfo {
+    pub fn descriptor(&self, constant_pool: &ConstantPool) -> Result<MethodDescriptor, ClassFormatError> {
+        MethodDescriptor::parse(constant_pool.utf8(self.descriptor_index)?)
+            .map_err(|e| e.with_constant_pool_index(self.descriptor_index))
+    }
+}
+
+/// `MethodParsing::Lazy` only takes effect for a reader over a shared buffer,
+/// which the attributes keep in order to be decoded later.
+pub fn load_methods(count: u16, buffer: &mut ClassReader, constant_pool: &ConstantPool, parsing: MethodParsing) -> Result<Vec<MethodInfo>, ClassFormatError> {
     let mut methods = Vec::new();
+    let class_file = buffer.shared_bytes().filter(|_| parsing == MethodParsing::Lazy);
 
     for _ in 0..count {
         let attributes_count;
         methods.push(MethodInfo {
-            access_flags: buffer.read_u16::<BigEndian>().unwrap(),
-            name_index: buffer.read_u16::<BigEndian>().unwrap(),
-            descriptor_index: buffer.read_u16::<BigEndian>().unwrap(),
+            access_flags: MethodAccessFlags(buffer.read_u16()?),
+            name_index: buffer.read_u16()?,
+            descriptor_index: buffer.read_u16()?,
             attributes_count: {
-                attributes_count = buffer.read_u16::<BigEndian>().unwrap();
+                attributes_count = buffer.read_u16()?;
                 attributes_count
             },
-            attributes: load_attributes(attributes_count, buffer, constant_pool),
+            attributes: match class_file {
+                Some(class_file) => skip_attributes(attributes_count, buffer, constant_pool, class_file)?,
+                None => load_attributes(attributes_count, buffer, constant_pool)?.into(),
+            },
         });
     }
 
-    methods
+    Ok(methods)
+}
+
+/// Checks only that the attributes fit in the class file and are named.
+fn skip_attributes(count: u16, buffer: &mut ClassReader, constant_pool: &ConstantPool, class_file: &ClassBytes) -> Result<MethodAttributes, ClassFormatError> {
+    let start = buffer.position();
+    let mut name_indices = Vec::new();
+
+    for _ in 0..count {
+        let name_offset = buffer.position();
+        let attribute_name_index = buffer.read_u16()?;
+        constant_pool.utf8(attribute_name_index).map_err(|e| e.with_offset(name_offset))?;
+        let attribute_length = buffer.read_u32()?;
+        buffer.read_bytes(attribute_length as usize)?;
+        name_indices.push(attribute_name_index);
+    }
+
+    Ok(MethodAttributes {
+        unparsed: Some(UnparsedAttributes {
+            count,
+            class_file: class_file.clone(),
+            range: start..buffer.position(),
+            name_indices,
+        }),
+        parsed: OnceLock::new(),
+    })
+}
+
+pub fn write_methods(methods: &[MethodInfo], buffer: &mut ClassWriter) -> Result<(), ClassFormatError> {
+    buffer.write_length_u16(methods.len(), "methods_count")?;
+
+    for method in methods {
+        buffer.write_u16(method.access_flags.bits());
+        buffer.write_u16(method.name_index);
+        buffer.write_u16(method.descriptor_index);
+        match (&method.attributes.unparsed, method.attributes.parsed()) {
+            // Attributes that were never decoded are copied as they were read.
+            (Some(unparsed), _) => {
+                buffer.write_u16(unparsed.count);
+                buffer.write_bytes(&unparsed.class_file[unparsed.range.clone()]);
+            }
+            (None, Some(attributes)) => write_attributes(attributes, buffer)?,
+            (None, None) => unreachable!("eagerly loaded attributes are always parsed"),
+        }
+    }
+    Ok(())
 }
@@ -0,0 +1,135 @@
+// Modified UTF-8 as used by CONSTANT_Utf8_info (JVMS 4.4.7).
+//
+// It differs from standard UTF-8 in two ways: NUL is encoded as the two bytes
+// `0xC0 0x80`, and supplementary characters are encoded as a pair of UTF-16
+// surrogates of three bytes each instead of a single four-byte sequence.
+
+/// Decodes Modified UTF-8 into UTF-16 code units.
+/// On failure, returns the index of the first byte of the malformed sequence.
+///
+/// Only the shortest form of each code unit is accepted (apart from `0xC0 0x80`),
+/// so `encode(&decode(bytes)?) == bytes` holds for every accepted input.
+pub fn decode(bytes: &[u8]) -> Result<Vec<u16>, usize> {
+    let mut utf16 = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let x = bytes[i];
+        let continuation = |offset: usize| match bytes.get(i + offset) {
+            Some(&byte) if byte & 0xC0 == 0x80 => Ok((byte & 0x3F) as u16),
+            _ => Err(i),
+        };
+
+        match x {
+            0x01..=0x7F => {
+                utf16.push(x as u16);
+                i += 1;
+            }
+
+            0xC0..=0xDF => {
+                let unit = (((x & 0x1F) as u16) << 6) | continuation(1)?;
+                if unit != 0 && unit < 0x80 {
+                    return Err(i);
+                }
+                utf16.push(unit);
+                i += 2;
+            }
+
+            0xE0..=0xEF => {
+                let unit = (((x & 0x0F) as u16) << 12) | (continuation(1)? << 6) | continuation(2)?;
+                if unit < 0x800 {
+                    return Err(i);
+                }
+                utf16.push(unit);
+                i += 3;
+            }
+
+            // 0x00 and four-byte forms never appear in Modified UTF-8.
+            _ => return Err(i),
+        }
+    }
+
+    Ok(utf16)
+}
+
+/// Encodes UTF-16 code units as Modified UTF-8. Unpaired surrogates are
+/// encoded as-is, so any Java string round-trips.
+pub fn encode(utf16: &[u16]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(utf16.len());
+
+    for &unit in utf16 {
+        match unit {
+            0x0001..=0x007F => bytes.push(unit as u8),
+            0x0000 | 0x0080..=0x07FF => {
+                bytes.push(0xC0 | (unit >> 6) as u8);
+                bytes.push(0x80 | (unit & 0x3F) as u8);
+            }
+            _ => {
+                bytes.push(0xE0 | (unit >> 12) as u8);
+                bytes.push(0x80 | ((unit >> 6) & 0x3F) as u8);
+                bytes.push(0x80 | (unit & 0x3F) as u8);
+            }
+        }
+    }
+
+    bytes
+}
+
+pub fn encode_str(text: &str) -> Vec<u8> {
+    encode(&text.encode_utf16().collect::<Vec<u16>>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn characters_are_encoded_in_one_two_or_three_bytes() {
+        assert_eq!(encode_str("Az"), b"Az");
+        assert_eq!(encode_str("\u{E9}"), [0xC3, 0xA9]);
+        assert_eq!(encode_str("\u{20AC}"), [0xE2, 0x82, 0xAC]);
+    }
+
+    #[test]
+    fn nul_is_encoded_as_c0_80() {
+        assert_eq!(encode(&[0x0000]), [0xC0, 0x80]);
+        assert_eq!(encode_str("a\0b"), [b'a', 0xC0, 0x80, b'b']);
+        assert_eq!(decode(&[0xC0, 0x80]), Ok(vec![0x0000]));
+        assert_eq!(decode(&[0x00]), Err(0));
+    }
+
+    #[test]
+    fn supplementary_characters_are_encoded_as_surrogate_pairs() {
+        let bytes = encode_str("\u{1F600}");
+        assert_eq!(bytes, [0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80]);
+        assert_eq!(decode(&bytes), Ok(vec![0xD83D, 0xDE00]));
+        // An unpaired surrogate round-trips as well.
+        assert_eq!(decode(&encode(&[0xDC00, 0x41])), Ok(vec![0xDC00, 0x41]));
+    }
+
+    #[test]
+    fn four_byte_utf8_is_rejected() {
+        assert_eq!(decode("\u{1F600}".as_bytes()), Err(0));
+        assert_eq!(decode(&[b'a', 0xF0, 0x9F, 0x98, 0x80]), Err(1));
+    }
+
+    #[test]
+    fn truncated_and_overlong_sequences_are_rejected() {
+        for (bytes, index) in [
+            (&[0xC3][..], 0),
+            (&[b'a', 0xE2, 0x82], 1),
+            (&[0xE2, 0x41, 0x82], 0),
+            (&[0x80], 0),
+            (&[0xC1, 0x81], 0),
+            (&[0xE0, 0x81, 0x81], 0),
+        ] {
+            assert_eq!(decode(bytes), Err(index), "{:02X?}", bytes);
+        }
+    }
+
+    #[test]
+    fn every_code_unit_round_trips() {
+        let utf16: Vec<u16> = (0..=u16::MAX).collect();
+        assert_eq!(decode(&encode(&utf16)), Ok(utf16));
+    }
+}
@@ -0,0 +1,509 @@
+use std::fmt;
+
+use crate::entity::class_format_error::*;
+use crate::entity::descriptor::FieldType;
+
+// Generic signatures of the Signature attribute (JVMS 4.7.9.1).
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JavaTypeSignature {
+    Base(FieldType),  // always a primitive type
+    Reference(ReferenceTypeSignature),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReferenceTypeSignature {
+    Class(ClassTypeSignature),
+    TypeVariable(String),
+    Array(Box<JavaTypeSignature>),
+}
+
+/// `Ljava/util/Map<TK;TV;>.Entry<TK;TV;>;` has the package `java/util` and two
+/// segments, `Map<K, V>` and the inner class suffix `Entry<K, V>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClassTypeSignature {
+    pub package: Vec<String>,
+    pub segments: Vec<SimpleClassTypeSignature>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimpleClassTypeSignature {
+    pub identifier: String,
+    pub type_arguments: Vec<TypeArgument>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeArgument {
+    Wildcard,  // *
+    Exact(ReferenceTypeSignature),
+    Extends(ReferenceTypeSignature),  // +
+    Super(ReferenceTypeSignature),  // -
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeParameter {
+    pub identifier: String,
+    pub class_bound: Option<ReferenceTypeSignature>,
+    pub interface_bounds: Vec<ReferenceTypeSignature>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClassSignature {
+    pub type_parameters: Vec<TypeParameter>,
+    pub superclass: ClassTypeSignature,
+    pub interfaces: Vec<ClassTypeSignature>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MethodSignature {
+    pub type_parameters: Vec<TypeParameter>,
+    pub parameters: Vec<JavaTypeSignature>,
+    pub result: Option<JavaTypeSignature>,  // None for void
+    pub throws: Vec<ReferenceTypeSignature>,
+}
+
+pub type FieldSignature = ReferenceTypeSignature;
+
+impl ClassSignature {
+    pub fn parse(signature: &str) -> Result<ClassSignature, ClassFormatError> {
+        let mut parser = SignatureParser::new(signature);
+        let type_parameters = parser.type_parameters()?;
+        let superclass = parser.class_type_signature()?;
+        let mut interfaces = Vec::new();
+        while !parser.rest.is_empty() {
+            interfaces.push(parser.class_type_signature()?);
+        }
+        Ok(ClassSignature { type_parameters, superclass, interfaces })
+    }
+}
+
+impl MethodSignature {
+    pub fn parse(signature: &str) -> Result<MethodSignature, ClassFormatError> {
+        let mut parser = SignatureParser::new(signature);
+        let type_parameters = parser.type_parameters()?;
+        parser.expect('(')?;
+        let mut parameters = Vec::new();
+        while !parser.eat(')') {
+            parameters.push(parser.java_type_signature()?);
+        }
+        let result = if parser.eat('V') {
+            None
+        } else {
+            Some(parser.java_type_signature()?)
+        };
+        let mut throws = Vec::new();
+        while parser.eat('^') {
+            match parser.reference_type_signature()? {
+                ReferenceTypeSignature::Array(_) => return Err(parser.error()),
+                thrown => throws.push(thrown),
+            }
+        }
+        parser.expect_end()?;
+        Ok(MethodSignature { type_parameters, parameters, result, throws })
+    }
+}
+
+impl ReferenceTypeSignature {
+    /// Parses a field signature, which is any reference type signature.
+    pub fn parse(signature: &str) -> Result<FieldSignature, ClassFormatError> {
+        let mut parser = SignatureParser::new(signature);
+        let field_signature = parser.reference_type_signature()?;
+        parser.expect_end()?;
+        Ok(field_signature)
+    }
+}
+
+impl ClassTypeSignature {
+    /// Binary name of the erased class, e.g. `java/util/Map$Entry`.
+    pub fn binary_name(&self) -> String {
+        let mut name = String::new();
+        for package in &self.package {
+            name.push_str(package);
+            name.push('/');
+        }
+        let segments: Vec<&str> = self.segments.iter().map(|segment| segment.identifier.as_str()).collect();
+        name.push_str(&segments.join("$"));
+        name
+    }
+}
+
+struct SignatureParser<'a> {
+    signature: &'a str,
+    rest: &'a str,
+}
+
+impl<'a> SignatureParser<'a> {
+    fn new(signature: &'a str) -> Self {
+        SignatureParser { signature, rest: signature }
+    }
+
+    fn error(&self) -> ClassFormatError {
+        ClassFormatError::from(ClassFormatErrorKind::InvalidSignature(self.signature.to_string()))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest.chars().next()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        match self.rest.strip_prefix(c) {
+            Some(rest) => {
+                self.rest = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), ClassFormatError> {
+        if self.eat(c) { Ok(()) } else { Err(self.error()) }
+    }
+
+    fn expect_end(&self) -> Result<(), ClassFormatError> {
+        if self.rest.is_empty() { Ok(()) } else { Err(self.error()) }
+    }
+
+    fn identifier(&mut self) -> Result<String, ClassFormatError> {
+        let end = self.rest.find(['.', ';', '[', '/', '<', '>', ':']).unwrap_or(self.rest.len());
+        if end == 0 {
+            return Err(self.error());
+        }
+        let identifier = self.rest[..end].to_string();
+        self.rest = &self.rest[end..];
+        Ok(identifier)
+    }
+
+    fn type_parameters(&mut self) -> Result<Vec<TypeParameter>, ClassFormatError> {
+        let mut type_parameters = Vec::new();
+        if !self.eat('<') {
+            return Ok(type_parameters);
+        }
+
+        while !self.eat('>') {
+            let identifier = self.identifier()?;
+            self.expect(':')?;
+            let class_bound = match self.peek() {
+                Some(':') | Some('>') => None,
+                _ => Some(self.reference_type_signature()?),
+            };
+            let mut interface_bounds = Vec::new();
+            while self.eat(':') {
+                interface_bounds.push(self.reference_type_signature()?);
+            }
+            type_parameters.push(TypeParameter { identifier, class_bound, interface_bounds });
+        }
+
+        if type_parameters.is_empty() {
+            return Err(self.error());
+        }
+        Ok(type_parameters)
+    }
+
+    fn java_type_signature(&mut self) -> Result<JavaTypeSignature, ClassFormatError> {
+        let base_type = match self.peek() {
+            Some('B') => FieldType::Byte,
+            Some('C') => FieldType::Char,
+            Some('D') => FieldType::Double,
+            Some('F') => FieldType::Float,
+            Some('I') => FieldType::Int,
+            Some('J') => FieldType::Long,
+            Some('S') => FieldType::Short,
+            Some('Z') => FieldType::Boolean,
+            _ => return Ok(JavaTypeSignature::Reference(self.reference_type_signature()?)),
+        };
+        self.rest = &self.rest[1..];
+        Ok(JavaTypeSignature::Base(base_type))
+    }
+
+    fn reference_type_signature(&mut self) -> Result<ReferenceTypeSignature, ClassFormatError> {
+        match self.peek() {
+            Some('L') => Ok(ReferenceTypeSignature::Class(self.class_type_signature()?)),
+            Some('T') => {
+                self.rest = &self.rest[1..];
+                let identifier = self.identifier()?;
+                self.expect(';')?;
+                Ok(ReferenceTypeSignature::TypeVariable(identifier))
+            }
+            Some('[') => {
+                self.rest = &self.rest[1..];
+                Ok(ReferenceTypeSignature::Array(Box::new(self.java_type_signature()?)))
+            }
+            _ => Err(self.error()),
+        }
+    }
+
+    fn class_type_signature(&mut self) -> Result<ClassTypeSignature, ClassFormatError> {
+        self.expect('L')?;
+
+        let mut package = Vec::new();
+        let mut identifier = self.identifier()?;
+        while self.eat('/') {
+            package.push(identifier);
+            identifier = self.identifier()?;
+        }
+
+        let mut segments = vec![SimpleClassTypeSignature {
+            identifier,
+            type_arguments: self.type_arguments()?,
+        }];
+        while self.eat('.') {
+            segments.push(SimpleClassTypeSignature {
+                identifier: self.identifier()?,
+                type_arguments: self.type_arguments()?,
+            });
+        }
+        self.expect(';')?;
+
+        Ok(ClassTypeSignature { package, segments })
+    }
+
+    fn type_arguments(&mut self) -> Result<Vec<TypeArgument>, ClassFormatError> {
+        let mut type_arguments = Vec::new();
+        if !self.eat('<') {
+            return Ok(type_arguments);
+        }
+
+        while !self.eat('>') {
+            let type_argument = if self.eat('*') {
+                TypeArgument::Wildcard
+            } else if self.eat('+') {
+                TypeArgument::Extends(self.reference_type_signature()?)
+            } else if self.eat('-') {
+                TypeArgument::Super(self.reference_type_signature()?)
+            } else {
+                TypeArgument::Exact(self.reference_type_signature()?)
+            };
+            type_arguments.push(type_argument);
+        }
+
+        if type_arguments.is_empty() {
+            return Err(self.error());
+        }
+        Ok(type_arguments)
+    }
+}
+
+fn fmt_type_parameters(type_parameters: &[TypeParameter], f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    if type_parameters.is_empty() {
+        return Ok(());
+    }
+
+    write!(f, "<")?;
+    for type_parameter in type_parameters {
+        write!(f, "{}:", type_parameter.identifier)?;
+        if let Some(class_bound) = &type_parameter.class_bound {
+            write!(f, "{}", class_bound)?;
+        }
+        for interface_bound in &type_parameter.interface_bounds {
+            write!(f, ":{}", interface_bound)?;
+        }
+    }
+    write!(f, ">")
+}
+
+impl fmt::Display for JavaTypeSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JavaTypeSignature::Base(base_type) => write!(f, "{}", base_type),
+            JavaTypeSignature::Reference(reference_type) => write!(f, "{}", reference_type),
+        }
+    }
+}
+
+impl fmt::Display for ReferenceTypeSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReferenceTypeSignature::Class(class_type) => write!(f, "{}", class_type),
+            ReferenceTypeSignature::TypeVariable(identifier) => write!(f, "T{};", identifier),
+            ReferenceTypeSignature::Array(component_type) => write!(f, "[{}", component_type),
+        }
+    }
+}
+
+impl fmt::Display for ClassTypeSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "L")?;
+        for package in &self.package {
+            write!(f, "{}/", package)?;
+        }
+        for (i, segment) in self.segments.iter().enumerate() {
+            if i > 0 {
+                write!(f, ".")?;
+            }
+            write!(f, "{}", segment.identifier)?;
+            if !segment.type_arguments.is_empty() {
+                write!(f, "<")?;
+                for type_argument in &segment.type_arguments {
+                    match type_argument {
+                        TypeArgument::Wildcard => write!(f, "*")?,
+                        TypeArgument::Exact(reference_type) => write!(f, "{}", reference_type)?,
+                        TypeArgument::Extends(reference_type) => write!(f, "+{}", reference_type)?,
+                        TypeArgument::Super(reference_type) => write!(f, "-{}", reference_type)?,
+                    }
+                }
+                write!(f, ">")?;
+            }
+        }
+        write!(f, ";")
+    }
+}
+
+impl fmt::Display for ClassSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_type_parameters(&self.type_parameters, f)?;
+        write!(f, "{}", self.superclass)?;
+        for interface in &self.interfaces {
+            write!(f, "{}", interface)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for MethodSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_type_parameters(&self.type_parameters, f)?;
+        write!(f, "(")?;
+        for parameter in &self.parameters {
+            write!(f, "{}", parameter)?;
+        }
+        write!(f, ")")?;
+        match &self.result {
+            Some(result) => write!(f, "{}", result)?,
+            None => write!(f, "V")?,
+        }
+        for thrown in &self.throws {
+            write!(f, "^{}", thrown)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn class(name: &str, type_arguments: Vec<TypeArgument>) -> ReferenceTypeSignature {
+        let (package, identifier) = name.rsplit_once('/').unwrap_or(("", name));
+        ReferenceTypeSignature::Class(ClassTypeSignature {
+            package: package.split('/').filter(|segment| !segment.is_empty()).map(str::to_string).collect(),
+            segments: vec![SimpleClassTypeSignature { identifier: identifier.to_string(), type_arguments }],
+        })
+    }
+
+    fn variable(name: &str) -> ReferenceTypeSignature {
+        ReferenceTypeSignature::TypeVariable(name.to_string())
+    }
+
+    #[test]
+    fn type_parameters_have_class_and_interface_bounds() {
+        let text = "<T:Ljava/lang/Number;:Ljava/lang/Comparable<TT;>;U::Ljava/io/Serializable;>Ljava/lang/Object;Ljava/lang/Iterable<TU;>;";
+        let signature = ClassSignature::parse(text).unwrap();
+        assert_eq!(signature.type_parameters, [
+            TypeParameter {
+                identifier: "T".to_string(),
+                class_bound: Some(class("java/lang/Number", vec![])),
+                interface_bounds: vec![class("java/lang/Comparable", vec![TypeArgument::Exact(variable("T"))])],
+            },
+            TypeParameter {
+                identifier: "U".to_string(),
+                class_bound: None,
+                interface_bounds: vec![class("java/io/Serializable", vec![])],
+            },
+        ]);
+        assert_eq!(signature.superclass.binary_name(), "java/lang/Object");
+        assert_eq!(signature.interfaces.len(), 1);
+        assert_eq!(signature.to_string(), text);
+    }
+
+    #[test]
+    fn parameterized_types_nest() {
+        let text = "Ljava/util/Map<Ljava/lang/String;Ljava/util/List<[Ljava/util/Set<TE;>;>;>;";
+        let signature = ReferenceTypeSignature::parse(text).unwrap();
+        let set = class("java/util/Set", vec![TypeArgument::Exact(variable("E"))]);
+        let list = class("java/util/List", vec![TypeArgument::Exact(ReferenceTypeSignature::Array(Box::new(JavaTypeSignature::Reference(set))))]);
+        let map = class("java/util/Map", vec![TypeArgument::Exact(class("java/lang/String", vec![])), TypeArgument::Exact(list)]);
+        assert_eq!(signature, map);
+        assert_eq!(signature.to_string(), text);
+    }
+
+    #[test]
+    fn wildcards_come_in_three_forms() {
+        let text = "Ljava/util/Map<*+Ljava/lang/Number;-TT;>;";
+        let ReferenceTypeSignature::Class(signature) = ReferenceTypeSignature::parse(text).unwrap() else { panic!("not a class type") };
+        assert_eq!(signature.segments[0].type_arguments, [
+            TypeArgument::Wildcard,
+            TypeArgument::Extends(class("java/lang/Number", vec![])),
+            TypeArgument::Super(variable("T")),
+        ]);
+        assert_eq!(signature.to_string(), text);
+    }
+
+    #[test]
+    fn type_variables_and_arrays_of_them() {
+        assert_eq!(ReferenceTypeSignature::parse("TElement;").unwrap(), variable("Element"));
+        let array = ReferenceTypeSignature::parse("[[TE;").unwrap();
+        assert_eq!(array.to_string(), "[[TE;");
+        assert_eq!(ReferenceTypeSignature::parse("[I").unwrap(), ReferenceTypeSignature::Array(Box::new(JavaTypeSignature::Base(FieldType::Int))));
+    }
+
+    #[test]
+    fn inner_class_suffixes_are_segments() {
+        let text = "Ljava/util/Map<TK;TV;>.Entry<TK;TV;>;";
+        let ReferenceTypeSignature::Class(signature) = ReferenceTypeSignature::parse(text).unwrap() else { panic!("not a class type") };
+        assert_eq!(signature.package, ["java", "util"]);
+        let identifiers: Vec<_> = signature.segments.iter().map(|segment| segment.identifier.as_str()).collect();
+        assert_eq!(identifiers, ["Map", "Entry"]);
+        assert_eq!(signature.segments[1].type_arguments, [TypeArgument::Exact(variable("K")), TypeArgument::Exact(variable("V"))]);
+        assert_eq!(signature.binary_name(), "java/util/Map$Entry");
+        assert_eq!(signature.to_string(), text);
+
+        let unparameterized = ClassSignature::parse("LOuter<TT;>.Inner;").unwrap();
+        assert_eq!(unparameterized.superclass.binary_name(), "Outer$Inner");
+    }
+
+    #[test]
+    fn methods_declare_type_parameters_results_and_throws() {
+        let text = "<X:Ljava/lang/Throwable;>(TX;[IJ)Ljava/util/List<*>;^TX;^Ljava/io/IOException;";
+        let signature = MethodSignature::parse(text).unwrap();
+        assert_eq!(signature.type_parameters.len(), 1);
+        assert_eq!(signature.parameters, [
+            JavaTypeSignature::Reference(variable("X")),
+            JavaTypeSignature::Reference(ReferenceTypeSignature::Array(Box::new(JavaTypeSignature::Base(FieldType::Int)))),
+            JavaTypeSignature::Base(FieldType::Long),
+        ]);
+        assert_eq!(signature.result, Some(JavaTypeSignature::Reference(class("java/util/List", vec![TypeArgument::Wildcard]))));
+        assert_eq!(signature.throws, [variable("X"), class("java/io/IOException", vec![])]);
+        assert_eq!(signature.to_string(), text);
+
+        let void = MethodSignature::parse("()V").unwrap();
+        assert_eq!((void.parameters.len(), void.result, void.throws.len()), (0, None, 0));
+    }
+
+    #[test]
+    fn malformed_signatures_are_rejected() {
+        let invalid = |error: ClassFormatError, text: &str| {
+            assert_eq!(error.kind, ClassFormatErrorKind::InvalidSignature(text.to_string()));
+        };
+        for text in [
+            "",
+            "Ljava/lang/String",  // missing ;
+            "Ljava/util/List<TT;",  // truncated
+            "Ljava/util/List<>;",  // no type arguments
+            "L;",  // empty identifier
+            "Ljava//String;",
+            "T;",
+            "TT",
+            "I",  // a base type is not a reference type
+            "[",
+            "Ljava/lang/String;X",  // trailing characters
+        ] {
+            invalid(ReferenceTypeSignature::parse(text).unwrap_err(), text);
+        }
+        for text in ["(", "(I", "()", "(I)", "()V^", "()V^[Ljava/lang/Exception;", "<>()V", "<T>()V", "<:Ljava/lang/Object;>()V", "()VV"] {
+            invalid(MethodSignature::parse(text).unwrap_err(), text);
+        }
+        for text in ["", "<T:>", "<T:Ljava/lang/Object;>", "Ljava/lang/Object", "Ljava/lang/Object;TT;"] {
+            invalid(ClassSignature::parse(text).unwrap_err(), text);
+        }
+    }
+}
@@ -0,0 +1,554 @@
+use std::fmt::{self, Write};
+use std::fs;
+use std::io::{self, Write as _};
+use std::path::Path;
+
+use crate::classloader::bootstrap_class_loader::parse_class_file;
+use crate::classloader::class_loading_error::ClassLoadingError;
+use crate::classloader::class_path::ClassPath;
+use crate::classloader::version_policy::VersionPolicy;
+use crate::entity::class_bytes::ClassBytes;
+use crate::entity::java_class_file::JavaClassFileFormat;
+use crate::entity::constant_pool::{ConstantKind, ConstantPool};
+use crate::entity::attribute_info::*;
+use crate::entity::instruction::{Instruction, WideInstruction};
+use crate::class_json;
+
+// `churu-vm-x javap [-cp <path>] [--json] <file|class>`: prints a class the way this
+// VM parsed it. The layout follows `javap -v` loosely but is kept free of anything
+// that varies between runs (paths, timestamps, checksums) so two dumps can be diffed.
+// With `--json` the class is printed in the form `class_json` reads back. The format
+// checker is not run, so malformed classes, such as ones built with the assembler,
+// can be looked at too.
+
+pub fn run(target: &str, class_path: &[String], json: bool, versions: &VersionPolicy) -> Result<(), String> {
+    let bytes = if Path::new(target).is_file() {
+        ClassBytes::from(fs::read(target).map_err(|e| format!("{}: {}", target, e))?)
+    } else {
+        ClassPath::new(class_path).find(&target.replace('.', "/"))
+            .map_err(|e| match e {
+                ClassLoadingError::NotFound(_) => format!("class not found: {}", target),
+                e => e.to_string(),
+            })?
+    };
+    let class = parse_class_file(&bytes, versions).map_err(|e| e.to_string())?;
+
+    let output = if json { class_json::to_json(&class)? } else { disassemble(&class) };
+    // A closed pipe (e.g. `| head`) is not an error worth reporting.
+    let _ = io::stdout().write_all(output.as_bytes());
+    Ok(())
+}
+
+pub fn disassemble(class: &JavaClassFileFormat) -> String {
+    let mut out = String::new();
+    write_class(&mut out, class).expect("writing to a String never fails");
+    out
+}
+
+fn write_class(out: &mut String, class: &JavaClassFileFormat) -> fmt::Result {
+    let constant_pool = &class.constant_pool;
+
+    writeln!(out, "class {}", class.name().unwrap_or("?"))?;
+    writeln!(out, "  minor version: {}", class.minor_version)?;
+    writeln!(out, "  major version: {}", class.major_version)?;
+    writeln!(out, "  flags: {}", flags(class.access_flags.bits(), &class.access_flags.names()))?;
+    writeln!(out, "{}", commented(42, format!("  this_class: #{}", class.this_class), constant_text(constant_pool, class.this_class)))?;
+    if class.super_class == 0 {
+        writeln!(out, "  super_class: #0")?;
+    } else {
+        writeln!(out, "{}", commented(42, format!("  super_class: #{}", class.super_class), constant_text(constant_pool, class.super_class)))?;
+    }
+    writeln!(
+        out,
+        "  interfaces: {}, fields: {}, methods: {}, attributes: {}",
+        class.interfaces.len(), class.fields.len(), class.methods.len(), class.attributes.len(),
+    )?;
+    for &interface in &class.interfaces {
+        writeln!(out, "{}", commented(42, format!("    #{}", interface), constant_text(constant_pool, interface)))?;
+    }
+
+    writeln!(out, "Constant pool:")?;
+    for (index, constant) in constant_pool.entries().iter().enumerate() {
+        if let Some((tag, raw)) = constant_raw(constant) {
+            let index = index as u16;
+            let line = format!("{:>6} = {:<18} {}", format!("#{}", index), tag, raw);
+            // Utf8 and numeric constants already show their value.
+            if matches!(constant, ConstantKind::Utf8(_) | ConstantKind::Integer(_) | ConstantKind::Float(_) | ConstantKind::Long(_) | ConstantKind::Double(_)) {
+                writeln!(out, "{}", line.trim_end())?;
+            } else {
+                writeln!(out, "{}", commented(42, line, constant_text(constant_pool, index)))?;
+            }
+        }
+    }
+
+    writeln!(out, "Fields:")?;
+    for field in &class.fields {
+        writeln!(out, "  {}", utf8(constant_pool, field.name_index))?;
+        writeln!(out, "    descriptor: {}", utf8(constant_pool, field.descriptor_index))?;
+        writeln!(out, "    flags: {}", flags(field.access_flags.bits(), &field.access_flags.names()))?;
+        write_attributes(out, constant_pool, &field.attributes, 4)?;
+    }
+
+    writeln!(out, "Methods:")?;
+    for method in &class.methods {
+        writeln!(out, "  {}", utf8(constant_pool, method.name_index))?;
+        writeln!(out, "    descriptor: {}", utf8(constant_pool, method.descriptor_index))?;
+        writeln!(out, "    flags: {}", flags(method.access_flags.bits(), &method.access_flags.names()))?;
+        match method.attributes.get(constant_pool) {
+            Ok(attributes) => write_attributes(out, constant_pool, attributes, 4)?,
+            Err(error) => writeln!(out, "    // {}", error)?,
+        }
+    }
+
+    writeln!(out, "Attributes:")?;
+    write_attributes(out, constant_pool, &class.attributes, 2)
+}
+
+/// `line` followed by a `//` comment, aligned to `column` where the line is short enough.
+fn commented(column: usize, line: String, comment: impl fmt::Display) -> String {
+    format!("{:<width$} // {}", line, comment, width = column.saturating_sub(1))
+}
+
+fn flags(bits: u16, names: &[&str]) -> String {
+    format!("({:#06x}) {}", bits, names.join(", ")).trim_end().to_string()
+}
+
+fn utf8(constant_pool: &ConstantPool, index: u16) -> &str {
+    constant_pool.utf8(index).unwrap_or("?")
+}
+
+fn class_name(constant_pool: &ConstantPool, index: u16) -> &str {
+    constant_pool.class_name(index).unwrap_or("?")
+}
+
+/// Tag name and the raw indices or value of a constant, or `None` for placeholders.
+fn constant_raw(constant: &ConstantKind) -> Option<(&'static str, String)> {
+    let raw = match constant {
+        ConstantKind::Empty(_) | ConstantKind::Unusable(_) => return None,
+        ConstantKind::Utf8(utf8_info) => ("Utf8", utf8_info.text.escape_debug().to_string()),
+        ConstantKind::Integer(integer_info) => ("Integer", (integer_info.bytes as i32).to_string()),
+        ConstantKind::Float(float_info) => ("Float", format!("{:?}f", f32::from_bits(float_info.bytes))),
+        ConstantKind::Long(long_info) => ("Long", format!("{}l", ((long_info.high_bytes as u64) << 32 | long_info.low_bytes as u64) as i64)),
+        ConstantKind::Double(double_info) => {
+            ("Double", format!("{:?}d", f64::from_bits((double_info.high_bytes as u64) << 32 | double_info.low_bytes as u64)))
+        }
+        ConstantKind::Class(class_info) => ("Class", format!("#{}", class_info.name_index)),
+        ConstantKind::String(string_info) => ("String", format!("#{}", string_info.string_index)),
+        ConstantKind::Fieldref(fieldref_info) => {
+            ("Fieldref", format!("#{}.#{}", fieldref_info.class_index, fieldref_info.name_and_type_index))
+        }
+        ConstantKind::Methodref(methodref_info) => {
+            ("Methodref", format!("#{}.#{}", methodref_info.class_index, methodref_info.name_and_type_index))
+        }
+        ConstantKind::InterfaceMethodref(interface_methodref_info) => (
+            "InterfaceMethodref",
+            format!("#{}.#{}", interface_methodref_info.class_index, interface_methodref_info.name_and_type_index),
+        ),
+        ConstantKind::NameAndType(name_and_type_info) => {
+            ("NameAndType", format!("#{}:#{}", name_and_type_info.name_index, name_and_type_info.descriptor_index))
+        }
+        ConstantKind::MethodHandle(method_handle_info) => {
+            ("MethodHandle", format!("{}:#{}", method_handle_info.reference_kind, method_handle_info.reference_index))
+        }
+        ConstantKind::MethodType(method_type_info) => ("MethodType", format!("#{}", method_type_info.descriptor_index)),
+        ConstantKind::Dynamic(dynamic_info) => {
+            ("Dynamic", format!("#{}:#{}", dynamic_info.bootstrap_method_attr_index, dynamic_info.name_and_type_index))
+        }
+        ConstantKind::InvokeDynamic(invoke_dynamic_info) => (
+            "InvokeDynamic",
+            format!("#{}:#{}", invoke_dynamic_info.bootstrap_method_attr_index, invoke_dynamic_info.name_and_type_index),
+        ),
+        ConstantKind::Module(module_info) => ("Module", format!("#{}", module_info.name_index)),
+        ConstantKind::Package(package_info) => ("Package", format!("#{}", package_info.name_index)),
+    };
+    Some(raw)
+}
+
+fn reference_kind_name(reference_kind: u8) -> &'static str {
+    match reference_kind {
+        1 => "REF_getField",
+        2 => "REF_getStatic",
+        3 => "REF_putField",
+        4 => "REF_putStatic",
+        5 => "REF_invokeVirtual",
+        6 => "REF_invokeStatic",
+        7 => "REF_invokeSpecial",
+        8 => "REF_newInvokeSpecial",
+        9 => "REF_invokeInterface",
+        _ => "REF_?",
+    }
+}
+
+/// The constant at `index` with every reference followed, e.g. `java/lang/Object."<init>":()V`.
+pub fn constant_text(constant_pool: &ConstantPool, index: u16) -> String {
+    let name_and_type = |index: u16| match constant_pool.name_and_type(index) {
+        Ok((name, descriptor)) if name.starts_with('<') => format!("\"{}\":{}", name, descriptor),
+        Ok((name, descriptor)) => format!("{}:{}", name, descriptor),
+        Err(_) => "?".to_string(),
+    };
+
+    match constant_pool.get(index) {
+        Ok(ConstantKind::Utf8(utf8_info)) => utf8_info.text.escape_debug().to_string(),
+        Ok(constant @ (ConstantKind::Integer(_) | ConstantKind::Float(_) | ConstantKind::Long(_) | ConstantKind::Double(_))) => {
+            constant_raw(constant).map(|(_, raw)| raw).unwrap_or_default()
+        }
+        Ok(ConstantKind::Class(_)) => class_name(constant_pool, index).to_string(),
+        Ok(ConstantKind::String(string_info)) => utf8(constant_pool, string_info.string_index).escape_debug().to_string(),
+        Ok(ConstantKind::Fieldref(fieldref_info)) => format!(
+            "{}.{}",
+            class_name(constant_pool, fieldref_info.class_index),
+            name_and_type(fieldref_info.name_and_type_index),
+        ),
+        Ok(ConstantKind::Methodref(methodref_info)) => format!(
+            "{}.{}",
+            class_name(constant_pool, methodref_info.class_index),
+            name_and_type(methodref_info.name_and_type_index),
+        ),
+        Ok(ConstantKind::InterfaceMethodref(interface_methodref_info)) => format!(
+            "{}.{}",
+            class_name(constant_pool, interface_methodref_info.class_index),
+            name_and_type(interface_methodref_info.name_and_type_index),
+        ),
+        Ok(ConstantKind::NameAndType(_)) => name_and_type(index),
+        Ok(ConstantKind::MethodHandle(method_handle_info)) => format!(
+            "{} {}",
+            reference_kind_name(method_handle_info.reference_kind),
+            constant_text(constant_pool, method_handle_info.reference_index),
+        ),
+        Ok(ConstantKind::MethodType(method_type_info)) => utf8(constant_pool, method_type_info.descriptor_index).to_string(),
+        Ok(ConstantKind::Dynamic(dynamic_info)) => {
+            format!("#{}:{}", dynamic_info.bootstrap_method_attr_index, name_and_type(dynamic_info.name_and_type_index))
+        }
+        Ok(ConstantKind::InvokeDynamic(invoke_dynamic_info)) => format!(
+            "#{}:{}",
+            invoke_dynamic_info.bootstrap_method_attr_index,
+            name_and_type(invoke_dynamic_info.name_and_type_index),
+        ),
+        Ok(ConstantKind::Module(module_info)) => utf8(constant_pool, module_info.name_index).to_string(),
+        Ok(ConstantKind::Package(package_info)) => utf8(constant_pool, package_info.name_index).to_string(),
+        Ok(ConstantKind::Empty(_)) | Ok(ConstantKind::Unusable(_)) | Err(_) => "?".to_string(),
+    }
+}
+
+/// `constant_text` prefixed with the kind of constant, as in instruction comments.
+fn constant_comment(constant_pool: &ConstantPool, index: u16) -> String {
+    let kind = match constant_pool.get(index) {
+        Ok(ConstantKind::Class(_)) => "class",
+        Ok(ConstantKind::String(_)) => "String",
+        Ok(ConstantKind::Integer(_)) => "int",
+        Ok(ConstantKind::Float(_)) => "float",
+        Ok(ConstantKind::Long(_)) => "long",
+        Ok(ConstantKind::Double(_)) => "double",
+        Ok(ConstantKind::Fieldref(_)) => "Field",
+        Ok(ConstantKind::Methodref(_)) => "Method",
+        Ok(ConstantKind::InterfaceMethodref(_)) => "InterfaceMethod",
+        Ok(ConstantKind::MethodHandle(_)) => "MethodHandle",
+        Ok(ConstantKind::MethodType(_)) => "MethodType",
+        Ok(ConstantKind::Dynamic(_)) => "Dynamic",
+        Ok(ConstantKind::InvokeDynamic(_)) => "InvokeDynamic",
+        _ => "",
+    };
+    format!("{} {}", kind, constant_text(constant_pool, index)).trim_start().to_string()
+}
+
+fn write_attributes(out: &mut String, constant_pool: &ConstantPool, attributes: &[AttributeKind], indent: usize) -> fmt::Result {
+    let pad = " ".repeat(indent);
+
+    for attribute in attributes {
+        let name = utf8(constant_pool, attribute.base().attribute_name_index);
+        match attribute {
+            AttributeKind::Code(code) => write_code(out, constant_pool, code, indent)?,
+            AttributeKind::ConstantValue(constant_value) => {
+                writeln!(out, "{}ConstantValue: {}", pad, constant_comment(constant_pool, constant_value.constant_value_index))?;
+            }
+            AttributeKind::Exceptions(exceptions) => {
+                writeln!(out, "{}Exceptions:", pad)?;
+                for &exception in &exceptions.exception_index_table {
+                    writeln!(out, "{}  throws {}", pad, class_name(constant_pool, exception))?;
+                }
+            }
+            AttributeKind::Signature(signature) => {
+                writeln!(out, "{}", commented(42, format!("{}Signature: #{}", pad, signature.signature_index), utf8(constant_pool, signature.signature_index)))?;
+            }
+            AttributeKind::SourceFile(source_file) => {
+                writeln!(out, "{}SourceFile: \"{}\"", pad, utf8(constant_pool, source_file.sourcefile_index))?;
+            }
+            AttributeKind::LineNumberTable(line_number_table) => {
+                writeln!(out, "{}LineNumberTable:", pad)?;
+                for entry in &line_number_table.line_number_table {
+                    writeln!(out, "{}  line {}: {}", pad, entry.line_number, entry.start_pc)?;
+                }
+            }
+            AttributeKind::LocalVariableTable(local_variable_table) => {
+                writeln!(out, "{}LocalVariableTable:", pad)?;
+                writeln!(out, "{}  Start  Length  Slot  Name   Signature", pad)?;
+                for entry in &local_variable_table.local_variable_table {
+                    writeln!(
+                        out,
+                        "{}  {:>5}  {:>6}  {:>4}  {:>5}   {}",
+                        pad, entry.start_pc, entry.length, entry.index,
+                        utf8(constant_pool, entry.name_index), utf8(constant_pool, entry.descriptor_index),
+                    )?;
+                }
+            }
+            AttributeKind::LocalVariableTypeTable(local_variable_type_table) => {
+                writeln!(out, "{}LocalVariableTypeTable:", pad)?;
+                writeln!(out, "{}  Start  Length  Slot  Name   Signature", pad)?;
+                for entry in &local_variable_type_table.local_variable_type_table {
+                    writeln!(
+                        out,
+                        "{}  {:>5}  {:>6}  {:>4}  {:>5}   {}",
+                        pad, entry.start_pc, entry.length, entry.index,
+                        utf8(constant_pool, entry.name_index), utf8(constant_pool, entry.signature_index),
+                    )?;
+                }
+            }
+            AttributeKind::StackMapTable(stack_map_table) => write_stack_map_table(out, constant_pool, stack_map_table, &pad)?,
+            AttributeKind::InnerClasses(inner_classes) => {
+                writeln!(out, "{}InnerClasses:", pad)?;
+                for inner_class in &inner_classes.classes {
+                    let access_flags = inner_class.inner_class_access_flags;
+                    let mut line = format!("{}  {}", pad, flags(access_flags.bits(), &access_flags.names()));
+                    line.push_str(&format!(" #{}", inner_class.inner_class_info_index));
+                    if inner_class.inner_name_index != 0 {
+                        line.push_str(&format!(" = #{}", inner_class.inner_name_index));
+                    }
+                    let mut comment = class_name(constant_pool, inner_class.inner_class_info_index).to_string();
+                    if inner_class.outer_class_info_index != 0 {
+                        line.push_str(&format!(" of #{}", inner_class.outer_class_info_index));
+                        comment.push_str(&format!(" of {}", class_name(constant_pool, inner_class.outer_class_info_index)));
+                    }
+                    if inner_class.inner_name_index != 0 {
+                        comment = format!("{}={}", utf8(constant_pool, inner_class.inner_name_index), comment);
+                    }
+                    writeln!(out, "{}", commented(42, line, comment))?;
+                }
+            }
+            AttributeKind::EnclosingMethod(enclosing_method) => {
+                let method = if enclosing_method.method_index == 0 {
+                    String::new()
+                } else {
+                    format!(".{}", constant_text(constant_pool, enclosing_method.method_index))
+                };
+                writeln!(out, "{}EnclosingMethod: {}{}", pad, class_name(constant_pool, enclosing_method.class_index), method)?;
+            }
+            AttributeKind::NestHost(nest_host) => {
+                writeln!(out, "{}NestHost: class {}", pad, class_name(constant_pool, nest_host.host_class_index))?;
+            }
+            AttributeKind::NestMembers(nest_members) => {
+                writeln!(out, "{}NestMembers:", pad)?;
+                for &class in &nest_members.classes {
+                    writeln!(out, "{}  {}", pad, class_name(constant_pool, class))?;
+                }
+            }
+            AttributeKind::PermittedSubclasses(permitted_subclasses) => {
+                writeln!(out, "{}PermittedSubclasses:", pad)?;
+                for &class in &permitted_subclasses.classes {
+                    writeln!(out, "{}  {}", pad, class_name(constant_pool, class))?;
+                }
+            }
+            AttributeKind::MethodParameters(method_parameters) => {
+                writeln!(out, "{}MethodParameters:", pad)?;
+                for parameter in &method_parameters.parameters {
+                    let name = if parameter.name_index == 0 { "<no name>" } else { utf8(constant_pool, parameter.name_index) };
+                    writeln!(out, "{}  {} {:#06x}", pad, name, parameter.access_flags)?;
+                }
+            }
+            AttributeKind::BootstrapMethods(bootstrap_methods) => {
+                writeln!(out, "{}BootstrapMethods:", pad)?;
+                for (index, bootstrap_method) in bootstrap_methods.bootstrap_methods.iter().enumerate() {
+                    writeln!(
+                        out,
+                        "{}  {}: #{} {}",
+                        pad, index, bootstrap_method.bootstrap_method_ref,
+                        constant_text(constant_pool, bootstrap_method.bootstrap_method_ref),
+                    )?;
+                    writeln!(out, "{}    Method arguments:", pad)?;
+                    for &argument in &bootstrap_method.bootstrap_arguments {
+                        writeln!(out, "{}      #{} {}", pad, argument, constant_text(constant_pool, argument))?;
+                    }
+                }
+            }
+            AttributeKind::Record(record) => {
+                writeln!(out, "{}Record:", pad)?;
+                for component in &record.components {
+                    writeln!(out, "{}  {} {}", pad, utf8(constant_pool, component.descriptor_index), utf8(constant_pool, component.name_index))?;
+                    write_attributes(out, constant_pool, &component.attributes, indent + 4)?;
+                }
+            }
+            AttributeKind::Synthetic(_) | AttributeKind::Deprecated(_) => writeln!(out, "{}{}: true", pad, name)?,
+            _ => writeln!(out, "{}{}: length = {:#x}", pad, name, attribute.base().attribute_length)?,
+        }
+    }
+
+    Ok(())
+}
+
+fn write_code(out: &mut String, constant_pool: &ConstantPool, code: &CodeAttribute, indent: usize) -> fmt::Result {
+    let pad = " ".repeat(indent);
+    writeln!(out, "{}Code:", pad)?;
+    writeln!(out, "{}  stack={}, locals={}", pad, code.max_stack, code.max_locals)?;
+
+    match code.instructions() {
+        Ok(instructions) => {
+            for (pc, instruction) in &instructions {
+                write_instruction(out, constant_pool, *pc, instruction, &pad)?;
+            }
+        }
+        Err(e) => writeln!(out, "{}  // {}", pad, e)?,
+    }
+
+    if !code.exception_table.is_empty() {
+        writeln!(out, "{}  Exception table:", pad)?;
+        writeln!(out, "{}     from    to  target type", pad)?;
+        for entry in &code.exception_table {
+            let catch_type = if entry.catch_type == 0 {
+                "any".to_string()
+            } else {
+                format!("Class {}", class_name(constant_pool, entry.catch_type))
+            };
+            writeln!(
+                out,
+                "{}    {:>5} {:>5} {:>5}   {}",
+                pad, entry.start_pc, entry.end_pc, entry.handler_pc, catch_type,
+            )?;
+        }
+    }
+
+    write_attributes(out, constant_pool, &code.attributes, indent + 2)
+}
+
+/// One instruction as `write_code` prints it, without the indentation.
+pub fn instruction_text(constant_pool: &ConstantPool, pc: u32, instruction: &Instruction) -> String {
+    let mut out = String::new();
+    let _ = write_instruction(&mut out, constant_pool, pc, instruction, "");
+    out.lines().map(str::trim).collect::<Vec<_>>().join("\n")
+}
+
+fn write_instruction(out: &mut String, constant_pool: &ConstantPool, pc: u32, instruction: &Instruction, pad: &str) -> fmt::Result {
+    let target = |offset: i32| pc as i64 + offset as i64;
+    let prefix = format!("{}  {:>6}: {:<13} ", pad, pc, instruction.mnemonic());
+    let with_constant = |index: u16, operands: String| {
+        commented(prefix.len() + 17, format!("{}{}", prefix, operands), constant_comment(constant_pool, index))
+    };
+
+    let line = match instruction {
+        Instruction::Bipush(value) => format!("{}{}", prefix, value),
+        Instruction::Sipush(value) => format!("{}{}", prefix, value),
+        Instruction::Ldc(index) => with_constant(*index as u16, format!("#{}", index)),
+        Instruction::LdcW(index) | Instruction::Ldc2W(index)
+        | Instruction::Getstatic(index) | Instruction::Putstatic(index)
+        | Instruction::Getfield(index) | Instruction::Putfield(index)
+        | Instruction::Invokevirtual(index) | Instruction::Invokespecial(index) | Instruction::Invokestatic(index)
+        | Instruction::Invokedynamic(index) | Instruction::New(index) | Instruction::Anewarray(index)
+        | Instruction::Checkcast(index) | Instruction::Instanceof(index) => with_constant(*index, format!("#{}", index)),
+        Instruction::Invokeinterface { index, count } => with_constant(*index, format!("#{},  {}", index, count)),
+        Instruction::Multianewarray { index, dimensions } => with_constant(*index, format!("#{},  {}", index, dimensions)),
+        Instruction::Iload(index) | Instruction::Lload(index) | Instruction::Fload(index)
+        | Instruction::Dload(index) | Instruction::Aload(index) | Instruction::Istore(index)
+        | Instruction::Lstore(index) | Instruction::Fstore(index) | Instruction::Dstore(index)
+        | Instruction::Astore(index) | Instruction::Ret(index) => format!("{}{}", prefix, index),
+        Instruction::Iinc { index, constant } => format!("{}{}, {}", prefix, index, constant),
+        Instruction::Newarray(array_type) => format!("{}{}", prefix, array_type.name()),
+        Instruction::Ifeq(offset) | Instruction::Ifne(offset) | Instruction::Iflt(offset)
+        | Instruction::Ifge(offset) | Instruction::Ifgt(offset) | Instruction::Ifle(offset)
+        | Instruction::IfIcmpeq(offset) | Instruction::IfIcmpne(offset) | Instruction::IfIcmplt(offset)
+        | Instruction::IfIcmpge(offset) | Instruction::IfIcmpgt(offset) | Instruction::IfIcmple(offset)
+        | Instruction::IfAcmpeq(offset) | Instruction::IfAcmpne(offset) | Instruction::Goto(offset)
+        | Instruction::Jsr(offset) | Instruction::Ifnull(offset) | Instruction::Ifnonnull(offset) => {
+            format!("{}{}", prefix, target(*offset as i32))
+        }
+        Instruction::GotoW(offset) | Instruction::JsrW(offset) => format!("{}{}", prefix, target(*offset)),
+        Instruction::Wide(wide_instruction) => {
+            let operands = match wide_instruction {
+                WideInstruction::Iinc { index, constant } => format!("{}, {}", index, constant),
+                WideInstruction::Iload(index) | WideInstruction::Lload(index) | WideInstruction::Fload(index)
+                | WideInstruction::Dload(index) | WideInstruction::Aload(index) | WideInstruction::Istore(index)
+                | WideInstruction::Lstore(index) | WideInstruction::Fstore(index) | WideInstruction::Dstore(index)
+                | WideInstruction::Astore(index) | WideInstruction::Ret(index) => index.to_string(),
+            };
+            format!("{}{} {}", prefix, wide_instruction.mnemonic(), operands)
+        }
+        Instruction::Tableswitch { default, low, high, offsets } => {
+            let mut line = format!("{}{{ // {} to {}\n", prefix, low, high);
+            for (key, offset) in (*low..=*high).zip(offsets) {
+                line.push_str(&format!("{}  {:>18}: {}\n", pad, key, target(*offset)));
+            }
+            line.push_str(&format!("{}  {:>18}: {}\n{}  }}", pad, "default", target(*default), pad));
+            line
+        }
+        Instruction::Lookupswitch { default, pairs } => {
+            let mut line = format!("{}{{ // {}\n", prefix, pairs.len());
+            for (key, offset) in pairs {
+                line.push_str(&format!("{}  {:>18}: {}\n", pad, key, target(*offset)));
+            }
+            line.push_str(&format!("{}  {:>18}: {}\n{}  }}", pad, "default", target(*default), pad));
+            line
+        }
+        _ => prefix,
+    };
+
+    writeln!(out, "{}", line.trim_end())
+}
+
+fn write_stack_map_table(out: &mut String, constant_pool: &ConstantPool, stack_map_table: &StackMapTableAttribute, pad: &str) -> fmt::Result {
+    let types = |types: &[VerificationTypeInfo]| {
+        let types: Vec<String> = types.iter()
+            .map(|verification_type| match verification_type {
+                VerificationTypeInfo::Top => "top".to_string(),
+                VerificationTypeInfo::Integer => "int".to_string(),
+                VerificationTypeInfo::Float => "float".to_string(),
+                VerificationTypeInfo::Double => "double".to_string(),
+                VerificationTypeInfo::Long => "long".to_string(),
+                VerificationTypeInfo::Null => "null".to_string(),
+                VerificationTypeInfo::UninitializedThis => "this".to_string(),
+                VerificationTypeInfo::Object { cpool_index } => format!("class {}", class_name(constant_pool, *cpool_index)),
+                VerificationTypeInfo::Uninitialized { offset } => format!("uninitialized {}", offset),
+            })
+            .collect();
+        if types.is_empty() {
+            "[]".to_string()
+        } else {
+            format!("[ {} ]", types.join(", "))
+        }
+    };
+
+    writeln!(out, "{}StackMapTable: number_of_entries = {}", pad, stack_map_table.entries.len())?;
+    for frame in &stack_map_table.entries {
+        let (frame_type, kind) = match frame {
+            StackMapFrame::SameFrame(frame) => (frame.frame_type, "same"),
+            StackMapFrame::SameLocals1StackItemFrame(frame) => (frame.frame_type, "same_locals_1_stack_item"),
+            StackMapFrame::SameLocals1StackItemFrameExtended(frame) => (frame.frame_type, "same_locals_1_stack_item_frame_extended"),
+            StackMapFrame::ChopFrame(frame) => (frame.frame_type, "chop"),
+            StackMapFrame::SameFrameExtended(frame) => (frame.frame_type, "same_frame_extended"),
+            StackMapFrame::AppendFrame(frame) => (frame.frame_type, "append"),
+            StackMapFrame::FullFrame(frame) => (frame.frame_type, "full_frame"),
+        };
+        writeln!(out, "{}  frame_type = {} /* {} */", pad, frame_type, kind)?;
+
+        match frame {
+            StackMapFrame::SameFrame(_) => {}
+            StackMapFrame::SameLocals1StackItemFrame(frame) => {
+                writeln!(out, "{}    stack = {}", pad, types(std::slice::from_ref(&frame.stack)))?;
+            }
+            StackMapFrame::SameLocals1StackItemFrameExtended(frame) => {
+                writeln!(out, "{}    offset_delta = {}", pad, frame.offset_delta)?;
+                writeln!(out, "{}    stack = {}", pad, types(std::slice::from_ref(&frame.stack)))?;
+            }
+            StackMapFrame::ChopFrame(_) | StackMapFrame::SameFrameExtended(_) => {
+                writeln!(out, "{}    offset_delta = {}", pad, frame.offset_delta())?;
+            }
+            StackMapFrame::AppendFrame(frame) => {
+                writeln!(out, "{}    offset_delta = {}", pad, frame.offset_delta)?;
+                writeln!(out, "{}    locals = {}", pad, types(&frame.locals))?;
+            }
+            StackMapFrame::FullFrame(frame) => {
+                writeln!(out, "{}    offset_delta = {}", pad, frame.offset_delta)?;
+                writeln!(out, "{}    locals = {}", pad, types(&frame.locals))?;
+                writeln!(out, "{}    stack = {}", pad, types(&frame.stack))?;
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -1,48 +1,101 @@
 use std::{collections::HashMap, sync::Mutex};
 use std::fs;
-use std::fs::File;
-use std::io::Read;
 
-use crate::entity::java_class_file::JavaClassFileFormat;
-use crate::classloader::bootstrap_class_loader::define_class;
+use crate::classloader::class_loader::{ClassLoaderId, ClassLoaders};
+use crate::classloader::version_policy::VersionPolicy;
+use crate::classloader::class_loading_error::ClassLoadingError;
+use crate::classloader::linker::link;
+use crate::classloader::linking_error::LinkingError;
+use crate::classloader::class_path::ClassPath;
+use crate::classloader::jar_manifest::{JarManifest, MANIFEST_PATH};
+use crate::classloader::zip_archive::ZipArchive;
+use crate::entity::class_bytes::ClassBytes;
+use crate::entity::method_info::MethodParsing;
 use byteorder::{BigEndian, ReadBytesExt};
 
 pub struct JVMOption {
+    pub boot_class_pathes: Vec<String>,
     pub class_pathes: Vec<String>,
     pub properties: HashMap<String, String>,
+    pub enable_preview: bool,
+    pub max_major_version: u16,
+    pub method_parsing: MethodParsing,
+}
+
+impl JVMOption {
+    pub fn version_policy(&self) -> VersionPolicy {
+        VersionPolicy {
+            max_major_version: self.max_major_version,
+            enable_preview: self.enable_preview,
+            ..VersionPolicy::default()
+        }
+    }
 }
 
 pub struct JVM {
     pub option: JVMOption,
     pub entry_point_path: String,
-    pub class_files: Mutex<HashMap<String, JavaClassFileFormat>>,
+    pub class_loaders: Mutex<ClassLoaders>,
 }
 
 impl JVM {
     
-    pub fn run(&mut self) {
-        let mut buffer = &self.read_file(&self.entry_point_path)[..];
-        let buffer2 = buffer.clone();
-        let magic = buffer.read_u32::<BigEndian>().unwrap();
+    pub fn run(&mut self) -> Result<(), LinkingError> {
+        let bytes = ClassBytes::from(self.read_file(&self.entry_point_path)?);
+        let magic = (&bytes[..]).read_u32::<BigEndian>().unwrap_or(0);
+        let versions = self.option.version_policy();
         
         // is class file
         if magic == 0xCAFEBABE {
-            define_class(&self.entry_point_path, buffer2, self.class_files.get_mut().unwrap());
-            //dbg!(&self.class_files);
+            self.class_loaders = Mutex::new(self.create_class_loaders(versions));
+            let class_loaders = self.class_loaders.get_mut().unwrap();
+            let main_class = class_loaders.define_class(ClassLoaderId::APPLICATION, None, &bytes)?;
+            link(class_loaders, &main_class)?;
         
         // is jar file
         } else if magic == 0x504B0304 || magic == 0x504B0506 || magic == 0x504B0708 {
-            // TODO
+            let main_class = self.open_jar(bytes)?;
+            self.class_loaders = Mutex::new(self.create_class_loaders(versions));
+            let class_loaders = self.class_loaders.get_mut().unwrap();
+            let main_class = class_loaders.load_class(ClassLoaderId::APPLICATION, &main_class)?;
+            link(class_loaders, &main_class)?;
         }
+
+        Ok(())
     }
 
-    fn read_file(&self, filename: &String) -> Vec<u8> {
-        let mut f = File::open(&filename).expect("no file found");
-        let metadata = fs::metadata(&filename).expect("unable to read metadata");
-        let mut buffer = vec![0; metadata.len() as usize];
-        f.read(&mut buffer).expect("buffer overflow");
+    /// The platform loader has no class path of its own: the platform modules are on the
+    /// boot class path.
+    fn create_class_loaders(&self, versions: VersionPolicy) -> ClassLoaders {
+        ClassLoaders::new(
+            ClassPath::new(&self.option.boot_class_pathes),
+            ClassPath::default(),
+            ClassPath::new(&self.option.class_pathes),
+            versions,
+            self.option.method_parsing,
+        )
+    }
+
+    /// Puts the jar being launched and the `Class-Path` of its manifest on the class path,
+    /// and returns its `Main-Class`.
+    fn open_jar(&mut self, bytes: ClassBytes) -> Result<String, ClassLoadingError> {
+        let jar_path = self.entry_point_path.clone();
+        let unreadable = |message: String| ClassLoadingError::Unreadable { location: jar_path.clone(), message };
+
+        let archive = ZipArchive::new(bytes).map_err(unreadable)?;
+        let manifest = match archive.read(MANIFEST_PATH).map_err(unreadable)? {
+            Some(bytes) => JarManifest::parse(&bytes).map_err(unreadable)?,
+            None => JarManifest::default(),
+        };
+        let main_class = manifest.main_class().ok_or_else(|| unreadable("no main manifest attribute".to_string()))?;
+
+        self.option.class_pathes.push(jar_path.clone());
+        self.option.class_pathes.extend(manifest.class_path(&jar_path));
+        Ok(main_class)
+    }
 
-        buffer
+    fn read_file(&self, filename: &String) -> Result<Vec<u8>, ClassLoadingError> {
+        fs::read(filename).map_err(|e| ClassLoadingError::Unreadable { location: filename.clone(), message: e.to_string() })
     }
 
 
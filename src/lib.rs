@@ -0,0 +1,7 @@
+pub mod classloader;
+pub mod entity;
+pub mod jvm;
+pub mod javap;
+pub mod assembler;
+pub mod benchmark;
+pub mod class_json;
@@ -1,21 +1,127 @@
-mod classloader;
-mod entity;
-mod jvm;
+use churu_vm_x::{assembler, benchmark, class_json, classloader, entity, javap, jvm};
+use churu_vm_x::classloader::class_loading_error::ClassLoadingError;
+use churu_vm_x::classloader::linking_error::LinkingError;
 use std::time::Instant;
 
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let tool = match (args.get(1).map(String::as_str), args.get(2)) {
+        (Some("javap"), Some(_)) => Some(run_javap(&args[2..])),
+        (Some("javap"), None) => Some(Err(JAVAP_USAGE.to_string())),
+        (Some("asm"), Some(source)) => Some(assembler::run(source, args.get(3).map(String::as_str))),
+        (Some("asm"), None) => Some(Err("usage: churu-vm-x asm <source> [output directory]".to_string())),
+        (Some("bench"), Some(_)) => Some(benchmark::run(&args[2..])),
+        (Some("import"), Some(json)) => Some(class_json::run_import(json, args.get(3).map(String::as_str))),
+        (Some("import"), None) => Some(Err("usage: churu-vm-x import <file.json> [output directory]".to_string())),
+        (Some("bench"), None) => Some(Err("usage: churu-vm-x bench <class file or directory>...".to_string())),
+        _ => None,
+    };
+    if let Some(result) = tool {
+        if let Err(message) = result {
+            eprintln!("Error: {}", message);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     let start = Instant::now();
 
+    let mut boot_class_pathes = vec![];
+    let mut class_pathes = vec![".".to_string()];
+    let mut entry_point_path = "Main.class".to_string();
+    let mut max_major_version = classloader::version_policy::VersionPolicy::default().max_major_version;
+    let mut method_parsing = entity::method_info::MethodParsing::Eager;
+    let mut options = args.iter().skip(1);
+    while let Some(option) = options.next() {
+        match option.as_str() {
+            "-cp" | "-classpath" | "--class-path" => if let Some(path) = options.next() {
+                class_pathes = path.split(':').map(str::to_string).collect();
+            },
+            "-jar" => if let Some(jar) = options.next() {
+                entry_point_path = jar.clone();
+            },
+            LAZY_METHOD_PARSING => method_parsing = entity::method_info::MethodParsing::Lazy,
+            _ => if let Some(path) = option.strip_prefix("-Xbootclasspath/a:") {
+                boot_class_pathes.extend(path.split(':').map(str::to_string));
+            } else if let Some(version) = option.strip_prefix(MAX_CLASS_FILE_VERSION) {
+                max_major_version = parse_max_class_file_version(version).unwrap_or_else(|message| {
+                    eprintln!("Error: {}", message);
+                    std::process::exit(1);
+                });
+            },
+        }
+    }
+
     let mut jvm = jvm::JVM {
         option: jvm::JVMOption {
-            class_pathes: vec![],
+            boot_class_pathes,
+            class_pathes,
             properties: std::collections::HashMap::new(),
+            enable_preview: args.iter().any(|arg| arg == "--enable-preview"),
+            max_major_version,
+            method_parsing,
         },
-        entry_point_path: "Main.class".to_string(),
-        class_files: std::sync::Mutex::new(std::collections::HashMap::new()),
+        entry_point_path,
+        class_loaders: Default::default(),
     };
-    jvm.run();
+    match jvm.run() {
+        Ok(()) => {}
+        // Only the boot class path can provide Object, and nothing links without it.
+        Err(LinkingError::Loading(ClassLoadingError::NotFound(name))) if name == "java/lang/Object" => {
+            eprintln!("Error occurred during initialization of VM");
+            eprintln!("java/lang/Object is not on the boot class path; add the Java class library with -Xbootclasspath/a:<path>");
+            std::process::exit(1);
+        }
+        Err(error) => {
+            eprintln!("Exception in thread \"main\" {}: {}", error.java_exception_name().replace('/', "."), error);
+            std::process::exit(1);
+        }
+    }
 
     let end = start.elapsed();
-    println!("Running time: {}.{:03} sec", end.as_secs(), end.subsec_nanos() / 1_000_000);
-}
\ No newline at end of file
+    println!("Running time: {}.{:03} sec", end.as_secs(), end.subsec_millis());
+}
+
+/// Decodes method attributes on first use instead of while loading the class. Faster
+/// for large applications, but a malformed `Code` or `Exceptions` attribute is then
+/// reported when the method is first used, or never if it is not.
+const LAZY_METHOD_PARSING: &str = "-XX:+LazyMethodParsing";
+
+/// Raises (or lowers) the newest class file major version accepted, 61 by default.
+const MAX_CLASS_FILE_VERSION: &str = "-XX:MaxClassFileVersion=";
+
+fn parse_max_class_file_version(version: &str) -> Result<u16, String> {
+    let min_major_version = classloader::version_policy::VersionPolicy::default().min_major_version;
+    match version.parse::<u16>() {
+        Ok(version) if version >= min_major_version => Ok(version),
+        _ => Err(format!("invalid class file version for {}: {}", MAX_CLASS_FILE_VERSION, version)),
+    }
+}
+
+const JAVAP_USAGE: &str = "usage: churu-vm-x javap [-cp <path>] [--json] [--enable-preview] [-XX:MaxClassFileVersion=<n>] <file|class>";
+
+fn run_javap(args: &[String]) -> Result<(), String> {
+    let mut class_path = vec![".".to_string()];
+    let mut json = false;
+    let mut versions = classloader::version_policy::VersionPolicy::default();
+    let mut target = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-cp" | "-classpath" | "--class-path" => {
+                let path = args.next().ok_or(JAVAP_USAGE)?;
+                class_path = path.split(':').map(str::to_string).collect();
+            }
+            "--json" => json = true,
+            "--enable-preview" => versions.enable_preview = true,
+            _ if arg.starts_with(MAX_CLASS_FILE_VERSION) => {
+                versions.max_major_version = parse_max_class_file_version(&arg[MAX_CLASS_FILE_VERSION.len()..])?;
+            }
+            _ if target.is_none() => target = Some(arg),
+            _ => return Err(JAVAP_USAGE.to_string()),
+        }
+    }
+
+    javap::run(target.ok_or(JAVAP_USAGE)?, &class_path, json, &versions)
+}
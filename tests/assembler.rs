@@ -0,0 +1,234 @@
+use churu_vm_x::assembler::assemble;
+use churu_vm_x::classloader::bootstrap_class_loader::check_class_file;
+use churu_vm_x::classloader::version_policy::VersionPolicy;
+use churu_vm_x::entity::attribute_info::{AttributeKind, CodeAttribute, StackMapFrame, VerificationTypeInfo};
+use churu_vm_x::entity::class_format_error::ClassFormatErrorKind;
+use churu_vm_x::entity::class_reader::ClassReader;
+use churu_vm_x::entity::constant_pool::ConstantKind;
+use churu_vm_x::entity::instruction::{decode, Instruction};
+use churu_vm_x::entity::method_info::MethodParsing;
+use churu_vm_x::javap::constant_text;
+
+const HANDLES: &str = r#"
+.version 52 0
+.class public super Handles
+.super java/lang/Object
+
+.method public static handles()V
+    .limit stack 1
+    ldc methodhandle invokestatic Handles/handles()V
+    ldc methodhandle getstatic java/lang/System/out Ljava/io/PrintStream;
+    ldc methodhandle newinvokespecial java/lang/Object/<init>()V
+    ldc methodhandle invokeinterface java/lang/Runnable/run()V
+    ldc methodhandle invokestatic interface java/util/List/of()Ljava/util/List;
+    ldc methodhandle invokestatic Handles/handles()V
+    return
+.end method
+"#;
+
+#[test]
+fn method_handle_constants_are_assembled() {
+    let class = assemble(HANDLES).unwrap();
+    let constant_pool = &class.constant_pool;
+    let handles: Vec<_> = (1..constant_pool.len() as u16)
+        .filter(|&index| matches!(constant_pool.get(index), Ok(ConstantKind::MethodHandle(_))))
+        .map(|index| constant_text(constant_pool, index))
+        .collect();
+    assert_eq!(handles, [
+        "REF_invokeStatic Handles.handles:()V",
+        "REF_getStatic java/lang/System.out:Ljava/io/PrintStream;",
+        "REF_newInvokeSpecial java/lang/Object.\"<init>\":()V",
+        "REF_invokeInterface java/lang/Runnable.run:()V",
+        "REF_invokeStatic java/util/List.of:()Ljava/util/List;",
+    ]);
+
+    // A static interface method is referred to through an InterfaceMethodref.
+    let list_of = constant_pool.entries().iter().find_map(|constant| match constant {
+        ConstantKind::MethodHandle(handle) if constant_text(constant_pool, handle.reference_index).starts_with("java/util/List") => {
+            Some(handle.reference_index)
+        }
+        _ => None,
+    });
+    assert!(matches!(constant_pool.get(list_of.unwrap()), Ok(ConstantKind::InterfaceMethodref(_))));
+
+    let bytes = class.to_bytes().unwrap();
+    assert!(check_class_file(&mut ClassReader::new(&bytes), MethodParsing::Eager, &VersionPolicy::default()).is_ok());
+}
+
+#[test]
+fn unknown_method_handle_kinds_are_rejected() {
+    let source = HANDLES.replace("methodhandle getstatic", "methodhandle getsomething");
+    let error = assemble(&source).unwrap_err();
+    assert_eq!(error.line, 9);
+    assert!(error.message.contains("unknown method handle kind `getsomething`"), "{}", error.message);
+}
+
+/// The Code attribute of the only method of the class assembled from `source`.
+fn code(source: &str) -> CodeAttribute {
+    let class = assemble(source).unwrap();
+    let attributes = class.methods[0].attributes.parsed().unwrap();
+    attributes.iter().find_map(|attribute| match attribute {
+        AttributeKind::Code(code) => Some(code.clone()),
+        _ => None,
+    }).unwrap()
+}
+
+fn method(body: &str) -> String {
+    format!(".version 50 0\n.class public t/Code\n.method public static m(I)I\n{}\n.end method\n", body)
+}
+
+#[test]
+fn labels_resolve_to_forward_and_backward_offsets() {
+    let code = code(&method(r#"
+    .limit stack 1
+Loop:
+    iload_0
+    ifeq Done
+    iinc 0 -1
+    goto Loop
+Done: iload_0
+    ireturn"#));
+    assert_eq!(decode(&code.code).unwrap(), [
+        (0, Instruction::Iload0),
+        (1, Instruction::Ifeq(9)),
+        (4, Instruction::Iinc { index: 0, constant: -1 }),
+        (7, Instruction::Goto(-7)),
+        (10, Instruction::Iload0),
+        (11, Instruction::Ireturn),
+    ]);
+}
+
+#[test]
+fn switches_list_their_targets_on_the_following_lines() {
+    let code = code(&method(r#"
+    .limit stack 1
+    iload_0
+    tableswitch 1
+        One
+        Two
+        default: Other
+    iload_0
+    lookupswitch
+        20: Two
+        -5: One
+        default: Other
+One: iconst_1
+    ireturn
+Two: iconst_2
+    ireturn
+Other: iconst_0
+    ireturn"#));
+    let instructions = decode(&code.code).unwrap();
+    let pc = |wanted: Instruction| instructions.iter().find(|(_, instruction)| *instruction == wanted).unwrap().0 as i32;
+    let (one, two, other) = (pc(Instruction::Iconst1), pc(Instruction::Iconst2), pc(Instruction::Iconst0));
+
+    let (table_pc, table) = &instructions[1];
+    let table_pc = *table_pc as i32;
+    assert_eq!(*table, Instruction::Tableswitch { default: other - table_pc, low: 1, high: 2, offsets: vec![one - table_pc, two - table_pc] });
+    let (lookup_pc, lookup) = &instructions[3];
+    let lookup_pc = *lookup_pc as i32;
+    assert_eq!(*lookup, Instruction::Lookupswitch { default: other - lookup_pc, pairs: vec![(-5, one - lookup_pc), (20, two - lookup_pc)] });
+}
+
+#[test]
+fn catch_directives_build_the_exception_table() {
+    let code = code(&method(r#"
+    .limit stack 1
+    .catch java/lang/ArithmeticException from Start to End using Handler
+    .catch all from Start to End using Handler
+Start: iconst_1
+    iload_0
+    idiv
+End: ireturn
+Handler: pop
+    iconst_0
+    ireturn"#));
+    let ranges: Vec<_> = code.exception_table.iter().map(|entry| (entry.start_pc, entry.end_pc, entry.handler_pc)).collect();
+    assert_eq!(ranges, [(0, 3, 4), (0, 3, 4)]);
+    assert_ne!(code.exception_table[0].catch_type, 0);
+    assert_eq!(code.exception_table[1].catch_type, 0);
+}
+
+#[test]
+fn stack_directives_describe_full_frames() {
+    let class = assemble(r#"
+.version 50 0
+.class public t/Frames
+.method public <init>()V
+    .limit stack 3
+    aload_0
+    invokespecial java/lang/Object/<init>()V
+    iconst_0
+    ifeq Skip
+New: new t/Frames
+    dup
+.stack
+    locals Object t/Frames
+    stack Uninitialized New Uninitialized New
+.end stack
+    invokespecial t/Frames/<init>()V
+    pop
+.stack
+    locals Object t/Frames
+.end stack
+Skip: return
+.end method
+"#).unwrap();
+    let attributes = class.methods[0].attributes.parsed().unwrap();
+    let AttributeKind::Code(code) = &attributes[0] else { panic!("expected Code") };
+    let AttributeKind::StackMapTable(table) = &code.attributes[0] else { panic!("expected StackMapTable") };
+    let frames: Vec<_> = table.entries.iter().map(|frame| match frame {
+        StackMapFrame::FullFrame(frame) => (frame.offset_delta, frame.locals.len(), frame.stack.clone()),
+        other => panic!("expected a full frame but got {:?}", other),
+    }).collect();
+    // `new` is at pc 8, the first frame at 12 and the second at 16.
+    assert_eq!(frames, [
+        (12, 1, vec![VerificationTypeInfo::Uninitialized { offset: 8 }, VerificationTypeInfo::Uninitialized { offset: 8 }]),
+        (3, 1, vec![]),
+    ]);
+}
+
+#[test]
+fn raw_bytes_are_assembled_even_when_they_are_not_valid_code() {
+    let code = code(&method("    .bytes 1A CB AC"));
+    assert_eq!(&code.code[..], [0x1A, 0xCB, 0xAC]);
+    assert_eq!(decode(&code.code).unwrap_err().kind, ClassFormatErrorKind::InvalidOpcode { opcode: 0xCB, pc: 1 });
+}
+
+#[test]
+fn errors_point_at_the_line_that_caused_them() {
+    for (body, line, message) in [
+        ("    goto Nowhere", 4, "undefined label Nowhere"),
+        ("    iconst_0\n    .catch all from Start to End using Start\nStart: ireturn", 5, "undefined label End"),
+        ("    bipush 300", 4, "300 is out of range -128..=127"),
+        ("    iload_0\n    frobnicate", 5, "unknown instruction frobnicate"),
+        ("    .bytes 1A XY", 4, "expected a hex byte but found `XY`"),
+        ("A: iconst_0\nA: ireturn", 5, "label A is defined twice"),
+    ] {
+        let error = assemble(&method(body)).unwrap_err();
+        assert_eq!((error.line, error.message.as_str()), (line, message), "{}", body);
+    }
+}
+
+#[test]
+fn pcs_beyond_16_bits_are_errors_rather_than_truncated() {
+    let filler = format!("    .bytes {}", "00 ".repeat(0x10000));
+    for (directive, message) in [
+        (".catch all from Start to End using Start", "pc 65537 does not fit in 16 bits"),
+        (".line 7", "pc 65537 does not fit in 16 bits"),
+        (".stack\n.end stack", "pc 65537 does not fit in 16 bits"),
+    ] {
+        let source = method(&format!("Start: iconst_0\n{}\n{}\nEnd: ireturn", filler, directive));
+        let error = assemble(&source).unwrap_err();
+        assert_eq!((error.line, error.message.as_str()), (6, message), "{}", directive);
+    }
+}
+
+#[test]
+fn constants_beyond_the_last_constant_pool_index_are_errors() {
+    let constants: String = (0..u16::MAX).map(|value| format!("    ldc_w {}\n", value)).collect();
+    let error = assemble(&method(&format!("{}    ireturn", constants))).unwrap_err();
+    // The class and method names take the first four indices.
+    assert_eq!(error.line, 4 + 65530);
+    assert_eq!(error.message, "constant_pool_count of 65536 exceeds the class file format limit of 65535");
+}
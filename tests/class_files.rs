@@ -0,0 +1,165 @@
+mod common;
+
+use std::sync::Arc;
+use std::thread;
+
+use churu_vm_x::classloader::bootstrap_class_loader::check_class_file;
+use churu_vm_x::classloader::verifier::verify;
+use churu_vm_x::classloader::version_policy::VersionPolicy;
+use churu_vm_x::entity::attribute_info::{AttributeKind, VerificationTypeInfo};
+use churu_vm_x::entity::descriptor::{FieldType, MethodDescriptor};
+use churu_vm_x::entity::instruction::{decode, encode};
+use churu_vm_x::entity::java_class_file::JavaClassFileFormat;
+use churu_vm_x::entity::method_info::MethodInfo;
+use churu_vm_x::entity::class_reader::ClassReader;
+use churu_vm_x::entity::method_info::MethodParsing;
+
+use common::corpus;
+
+#[test]
+fn corpus_covers_records_enums_generics_constants_and_inner_classes() {
+    let file_names: Vec<_> = corpus().into_iter().map(|(file_name, _)| file_name).collect();
+    for expected in ["Point.class", "Color.class", "Box.class", "Constants.class", "Outer$Inner.class", "Outer$1.class"] {
+        assert!(file_names.iter().any(|file_name| file_name == expected), "missing {}", expected);
+    }
+}
+
+#[test]
+fn every_byte_of_each_class_is_consumed() {
+    for parsing in [MethodParsing::Eager, MethodParsing::Lazy] {
+        for (file_name, bytes) in corpus() {
+            let mut reader = ClassReader::shared(&bytes);
+            let result = check_class_file(&mut reader, parsing, &VersionPolicy::default());
+            assert!(result.is_ok(), "{}: {:?}", file_name, result.err());
+            assert!(reader.is_empty(), "{}: stopped at {} of {} bytes", file_name, reader.position(), bytes.len());
+        }
+    }
+}
+
+#[test]
+fn unmodified_classes_are_written_back_byte_for_byte() {
+    for parsing in [MethodParsing::Eager, MethodParsing::Lazy] {
+        for (file_name, bytes) in corpus() {
+            let class = check_class_file(&mut ClassReader::shared(&bytes), parsing, &VersionPolicy::default()).unwrap();
+            assert!(class.to_bytes().unwrap() == bytes[..], "{} ({:?}) changed when written back", file_name, parsing);
+        }
+    }
+}
+
+#[test]
+fn class_names_match_file_names() {
+    for (file_name, bytes) in corpus() {
+        let mut reader = ClassReader::shared(&bytes);
+        let class = check_class_file(&mut reader, MethodParsing::Eager, &VersionPolicy::default()).unwrap();
+        assert_eq!(format!("{}.class", class.name().unwrap()), file_name);
+    }
+}
+
+/// The frame a method starts with. Reference types only need a constant index here.
+fn initial_locals(class: &JavaClassFileFormat, method: &MethodInfo, descriptor: &MethodDescriptor) -> Vec<VerificationTypeInfo> {
+    let mut locals = Vec::new();
+    if !method.access_flags.is_static() {
+        let is_constructor = class.constant_pool.utf8(method.name_index).unwrap() == "<init>";
+        locals.push(if is_constructor { VerificationTypeInfo::UninitializedThis } else { VerificationTypeInfo::Object { cpool_index: class.this_class } });
+    }
+    for parameter in &descriptor.parameters {
+        locals.push(match parameter {
+            FieldType::Long => VerificationTypeInfo::Long,
+            FieldType::Float => VerificationTypeInfo::Float,
+            FieldType::Double => VerificationTypeInfo::Double,
+            FieldType::Object(_) | FieldType::Array { .. } => VerificationTypeInfo::Object { cpool_index: class.this_class },
+            _ => VerificationTypeInfo::Integer,
+        });
+    }
+    locals
+}
+
+#[test]
+fn stack_map_tables_expand_onto_instructions() {
+    let mut expanded_frames = 0;
+    for (file_name, bytes) in corpus() {
+        let class = check_class_file(&mut ClassReader::shared(&bytes), MethodParsing::Eager, &VersionPolicy::default()).unwrap();
+        for method in &class.methods {
+            for attribute in method.attributes.get(&class.constant_pool).unwrap() {
+                let AttributeKind::Code(code) = attribute else { continue };
+                let pcs: Vec<u32> = code.instructions().unwrap().into_iter().map(|(pc, _)| pc).collect();
+                for code_attribute in &code.attributes {
+                    let AttributeKind::StackMapTable(stack_map_table) = code_attribute else { continue };
+
+                    let descriptor = MethodDescriptor::parse(class.constant_pool.utf8(method.descriptor_index).unwrap()).unwrap();
+                    let frames = stack_map_table.expand(&initial_locals(&class, method, &descriptor)).unwrap();
+                    assert_eq!(frames.len(), stack_map_table.entries.len());
+                    for (frame, window) in frames.iter().zip(frames.iter().skip(1)) {
+                        assert!(frame.offset < window.offset, "{}: frames out of order", file_name);
+                    }
+                    for frame in &frames {
+                        assert!(pcs.contains(&u32::from(frame.offset)), "{}: frame at {} is not on an instruction", file_name, frame.offset);
+                        assert!(frame.stack.len() <= usize::from(code.max_stack));
+                        for slots in [&frame.locals, &frame.stack] {
+                            for (index, slot) in slots.iter().enumerate() {
+                                if matches!(slot, VerificationTypeInfo::Long | VerificationTypeInfo::Double) {
+                                    assert_eq!(slots.get(index + 1), Some(&VerificationTypeInfo::Top), "{}: wide value without Top", file_name);
+                                }
+                            }
+                        }
+                    }
+                    expanded_frames += frames.len();
+                }
+            }
+        }
+    }
+    assert!(expanded_frames > 0);
+}
+
+#[test]
+fn corpus_classes_pass_verification() {
+    for (file_name, bytes) in corpus() {
+        let class = check_class_file(&mut ClassReader::shared(&bytes), MethodParsing::Eager, &VersionPolicy::default()).unwrap();
+        assert_eq!(verify(&class), Ok(()), "{}", file_name);
+    }
+}
+
+#[test]
+fn code_arrays_encode_back_to_their_bytes() {
+    for (file_name, bytes) in corpus() {
+        let class = check_class_file(&mut ClassReader::shared(&bytes), MethodParsing::Eager, &VersionPolicy::default()).unwrap();
+        for method in &class.methods {
+            for attribute in method.attributes.get(&class.constant_pool).unwrap() {
+                let AttributeKind::Code(code) = attribute else { continue };
+                let instructions: Vec<_> = decode(&code.code).unwrap().into_iter().map(|(_, instruction)| instruction).collect();
+                assert_eq!(encode(&instructions), &code.code[..], "{}", file_name);
+            }
+        }
+    }
+}
+
+#[test]
+fn lazily_parsed_methods_are_decoded_once_across_threads() {
+    for (file_name, bytes) in corpus() {
+        let class = check_class_file(&mut ClassReader::shared(&bytes), MethodParsing::Lazy, &VersionPolicy::default()).unwrap();
+        let class = Arc::new(class);
+        assert!(class.methods.iter().all(|method| method.attributes.parsed().is_none()), "{}", file_name);
+
+        let decode_all = || {
+            let class = class.clone();
+            move || {
+                class.methods.iter()
+                    .map(|method| method.attributes.get(&class.constant_pool).unwrap().as_ptr() as usize)
+                    .collect::<Vec<_>>()
+            }
+        };
+        let (first, second) = thread::scope(|scope| {
+            let first = scope.spawn(decode_all());
+            let second = scope.spawn(decode_all());
+            (first.join().unwrap(), second.join().unwrap())
+        });
+        assert_eq!(first, second, "{}: threads saw different decodings", file_name);
+
+        let eager = check_class_file(&mut ClassReader::shared(&bytes), MethodParsing::Eager, &VersionPolicy::default()).unwrap();
+        for (lazy_method, eager_method) in class.methods.iter().zip(&eager.methods) {
+            let lazy_names = lazy_method.attributes.name_indices();
+            assert_eq!(lazy_names, eager_method.attributes.name_indices());
+            assert_eq!(lazy_method.attributes.parsed().unwrap().len(), lazy_names.len());
+        }
+    }
+}
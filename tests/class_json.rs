@@ -0,0 +1,130 @@
+mod common;
+
+use std::fs;
+
+use serde_json::Value;
+
+use churu_vm_x::class_json::{from_json, run_import, to_json};
+use churu_vm_x::classloader::bootstrap_class_loader::check_class_file;
+use churu_vm_x::classloader::version_policy::VersionPolicy;
+use churu_vm_x::entity::attribute_info::AttributeKind;
+use churu_vm_x::entity::class_bytes::ClassBytes;
+use churu_vm_x::entity::class_reader::ClassReader;
+use churu_vm_x::entity::java_class_file::JavaClassFileFormat;
+use churu_vm_x::entity::method_info::MethodParsing;
+
+use common::{corpus, corpus_class, scratch_directory};
+
+fn parse(bytes: &ClassBytes) -> JavaClassFileFormat {
+    check_class_file(&mut ClassReader::shared(bytes), MethodParsing::Eager, &VersionPolicy::default()).unwrap()
+}
+
+fn exported(file_name: &str) -> Value {
+    serde_json::from_str(&to_json(&parse(&corpus_class(file_name))).unwrap()).unwrap()
+}
+
+/// The Utf8 entry of the exported constant pool whose text is `text`.
+fn utf8_entry<'a>(json: &'a mut Value, text: &str) -> &'a mut Value {
+    json["constant_pool"].as_array_mut().unwrap().iter_mut()
+        .filter_map(|constant| constant.get_mut("Utf8"))
+        .find(|utf8| utf8["text"] == text)
+        .unwrap()
+}
+
+#[test]
+fn every_corpus_class_survives_export_and_import_byte_for_byte() {
+    for (file_name, bytes) in corpus() {
+        let json = to_json(&parse(&bytes)).unwrap();
+        let imported = from_json(&json).unwrap_or_else(|e| panic!("{}: {}", file_name, e));
+        assert!(imported.to_bytes().unwrap() == bytes[..], "{}", file_name);
+    }
+}
+
+#[test]
+fn indices_and_flags_are_resolved() {
+    let json = exported("Point.class");
+    assert_eq!(json["this_class_resolved"], "Point");
+    assert_eq!(json["super_class_resolved"], "java/lang/Record");
+    assert_eq!(json["access_flags_names"], serde_json::json!(["ACC_PUBLIC", "ACC_FINAL", "ACC_SUPER"]));
+
+    let methods = json["methods"].as_array().unwrap();
+    let accessor = methods.iter().find(|method| method["name_index_resolved"] == "x").unwrap();
+    assert_eq!(accessor["descriptor_index_resolved"], "()I");
+    assert_eq!(accessor["access_flags_names"], serde_json::json!(["ACC_PUBLIC"]));
+    let code = &accessor["attributes"][0]["Code"];
+    assert_eq!(code["base"]["attribute_name_index_resolved"], "Code");
+    assert!(code["code_resolved"].as_array().unwrap().iter().any(|line| line.as_str().unwrap().contains("getfield")));
+
+    let method_ref = json["constant_pool"].as_array().unwrap().iter().find_map(|constant| constant.get("Methodref")).unwrap();
+    assert_eq!(method_ref["class_index_resolved"], "java/lang/Record");
+}
+
+#[test]
+fn edited_text_is_imported() {
+    let mut json = exported("Point.class");
+    utf8_entry(&mut json, "Point.java")["text"] = "Renamed.java".into();
+    let class = from_json(&json.to_string()).unwrap();
+    let class = parse(&ClassBytes::from(class.to_bytes().unwrap()));
+    let source_file = class.attributes.iter().find_map(|attribute| match attribute {
+        AttributeKind::SourceFile(source_file) => Some(source_file.sourcefile_index),
+        _ => None,
+    });
+    assert_eq!(class.constant_pool.utf8(source_file.unwrap()), Ok("Renamed.java"));
+}
+
+#[test]
+fn unpaired_surrogates_are_imported_from_utf16() {
+    let mut json = exported("Point.class");
+    let utf8 = utf8_entry(&mut json, "Point.java");
+    utf8["text"] = "A\u{FFFD}".into();
+    utf8["utf16"] = serde_json::json!([0x41, 0xD800]);
+    let class = from_json(&json.to_string()).unwrap();
+    let bytes = class.to_bytes().unwrap();
+    assert!(bytes.windows(5).any(|window| window == [0x00, 0x04, b'A', 0xED, 0xA0]));
+
+    // Exporting again gives the same utf16.
+    let exported: Value = serde_json::from_str(&to_json(&parse(&ClassBytes::from(bytes))).unwrap()).unwrap();
+    let utf8 = exported["constant_pool"].as_array().unwrap().iter().find(|constant| constant["Utf8"]["text"] == "A\u{FFFD}").unwrap();
+    assert_eq!(utf8["Utf8"]["utf16"], serde_json::json!([0x41, 0xD800]));
+}
+
+#[test]
+fn malformed_json_is_rejected() {
+    assert!(from_json("").is_err());
+    assert!(from_json("{").is_err());
+    assert!(from_json("{}").unwrap_err().contains("missing field"));
+
+    let mut json = exported("Point.class");
+    json["methods"][0]["access_flags"] = "public".into();
+    assert!(from_json(&json.to_string()).is_err());
+
+    let mut json = exported("Point.class");
+    utf8_entry(&mut json, "Point.java")["text"] = "x".repeat(65536).into();
+    let error = from_json(&json.to_string()).unwrap_err();
+    assert!(error.contains("Utf8 constant of 65536 bytes exceeds the class file format limit of 65535"), "{}", error);
+
+    // Only the code units are imported when present, so an edited text would be lost.
+    let mut json = exported("Point.class");
+    let utf8 = utf8_entry(&mut json, "Point.java");
+    utf8["text"] = "Edited.java".into();
+    utf8["utf16"] = serde_json::json!([0x41, 0xD800]);
+    let error = from_json(&json.to_string()).unwrap_err();
+    assert!(error.contains("Utf8 constant \"Edited.java\" does not match its utf16 code units"), "{}", error);
+}
+
+#[test]
+fn import_writes_the_class_file() {
+    let directory = scratch_directory("import");
+    let bytes = corpus_class("Outer$Inner.class");
+    let json_path = directory.join("inner.json");
+    fs::write(&json_path, to_json(&parse(&bytes)).unwrap()).unwrap();
+
+    run_import(json_path.to_str().unwrap(), directory.to_str()).unwrap();
+    assert!(fs::read(directory.join("Outer$Inner.class")).unwrap() == bytes[..]);
+
+    fs::write(&json_path, "[]").unwrap();
+    let error = run_import(json_path.to_str().unwrap(), directory.to_str()).unwrap_err();
+    assert!(error.starts_with(json_path.to_str().unwrap()), "{}", error);
+
+    fs::remove_dir_all(directory).unwrap();
+}
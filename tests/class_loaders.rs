@@ -0,0 +1,236 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use churu_vm_x::assembler::assemble;
+use churu_vm_x::classloader::bootstrap_class_loader::define_class;
+use churu_vm_x::classloader::class_loader::{ClassLoaderId, ClassLoaders};
+use churu_vm_x::classloader::class_loader_natives::{find_native, NativeMethod, NativeValue, CLASS_LOADER};
+use churu_vm_x::classloader::class_loading_error::ClassLoadingError;
+use churu_vm_x::classloader::class_path::ClassPath;
+use churu_vm_x::classloader::native_error::NativeError;
+use churu_vm_x::classloader::runtime_class::RuntimeClass;
+use churu_vm_x::classloader::version_policy::VersionPolicy;
+use churu_vm_x::entity::class_bytes::ClassBytes;
+use churu_vm_x::entity::method_info::MethodParsing;
+
+use common::{corpus_class, scratch_directory};
+
+fn class_bytes(name: &str) -> Vec<u8> {
+    assemble(&format!(".class public {}\n", name)).unwrap().to_bytes().unwrap()
+}
+
+fn write_class(root: &Path, name: &str) {
+    let path = root.join(format!("{}.class", name));
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, class_bytes(name)).unwrap();
+}
+
+/// Built-in loaders over `boot`, `platform` and `app` directories of `directory`.
+/// `Shared` is in the boot and application class paths, `p/Platform` in the
+/// platform one and `a/App` in the application one.
+fn built_in_loaders(directory: &Path) -> ClassLoaders {
+    write_class(&directory.join("boot"), "Shared");
+    write_class(&directory.join("platform"), "p/Platform");
+    write_class(&directory.join("app"), "a/App");
+    write_class(&directory.join("app"), "Shared");
+
+    let class_path = |name: &str| ClassPath::new(&[directory.join(name).display().to_string()]);
+    ClassLoaders::new(class_path("boot"), class_path("platform"), class_path("app"), VersionPolicy::default(), MethodParsing::Eager)
+}
+
+#[test]
+fn class_declaring_another_name_is_rejected() {
+    let error = define_class(Some("Crate"), &corpus_class("Box.class"), MethodParsing::Eager, &VersionPolicy::default()).unwrap_err();
+    assert_eq!(error, ClassLoadingError::WrongName { requested: "Crate".to_string(), found: "Box".to_string() });
+    assert_eq!(error.java_exception_name(), "java/lang/NoClassDefFoundError");
+}
+
+#[test]
+fn second_definition_of_a_name_is_a_linkage_error() {
+    let mut class_loaders = ClassLoaders::default();
+    let bytes = corpus_class("Box.class");
+    let first = class_loaders.define_class(ClassLoaderId::APPLICATION, Some("Box"), &bytes).unwrap();
+
+    for name in [Some("Box"), None] {
+        let error = class_loaders.define_class(ClassLoaderId::APPLICATION, name, &bytes).unwrap_err();
+        assert_eq!(error, ClassLoadingError::DuplicateDefinition { loader: "app".to_string(), name: "Box".to_string() });
+        assert_eq!(error.java_exception_name(), "java/lang/LinkageError");
+    }
+    let loaded = class_loaders.load_class(ClassLoaderId::APPLICATION, "Box").unwrap();
+    assert!(Arc::ptr_eq(&first, &loaded));
+}
+
+fn native(name: &str, descriptor: &str) -> NativeMethod {
+    find_native(CLASS_LOADER, name, descriptor).unwrap()
+}
+
+fn define_class1(class_loaders: &mut ClassLoaders, loader: ClassLoaderId, name: &str, bytes: &ClassBytes) -> Result<NativeValue, NativeError> {
+    let define_class1 = native(
+        "defineClass1",
+        "(Ljava/lang/ClassLoader;Ljava/lang/String;[BIILjava/security/ProtectionDomain;Ljava/lang/String;)Ljava/lang/Class;",
+    );
+    let arguments = [
+        NativeValue::ClassLoader(loader),
+        NativeValue::String(name.to_string()),
+        NativeValue::ByteArray(bytes.clone()),
+        NativeValue::Int(0),
+        NativeValue::Int(bytes.len() as i32),
+        NativeValue::Opaque,
+        NativeValue::Null,
+    ];
+    define_class1(class_loaders, &arguments)
+}
+
+fn class(value: Result<NativeValue, NativeError>) -> Arc<RuntimeClass> {
+    match value {
+        Ok(NativeValue::Class(class)) => class,
+        other => panic!("expected a class but got {:?}", other),
+    }
+}
+
+#[test]
+fn user_defined_loaders_define_distinct_classes_of_one_name() {
+    let mut class_loaders = ClassLoaders::default();
+    let first_loader = class_loaders.create_user_defined(None);
+    let second_loader = class_loaders.create_user_defined(Some(ClassLoaderId::APPLICATION));
+    assert!(first_loader.is_user_defined() && second_loader.is_user_defined());
+    assert_ne!(first_loader, second_loader);
+    assert_eq!(class_loaders.parent(first_loader), Some(ClassLoaderId::BOOTSTRAP));
+
+    let bytes = corpus_class("Box.class");
+    let first = class(define_class1(&mut class_loaders, first_loader, "Box", &bytes));
+    let second = class(define_class1(&mut class_loaders, second_loader, "Box", &bytes));
+    assert!(!Arc::ptr_eq(&first, &second));
+    assert_eq!((first.defining_loader, second.defining_loader), (first_loader, second_loader));
+    assert!(!first.is_same_runtime_package(&second));
+
+    let error = define_class1(&mut class_loaders, first_loader, "Box", &bytes).unwrap_err();
+    assert_eq!(error, NativeError::Loading(ClassLoadingError::DuplicateDefinition { loader: first_loader.to_string(), name: "Box".to_string() }));
+    assert_eq!(error.java_exception_name(), "java/lang/LinkageError");
+
+    let find_loaded_class0 = native("findLoadedClass0", "(Ljava/lang/String;)Ljava/lang/Class;");
+    let name = NativeValue::String("Box".to_string());
+    let found = class(find_loaded_class0(&mut class_loaders, &[NativeValue::ClassLoader(first_loader), name.clone()]));
+    assert!(Arc::ptr_eq(&found, &first));
+    let not_found = find_loaded_class0(&mut class_loaders, &[NativeValue::ClassLoader(ClassLoaderId::APPLICATION), name.clone()]);
+    assert!(matches!(not_found, Ok(NativeValue::Null)));
+
+    let find_bootstrap_class = native("findBootstrapClass", "(Ljava/lang/String;)Ljava/lang/Class;");
+    assert!(matches!(find_bootstrap_class(&mut class_loaders, &[name]), Ok(NativeValue::Null)));
+}
+
+#[test]
+fn define_class1_checks_the_array_region() {
+    let mut class_loaders = ClassLoaders::default();
+    let loader = class_loaders.create_user_defined(None);
+    let bytes = ClassBytes::from(&[0xCA, 0xFE][..]);
+    let define_class1 = native(
+        "defineClass1",
+        "(Ljava/lang/ClassLoader;Ljava/lang/String;[BIILjava/security/ProtectionDomain;Ljava/lang/String;)Ljava/lang/Class;",
+    );
+    for (off, len) in [(1, 2), (-1, 1), (0, -1), (i32::MAX, i32::MAX)] {
+        let arguments = [
+            NativeValue::ClassLoader(loader),
+            NativeValue::Null,
+            NativeValue::ByteArray(bytes.clone()),
+            NativeValue::Int(off),
+            NativeValue::Int(len),
+            NativeValue::Null,
+            NativeValue::Null,
+        ];
+        let error = define_class1(&mut class_loaders, &arguments).unwrap_err();
+        assert_eq!(error.java_exception_name(), "java/lang/ArrayIndexOutOfBoundsException", "{} {}", off, len);
+    }
+    assert!(find_native(CLASS_LOADER, "defineClass0", "()V").is_none());
+    assert!(find_native("java/lang/Object", "findLoadedClass0", "(Ljava/lang/String;)Ljava/lang/Class;").is_none());
+}
+
+#[test]
+fn built_in_loaders_delegate_to_their_parent_first() {
+    let directory = scratch_directory("delegation");
+    let mut class_loaders = built_in_loaders(&directory);
+    assert_eq!(class_loaders.parent(ClassLoaderId::APPLICATION), Some(ClassLoaderId::PLATFORM));
+    assert_eq!(class_loaders.parent(ClassLoaderId::PLATFORM), Some(ClassLoaderId::BOOTSTRAP));
+    assert_eq!(class_loaders.parent(ClassLoaderId::BOOTSTRAP), None);
+
+    for (name, defining_loader) in [
+        ("Shared", ClassLoaderId::BOOTSTRAP),
+        ("p/Platform", ClassLoaderId::PLATFORM),
+        ("a/App", ClassLoaderId::APPLICATION),
+    ] {
+        let class = class_loaders.load_class(ClassLoaderId::APPLICATION, name).unwrap();
+        assert_eq!(class.defining_loader, defining_loader, "{}", name);
+    }
+
+    let not_found = ClassLoadingError::NotFound("a/App".to_string());
+    assert_eq!(class_loaders.load_class(ClassLoaderId::PLATFORM, "a/App").unwrap_err(), not_found);
+    assert_eq!(class_loaders.load_class(ClassLoaderId::BOOTSTRAP, "a/App").unwrap_err(), not_found);
+    assert_eq!(class_loaders.load_class(ClassLoaderId::BOOTSTRAP, "p/Platform").unwrap_err(), ClassLoadingError::NotFound("p/Platform".to_string()));
+
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn loaders_a_class_was_requested_through_are_initiating_loaders() {
+    let directory = scratch_directory("initiating-loaders");
+    let mut class_loaders = built_in_loaders(&directory);
+    let all = [ClassLoaderId::BOOTSTRAP, ClassLoaderId::PLATFORM, ClassLoaderId::APPLICATION];
+    assert!(all.iter().all(|&loader| !class_loaders.is_initiating_loader(loader, "Shared")));
+
+    let shared = class_loaders.load_class(ClassLoaderId::APPLICATION, "Shared").unwrap();
+    assert_eq!(shared.defining_loader, ClassLoaderId::BOOTSTRAP);
+    for loader in all {
+        assert!(class_loaders.is_initiating_loader(loader, "Shared"), "{}", loader);
+        assert!(Arc::ptr_eq(&class_loaders.find_loaded_class(loader, "Shared").unwrap(), &shared));
+    }
+
+    // Asking the platform loader records nothing for the application loader.
+    class_loaders.load_class(ClassLoaderId::PLATFORM, "p/Platform").unwrap();
+    assert!(class_loaders.is_initiating_loader(ClassLoaderId::PLATFORM, "p/Platform"));
+    assert!(!class_loaders.is_initiating_loader(ClassLoaderId::APPLICATION, "p/Platform"));
+    assert!(!class_loaders.is_initiating_loader(ClassLoaderId::BOOTSTRAP, "p/Platform"));
+
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn repeated_loads_return_the_same_class() {
+    let directory = scratch_directory("repeated-loads");
+    let mut class_loaders = built_in_loaders(&directory);
+
+    let first = class_loaders.load_class(ClassLoaderId::APPLICATION, "a/App").unwrap();
+    // Removing the file shows the second load is answered from what was recorded.
+    fs::remove_file(directory.join("app/a/App.class")).unwrap();
+    let second = class_loaders.load_class(ClassLoaderId::APPLICATION, "a/App").unwrap();
+    assert!(Arc::ptr_eq(&first, &second));
+
+    let shared = class_loaders.load_class(ClassLoaderId::APPLICATION, "Shared").unwrap();
+    let from_bootstrap = class_loaders.load_class(ClassLoaderId::BOOTSTRAP, "Shared").unwrap();
+    assert!(Arc::ptr_eq(&shared, &from_bootstrap));
+
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn a_loader_cannot_define_a_class_it_loaded_through_its_parent() {
+    let directory = scratch_directory("define-after-delegation");
+    let mut class_loaders = built_in_loaders(&directory);
+    let shared = class_loaders.load_class(ClassLoaderId::APPLICATION, "Shared").unwrap();
+
+    let bytes = ClassBytes::from(class_bytes("Shared"));
+    for name in [Some("Shared"), None] {
+        let error = class_loaders.define_class(ClassLoaderId::APPLICATION, name, &bytes).unwrap_err();
+        assert_eq!(error, ClassLoadingError::DuplicateDefinition { loader: "app".to_string(), name: "Shared".to_string() });
+    }
+    assert!(Arc::ptr_eq(&class_loaders.load_class(ClassLoaderId::APPLICATION, "Shared").unwrap(), &shared));
+
+    // A user-defined loader under the application loader has not loaded it, so it may.
+    let loader = class_loaders.create_user_defined(Some(ClassLoaderId::APPLICATION));
+    let own = class_loaders.define_class(loader, Some("Shared"), &bytes).unwrap();
+    assert_eq!(own.defining_loader, loader);
+
+    fs::remove_dir_all(directory).unwrap();
+}
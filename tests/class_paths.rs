@@ -0,0 +1,86 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use churu_vm_x::classloader::class_loading_error::ClassLoadingError;
+use churu_vm_x::classloader::class_path::ClassPath;
+
+use common::scratch_directory;
+use common::zip::{ZipWriter, STORED};
+
+fn write_class(root: &Path, name: &str, contents: &[u8]) {
+    let path = root.join(format!("{}.class", name));
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, contents).unwrap();
+}
+
+fn class_path(entries: &[&Path]) -> ClassPath {
+    let entries: Vec<String> = entries.iter().map(|entry| entry.display().to_string()).collect();
+    ClassPath::new(&entries)
+}
+
+fn find(class_path: &ClassPath, name: &str) -> Vec<u8> {
+    class_path.find(name).unwrap().to_vec()
+}
+
+#[test]
+fn entries_are_searched_in_order_and_the_first_match_wins() {
+    let directory = scratch_directory("class-path-order");
+    let (first, second) = (directory.join("first"), directory.join("second"));
+    write_class(&first, "a/Both", b"first");
+    write_class(&second, "a/Both", b"second");
+    write_class(&second, "a/OnlySecond", b"only second");
+
+    assert_eq!(find(&class_path(&[&first, &second]), "a/Both"), b"first");
+    assert_eq!(find(&class_path(&[&second, &first]), "a/Both"), b"second");
+    assert_eq!(find(&class_path(&[&first, &second]), "a/OnlySecond"), b"only second");
+
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn directories_and_archives_are_both_searched() {
+    let directory = scratch_directory("class-path-archive");
+    let classes = directory.join("classes");
+    write_class(&classes, "a/Both", b"directory");
+    write_class(&classes, "a/InDirectory", b"directory");
+    let archive = ZipWriter::new().add("a/Both.class", b"archive", STORED).add("a/InArchive.class", b"archive", STORED).finish("");
+    let jar = directory.join("lib.jar");
+    fs::write(&jar, archive).unwrap();
+
+    let class_path = class_path(&[&jar, &directory.join("missing"), &classes]);
+    assert_eq!(find(&class_path, "a/Both"), b"archive");
+    assert_eq!(find(&class_path, "a/InArchive"), b"archive");
+    assert_eq!(find(&class_path, "a/InDirectory"), b"directory");
+
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn missing_classes_are_not_found() {
+    let directory = scratch_directory("class-path-missing");
+    write_class(&directory, "a/Present", b"present");
+
+    for class_path in [class_path(&[&directory]), ClassPath::default()] {
+        assert_eq!(class_path.find("a/Absent"), Err(ClassLoadingError::NotFound("a/Absent".to_string())));
+    }
+
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn names_that_could_escape_an_entry_are_not_found() {
+    let directory = scratch_directory("class-path-names");
+    let entry = directory.join("entry");
+    write_class(&directory, "Outside", b"outside");
+    write_class(&entry, "a/b", b"inside");
+    let class_path = class_path(&[&entry]);
+
+    assert_eq!(find(&class_path, "a/b"), b"inside");
+    for name in ["../Outside", "a//b", "a/./b", "/a/b", "a/b/", "", "a.b", "a;b", "[La/b;", "a\\b"] {
+        assert_eq!(class_path.find(name), Err(ClassLoadingError::NotFound(name.to_string())), "{}", name);
+    }
+
+    fs::remove_dir_all(directory).unwrap();
+}
@@ -0,0 +1,23 @@
+import java.util.ArrayList;
+import java.util.List;
+import java.util.function.Function;
+
+public class Box<T extends Comparable<? super T>> {
+    private final List<T> items = new ArrayList<>();
+
+    public void add(T item) {
+        items.add(item);
+    }
+
+    public <R> List<R> map(Function<? super T, ? extends R> mapper) {
+        List<R> result = new ArrayList<>();
+        for (T item : items) {
+            result.add(mapper.apply(item));
+        }
+        return result;
+    }
+
+    public T max() {
+        return items.stream().max(Comparable::compareTo).orElse(null);
+    }
+}
@@ -0,0 +1,16 @@
+public class Constants {
+    public static final byte BYTE = 12;
+    public static final short SHORT = 1234;
+    public static final char CHAR = 'c';
+    public static final int INT = 123456789;
+    public static final long LONG = 1234567890123456789L;
+    public static final float FLOAT = 3.25f;
+    public static final double DOUBLE = 2.718281828459045;
+    public static final boolean BOOLEAN = true;
+    public static final String STRING = "nul\0 and \uD83D\uDE00";
+
+    @Deprecated
+    public static long sum(long a, double b) {
+        return a + (long) b + LONG;
+    }
+}
@@ -0,0 +1,31 @@
+public class Outer {
+    private int counter;
+
+    public class Inner {
+        public int next() {
+            return ++counter;
+        }
+    }
+
+    public static class Nested {
+        public static final String NAME = "nested";
+    }
+
+    public Runnable anonymous() {
+        return new Runnable() {
+            @Override
+            public void run() {
+                counter--;
+            }
+        };
+    }
+
+    public int local() {
+        class Local {
+            int twice() {
+                return counter * 2;
+            }
+        }
+        return new Local().twice();
+    }
+}
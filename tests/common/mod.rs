@@ -0,0 +1,35 @@
+// Helpers shared by the integration tests. Each test crate uses only some of them.
+#![allow(dead_code)]
+
+pub mod zip;
+
+use std::fs;
+use std::path::PathBuf;
+
+use churu_vm_x::entity::class_bytes::ClassBytes;
+
+/// A javac-compiled class from tests/classes, e.g. `Box.class`.
+pub fn corpus_class(file_name: &str) -> ClassBytes {
+    ClassBytes::from(fs::read(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/classes").join(file_name)).unwrap())
+}
+
+/// The javac-compiled classes in tests/classes, with their file names.
+pub fn corpus() -> Vec<(String, ClassBytes)> {
+    let directory = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/classes");
+    let mut classes: Vec<_> = fs::read_dir(directory)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "class"))
+        .map(|path| (path.file_name().unwrap().to_string_lossy().into_owned(), ClassBytes::from(fs::read(&path).unwrap())))
+        .collect();
+    classes.sort_by(|a, b| a.0.cmp(&b.0));
+    classes
+}
+
+/// A directory of its own under the system temporary directory, emptied first.
+pub fn scratch_directory(name: &str) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("churu-vm-x-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(&directory).unwrap();
+    directory
+}
@@ -0,0 +1,156 @@
+// Writes small ZIP archives in memory, with the variations `ZipArchive` has to read:
+// stored and deflated entries, sizes in a trailing data descriptor, and ZIP64 records.
+
+pub const STORED: u16 = 0;
+pub const DEFLATED: u16 = 8;
+
+const DATA_DESCRIPTOR_FLAG: u16 = 0x0008;
+
+#[derive(Default)]
+pub struct ZipWriter {
+    data: Vec<u8>,
+    directory: Vec<u8>,
+    entries: u16,
+    zip64: bool,
+    data_descriptors: bool,
+}
+
+impl ZipWriter {
+    pub fn new() -> Self {
+        ZipWriter::default()
+    }
+
+    /// Writes sizes and offsets only in ZIP64 extra fields and end records.
+    pub fn zip64() -> Self {
+        ZipWriter { zip64: true, ..ZipWriter::default() }
+    }
+
+    /// Leaves the sizes and CRC out of local headers, writing them after the data.
+    pub fn with_data_descriptors() -> Self {
+        ZipWriter { data_descriptors: true, ..ZipWriter::default() }
+    }
+
+    pub fn add(&mut self, name: &str, contents: &[u8], method: u16) -> &mut Self {
+        let data = match method {
+            DEFLATED => miniz_oxide::deflate::compress_to_vec(contents, 6),
+            _ => contents.to_vec(),
+        };
+        self.add_raw(name, &data, method, crc32fast::hash(contents), contents.len() as u64)
+    }
+
+    /// Adds `data` as it is, claiming `crc32` and `uncompressed_size` for it.
+    pub fn add_raw(&mut self, name: &str, data: &[u8], method: u16, crc32: u32, uncompressed_size: u64) -> &mut Self {
+        let offset = self.data.len() as u64;
+        let compressed_size = data.len() as u64;
+        let flags = if self.data_descriptors { DATA_DESCRIPTOR_FLAG } else { 0 };
+
+        let local = &mut self.data;
+        put_u32(local, 0x04034B50);
+        put_u16(local, 20);
+        put_u16(local, flags);
+        put_u16(local, method);
+        put_u32(local, 0); // modification time and date
+        if self.data_descriptors {
+            local.extend([0; 12]);
+        } else {
+            put_u32(local, crc32);
+            put_u32(local, compressed_size as u32);
+            put_u32(local, uncompressed_size as u32);
+        }
+        put_u16(local, name.len() as u16);
+        put_u16(local, 0);
+        local.extend(name.as_bytes());
+        local.extend(data);
+        if self.data_descriptors {
+            put_u32(local, 0x08074B50);
+            put_u32(local, crc32);
+            put_u32(local, compressed_size as u32);
+            put_u32(local, uncompressed_size as u32);
+        }
+
+        let mut extra = Vec::new();
+        let (compressed_field, uncompressed_field, offset_field) = if self.zip64 {
+            put_u16(&mut extra, 0x0001);
+            put_u16(&mut extra, 24);
+            put_u64(&mut extra, uncompressed_size);
+            put_u64(&mut extra, compressed_size);
+            put_u64(&mut extra, offset);
+            (0xFFFF_FFFF, 0xFFFF_FFFF, 0xFFFF_FFFF)
+        } else {
+            (compressed_size as u32, uncompressed_size as u32, offset as u32)
+        };
+
+        let central = &mut self.directory;
+        put_u32(central, 0x02014B50);
+        put_u16(central, 45);
+        put_u16(central, 45);
+        put_u16(central, flags);
+        put_u16(central, method);
+        put_u32(central, 0);
+        put_u32(central, crc32);
+        put_u32(central, compressed_field);
+        put_u32(central, uncompressed_field);
+        put_u16(central, name.len() as u16);
+        put_u16(central, extra.len() as u16);
+        put_u16(central, 0); // comment length
+        central.extend([0; 8]); // disk number, internal and external attributes
+        put_u32(central, offset_field);
+        central.extend(name.as_bytes());
+        central.extend(extra);
+
+        self.entries += 1;
+        self
+    }
+
+    pub fn finish(&mut self, comment: &str) -> Vec<u8> {
+        let mut archive = std::mem::take(&mut self.data);
+        let directory_offset = archive.len() as u64;
+        let directory_size = self.directory.len() as u64;
+        archive.append(&mut self.directory);
+
+        if self.zip64 {
+            let record = archive.len() as u64;
+            put_u32(&mut archive, 0x06064B50);
+            put_u64(&mut archive, 44);
+            put_u16(&mut archive, 45);
+            put_u16(&mut archive, 45);
+            put_u32(&mut archive, 0);
+            put_u32(&mut archive, 0);
+            put_u64(&mut archive, u64::from(self.entries));
+            put_u64(&mut archive, u64::from(self.entries));
+            put_u64(&mut archive, directory_size);
+            put_u64(&mut archive, directory_offset);
+
+            put_u32(&mut archive, 0x07064B50);
+            put_u32(&mut archive, 0);
+            put_u64(&mut archive, record);
+            put_u32(&mut archive, 1);
+        }
+
+        let (entries, size, offset) = match self.zip64 {
+            true => (0xFFFF, 0xFFFF_FFFF, 0xFFFF_FFFF),
+            false => (self.entries, directory_size as u32, directory_offset as u32),
+        };
+        put_u32(&mut archive, 0x06054B50);
+        put_u32(&mut archive, 0); // disk numbers
+        put_u16(&mut archive, entries);
+        put_u16(&mut archive, entries);
+        put_u32(&mut archive, size);
+        put_u32(&mut archive, offset);
+        put_u16(&mut archive, comment.len() as u16);
+        archive.extend(comment.as_bytes());
+        archive
+    }
+}
+
+fn put_u16(buffer: &mut Vec<u8>, value: u16) {
+    buffer.extend(value.to_le_bytes());
+}
+
+fn put_u32(buffer: &mut Vec<u8>, value: u32) {
+    buffer.extend(value.to_le_bytes());
+}
+
+fn put_u64(buffer: &mut Vec<u8>, value: u64) {
+    buffer.extend(value.to_le_bytes());
+}
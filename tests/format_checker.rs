@@ -0,0 +1,188 @@
+mod common;
+
+use churu_vm_x::assembler::assemble;
+use churu_vm_x::classloader::bootstrap_class_loader::check_class_file;
+use churu_vm_x::classloader::version_policy::VersionPolicy;
+use churu_vm_x::entity::access_flags::MethodAccessFlags;
+use churu_vm_x::entity::attribute_info::{AttributeKind, PermittedSubclassesAttribute};
+use churu_vm_x::entity::class_bytes::ClassBytes;
+use churu_vm_x::entity::class_format_error::ClassFormatErrorKind;
+use churu_vm_x::entity::class_reader::ClassReader;
+use churu_vm_x::entity::constant_pool::{ConstantInfoBase, ConstantKind, ConstantPool, ConstantUtf8Info};
+use churu_vm_x::entity::method_info::MethodParsing;
+
+use common::corpus_class;
+
+/// The errors `check_class_file` reports for `bytes`, none if it passes.
+fn errors(bytes: &[u8]) -> Vec<ClassFormatErrorKind> {
+    match check_class_file(&mut ClassReader::new(bytes), MethodParsing::Eager, &VersionPolicy::default()) {
+        Ok(_) => Vec::new(),
+        Err(errors) => errors.into_iter().map(|e| e.kind).collect(),
+    }
+}
+
+fn format_errors(source: &str) -> Vec<ClassFormatErrorKind> {
+    errors(&assemble(source).unwrap().to_bytes().unwrap())
+}
+
+fn with_major_version(mut bytes: Vec<u8>, major_version: u16) -> Vec<u8> {
+    bytes[6..8].copy_from_slice(&major_version.to_be_bytes());
+    bytes
+}
+
+fn with_method(access: &str, descriptor: &str) -> String {
+    format!(".class public t/Slots\n.method {} m{}\n    return\n.end method\n", access, descriptor)
+}
+
+#[test]
+fn instance_methods_count_this_among_their_255_argument_slots() {
+    let ints = |count: usize| format!("({})V", "I".repeat(count));
+    assert!(format_errors(&with_method("public static", &ints(255))).is_empty());
+    assert!(format_errors(&with_method("public", &ints(254))).is_empty());
+    assert_eq!(format_errors(&with_method("public", &ints(255))), [ClassFormatErrorKind::InvalidDescriptor(ints(255))]);
+
+    let longs = format!("({}I)V", "J".repeat(127));
+    assert_eq!(format_errors(&with_method("public", &longs)), [ClassFormatErrorKind::InvalidDescriptor(longs)]);
+}
+
+const BROKEN: &str = r#"
+.class public t/Broken
+.method public m()V
+    .limit stack 1
+    aload_0
+    invokevirtual t/Broken/m()V
+    return
+.end method
+.method public m()V
+    return
+.end method
+.method public abstract missing()V
+.end method
+.method public static bad(L;)V
+    return
+.end method
+"#;
+
+#[test]
+fn independent_violations_are_all_reported() {
+    let mut class = assemble(BROKEN).unwrap();
+
+    // The Methodref's class_index points at its NameAndType instead of a Class.
+    let mut constants = class.constant_pool.entries().to_vec();
+    let name_and_type_index = constants.iter_mut()
+        .find_map(|constant| match constant {
+            ConstantKind::Methodref(methodref) => {
+                methodref.class_index = methodref.name_and_type_index;
+                Some(methodref.name_and_type_index)
+            }
+            _ => None,
+        })
+        .unwrap();
+    class.constant_pool = ConstantPool::new(constants);
+    // `missing` is no longer abstract, so it needs the Code attribute it lacks.
+    class.methods[2].access_flags = MethodAccessFlags(0x0001);
+
+    let bytes = class.to_bytes().unwrap();
+    let errors = check_class_file(&mut ClassReader::new(&bytes), MethodParsing::Eager, &VersionPolicy::default()).unwrap_err();
+    let errors: Vec<_> = errors.into_iter().map(|e| (e.kind, e.constant_pool_index, e.class_name)).collect();
+    let broken = Some("t/Broken".to_string());
+    assert_eq!(errors, [
+        (ClassFormatErrorKind::UnexpectedConstantPoolEntry { expected: "Class" }, Some(name_and_type_index), broken.clone()),
+        (
+            ClassFormatErrorKind::DuplicateMethod { name: "m".to_string(), descriptor: "()V".to_string() },
+            Some(class.methods[1].name_index),
+            broken.clone(),
+        ),
+        (ClassFormatErrorKind::MissingCodeAttribute, Some(class.methods[2].name_index), broken.clone()),
+        (ClassFormatErrorKind::InvalidDescriptor("(L;)V".to_string()), Some(class.methods[3].descriptor_index), broken),
+    ]);
+}
+
+fn unsupported_attribute(name: &'static str, major_version: u16) -> ClassFormatErrorKind {
+    ClassFormatErrorKind::UnsupportedAttribute { name, major_version }
+}
+
+#[test]
+fn nest_attributes_need_major_version_55() {
+    let nest = |version: u16| format!(".version {} 0\n.class public t/Host\n.nesthost t/Other\n.nestmembers t/Host$Member\n", version);
+    assert_eq!(format_errors(&nest(54)), [unsupported_attribute("NestHost", 54), unsupported_attribute("NestMembers", 54)]);
+    assert!(format_errors(&nest(55)).is_empty());
+}
+
+#[test]
+fn record_attributes_need_major_version_60() {
+    let point = corpus_class("Point.class").to_vec();
+    assert_eq!(errors(&with_major_version(point.clone(), 59)), [unsupported_attribute("Record", 59)]);
+    assert!(errors(&with_major_version(point, 60)).is_empty());
+}
+
+#[test]
+fn permitted_subclasses_attributes_need_major_version_61() {
+    // The assembler has no directive for PermittedSubclasses, so NestMembers is renamed.
+    let mut class = assemble(".version 61 0\n.class public abstract t/Sealed\n.nestmembers t/Sub\n").unwrap();
+    let mut constants = class.constant_pool.entries().to_vec();
+    for attribute in &mut class.attributes {
+        if let AttributeKind::NestMembers(nest_members) = attribute {
+            let name = "PermittedSubclasses";
+            let utf8 = ConstantUtf8Info {
+                base: ConstantInfoBase { tag: 1 },
+                length: name.len() as u16,
+                text: name.to_string(),
+                bytes: ClassBytes::from(name.as_bytes()),
+            };
+            constants[nest_members.base.attribute_name_index as usize] = ConstantKind::Utf8(utf8);
+            *attribute = AttributeKind::PermittedSubclasses(PermittedSubclassesAttribute {
+                base: nest_members.base.clone(),
+                number_of_classes: nest_members.number_of_classes,
+                classes: nest_members.classes.clone(),
+            });
+        }
+    }
+    class.constant_pool = ConstantPool::new(constants);
+
+    let sealed = class.to_bytes().unwrap();
+    assert_eq!(errors(&with_major_version(sealed.clone(), 60)), [unsupported_attribute("PermittedSubclasses", 60)]);
+    assert!(errors(&with_major_version(sealed, 61)).is_empty());
+}
+
+fn dynamic_constant(bootstrap_index: u16, descriptor: &str) -> String {
+    format!(r#"
+.version 55 0
+.class public t/Constants
+.bootstrap invokestatic t/Constants/bootstrap(Ljava/lang/invoke/MethodHandles$Lookup;Ljava/lang/String;Ljava/lang/Class;)I
+.method public static value()I
+    .limit stack 1
+    ldc dynamic {} value {}
+    ireturn
+.end method
+"#, bootstrap_index, descriptor)
+}
+
+#[test]
+fn dynamic_constants_are_parsed() {
+    let bytes = assemble(&dynamic_constant(0, "I")).unwrap().to_bytes().unwrap();
+    let class = check_class_file(&mut ClassReader::new(&bytes), MethodParsing::Eager, &VersionPolicy::default()).unwrap();
+    let constant_pool = &class.constant_pool;
+    let dynamic = constant_pool.entries().iter().find_map(|constant| match constant {
+        ConstantKind::Dynamic(dynamic) => Some(dynamic),
+        _ => None,
+    });
+    let dynamic = dynamic.unwrap();
+    assert_eq!(dynamic.base.tag, 17);
+    assert_eq!(dynamic.bootstrap_method_attr_index, 0);
+    assert_eq!(constant_pool.name_and_type(dynamic.name_and_type_index), Ok(("value", "I")));
+}
+
+#[test]
+fn dynamic_constants_need_major_version_55() {
+    let bytes = assemble(&dynamic_constant(0, "I")).unwrap().to_bytes().unwrap();
+    assert_eq!(errors(&with_major_version(bytes, 54)), [ClassFormatErrorKind::UnsupportedConstantPoolTag { tag: 17, major_version: 54 }]);
+}
+
+#[test]
+fn dynamic_constants_must_name_a_bootstrap_method_and_a_field_type() {
+    assert_eq!(format_errors(&dynamic_constant(1, "I")), [ClassFormatErrorKind::InvalidBootstrapMethodIndex(1)]);
+    let without_bootstrap_methods = dynamic_constant(0, "I").replace(".bootstrap", "; .bootstrap");
+    assert_eq!(format_errors(&without_bootstrap_methods), [ClassFormatErrorKind::InvalidBootstrapMethodIndex(0)]);
+    assert_eq!(format_errors(&dynamic_constant(0, "()I")), [ClassFormatErrorKind::InvalidDescriptor("()I".to_string())]);
+}
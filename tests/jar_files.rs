@@ -0,0 +1,102 @@
+mod common;
+
+use churu_vm_x::classloader::jar_manifest::JarManifest;
+use churu_vm_x::classloader::zip_archive::ZipArchive;
+use churu_vm_x::entity::class_bytes::ClassBytes;
+
+use common::zip::{ZipWriter, DEFLATED, STORED};
+
+const CONTENTS: &[u8] = b"class file bytes, repeated: class file bytes, repeated: class file bytes";
+
+fn open(bytes: Vec<u8>) -> ZipArchive {
+    ZipArchive::new(ClassBytes::from(bytes)).unwrap()
+}
+
+fn read(archive: &ZipArchive, name: &str) -> Vec<u8> {
+    archive.read(name).unwrap().unwrap().to_vec()
+}
+
+#[test]
+fn stored_and_deflated_entries_are_read() {
+    let archive = open(ZipWriter::new().add("a/Stored.class", CONTENTS, STORED).add("a/Deflated.class", CONTENTS, DEFLATED).finish(""));
+    assert_eq!(read(&archive, "a/Stored.class"), CONTENTS);
+    assert_eq!(read(&archive, "a/Deflated.class"), CONTENTS);
+    assert!(archive.read("a/Missing.class").unwrap().is_none());
+}
+
+#[test]
+fn sizes_in_data_descriptors_are_taken_from_the_central_directory() {
+    let archive = open(ZipWriter::with_data_descriptors().add("Stored.class", CONTENTS, STORED).add("Deflated.class", CONTENTS, DEFLATED).finish(""));
+    assert_eq!(read(&archive, "Stored.class"), CONTENTS);
+    assert_eq!(read(&archive, "Deflated.class"), CONTENTS);
+}
+
+#[test]
+fn zip64_records_and_extra_fields_are_read() {
+    let archive = open(ZipWriter::zip64().add("Stored.class", CONTENTS, STORED).add("Deflated.class", CONTENTS, DEFLATED).finish(""));
+    assert_eq!(read(&archive, "Stored.class"), CONTENTS);
+    assert_eq!(read(&archive, "Deflated.class"), CONTENTS);
+}
+
+#[test]
+fn archive_comment_does_not_hide_the_end_of_central_directory() {
+    let archive = open(ZipWriter::new().add("Stored.class", CONTENTS, STORED).finish("built by a test"));
+    assert_eq!(read(&archive, "Stored.class"), CONTENTS);
+}
+
+#[test]
+fn corrupt_entries_are_rejected() {
+    let crc32 = crc32fast::hash(CONTENTS);
+    let archive = open(ZipWriter::new()
+        .add_raw("BadCrc.class", CONTENTS, STORED, crc32 ^ 1, CONTENTS.len() as u64)
+        .add_raw("BadSize.class", CONTENTS, STORED, crc32, CONTENTS.len() as u64 + 1)
+        .add_raw("BadDeflate.class", CONTENTS, DEFLATED, crc32, CONTENTS.len() as u64)
+        .add_raw("Bzip2.class", CONTENTS, 12, crc32, CONTENTS.len() as u64)
+        .finish(""));
+
+    let error = |name: &str| archive.read(name).unwrap_err();
+    assert_eq!(error("BadCrc.class"), "BadCrc.class: CRC mismatch");
+    assert_eq!(error("BadSize.class"), "BadSize.class: size does not match the central directory");
+    assert!(error("BadDeflate.class").starts_with("BadDeflate.class: invalid deflate data"));
+    assert_eq!(error("Bzip2.class"), "Bzip2.class: unsupported compression method 12");
+}
+
+#[test]
+fn data_that_is_not_an_archive_is_rejected() {
+    for bytes in [vec![], vec![0xCA, 0xFE, 0xBA, 0xBE], vec![0; 64]] {
+        assert!(ZipArchive::new(ClassBytes::from(bytes)).is_err());
+    }
+    let mut truncated = ZipWriter::new().add("Stored.class", CONTENTS, STORED).finish("");
+    truncated.drain(..10);
+    assert!(ZipArchive::new(ClassBytes::from(truncated)).is_err());
+}
+
+#[test]
+fn manifest_continuation_lines_are_joined() {
+    let manifest = JarManifest::parse(b"Manifest-Version: 1.0\r\nMain-Class: com.acme.app\r\n .Main\r\nClass-Path: a.jar\r\n  b.jar\r\n\r\nName: ignored\r\n").unwrap();
+    assert_eq!(manifest.main_class().as_deref(), Some("com/acme/app/Main"));
+    assert_eq!(manifest.main_attribute("Class-Path"), Some("a.jar b.jar"));
+    assert_eq!(manifest.main_attribute("Name"), None);
+}
+
+#[test]
+fn manifest_attribute_names_are_case_insensitive() {
+    let manifest = JarManifest::parse(b"main-class: Main\nCLASS-PATH: lib.jar\n").unwrap();
+    assert_eq!(manifest.main_class().as_deref(), Some("Main"));
+    assert_eq!(manifest.main_attribute("Class-Path"), Some("lib.jar"));
+}
+
+#[test]
+fn malformed_manifests_are_rejected() {
+    assert!(JarManifest::parse(b" continued\n").is_err());
+    assert!(JarManifest::parse(b"Main-Class Main\n").is_err());
+    assert!(JarManifest::parse(&[b'A', b':', b' ', 0xFF, b'\n']).is_err());
+}
+
+#[test]
+fn class_path_entries_resolve_against_the_jar_directory() {
+    let manifest = JarManifest::parse(b"Class-Path: lib/a.jar file:b.jar /opt/c.jar\n").unwrap();
+    assert_eq!(manifest.class_path("apps/app.jar"), ["apps/lib/a.jar", "apps/b.jar", "/opt/c.jar"]);
+    assert_eq!(manifest.class_path("app.jar"), ["lib/a.jar", "b.jar", "/opt/c.jar"]);
+    assert!(JarManifest::parse(b"Main-Class: Main\n").unwrap().class_path("app.jar").is_empty());
+}
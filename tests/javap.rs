@@ -0,0 +1,37 @@
+mod common;
+
+use churu_vm_x::classloader::bootstrap_class_loader::{check_class_file, parse_class_file};
+use churu_vm_x::classloader::version_policy::VersionPolicy;
+use churu_vm_x::entity::class_bytes::ClassBytes;
+use churu_vm_x::entity::class_reader::ClassReader;
+use churu_vm_x::entity::method_info::MethodParsing;
+use churu_vm_x::javap::disassemble;
+
+use common::corpus_class;
+
+#[test]
+fn numeric_constants_are_printed_once() {
+    let class = parse_class_file(&corpus_class("Constants.class"), &VersionPolicy::default()).unwrap();
+    let output = disassemble(&class);
+    let long_line = output.lines().find(|line| line.contains("= Long")).unwrap();
+    assert_eq!(long_line.trim(), "#9 = Long               1234567890123456789l");
+    for tag in ["Integer", "Float", "Long", "Double"] {
+        for line in output.lines().filter(|line| line.contains(&format!("= {} ", tag))) {
+            assert!(!line.contains("//"), "{}", line);
+        }
+    }
+    assert!(output.contains("// long 1234567890123456789l"));
+}
+
+#[test]
+fn classes_failing_the_format_checker_are_disassembled() {
+    // Records need version 60, so the format checker rejects this one.
+    let mut bytes = corpus_class("Point.class").to_vec();
+    bytes[6..8].copy_from_slice(&52u16.to_be_bytes());
+    let bytes = ClassBytes::from(bytes);
+    let versions = VersionPolicy::default();
+    assert!(check_class_file(&mut ClassReader::shared(&bytes), MethodParsing::Eager, &versions).is_err());
+
+    let class = parse_class_file(&bytes, &versions).unwrap();
+    assert!(disassemble(&class).contains("Record"));
+}
@@ -0,0 +1,135 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+use churu_vm_x::assembler::assemble;
+use churu_vm_x::entity::attribute_info::AttributeKind;
+
+use common::{corpus_class, scratch_directory};
+use common::zip::{ZipWriter, DEFLATED, STORED};
+
+/// A corpus class with its major version changed to `major_version`.
+fn class_with_version(file_name: &str, major_version: u16) -> Vec<u8> {
+    let mut bytes = corpus_class(file_name).to_vec();
+    bytes[6..8].copy_from_slice(&major_version.to_be_bytes());
+    bytes
+}
+
+fn churu(directory: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_churu-vm-x")).current_dir(directory).args(args).output().unwrap()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+/// Writes a stub `java/lang/Object` to `<directory>/boot` for `-Xbootclasspath/a:boot`.
+fn write_boot_class_path(directory: &Path) {
+    let object = assemble(".class public super java/lang/Object\n").unwrap();
+    fs::create_dir_all(directory.join("boot/java/lang")).unwrap();
+    fs::write(directory.join("boot/java/lang/Object.class"), object.to_bytes().unwrap()).unwrap();
+}
+
+#[test]
+fn class_file_version_65_needs_max_class_file_version() {
+    let directory = scratch_directory("version");
+    fs::write(directory.join("Main.class"), class_with_version("Box.class", 65)).unwrap();
+
+    let rejected = churu(&directory, &[]);
+    assert_eq!(rejected.status.code(), Some(1));
+    assert!(stderr(&rejected).contains("java.lang.UnsupportedClassVersionError"), "{}", stderr(&rejected));
+
+    let accepted = churu(&directory, &["-XX:MaxClassFileVersion=65"]);
+    assert!(!stderr(&accepted).contains("UnsupportedClassVersionError"), "{}", stderr(&accepted));
+
+    let javap_rejected = churu(&directory, &["javap", "Main.class"]);
+    assert_eq!(javap_rejected.status.code(), Some(1));
+    let javap_accepted = churu(&directory, &["javap", "-XX:MaxClassFileVersion=65", "Main.class"]);
+    assert!(javap_accepted.status.success(), "{}", stderr(&javap_accepted));
+    assert!(String::from_utf8_lossy(&javap_accepted.stdout).contains("major version: 65"));
+
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn invalid_max_class_file_version_is_reported() {
+    let directory = scratch_directory("invalid-version");
+    let output = churu(&directory, &["-XX:MaxClassFileVersion=forty"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("invalid class file version"), "{}", stderr(&output));
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn launching_without_object_on_the_boot_class_path_asks_for_one() {
+    let directory = scratch_directory("boot-class-path");
+    fs::write(directory.join("Main.class"), class_with_version("Box.class", 52)).unwrap();
+
+    let output = churu(&directory, &[]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("-Xbootclasspath/a:"), "{}", stderr(&output));
+    assert!(!stderr(&output).contains("Exception in thread"), "{}", stderr(&output));
+
+    write_boot_class_path(&directory);
+    let output = churu(&directory, &["-Xbootclasspath/a:boot"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn jars_are_launched_through_their_main_class() {
+    let directory = scratch_directory("jar");
+    write_boot_class_path(&directory);
+
+    // The superclass is in a second jar, found through the manifest's Class-Path.
+    let main = assemble(".class public app/Hello\n.super lib/Base\n").unwrap().to_bytes().unwrap();
+    let base = assemble(".class public lib/Base\n").unwrap().to_bytes().unwrap();
+    let manifest = b"Manifest-Version: 1.0\r\nMain-Class: app.Hello\r\nClass-Path: lib.jar\r\n\r\n";
+    let app_jar = ZipWriter::new().add("META-INF/MANIFEST.MF", manifest, DEFLATED).add("app/Hello.class", &main, DEFLATED).finish("");
+    fs::write(directory.join("app.jar"), app_jar).unwrap();
+
+    let launched = churu(&directory, &["-Xbootclasspath/a:boot", "-jar", "app.jar"]);
+    assert!(!launched.status.success());
+    assert!(stderr(&launched).contains("java.lang.NoClassDefFoundError: lib/Base"), "{}", stderr(&launched));
+
+    fs::write(directory.join("lib.jar"), ZipWriter::new().add("lib/Base.class", &base, STORED).finish("")).unwrap();
+    let launched = churu(&directory, &["-Xbootclasspath/a:boot", "-jar", "app.jar"]);
+    assert!(launched.status.success(), "{}", stderr(&launched));
+
+    let no_main_class = ZipWriter::new().add("META-INF/MANIFEST.MF", b"Manifest-Version: 1.0\n", STORED).finish("");
+    fs::write(directory.join("library.jar"), no_main_class).unwrap();
+    let launched = churu(&directory, &["-Xbootclasspath/a:boot", "-jar", "library.jar"]);
+    assert!(stderr(&launched).contains("no main manifest attribute"), "{}", stderr(&launched));
+
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn malformed_method_attributes_are_reported_at_load_time_unless_method_parsing_is_lazy() {
+    let directory = scratch_directory("lazy-method-parsing");
+    write_boot_class_path(&directory);
+
+    // Point the Exceptions attribute of a method that never runs at a Utf8 entry.
+    let mut main = assemble(".class public Main\n.method static unused()V\n    .throws java/lang/Exception\n    return\n.end method\n").unwrap();
+    let method = &mut main.methods[0];
+    let mut attributes = method.attributes.parsed().unwrap().to_vec();
+    for attribute in &mut attributes {
+        if let AttributeKind::Exceptions(exceptions) = attribute {
+            exceptions.exception_index_table[0] = method.name_index;
+        }
+    }
+    method.attributes = attributes.into();
+    fs::write(directory.join("Main.class"), main.to_bytes().unwrap()).unwrap();
+
+    let eager = churu(&directory, &["-Xbootclasspath/a:boot"]);
+    assert_eq!(eager.status.code(), Some(1));
+    assert!(stderr(&eager).contains("java.lang.ClassFormatError: Constant pool entry is not a Class"), "{}", stderr(&eager));
+
+    let lazy = churu(&directory, &["-Xbootclasspath/a:boot", "-XX:+LazyMethodParsing"]);
+    assert!(lazy.status.success(), "{}", stderr(&lazy));
+
+    fs::remove_dir_all(directory).unwrap();
+}
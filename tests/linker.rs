@@ -0,0 +1,304 @@
+use std::sync::Arc;
+
+use churu_vm_x::assembler::assemble;
+use churu_vm_x::classloader::class_loader::{ClassLoaderId, ClassLoaders};
+use churu_vm_x::classloader::class_loading_error::ClassLoadingError;
+use churu_vm_x::classloader::linker::{link, resolve, Resolved};
+use churu_vm_x::classloader::linking_error::LinkingError;
+use churu_vm_x::classloader::runtime_class::{LinkState, RuntimeClass, Value};
+use churu_vm_x::entity::class_bytes::ClassBytes;
+use churu_vm_x::entity::constant_pool::ConstantKind;
+
+/// Enough of `java/lang/Object` for classes to link against.
+const OBJECT: &str = r#"
+.class public super java/lang/Object
+.method public <init>()V
+    return
+.end method
+"#;
+
+/// Class loaders whose bootstrap loader has defined the stub `Object` and `sources`.
+fn class_loaders(sources: &[&str]) -> ClassLoaders {
+    let mut class_loaders = ClassLoaders::default();
+    for source in [OBJECT].iter().chain(sources) {
+        define(&mut class_loaders, ClassLoaderId::BOOTSTRAP, source);
+    }
+    class_loaders
+}
+
+fn define(class_loaders: &mut ClassLoaders, loader: ClassLoaderId, source: &str) -> Arc<RuntimeClass> {
+    let bytes = ClassBytes::from(assemble(source).unwrap().to_bytes().unwrap());
+    class_loaders.define_class(loader, None, &bytes).unwrap()
+}
+
+fn linked(class_loaders: &mut ClassLoaders, name: &str) -> Arc<RuntimeClass> {
+    let class = class_loaders.load_class(ClassLoaderId::BOOTSTRAP, name).unwrap();
+    link(class_loaders, &class).unwrap();
+    class
+}
+
+/// The index of the Fieldref, Methodref or InterfaceMethodref naming member `name`.
+fn member_ref(class: &RuntimeClass, name: &str) -> u16 {
+    let constant_pool = &class.class_file.constant_pool;
+    (1..constant_pool.len() as u16)
+        .find(|&index| constant_pool.member_ref(index).is_ok_and(|(_, member_name, _)| member_name == name))
+        .unwrap()
+}
+
+fn class_ref(class: &RuntimeClass, name: &str) -> u16 {
+    let constant_pool = &class.class_file.constant_pool;
+    (1..constant_pool.len() as u16)
+        .find(|&index| matches!(constant_pool.get(index), Ok(ConstantKind::Class(_))) && constant_pool.class_name(index) == Ok(name))
+        .unwrap()
+}
+
+fn field_index(class: &RuntimeClass, name: &str) -> usize {
+    let constant_pool = &class.class_file.constant_pool;
+    class.class_file.fields.iter().position(|field| constant_pool.utf8(field.name_index) == Ok(name)).unwrap()
+}
+
+const HOLDER: &str = r#"
+.class public t/Holder
+.super java/lang/Object
+.field static count I
+.field static name Ljava/lang/String;
+.field static total J
+.field static ratio D
+.field size I
+.method static touch()V
+    .limit stack 2
+    getstatic t/Holder/count I
+    pop
+    invokestatic t/Holder/touch()V
+    getstatic t/Holder/missing I
+    pop
+    ldc class t/Later
+    pop
+    return
+.end method
+"#;
+
+#[test]
+fn resolved_members_are_cached() {
+    let mut class_loaders = class_loaders(&[HOLDER]);
+    let holder = linked(&mut class_loaders, "t/Holder");
+
+    for name in ["count", "touch"] {
+        let index = member_ref(&holder, name);
+        assert!(holder.resolved(index).is_none());
+        let first = resolve(&mut class_loaders, &holder, index).unwrap();
+        let second = resolve(&mut class_loaders, &holder, index).unwrap();
+        match (first, second) {
+            (Resolved::Field { class: first, index: first_index }, Resolved::Field { class: second, index: second_index })
+            | (Resolved::Method { class: first, index: first_index }, Resolved::Method { class: second, index: second_index }) => {
+                assert!(Arc::ptr_eq(&first, &second));
+                assert!(Arc::ptr_eq(&first, &holder));
+                assert_eq!(first_index, second_index);
+            }
+            other => panic!("expected the same member twice but got {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn resolution_failures_are_cached() {
+    let mut class_loaders = class_loaders(&[HOLDER]);
+    let holder = linked(&mut class_loaders, "t/Holder");
+
+    let missing = member_ref(&holder, "missing");
+    let error = resolve(&mut class_loaders, &holder, missing).unwrap_err();
+    assert_eq!(error, LinkingError::NoSuchField("t/Holder.missing".to_string()));
+    assert_eq!(error.java_exception_name(), "java/lang/NoSuchFieldError");
+    assert!(matches!(holder.resolved(missing), Some(Err(cached)) if cached == error));
+
+    // Defining the class afterwards does not change the outcome.
+    let later = class_ref(&holder, "t/Later");
+    let error = resolve(&mut class_loaders, &holder, later).unwrap_err();
+    assert_eq!(error, LinkingError::Loading(ClassLoadingError::NotFound("t/Later".to_string())));
+    define(&mut class_loaders, ClassLoaderId::BOOTSTRAP, ".class public t/Later\n.super java/lang/Object\n");
+    assert_eq!(resolve(&mut class_loaders, &holder, later).unwrap_err(), error);
+}
+
+#[test]
+fn preparation_gives_static_fields_their_default_values() {
+    let mut class_loaders = class_loaders(&[HOLDER]);
+    let holder = class_loaders.load_class(ClassLoaderId::BOOTSTRAP, "t/Holder").unwrap();
+    assert_eq!(holder.static_value(field_index(&holder, "count")), None);
+
+    link(&mut class_loaders, &holder).unwrap();
+    assert_eq!(holder.static_value(field_index(&holder, "count")), Some(Value::Int(0)));
+    assert_eq!(holder.static_value(field_index(&holder, "name")), Some(Value::Null));
+    assert_eq!(holder.static_value(field_index(&holder, "total")), Some(Value::Long(0)));
+    assert_eq!(holder.static_value(field_index(&holder, "ratio")), Some(Value::Double(0.0)));
+    assert_eq!(holder.static_value(field_index(&holder, "size")), None);
+}
+
+const DEFAULT_M: &str = r#"
+.version 52 0
+.interface public t/A
+.super java/lang/Object
+.method public m()V
+    return
+.end method
+"#;
+
+const ABSTRACT_M: &str = r#"
+.version 52 0
+.interface public t/B
+.super java/lang/Object
+.implements t/A
+.method public abstract m()V
+.end method
+"#;
+
+const INHERITS_M: &str = r#"
+.version 52 0
+.interface public t/E
+.super java/lang/Object
+.implements t/A
+"#;
+
+const CALLER: &str = r#"
+.class public t/Caller
+.super java/lang/Object
+.method static call(Lt/C;Lt/D;)V
+    .limit stack 1
+    aload_0
+    invokevirtual t/C/m()V
+    aload_1
+    invokevirtual t/D/m()V
+    return
+.end method
+"#;
+
+fn declaring_class(resolved: Resolved) -> String {
+    match resolved {
+        Resolved::Method { class, .. } => class.name().to_string(),
+        other => panic!("expected a method but got {:?}", other),
+    }
+}
+
+#[test]
+fn superinterface_methods_overridden_in_a_subinterface_are_not_maximally_specific() {
+    let mut class_loaders = class_loaders(&[
+        DEFAULT_M,
+        ABSTRACT_M,
+        INHERITS_M,
+        ".class public t/C\n.super java/lang/Object\n.implements t/B\n",
+        ".class public t/D\n.super java/lang/Object\n.implements t/E\n",
+        CALLER,
+    ]);
+    let caller = linked(&mut class_loaders, "t/Caller");
+
+    // B.m overrides the default A.m, so it is the only maximally-specific method of C.
+    let c_m = resolve(&mut class_loaders, &caller, member_ref(&caller, "m")).unwrap();
+    assert_eq!(declaring_class(c_m), "t/B");
+
+    let constant_pool = &caller.class_file.constant_pool;
+    let d_m = (1..constant_pool.len() as u16)
+        .find(|&index| constant_pool.member_ref(index).is_ok_and(|(class, _, _)| class == "t/D"))
+        .unwrap();
+    assert_eq!(declaring_class(resolve(&mut class_loaders, &caller, d_m).unwrap()), "t/A");
+}
+
+const HOST: &str = r#"
+.version 55 0
+.class public t/Host
+.super java/lang/Object
+.nestmembers t/Host$Member
+.field private static secret I
+"#;
+
+fn peeking_class(name: &str, nest_host: &str) -> String {
+    format!(r#"
+.version 55 0
+.class public {}
+.super java/lang/Object
+.nesthost {}
+.method static peek()V
+    .limit stack 1
+    getstatic t/Host/secret I
+    pop
+    return
+.end method
+"#, name, nest_host)
+}
+
+#[test]
+fn private_members_are_accessible_to_nest_members_the_host_lists() {
+    let member = peeking_class("t/Host$Member", "t/Host");
+    let impostor = peeking_class("t/Impostor", "t/Host");
+    let mut class_loaders = class_loaders(&[HOST, &member, &impostor]);
+
+    let member = linked(&mut class_loaders, "t/Host$Member");
+    let resolved = resolve(&mut class_loaders, &member, member_ref(&member, "secret")).unwrap();
+    assert!(matches!(resolved, Resolved::Field { class, .. } if class.name() == "t/Host"));
+    let host = member.nest_host().unwrap();
+    assert_eq!(host.name(), "t/Host");
+    assert!(Arc::ptr_eq(&host.nest_host().unwrap(), &host));
+
+    // Naming the host is not enough: the host has to list the class as a member.
+    let impostor = linked(&mut class_loaders, "t/Impostor");
+    let error = resolve(&mut class_loaders, &impostor, member_ref(&impostor, "secret")).unwrap_err();
+    assert_eq!(error.java_exception_name(), "java/lang/IllegalAccessError");
+    assert!(Arc::ptr_eq(&impostor.nest_host().unwrap(), &impostor));
+}
+
+#[test]
+fn nest_hosts_of_another_runtime_package_are_ignored() {
+    let member = peeking_class("t/Host$Member", "t/Host");
+    let mut class_loaders = class_loaders(&[HOST]);
+    let member = define(&mut class_loaders, ClassLoaderId::APPLICATION, &member);
+    link(&mut class_loaders, &member).unwrap();
+
+    let error = resolve(&mut class_loaders, &member, member_ref(&member, "secret")).unwrap_err();
+    assert_eq!(error.java_exception_name(), "java/lang/IllegalAccessError");
+    assert!(Arc::ptr_eq(&member.nest_host().unwrap(), &member));
+}
+
+#[test]
+fn classes_referring_to_themselves_are_freed() {
+    let member = peeking_class("t/Loner", "t/Loner");
+    let mut class_loaders = class_loaders(&[HOLDER, HOST, &member]);
+    let holder = linked(&mut class_loaders, "t/Holder");
+    for index in [class_ref(&holder, "t/Holder"), member_ref(&holder, "count"), member_ref(&holder, "touch")] {
+        resolve(&mut class_loaders, &holder, index).unwrap();
+    }
+    let loner = linked(&mut class_loaders, "t/Loner");
+    assert!(resolve(&mut class_loaders, &loner, member_ref(&loner, "secret")).is_err());
+    assert!(Arc::ptr_eq(&loner.nest_host().unwrap(), &loner));
+
+    let classes = [Arc::downgrade(&holder), Arc::downgrade(&loner)];
+    drop((class_loaders, holder, loner));
+    assert!(classes.iter().all(|class| class.upgrade().is_none()));
+}
+
+#[test]
+fn classes_that_are_their_own_supertypes_fail_with_class_circularity() {
+    let mut class_loaders = class_loaders(&[
+        ".class public t/P\n.super t/Q\n",
+        ".class public t/Q\n.super t/P\n",
+        ".interface public t/I\n.super java/lang/Object\n.implements t/I\n",
+    ]);
+    let p = class_loaders.load_class(ClassLoaderId::BOOTSTRAP, "t/P").unwrap();
+    let error = link(&mut class_loaders, &p).unwrap_err();
+    assert_eq!(error, LinkingError::ClassCircularity("t/P".to_string()));
+    assert_eq!(error.java_exception_name(), "java/lang/ClassCircularityError");
+
+    let i = class_loaders.load_class(ClassLoaderId::BOOTSTRAP, "t/I").unwrap();
+    assert_eq!(link(&mut class_loaders, &i).unwrap_err(), LinkingError::ClassCircularity("t/I".to_string()));
+}
+
+#[test]
+fn classes_that_failed_to_link_keep_failing_with_the_same_error() {
+    let mut class_loaders = class_loaders(&[".class public t/Orphan\n.super t/Parent\n"]);
+    let orphan = class_loaders.load_class(ClassLoaderId::BOOTSTRAP, "t/Orphan").unwrap();
+    let error = link(&mut class_loaders, &orphan).unwrap_err();
+    assert_eq!(error, LinkingError::Loading(ClassLoadingError::NotFound("t/Parent".to_string())));
+    assert_eq!(orphan.link_state(), LinkState::Failed(error.clone()));
+
+    // Defining the superclass afterwards does not let the class link.
+    define(&mut class_loaders, ClassLoaderId::BOOTSTRAP, ".class public t/Parent\n.super java/lang/Object\n");
+    assert_eq!(link(&mut class_loaders, &orphan).unwrap_err(), error);
+    assert_eq!(orphan.link_state(), LinkState::Failed(error));
+}